@@ -0,0 +1,131 @@
+use super::{devices, AudioError};
+use crate::config::{AudioConfig, CaptureSource};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Command-line recorder used to capture audio, in order of preference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecorderBackend {
+    PwRecord,
+    Parec,
+    Arecord,
+}
+
+impl RecorderBackend {
+    const ALL: [RecorderBackend; 3] = [RecorderBackend::PwRecord, RecorderBackend::Parec, RecorderBackend::Arecord];
+
+    /// Pick the first recorder found in PATH, so croaker also works on PulseAudio and bare ALSA.
+    pub fn detect() -> Option<Self> {
+        Self::ALL.into_iter().find(|backend| which::which(backend.program()).is_ok())
+    }
+
+    pub fn program(&self) -> &'static str {
+        match self {
+            RecorderBackend::PwRecord => "pw-record",
+            RecorderBackend::Parec => "parec",
+            RecorderBackend::Arecord => "arecord",
+        }
+    }
+
+    /// Whether the recorder can write a WAV file itself. The others only stream
+    /// raw samples to stdout and the WAV header is added on stop.
+    pub fn writes_wav(&self) -> bool {
+        *self == RecorderBackend::PwRecord
+    }
+
+    /// Build the capture command. `output` is a WAV path for recorders that
+    /// support it; `None` streams raw samples to stdout.
+    pub fn command(&self, audio: &AudioConfig, source: CaptureSource, output: Option<&Path>) -> Result<Command, AudioError> {
+        let channels = audio.channels.max(1);
+        let mut cmd = Command::new(self.program());
+        cmd.stdin(Stdio::null());
+
+        match self {
+            RecorderBackend::PwRecord => {
+                // Note: --target=auto (default) will auto-select the default recording source
+                // Remove --target=0 as that means "don't link" and won't record anything!
+                cmd.arg(format!("--format={}", audio.format))
+                    .arg(format!("--rate={}", audio.sample_rate))
+                    .arg(format!("--channels={}", channels));
+
+                match source {
+                    // Respect configured input device/source when provided.
+                    CaptureSource::Microphone => {
+                        if let Some(target) = devices::resolve_target(&audio.device) {
+                            cmd.arg("--target").arg(target);
+                        }
+                    }
+                    // Link to the default sink's monitor ports instead of a source
+                    CaptureSource::Monitor => {
+                        cmd.arg("-P").arg("{ stream.capture.sink=true }");
+                    }
+                }
+
+                // Writing to "-" makes pw-record emit raw samples on stdout
+                match output {
+                    Some(path) => cmd.arg(path),
+                    None => cmd.arg("-"),
+                };
+            }
+            RecorderBackend::Parec => {
+                cmd.arg(format!("--format={}", parec_format(&audio.format)?))
+                    .arg(format!("--rate={}", audio.sample_rate))
+                    .arg(format!("--channels={}", channels))
+                    .arg("--raw");
+
+                match source {
+                    CaptureSource::Microphone if audio.device != "default" && !audio.device.is_empty() => {
+                        cmd.arg(format!("--device={}", audio.device));
+                    }
+                    CaptureSource::Microphone => {}
+                    CaptureSource::Monitor => {
+                        cmd.arg("--device=@DEFAULT_MONITOR@");
+                    }
+                }
+            }
+            RecorderBackend::Arecord => {
+                cmd.args(["-q", "-t", "raw"])
+                    .arg(format!("--format={}", arecord_format(&audio.format)?))
+                    .arg(format!("--rate={}", audio.sample_rate))
+                    .arg(format!("--channels={}", channels));
+
+                match source {
+                    CaptureSource::Microphone if audio.device != "default" && !audio.device.is_empty() => {
+                        cmd.arg(format!("--device={}", audio.device));
+                    }
+                    CaptureSource::Microphone => {}
+                    CaptureSource::Monitor => {
+                        return Err(AudioError::SpawnError(
+                            "arecord can't capture system audio; install pw-record or parec for audio.source = \"monitor\"".to_string(),
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(cmd)
+    }
+}
+
+fn parec_format(format: &str) -> Result<&'static str, AudioError> {
+    match format {
+        "u8" => Ok("u8"),
+        "s16" => Ok("s16le"),
+        "s24" => Ok("s24le"),
+        "s32" => Ok("s32le"),
+        "f32" => Ok("float32le"),
+        _ => Err(AudioError::SpawnError(format!("parec doesn't support audio.format = {:?}", format))),
+    }
+}
+
+fn arecord_format(format: &str) -> Result<&'static str, AudioError> {
+    match format {
+        "u8" => Ok("U8"),
+        "s16" => Ok("S16_LE"),
+        "s24" => Ok("S24_3LE"),
+        "s32" => Ok("S32_LE"),
+        "f32" => Ok("FLOAT_LE"),
+        "f64" => Ok("FLOAT64_LE"),
+        _ => Err(AudioError::SpawnError(format!("arecord doesn't support audio.format = {:?}", format))),
+    }
+}
@@ -0,0 +1,40 @@
+use super::resample::resample_linear;
+use nnnoiseless::DenoiseState;
+
+// RNNoise is trained on 48kHz audio and expects samples in the i16 range
+const RNNOISE_RATE: u32 = 48000;
+
+/// Run RNNoise over mono samples in the -1.0..1.0 range.
+///
+/// Audio at other rates is linearly resampled to 48kHz and back, which is
+/// plenty for speech going to Whisper.
+pub fn denoise(samples: &[f32], sample_rate: u32) -> Vec<f32> {
+    let upsampled = resample_linear(samples, sample_rate, RNNOISE_RATE);
+
+    let mut state = DenoiseState::new();
+    let mut output = Vec::with_capacity(upsampled.len());
+    let mut in_frame = [0.0f32; DenoiseState::FRAME_SIZE];
+    let mut out_frame = [0.0f32; DenoiseState::FRAME_SIZE];
+
+    for chunk in upsampled.chunks(DenoiseState::FRAME_SIZE) {
+        in_frame.fill(0.0);
+        for (dst, src) in in_frame.iter_mut().zip(chunk) {
+            *dst = src * 32768.0;
+        }
+        state.process_frame(&mut out_frame, &in_frame);
+        output.extend(out_frame[..chunk.len()].iter().map(|s| s / 32768.0));
+    }
+
+    resample_linear(&output, RNNOISE_RATE, sample_rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn denoise_preserves_length() {
+        let samples: Vec<f32> = (0..1600).map(|i| (i as f32 * 0.05).sin() * 0.2).collect();
+        assert_eq!(denoise(&samples, 16000).len(), samples.len());
+    }
+}
@@ -0,0 +1,195 @@
+use super::AudioError;
+use serde_json::Value;
+use std::process::Command;
+
+/// A PipeWire node that can be used as a recording source.
+#[derive(Debug, Clone)]
+pub struct CaptureDevice {
+    pub id: u64,
+    pub name: String,
+    pub description: String,
+    pub is_default: bool,
+}
+
+/// List PipeWire capture nodes by parsing `pw-dump` output.
+pub fn list_capture_devices() -> Result<Vec<CaptureDevice>, AudioError> {
+    let output = Command::new("pw-dump")
+        .output()
+        .map_err(|e| AudioError::SpawnError(format!("failed to run pw-dump: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(AudioError::SpawnError(format!(
+            "pw-dump failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let objects: Vec<Value> = serde_json::from_slice(&output.stdout)
+        .map_err(|e| AudioError::ReadError(format!("invalid pw-dump output: {}", e)))?;
+
+    Ok(parse_capture_devices(&objects))
+}
+
+/// Resolve `audio.device` to a pw-record `--target` value.
+///
+/// Accepts an exact node name, a node id, or a case-insensitive substring of
+/// the node name or description. Returns `None` to record from the default
+/// source.
+pub fn resolve_target(device: &str) -> Option<String> {
+    let device = device.trim();
+    if device.is_empty() || device == "default" {
+        return None;
+    }
+
+    match list_capture_devices() {
+        Ok(devices) => match match_device(&devices, device) {
+            Some(found) => {
+                tracing::debug!("Audio device {:?} resolved to {:?} ({})", device, found.name, found.description);
+                Some(found.name.clone())
+            }
+            None => {
+                tracing::warn!(
+                    "No capture device matches {:?}, using the default source. Run `croaker devices` to list devices.",
+                    device
+                );
+                None
+            }
+        },
+        Err(e) => {
+            // Without pw-dump we can't match substrings; pass the value through unchanged
+            tracing::debug!("Could not list capture devices ({}), using {:?} as-is", e, device);
+            Some(device.to_string())
+        }
+    }
+}
+
+/// Check whether the source `audio.device` resolves to is muted, via `wpctl`.
+///
+/// Returns `None` when the state can't be determined (no WirePlumber, unknown device).
+pub fn source_muted(device: &str) -> Option<bool> {
+    let device = device.trim();
+    let target = if device.is_empty() || device == "default" {
+        "@DEFAULT_AUDIO_SOURCE@".to_string()
+    } else {
+        // wpctl only takes node ids
+        let devices = list_capture_devices().ok()?;
+        match_device(&devices, device)?.id.to_string()
+    };
+
+    let output = Command::new("wpctl").args(["get-volume", &target]).output().ok()?;
+    if !output.status.success() {
+        tracing::debug!("wpctl get-volume failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+        return None;
+    }
+    parse_wpctl_muted(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse `wpctl get-volume` output, e.g. `Volume: 0.40 [MUTED]`.
+fn parse_wpctl_muted(output: &str) -> Option<bool> {
+    let output = output.trim();
+    if !output.starts_with("Volume:") {
+        return None;
+    }
+    Some(output.contains("[MUTED]"))
+}
+
+fn match_device<'a>(devices: &'a [CaptureDevice], query: &str) -> Option<&'a CaptureDevice> {
+    let query_lower = query.to_lowercase();
+    devices
+        .iter()
+        .find(|d| d.name == query || d.id.to_string() == query)
+        .or_else(|| {
+            devices.iter().find(|d| {
+                d.name.to_lowercase().contains(&query_lower)
+                    || d.description.to_lowercase().contains(&query_lower)
+            })
+        })
+}
+
+fn parse_capture_devices(objects: &[Value]) -> Vec<CaptureDevice> {
+    let default_source = objects
+        .iter()
+        .filter(|o| o["type"] == "PipeWire:Interface:Metadata" && o["props"]["metadata.name"] == "default")
+        .filter_map(|o| o["metadata"].as_array())
+        .flatten()
+        .find(|entry| entry["key"] == "default.audio.source")
+        .and_then(|entry| entry["value"]["name"].as_str())
+        .map(str::to_string);
+
+    objects
+        .iter()
+        .filter(|o| o["type"] == "PipeWire:Interface:Node")
+        .filter_map(|o| {
+            let props = &o["info"]["props"];
+            let class = props["media.class"].as_str()?;
+            if !class.starts_with("Audio/Source") {
+                return None;
+            }
+            let name = props["node.name"].as_str()?.to_string();
+            let description = props["node.description"]
+                .as_str()
+                .or_else(|| props["node.nick"].as_str())
+                .unwrap_or("")
+                .to_string();
+            Some(CaptureDevice {
+                id: o["id"].as_u64()?,
+                is_default: default_source.as_deref() == Some(name.as_str()),
+                name,
+                description,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_capture_devices_filters_sources_and_marks_default() {
+        let objects: Vec<Value> = serde_json::from_str(
+            r#"[
+                {"id": 30, "type": "PipeWire:Interface:Metadata", "props": {"metadata.name": "default"},
+                 "metadata": [{"key": "default.audio.source", "value": {"name": "alsa_input.usb"}}]},
+                {"id": 41, "type": "PipeWire:Interface:Node",
+                 "info": {"props": {"media.class": "Audio/Sink", "node.name": "alsa_output.pci"}}},
+                {"id": 42, "type": "PipeWire:Interface:Node",
+                 "info": {"props": {"media.class": "Audio/Source", "node.name": "alsa_input.usb", "node.description": "USB Mic"}}}
+            ]"#,
+        )
+        .unwrap();
+
+        let devices = parse_capture_devices(&objects);
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].id, 42);
+        assert_eq!(devices[0].name, "alsa_input.usb");
+        assert_eq!(devices[0].description, "USB Mic");
+        assert!(devices[0].is_default);
+    }
+
+    #[test]
+    fn match_device_prefers_exact_then_substring() {
+        let device = |id, name: &str, description: &str| CaptureDevice {
+            id,
+            name: name.to_string(),
+            description: description.to_string(),
+            is_default: false,
+        };
+        let devices = vec![
+            device(40, "alsa_input.pci-0000_00_1f.3.analog-stereo", "Built-in Audio Analog Stereo"),
+            device(41, "alsa_input.usb-Blue_Yeti", "Yeti Stereo Microphone"),
+        ];
+
+        assert_eq!(match_device(&devices, "41").unwrap().id, 41);
+        assert_eq!(match_device(&devices, "yeti").unwrap().id, 41);
+        assert_eq!(match_device(&devices, "Built-in").unwrap().id, 40);
+        assert!(match_device(&devices, "webcam").is_none());
+    }
+
+    #[test]
+    fn parse_wpctl_muted_reads_flag() {
+        assert_eq!(parse_wpctl_muted("Volume: 0.40 [MUTED]\n"), Some(true));
+        assert_eq!(parse_wpctl_muted("Volume: 1.00\n"), Some(false));
+        assert_eq!(parse_wpctl_muted("Translate ID/name failed"), None);
+    }
+}
@@ -0,0 +1,59 @@
+use super::{devices, AudioError};
+use std::process::Command;
+
+/// Node name of the echo-cancelled source croaker records from.
+pub const SOURCE_NAME: &str = "croaker-echo-cancel-source";
+/// Node name of the matching virtual sink. Playback routed through it is what
+/// gets cancelled, so it works best as the default output.
+pub const SINK_NAME: &str = "croaker-echo-cancel-sink";
+
+/// An echo-cancel module croaker loaded itself, unloaded again on drop.
+pub struct EchoCancel {
+    module_id: Option<u32>,
+}
+
+impl EchoCancel {
+    /// Make sure the echo-cancel source exists, loading PipeWire's echo-cancel
+    /// module through `pactl` (pipewire-pulse) if it doesn't.
+    ///
+    /// `device` is the `audio.device` value used as the microphone to clean up.
+    pub fn ensure(device: &str) -> Result<Self, AudioError> {
+        let existing = devices::list_capture_devices()
+            .map(|list| list.iter().any(|d| d.name == SOURCE_NAME))
+            .unwrap_or(false);
+        if existing {
+            tracing::debug!("Echo-cancel source {} already exists", SOURCE_NAME);
+            return Ok(Self { module_id: None });
+        }
+
+        let mut cmd = Command::new("pactl");
+        cmd.args(["load-module", "module-echo-cancel", "aec_method=webrtc"])
+            .arg(format!("source_name={}", SOURCE_NAME))
+            .arg(format!("sink_name={}", SINK_NAME));
+        if let Some(master) = devices::resolve_target(device) {
+            cmd.arg(format!("source_master={}", master));
+        }
+
+        let output = cmd
+            .output()
+            .map_err(|e| AudioError::SpawnError(format!("failed to run pactl: {}", e)))?;
+        if !output.status.success() {
+            return Err(AudioError::SpawnError(format!(
+                "failed to load module-echo-cancel: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        let module_id = String::from_utf8_lossy(&output.stdout).trim().parse().ok();
+        tracing::info!("Loaded echo-cancel module {:?} ({})", module_id, SOURCE_NAME);
+        Ok(Self { module_id })
+    }
+}
+
+impl Drop for EchoCancel {
+    fn drop(&mut self) {
+        if let Some(id) = self.module_id {
+            let _ = Command::new("pactl").args(["unload-module", &id.to_string()]).output();
+        }
+    }
+}
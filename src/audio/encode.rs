@@ -0,0 +1,73 @@
+use super::AudioError;
+use crate::config::UploadCodec;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command as TokioCommand;
+
+/// Audio ready to be attached to a transcription request.
+pub struct EncodedAudio {
+    pub data: Vec<u8>,
+    pub file_name: &'static str,
+    pub mime: &'static str,
+}
+
+impl EncodedAudio {
+    pub fn wav(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            file_name: "audio.wav",
+            mime: "audio/wav",
+        }
+    }
+}
+
+/// Encode WAV bytes for upload.
+///
+/// FLAC and Opus are produced by piping through ffmpeg, so no extra temp
+/// files are created. WAV is passed through untouched.
+pub async fn encode_for_upload(wav: Vec<u8>, codec: UploadCodec) -> Result<EncodedAudio, AudioError> {
+    let (format_args, file_name, mime): (&[&str], _, _) = match codec {
+        UploadCodec::Wav => return Ok(EncodedAudio::wav(wav)),
+        UploadCodec::Flac => (&["-c:a", "flac", "-f", "flac"], "audio.flac", "audio/flac"),
+        // Whisper only needs speech-quality audio; 24 kbit/s mono Opus is plenty
+        UploadCodec::Opus => (
+            &["-c:a", "libopus", "-b:a", "24k", "-application", "voip", "-f", "ogg"],
+            "audio.ogg",
+            "audio/ogg",
+        ),
+    };
+
+    let mut child = TokioCommand::new("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error", "-i", "pipe:0"])
+        .args(format_args)
+        .arg("pipe:1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| AudioError::EncodeError(format!("failed to run ffmpeg: {}", e)))?;
+
+    // Feed stdin from a separate task so a full stdout pipe can't deadlock us
+    let mut stdin = child.stdin.take().ok_or_else(|| AudioError::EncodeError("ffmpeg stdin unavailable".to_string()))?;
+    let writer = tokio::spawn(async move {
+        let _ = stdin.write_all(&wav).await;
+    });
+
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|e| AudioError::EncodeError(e.to_string()))?;
+    let _ = writer.await;
+
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(AudioError::EncodeError(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(EncodedAudio {
+        data: output.stdout,
+        file_name,
+        mime,
+    })
+}
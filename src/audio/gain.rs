@@ -0,0 +1,163 @@
+// Never boost by more than this, so near-silent recordings don't turn into loud hiss
+const MAX_GAIN_DB: f32 = 30.0;
+// Leave a little headroom below full scale when limiting by peak
+const PEAK_CEILING_DBFS: f32 = -1.0;
+
+/// RMS level of the samples in dBFS (-inf for silence).
+pub fn rms_dbfs(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return f32::NEG_INFINITY;
+    }
+    let mean_square = samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32;
+    10.0 * mean_square.log10()
+}
+
+// Samples this close to full scale are treated as clipped (16-bit full scale decodes to 0.99997)
+const CLIP_THRESHOLD: f32 = 0.999;
+
+/// Fraction of samples (0.0..=1.0) at or beyond full scale.
+pub fn clipped_fraction(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    samples.iter().filter(|s| s.abs() >= CLIP_THRESHOLD).count() as f32 / samples.len() as f32
+}
+
+// Long enough to average over a few pitch periods, short enough not to miss a word
+const FRAME_MS: u64 = 50;
+
+/// How long the end of a growing recording has stayed below a level, and how loud it is now,
+/// measured in 50 ms frames.
+///
+/// Fed only the samples captured since the last `push`, so checking a long recording every
+/// second doesn't mean decoding all of it again.
+pub struct SilenceTracker {
+    /// Interleaved samples per frame
+    frame: usize,
+    samples_per_second: u64,
+    threshold_dbfs: f32,
+    /// Samples that don't fill a frame yet
+    pending: Vec<f32>,
+    silent: u64,
+    /// RMS of the last complete frame
+    level_dbfs: f32,
+}
+
+impl SilenceTracker {
+    pub fn new(sample_rate: u32, channels: u16, threshold_dbfs: f32) -> Self {
+        Self {
+            frame: (sample_rate as u64 * FRAME_MS / 1000) as usize * channels.max(1) as usize,
+            samples_per_second: sample_rate as u64 * channels.max(1) as u64,
+            threshold_dbfs,
+            pending: Vec::new(),
+            silent: 0,
+            level_dbfs: f32::NEG_INFINITY,
+        }
+    }
+
+    /// Add the interleaved samples that follow the ones already pushed.
+    pub fn push(&mut self, samples: &[f32]) {
+        if self.frame == 0 {
+            return;
+        }
+        self.pending.extend_from_slice(samples);
+        let complete = self.pending.len() / self.frame * self.frame;
+        for frame in self.pending[..complete].chunks_exact(self.frame) {
+            self.level_dbfs = rms_dbfs(frame);
+            if self.level_dbfs > self.threshold_dbfs {
+                self.silent = 0;
+            } else {
+                self.silent += frame.len() as u64;
+            }
+        }
+        self.pending.drain(..complete);
+    }
+
+    /// Time since the last frame above the threshold, in ms.
+    pub fn silent_ms(&self) -> u64 {
+        if self.samples_per_second == 0 {
+            return 0;
+        }
+        self.silent * 1000 / self.samples_per_second
+    }
+
+    /// Level of the last 50 ms, in dBFS (-inf for digital silence or nothing yet).
+    pub fn level_dbfs(&self) -> f32 {
+        self.level_dbfs
+    }
+}
+
+/// Scale samples so their RMS level approaches `target_dbfs`.
+///
+/// The gain is capped so the loudest peak stays under -1 dBFS and never
+/// exceeds +30 dB. Returns the gain applied, in dB.
+pub fn normalize(samples: &mut [f32], target_dbfs: f32) -> f32 {
+    let current = rms_dbfs(samples);
+    if !current.is_finite() {
+        return 0.0;
+    }
+
+    let peak = samples.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+    let peak_dbfs = 20.0 * peak.log10();
+
+    let gain_db = (target_dbfs - current)
+        .min(PEAK_CEILING_DBFS - peak_dbfs)
+        .min(MAX_GAIN_DB);
+    let gain = 10f32.powf(gain_db / 20.0);
+
+    for sample in samples.iter_mut() {
+        *sample *= gain;
+    }
+
+    gain_db
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn silence_tracker_restarts_at_each_sound() {
+        // 1 s of tone, then 2 s of near silence, at 16 kHz mono, pushed in uneven pieces
+        let mut samples: Vec<f32> = (0..16000).map(|i| (i as f32 * 0.1).sin() * 0.3).collect();
+        samples.extend(std::iter::repeat_n(0.0001, 32000));
+        let mut tracker = SilenceTracker::new(16000, 1, -40.0);
+        assert_eq!(tracker.silent_ms(), 0);
+        for piece in samples.chunks(1234) {
+            tracker.push(piece);
+        }
+        assert_eq!(tracker.silent_ms(), 2000);
+        assert!(tracker.level_dbfs() < -70.0);
+        tracker.push(&samples[..800]);
+        assert_eq!(tracker.silent_ms(), 0);
+        assert!(tracker.level_dbfs() > -20.0);
+    }
+
+    #[test]
+    fn normalize_boosts_quiet_audio_towards_target() {
+        let mut samples: Vec<f32> = (0..1600).map(|i| (i as f32 * 0.1).sin() * 0.01).collect();
+        let gain = normalize(&mut samples, -20.0);
+        assert!(gain > 20.0);
+        assert!((rms_dbfs(&samples) + 20.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn normalize_respects_peak_ceiling() {
+        let mut samples = vec![0.0f32; 1000];
+        samples[0] = 0.5;
+        normalize(&mut samples, -3.0);
+        assert!(samples[0] <= 10f32.powf(PEAK_CEILING_DBFS / 20.0) + 1e-4);
+    }
+
+    #[test]
+    fn normalize_leaves_silence_alone() {
+        let mut samples = vec![0.0f32; 100];
+        assert_eq!(normalize(&mut samples, -20.0), 0.0);
+    }
+
+    #[test]
+    fn clipped_fraction_counts_full_scale_samples() {
+        assert_eq!(clipped_fraction(&[1.0, -1.0, 0.5, 0.0]), 0.5);
+        assert_eq!(clipped_fraction(&[]), 0.0);
+    }
+}
@@ -1,11 +1,35 @@
-use crate::config::Config;
-use std::path::PathBuf;
-use std::process::{Child, Command, Stdio};
+pub mod backend;
+pub mod denoise;
+pub mod devices;
+pub mod echo_cancel;
+pub mod encode;
+pub mod gain;
+pub mod preroll;
+pub mod resample;
+pub mod wav;
+
+use crate::config::{AudioConfig, CaptureSource, Config};
+use backend::RecorderBackend;
+use preroll::PrerollBuffer;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Stdio};
+use std::sync::{Arc, Mutex};
 use tempfile::NamedTempFile;
 use thiserror::Error;
 use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
 use tokio::time::Duration;
 
+/// Enough of a WAV file to reach the `data` chunk, even with the extra chunks pw-record adds
+const WAV_HEADER_LIMIT: u64 = 4096;
+
+/// Temp recordings are named croaker.rec-XXXXXX.wav (croaker-work.rec-XXXXXX.wav for a named
+/// instance), so ones left by a crash can be found. The dot keeps one instance's prefix from
+/// matching another's, as instance names can't contain one.
+pub fn temp_prefix() -> String {
+    format!("{}.rec-", crate::daemon::instance::file_stem())
+}
+
 #[derive(Debug, Error)]
 pub enum AudioError {
     #[error("Failed to spawn pw-record: {0}")]
@@ -16,6 +40,8 @@ pub enum AudioError {
     ReadError(String),
     #[error("Failed to create temp file: {0}")]
     TempFileError(String),
+    #[error("Failed to encode audio: {0}")]
+    EncodeError(String),
 }
 
 impl From<std::io::Error> for AudioError {
@@ -24,124 +50,375 @@ impl From<std::io::Error> for AudioError {
     }
 }
 
+/// A finished recording, either persisted to a temp file or held in memory.
+#[derive(Debug, Clone)]
+pub enum Recording {
+    File(PathBuf),
+    Memory(Vec<u8>),
+}
+
+impl Recording {
+    /// Read the complete WAV bytes.
+    pub async fn read(&self) -> Result<Vec<u8>, std::io::Error> {
+        match self {
+            Recording::File(path) => fs::read(path).await,
+            Recording::Memory(bytes) => Ok(bytes.clone()),
+        }
+    }
+
+    /// Copy the recording into `dir` as `<timestamp>.wav`.
+    pub async fn archive(&self, dir: &str) -> Result<PathBuf, std::io::Error> {
+        fs::create_dir_all(dir).await?;
+        let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S%.3f");
+        let dest = Path::new(dir).join(format!("{}.wav", stamp));
+        match self {
+            Recording::File(path) => fs::copy(path, &dest).await.map(|_| ())?,
+            Recording::Memory(bytes) => fs::write(&dest, bytes).await?,
+        }
+        Ok(dest)
+    }
+
+    /// Delete the backing file, if any.
+    pub async fn remove(&self) {
+        if let Recording::File(path) = self {
+            if let Err(e) = fs::remove_file(path).await {
+                tracing::warn!("Failed to remove audio file {:?}: {}", path, e);
+            }
+        }
+    }
+}
+
 pub struct AudioRecorder {
     config: Config,
+    backend: Option<RecorderBackend>,
+    // Kept alive so a module we loaded is unloaded when the recorder goes away
+    _echo_cancel: Option<echo_cancel::EchoCancel>,
     process: Option<Child>,
     temp_file: Option<NamedTempFile>,
+    memory_capture: Option<std::thread::JoinHandle<()>>,
+    memory_buffer: Option<Arc<Mutex<Vec<u8>>>>,
+    preroll: Option<PrerollBuffer>,
+    preroll_audio: Vec<u8>,
+    clipped_fraction: Option<f32>,
+    silence_watch: Option<SilenceWatch>,
+}
+
+/// How far `read_tail` has read into the recording in progress
+struct SilenceWatch {
+    format: wav::WavFormat,
+    /// Offset of the first byte not read yet, in the temp file or the memory buffer
+    offset: usize,
+    tracker: gain::SilenceTracker,
 }
 
 impl AudioRecorder {
-    pub fn new(config: Config) -> Self {
+    pub fn new(mut config: Config) -> Self {
+        let echo_cancel = if config.audio.echo_cancel {
+            match echo_cancel::EchoCancel::ensure(&config.audio.device) {
+                Ok(module) => {
+                    // Record from the cleaned-up source instead of the raw microphone
+                    config.audio.device = echo_cancel::SOURCE_NAME.to_string();
+                    Some(module)
+                }
+                Err(e) => {
+                    tracing::warn!("Echo cancellation unavailable: {} (recording the raw microphone)", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let backend = RecorderBackend::detect();
+        match backend {
+            Some(backend) => tracing::info!("Using {} for audio capture", backend.program()),
+            None => tracing::error!("No audio recorder found; install pw-record (PipeWire), parec (PulseAudio) or arecord (ALSA)"),
+        }
+
+        let preroll = match backend {
+            Some(backend) if config.audio.preroll_ms > 0 => match PrerollBuffer::start(&config, backend) {
+                Ok(buffer) => Some(buffer),
+                Err(e) => {
+                    tracing::warn!("Failed to start pre-roll buffer: {} (continuing without it)", e);
+                    None
+                }
+            },
+            _ => None,
+        };
+
         Self {
             config,
+            backend,
+            _echo_cancel: echo_cancel,
             process: None,
             temp_file: None,
+            memory_capture: None,
+            memory_buffer: None,
+            preroll,
+            preroll_audio: Vec::new(),
+            clipped_fraction: None,
+            silence_watch: None,
         }
     }
 
-    pub async fn start(&mut self) -> Result<(), AudioError> {
+    /// The recorder found at startup, if any.
+    pub fn backend(&self) -> Option<RecorderBackend> {
+        self.backend
+    }
+
+    pub async fn start(&mut self, source: CaptureSource) -> Result<(), AudioError> {
         if self.process.is_some() {
             tracing::warn!("Recording already in progress");
             return Ok(());
         }
 
-        // Create temporary WAV file
-        let temp_file = NamedTempFile::new().map_err(|e| AudioError::TempFileError(e.to_string()))?;
-        let wav_path = temp_file.path().to_path_buf();
-        self.temp_file = Some(temp_file);
+        self.silence_watch = None;
 
-        // Build pw-record command
-        // Note: --target=auto (default) will auto-select the default recording source
-        // Remove --target=0 as that means "don't link" and won't record anything!
-        let mut cmd = Command::new("pw-record");
-        cmd.arg(format!("--format={}", self.config.audio.format))
-            .arg(&format!("--rate={}", self.config.audio.sample_rate))
-            .arg("--channels=1")
-            .stdin(Stdio::null())
-            .stdout(Stdio::null())
-            .stderr(Stdio::piped()); // Capture stderr for debugging
+        // Grab the pre-roll before pw-record starts so it covers the spin-up gap.
+        // It only follows the configured source, so skip it when that's overridden.
+        self.preroll_audio = match &self.preroll {
+            Some(preroll) if source == self.config.audio.source => preroll.snapshot(),
+            _ => Vec::new(),
+        };
 
-        // Respect configured input device/source when provided.
-        if !self.config.audio.device.trim().is_empty()
-            && self.config.audio.device != "default"
-        {
-            cmd.arg("--target").arg(self.config.audio.device.trim());
+        let backend = self.backend.ok_or_else(|| {
+            AudioError::SpawnError("no recorder found (install pw-record, parec or arecord)".to_string())
+        })?;
+
+        if !self.config.audio.in_memory {
+            // Create temporary WAV file
+            let temp_file = tempfile::Builder::new()
+                .prefix(&temp_prefix())
+                .suffix(".wav")
+                .tempfile()
+                .map_err(|e| AudioError::TempFileError(e.to_string()))?;
+            self.temp_file = Some(temp_file);
         }
 
-        cmd.arg(wav_path.to_string_lossy().as_ref());
+        // Raw samples on stdout unless the recorder can write the temp file itself;
+        // the WAV header is added on stop
+        let output = self.temp_file.as_ref().map(|f| f.path()).filter(|_| backend.writes_wav());
+        let mut cmd = backend.command(&self.config.audio, source, output)?;
+        cmd.stderr(Stdio::piped()); // Capture stderr for debugging
+        cmd.stdout(if output.is_some() { Stdio::null() } else { Stdio::piped() });
+
+        tracing::debug!("Starting {}: {:?}", backend.program(), cmd);
 
-        tracing::debug!("Starting pw-record: {:?}", cmd);
+        let mut child = cmd.spawn().map_err(|e| AudioError::SpawnError(e.to_string()))?;
+
+        if let Some(mut stdout) = child.stdout.take() {
+            // Shared so chunked transcription can read the audio while we're still recording
+            let buffer = Arc::new(Mutex::new(Vec::new()));
+            let buffer_clone = buffer.clone();
+            self.memory_capture = Some(std::thread::spawn(move || {
+                let mut chunk = [0u8; 4096];
+                loop {
+                    match std::io::Read::read(&mut stdout, &mut chunk) {
+                        Ok(0) => break,
+                        Ok(n) => buffer_clone.lock().unwrap().extend_from_slice(&chunk[..n]),
+                        Err(e) => {
+                            tracing::warn!("In-memory capture read error: {}", e);
+                            break;
+                        }
+                    }
+                }
+            }));
+            self.memory_buffer = Some(buffer);
+        }
 
-        let child = cmd.spawn().map_err(|e| AudioError::SpawnError(e.to_string()))?;
         self.process = Some(child);
 
-        tracing::info!("Audio recording started");
+        tracing::info!("Audio recording started (source: {})", source.as_str());
         Ok(())
     }
 
-    pub async fn stop(&mut self) -> Result<PathBuf, AudioError> {
-        let mut process = self.process.take().ok_or_else(|| {
-            AudioError::ProcessTerminated
-        })?;
+    pub async fn stop(&mut self) -> Result<Recording, AudioError> {
+        let mut process = self.process.take().ok_or(AudioError::ProcessTerminated)?;
 
         // Send SIGINT to gracefully stop recording and flush the file
         // This is better than SIGKILL which doesn't give pw-record time to write
         if let Err(e) = process.kill() {
-            tracing::warn!("Failed to send signal to pw-record: {}", e);
+            tracing::warn!("Failed to send signal to recorder: {}", e);
         }
         
         // Wait for process to finish and flush the file
         // Use blocking wait in a spawn_blocking to avoid blocking the async runtime
         let wait_result = tokio::task::spawn_blocking(move || process.wait()).await;
         if let Ok(Ok(status)) = wait_result {
-            tracing::debug!("pw-record exited with status: {:?}", status);
+            tracing::debug!("Recorder exited with status: {:?}", status);
         }
 
-        // Give pw-record time to flush the file to disk
-        tokio::time::sleep(Duration::from_millis(500)).await;
-
-        let temp_file = self.temp_file.take().ok_or_else(|| {
-            AudioError::ProcessTerminated
-        })?;
+        // Streamed audio only exists in memory until it's written to the temp file below
+        let streamed = self.memory_buffer.is_some();
+        let mut bytes = if let Some(buffer) = self.memory_buffer.take() {
+            if let Some(capture) = self.memory_capture.take() {
+                let _ = tokio::task::spawn_blocking(move || capture.join()).await;
+            }
+            let pcm = std::mem::take(&mut *buffer.lock().unwrap());
+            wav::wrap_pcm(&self.raw_format(), &pcm)
+        } else {
+            // Give pw-record time to flush the file to disk
+            tokio::time::sleep(Duration::from_millis(500)).await;
 
-        let wav_path = temp_file.path().to_path_buf();
+            let temp_file = self.temp_file.as_ref().ok_or(AudioError::ProcessTerminated)?;
+            fs::read(temp_file.path()).await.map_err(|e| AudioError::ReadError(e.to_string()))?
+        };
 
-        // Verify file exists and has content
-        let metadata = fs::metadata(&wav_path).await.map_err(|e| AudioError::ReadError(e.to_string()))?;
-        if metadata.len() == 0 {
+        // Verify we actually captured something
+        if bytes.is_empty() || wav::find_data_chunk(&bytes).is_some_and(|c| c.payload_offset >= bytes.len()) {
+            self.cleanup(None).await;
             return Err(AudioError::ReadError("Audio file is empty".to_string()));
         }
 
-        tracing::info!("Audio recording stopped, file size: {} bytes", metadata.len());
+        tracing::info!("Audio recording stopped, size: {} bytes", bytes.len());
 
-        // Persist the temp file so it can be read later
-        // This prevents the file from being deleted when temp_file is dropped
-        temp_file.keep().map_err(|e| AudioError::ReadError(format!("Failed to persist temp file: {}", e)))?;
+        // Killing pw-record can leave stale sizes in the header; fix them from the real length
+        let mut modified = streamed || match wav::repair_header(&mut bytes) {
+            Some(repaired) => {
+                if repaired {
+                    tracing::warn!("Repaired WAV header sizes after pw-record exit");
+                }
+                repaired
+            }
+            None => {
+                self.cleanup(None).await;
+                return Err(AudioError::ReadError("Recording is not a valid WAV file".to_string()));
+            }
+        };
+        if !self.preroll_audio.is_empty() {
+            let preroll_audio = std::mem::take(&mut self.preroll_audio);
+            if wav::prepend_pcm(&mut bytes, &preroll_audio).is_some() {
+                tracing::debug!("Prepended {} bytes of pre-roll audio", preroll_audio.len());
+                modified = true;
+            } else {
+                tracing::warn!("Could not find WAV data chunk, skipping pre-roll");
+            }
+        }
+
+        // Measure before the filters, since normalization rescales the peaks
+        self.clipped_fraction = wav::read_samples(&bytes).map(|(_, samples)| gain::clipped_fraction(&samples));
+
+        if needs_filters(&self.config.audio) {
+            bytes = apply_filters(bytes, &self.config.audio).await?;
+            modified = true;
+        }
+
+        let recording = match self.temp_file.take() {
+            Some(temp_file) => {
+                let wav_path = temp_file.path().to_path_buf();
+                if modified {
+                    fs::write(&wav_path, &bytes).await.map_err(|e| AudioError::ReadError(e.to_string()))?;
+                }
+
+                // Persist the temp file so it can be read later
+                // This prevents the file from being deleted when temp_file is dropped
+                temp_file.keep().map_err(|e| AudioError::ReadError(format!("Failed to persist temp file: {}", e)))?;
+
+                Recording::File(wav_path)
+            }
+            None => Recording::Memory(bytes),
+        };
 
-        Ok(wav_path)
+        // Archive here, while the recording is guaranteed to still exist
+        if !self.config.audio.archive_dir.is_empty() {
+            match recording.archive(&self.config.audio.archive_dir).await {
+                Ok(path) => tracing::debug!("Archived recording to {:?}", path),
+                Err(e) => tracing::warn!("Failed to archive recording: {}", e),
+            }
+        }
+
+        Ok(recording)
     }
 
-    pub fn is_recording(&self) -> bool {
-        self.process.is_some()
+    /// Fraction of clipped samples in the last recording returned by `stop()`.
+    pub fn clipped_fraction(&self) -> Option<f32> {
+        self.clipped_fraction
     }
 
-    pub async fn cleanup(&mut self, wav_path: Option<&PathBuf>) {
+    /// Decode the audio captured so far, including any pre-roll, without stopping.
+    ///
+    /// Sample offsets line up with the recording returned by `stop()`.
+    pub async fn snapshot(&self) -> Option<(wav::WavFormat, Vec<f32>)> {
+        let mut bytes = match &self.memory_buffer {
+            Some(buffer) => {
+                let pcm = buffer.lock().unwrap().clone();
+                wav::wrap_pcm(&self.raw_format(), &pcm)
+            }
+            None => fs::read(self.temp_file.as_ref()?.path()).await.ok()?,
+        };
+        if !self.preroll_audio.is_empty() {
+            wav::prepend_pcm(&mut bytes, &self.preroll_audio)?;
+        }
+        wav::read_samples(&bytes)
+    }
+
+    /// Feed the audio captured since the last call to the recording's `SilenceTracker`, for how
+    /// long it has stayed below `threshold_dbfs` at its end and how loud it is now.
+    ///
+    /// Only the new audio is read, so this stays cheap however long the recording gets. `None`
+    /// until the recorder has written its header.
+    pub async fn read_tail(&mut self, threshold_dbfs: f32) -> Option<&gain::SilenceTracker> {
+        if self.silence_watch.is_none() {
+            let (format, offset) = match &self.memory_buffer {
+                Some(_) => (self.raw_format(), 0),
+                None => {
+                    let mut header = Vec::new();
+                    let file = fs::File::open(self.temp_file.as_ref()?.path()).await.ok()?;
+                    file.take(WAV_HEADER_LIMIT).read_to_end(&mut header).await.ok()?;
+                    (wav::parse_format(&header)?, wav::find_data_chunk(&header)?.payload_offset)
+                }
+            };
+            let mut tracker = gain::SilenceTracker::new(format.sample_rate, format.channels, threshold_dbfs);
+            tracker.push(&wav::decode_pcm(&self.raw_format(), &self.preroll_audio));
+            self.silence_watch = Some(SilenceWatch { format, offset, tracker });
+        }
+
+        let offset = self.silence_watch.as_ref()?.offset;
+        let appended = match &self.memory_buffer {
+            Some(buffer) => buffer.lock().unwrap().get(offset..).map(<[u8]>::to_vec).unwrap_or_default(),
+            None => {
+                let mut file = fs::File::open(self.temp_file.as_ref()?.path()).await.ok()?;
+                file.seek(std::io::SeekFrom::Start(offset as u64)).await.ok()?;
+                let mut appended = Vec::new();
+                file.read_to_end(&mut appended).await.ok()?;
+                appended
+            }
+        };
+
+        let watch = self.silence_watch.as_mut()?;
+        // A partly written frame is read again next time
+        let frame_bytes = watch.format.frame_bytes();
+        let complete = appended.len() / frame_bytes * frame_bytes;
+        watch.tracker.push(&wav::decode_pcm(&watch.format, &appended[..complete]));
+        watch.offset += complete;
+        Some(&watch.tracker)
+    }
+
+    fn raw_format(&self) -> wav::WavFormat {
+        wav::format_for(&self.config.audio.format, self.config.audio.sample_rate, self.config.audio.channels.max(1))
+    }
+
+    pub async fn cleanup(&mut self, recording: Option<&Recording>) {
         // Kill any running process
         if let Some(mut process) = self.process.take() {
             let _ = process.kill();
             let _ = process.wait();
         }
 
+        // The capture thread exits once pw-record's stdout closes
+        self.memory_capture.take();
+        self.memory_buffer.take();
+        self.preroll_audio.clear();
+        self.silence_watch = None;
+
         // Clean up temp file
         if let Some(temp_file) = self.temp_file.take() {
             let _ = temp_file.close();
         }
 
         // Remove WAV file if provided
-        if let Some(path) = wav_path {
-            if let Err(e) = fs::remove_file(path).await {
-                tracing::warn!("Failed to remove audio file {:?}: {}", path, e);
-            }
+        if let Some(recording) = recording {
+            recording.remove().await;
         }
     }
 }
@@ -155,3 +432,41 @@ impl Drop for AudioRecorder {
     }
 }
 
+pub fn needs_filters(audio: &AudioConfig) -> bool {
+    audio.denoise || audio.normalize || audio.channels > 1 || audio.sample_rate > resample::WHISPER_SAMPLE_RATE
+}
+
+/// Run the optional DSP stages over a WAV file's bytes.
+pub async fn apply_filters(bytes: Vec<u8>, audio: &AudioConfig) -> Result<Vec<u8>, AudioError> {
+    let audio = audio.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let Some((mut format, mut samples)) = wav::read_samples(&bytes) else {
+            tracing::warn!("Could not parse recorded WAV, skipping audio filters");
+            return bytes;
+        };
+        // Whisper only uses one channel, so don't upload (or denoise) the rest
+        if format.channels > 1 {
+            samples = wav::downmix(&samples, format.channels);
+            format.channels = 1;
+        }
+        if audio.denoise {
+            let started = std::time::Instant::now();
+            samples = denoise::denoise(&samples, format.sample_rate);
+            tracing::debug!("Denoised {} samples in {:?}", samples.len(), started.elapsed());
+        }
+        // After denoising, which works best at the device's native rate
+        if format.sample_rate > resample::WHISPER_SAMPLE_RATE {
+            samples = resample::downsample(&samples, format.sample_rate, resample::WHISPER_SAMPLE_RATE);
+            format.sample_rate = resample::WHISPER_SAMPLE_RATE;
+        }
+        // Normalize last so the gain is computed on speech rather than background noise
+        if audio.normalize {
+            let gain_db = gain::normalize(&mut samples, audio.normalize_target_dbfs);
+            tracing::debug!("Applied {:.1} dB of gain (target {} dBFS)", gain_db, audio.normalize_target_dbfs);
+        }
+        wav::write_wav(&format, &samples)
+    })
+    .await
+    .map_err(|e| AudioError::ReadError(e.to_string()))
+}
@@ -0,0 +1,82 @@
+use super::backend::RecorderBackend;
+use crate::config::Config;
+use std::collections::VecDeque;
+use std::io::Read;
+use std::process::{Child, Stdio};
+use std::sync::{Arc, Mutex};
+
+/// Continuously captures the microphone into a small ring buffer so the audio
+/// from just before push-to-talk was pressed can be prepended to the recording.
+///
+/// pw-record takes a few hundred milliseconds to spin up, which is usually
+/// enough to clip the first syllable.
+pub struct PrerollBuffer {
+    process: Child,
+    buffer: Arc<Mutex<VecDeque<u8>>>,
+    frame_size: usize,
+}
+
+impl PrerollBuffer {
+    pub fn start(config: &Config, backend: RecorderBackend) -> Result<Self, std::io::Error> {
+        let audio = &config.audio;
+        let frame_size = super::wav::bytes_per_sample(&audio.format) * audio.channels.max(1) as usize;
+        let capacity = (audio.sample_rate as usize * audio.preroll_ms as usize / 1000) * frame_size;
+
+        let mut cmd = backend
+            .command(audio, audio.source, None)
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        cmd.stdout(Stdio::piped()).stderr(Stdio::null());
+
+        let mut process = cmd.spawn()?;
+        let mut stdout = process.stdout.take().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::BrokenPipe, "recorder stdout unavailable")
+        })?;
+
+        let buffer = Arc::new(Mutex::new(VecDeque::with_capacity(capacity)));
+        let buffer_clone = buffer.clone();
+        std::thread::spawn(move || {
+            let mut chunk = [0u8; 4096];
+            loop {
+                match stdout.read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        let mut ring = buffer_clone.lock().unwrap();
+                        ring.extend(&chunk[..n]);
+                        // Only drop whole frames from the front so the ring stays sample-aligned
+                        let excess = ring.len().saturating_sub(capacity);
+                        let excess = (excess.div_ceil(frame_size) * frame_size).min(ring.len());
+                        ring.drain(..excess);
+                    }
+                    Err(e) => {
+                        tracing::warn!("Pre-roll capture stopped: {}", e);
+                        break;
+                    }
+                }
+            }
+            tracing::debug!("Pre-roll capture thread exiting");
+        });
+
+        tracing::info!("Pre-roll buffer started ({} ms)", audio.preroll_ms);
+
+        Ok(Self {
+            process,
+            buffer,
+            frame_size,
+        })
+    }
+
+    /// Copy out the buffered audio, trimmed to whole frames.
+    pub fn snapshot(&self) -> Vec<u8> {
+        let ring = self.buffer.lock().unwrap();
+        // The last read may have ended mid-sample; leave the partial frame out
+        let len = ring.len() - ring.len() % self.frame_size;
+        ring.iter().take(len).copied().collect()
+    }
+}
+
+impl Drop for PrerollBuffer {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
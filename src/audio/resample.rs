@@ -0,0 +1,64 @@
+/// Whisper resamples everything to 16kHz server-side, so anything above that is wasted upload.
+pub const WHISPER_SAMPLE_RATE: u32 = 16000;
+
+/// Linear-interpolation resampler for mono audio.
+pub fn resample_linear(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || samples.is_empty() {
+        return samples.to_vec();
+    }
+
+    let out_len = (samples.len() as u64 * to_rate as u64 / from_rate as u64) as usize;
+    let step = from_rate as f64 / to_rate as f64;
+    (0..out_len)
+        .map(|i| {
+            let pos = i as f64 * step;
+            let idx = pos as usize;
+            let frac = (pos - idx as f64) as f32;
+            let a = samples[idx.min(samples.len() - 1)];
+            let b = samples[(idx + 1).min(samples.len() - 1)];
+            a + (b - a) * frac
+        })
+        .collect()
+}
+
+/// Downsample mono audio, low-passing first so content above the new Nyquist
+/// frequency doesn't alias back into the speech band.
+pub fn downsample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if to_rate >= from_rate {
+        return resample_linear(samples, from_rate, to_rate);
+    }
+
+    // A moving average over one output period is a crude but adequate filter for speech
+    let width = (from_rate as f64 / to_rate as f64).ceil() as usize;
+    let mut filtered = Vec::with_capacity(samples.len());
+    let mut sum = 0.0f32;
+    for (i, &sample) in samples.iter().enumerate() {
+        sum += sample;
+        if i >= width {
+            sum -= samples[i - width];
+        }
+        filtered.push(sum / (i + 1).min(width) as f32);
+    }
+
+    resample_linear(&filtered, from_rate, to_rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resample_linear_changes_length_by_ratio() {
+        let samples = vec![0.0; 16000];
+        assert_eq!(resample_linear(&samples, 16000, 48000).len(), 48000);
+        assert_eq!(resample_linear(&samples, 48000, 16000).len(), 5333);
+    }
+
+    #[test]
+    fn downsample_keeps_dc_level() {
+        let samples = vec![0.5; 48000];
+        let out = downsample(&samples, 48000, WHISPER_SAMPLE_RATE);
+        assert_eq!(out.len(), 16000);
+        assert!(out.iter().all(|s| (s - 0.5).abs() < 1e-4));
+    }
+}
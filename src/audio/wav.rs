@@ -0,0 +1,317 @@
+// Minimal helpers for editing the WAV files written by pw-record.
+// We only ever deal with PCM WAV, so this avoids a full parser: find the
+// `data` chunk, splice bytes, fix the sizes.
+
+/// Location of the `data` chunk inside a WAV file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataChunk {
+    /// Offset of the 4-byte size field that follows the `data` tag
+    pub size_offset: usize,
+    /// Offset of the first PCM byte
+    pub payload_offset: usize,
+}
+
+/// Find the `data` chunk by walking the RIFF chunk list.
+pub fn find_data_chunk(bytes: &[u8]) -> Option<DataChunk> {
+    find_chunk(bytes, b"data").map(|(payload_offset, _)| DataChunk {
+        size_offset: payload_offset - 4,
+        payload_offset,
+    })
+}
+
+/// Rewrite the RIFF and `data` size fields so they match the buffer length.
+pub fn fix_sizes(bytes: &mut [u8], chunk: DataChunk) {
+    let riff_size = (bytes.len() - 8) as u32;
+    bytes[4..8].copy_from_slice(&riff_size.to_le_bytes());
+
+    let data_size = (bytes.len() - chunk.payload_offset) as u32;
+    bytes[chunk.size_offset..chunk.size_offset + 4].copy_from_slice(&data_size.to_le_bytes());
+}
+
+/// Check the RIFF and `data` sizes against the actual length and fix them if they disagree.
+///
+/// pw-record only writes the final sizes when it shuts down cleanly, so a killed
+/// recording can be left with a zero (or 0xFFFFFFFF) data length that Groq rejects.
+/// Returns `None` if the bytes aren't a WAV file, otherwise whether anything changed.
+pub fn repair_header(bytes: &mut [u8]) -> Option<bool> {
+    let chunk = find_data_chunk(bytes)?;
+    let riff_size = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+    let data_size = u32::from_le_bytes([
+        bytes[chunk.size_offset],
+        bytes[chunk.size_offset + 1],
+        bytes[chunk.size_offset + 2],
+        bytes[chunk.size_offset + 3],
+    ]) as usize;
+
+    if riff_size == bytes.len() - 8 && data_size == bytes.len() - chunk.payload_offset {
+        return Some(false);
+    }
+    fix_sizes(bytes, chunk);
+    Some(true)
+}
+
+/// Insert raw PCM frames at the start of the `data` chunk.
+///
+/// `pcm` must already be in the same sample format as the file.
+pub fn prepend_pcm(bytes: &mut Vec<u8>, pcm: &[u8]) -> Option<()> {
+    let chunk = find_data_chunk(bytes)?;
+    bytes.splice(chunk.payload_offset..chunk.payload_offset, pcm.iter().copied());
+    fix_sizes(bytes, chunk);
+    Some(())
+}
+
+/// Sample layout read from the `fmt ` chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WavFormat {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub bits_per_sample: u16,
+    pub float: bool,
+}
+
+impl WavFormat {
+    fn bytes_per_sample(&self) -> usize {
+        (self.bits_per_sample as usize).div_ceil(8)
+    }
+
+    /// Bytes in one sample for every channel.
+    pub fn frame_bytes(&self) -> usize {
+        self.bytes_per_sample() * self.channels.max(1) as usize
+    }
+}
+
+fn find_chunk(bytes: &[u8], tag: &[u8; 4]) -> Option<(usize, usize)> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return None;
+    }
+
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let size = u32::from_le_bytes([bytes[pos + 4], bytes[pos + 5], bytes[pos + 6], bytes[pos + 7]]) as usize;
+        if &bytes[pos..pos + 4] == tag {
+            return Some((pos + 8, size));
+        }
+        pos += 8 + size + (size & 1);
+    }
+
+    None
+}
+
+/// Parse the `fmt ` chunk. Handles plain PCM, IEEE float and WAVE_FORMAT_EXTENSIBLE.
+pub fn parse_format(bytes: &[u8]) -> Option<WavFormat> {
+    let (offset, size) = find_chunk(bytes, b"fmt ")?;
+    if size < 16 || offset + 16 > bytes.len() {
+        return None;
+    }
+    let fmt = &bytes[offset..];
+    let mut format_tag = u16::from_le_bytes([fmt[0], fmt[1]]);
+    let channels = u16::from_le_bytes([fmt[2], fmt[3]]);
+    let sample_rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+    let bits_per_sample = u16::from_le_bytes([fmt[14], fmt[15]]);
+
+    // WAVE_FORMAT_EXTENSIBLE keeps the real format tag in the sub-format GUID
+    if format_tag == 0xFFFE && size >= 26 && offset + 26 <= bytes.len() {
+        format_tag = u16::from_le_bytes([fmt[24], fmt[25]]);
+    }
+
+    Some(WavFormat {
+        channels: channels.max(1),
+        sample_rate,
+        bits_per_sample,
+        float: format_tag == 3,
+    })
+}
+
+/// Decode the `data` chunk into interleaved samples in the -1.0..1.0 range.
+pub fn read_samples(bytes: &[u8]) -> Option<(WavFormat, Vec<f32>)> {
+    let format = parse_format(bytes)?;
+    let (offset, size) = find_chunk(bytes, b"data")?;
+    // pw-record can leave the size at 0 (or 0xFFFFFFFF) when it's killed; trust the file length then
+    let end = if size == 0 || offset + size > bytes.len() {
+        bytes.len()
+    } else {
+        offset + size
+    };
+    Some((format, decode_pcm(&format, &bytes[offset..end])))
+}
+
+/// Decode raw PCM bytes in `format` into interleaved samples in the -1.0..1.0 range.
+pub fn decode_pcm(format: &WavFormat, data: &[u8]) -> Vec<f32> {
+    let width = format.bytes_per_sample();
+    data.chunks_exact(width)
+        .map(|s| match (format.float, width) {
+            (true, 4) => f32::from_le_bytes([s[0], s[1], s[2], s[3]]),
+            (true, 8) => f64::from_le_bytes([s[0], s[1], s[2], s[3], s[4], s[5], s[6], s[7]]) as f32,
+            (_, 1) => (s[0] as f32 - 128.0) / 128.0,
+            (_, 3) => (i32::from_le_bytes([0, s[0], s[1], s[2]]) >> 8) as f32 / 8_388_608.0,
+            (_, 4) => i32::from_le_bytes([s[0], s[1], s[2], s[3]]) as f32 / 2_147_483_648.0,
+            _ => i16::from_le_bytes([s[0], s[1]]) as f32 / 32768.0,
+        })
+        .collect()
+}
+
+/// Length of the audio in milliseconds.
+pub fn duration_ms(bytes: &[u8]) -> Option<u64> {
+    let format = parse_format(bytes)?;
+    let (offset, size) = find_chunk(bytes, b"data")?;
+    let size = if size == 0 || offset + size > bytes.len() { bytes.len() - offset } else { size };
+    let frame_bytes = (format.bytes_per_sample() * format.channels as usize).max(1) as u64;
+    Some(size as u64 / frame_bytes * 1000 / format.sample_rate.max(1) as u64)
+}
+
+/// Describe the raw stream pw-record produces for a `--format` value.
+pub fn format_for(format: &str, sample_rate: u32, channels: u16) -> WavFormat {
+    WavFormat {
+        channels,
+        sample_rate,
+        bits_per_sample: (bytes_per_sample(format) * 8) as u16,
+        float: format.starts_with('f'),
+    }
+}
+
+/// Put a WAV header in front of raw PCM bytes.
+pub fn wrap_pcm(format: &WavFormat, pcm: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(44 + pcm.len());
+    write_header(format, pcm.len(), &mut bytes);
+    bytes.extend_from_slice(pcm);
+    bytes
+}
+
+fn write_header(format: &WavFormat, data_len: usize, bytes: &mut Vec<u8>) {
+    let block_align = format.channels as usize * format.bytes_per_sample();
+    let format_tag: u16 = if format.float { 3 } else { 1 };
+
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&((36 + data_len) as u32).to_le_bytes());
+    bytes.extend_from_slice(b"WAVE");
+    bytes.extend_from_slice(b"fmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&format_tag.to_le_bytes());
+    bytes.extend_from_slice(&format.channels.to_le_bytes());
+    bytes.extend_from_slice(&format.sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&((format.sample_rate as usize * block_align) as u32).to_le_bytes());
+    bytes.extend_from_slice(&(block_align as u16).to_le_bytes());
+    bytes.extend_from_slice(&format.bits_per_sample.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&(data_len as u32).to_le_bytes());
+}
+
+/// Encode samples into a canonical 44-byte-header WAV file using `format`.
+pub fn write_wav(format: &WavFormat, samples: &[f32]) -> Vec<u8> {
+    let width = format.bytes_per_sample();
+    let data_len = samples.len() * width;
+
+    let mut bytes = Vec::with_capacity(44 + data_len);
+    write_header(format, data_len, &mut bytes);
+
+    for &sample in samples {
+        let s = sample.clamp(-1.0, 1.0);
+        match (format.float, width) {
+            (true, 4) => bytes.extend_from_slice(&sample.to_le_bytes()),
+            (true, 8) => bytes.extend_from_slice(&(sample as f64).to_le_bytes()),
+            (_, 1) => bytes.push((s * 127.0 + 128.0) as u8),
+            (_, 3) => bytes.extend_from_slice(&((s * 8_388_607.0) as i32).to_le_bytes()[..3]),
+            (_, 4) => bytes.extend_from_slice(&((s as f64 * 2_147_483_647.0) as i32).to_le_bytes()),
+            _ => bytes.extend_from_slice(&((s * 32767.0) as i16).to_le_bytes()),
+        }
+    }
+
+    bytes
+}
+
+/// Average interleaved multi-channel samples down to mono.
+pub fn downmix(samples: &[f32], channels: u16) -> Vec<f32> {
+    let channels = channels.max(1) as usize;
+    samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect()
+}
+
+/// Bytes per sample for a pw-record `--format` value.
+pub fn bytes_per_sample(format: &str) -> usize {
+    match format {
+        "u8" | "s8" => 1,
+        "s24" => 3,
+        "s32" | "f32" => 4,
+        "f64" => 8,
+        _ => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav_with_data(data: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&((36 + data.len()) as u32).to_le_bytes());
+        bytes.extend_from_slice(b"WAVE");
+        bytes.extend_from_slice(b"fmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&[1, 0, 1, 0]); // PCM, mono
+        bytes.extend_from_slice(&16000u32.to_le_bytes());
+        bytes.extend_from_slice(&32000u32.to_le_bytes());
+        bytes.extend_from_slice(&[2, 0, 16, 0]);
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    #[test]
+    fn find_data_chunk_skips_fmt() {
+        let wav = wav_with_data(&[1, 2, 3, 4]);
+        let chunk = find_data_chunk(&wav).unwrap();
+        assert_eq!(chunk.payload_offset, 44);
+        assert_eq!(&wav[chunk.payload_offset..], &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn prepend_pcm_updates_sizes() {
+        let mut wav = wav_with_data(&[3, 4]);
+        prepend_pcm(&mut wav, &[1, 2]).unwrap();
+        assert_eq!(&wav[44..], &[1, 2, 3, 4]);
+        assert_eq!(u32::from_le_bytes([wav[40], wav[41], wav[42], wav[43]]), 4);
+        assert_eq!(u32::from_le_bytes([wav[4], wav[5], wav[6], wav[7]]) as usize, wav.len() - 8);
+    }
+
+    #[test]
+    fn samples_round_trip_through_write_wav() {
+        let format = WavFormat {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            float: false,
+        };
+        let wav = write_wav(&format, &[0.0, 0.5, -0.5]);
+        let (parsed, samples) = read_samples(&wav).unwrap();
+        assert_eq!(parsed, format);
+        assert_eq!(samples.len(), 3);
+        assert!((samples[1] - 0.5).abs() < 1e-3);
+        assert!((samples[2] + 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn repair_header_fixes_zero_sizes() {
+        let mut wav = wav_with_data(&[1, 2, 3, 4]);
+        wav[4..8].copy_from_slice(&0u32.to_le_bytes());
+        wav[40..44].copy_from_slice(&0u32.to_le_bytes());
+        assert_eq!(repair_header(&mut wav), Some(true));
+        assert_eq!(u32::from_le_bytes([wav[40], wav[41], wav[42], wav[43]]), 4);
+        assert_eq!(u32::from_le_bytes([wav[4], wav[5], wav[6], wav[7]]) as usize, wav.len() - 8);
+        assert_eq!(repair_header(&mut wav), Some(false));
+    }
+
+    #[test]
+    fn downmix_averages_frames() {
+        assert_eq!(downmix(&[1.0, 0.0, 0.5, 0.5, -1.0, 1.0], 2), vec![0.5, 0.5, 0.0]);
+        assert_eq!(downmix(&[0.25, 0.75], 1), vec![0.25, 0.75]);
+    }
+
+    #[test]
+    fn find_data_chunk_rejects_non_wav() {
+        assert!(find_data_chunk(b"not a wav file at all").is_none());
+    }
+}
@@ -0,0 +1,139 @@
+// API key in the freedesktop Secret Service (`groq.key_source = "keyring"`): GNOME Keyring,
+// KWallet or KeePassXC keep it encrypted instead of a plaintext file under ~/.config. The item
+// is found by the attributes `service = croaker` and `account = <groq.keyring_account>`, the
+// same ones `secret-tool store --label=croaker service croaker account groq` sets.
+
+use std::collections::HashMap;
+use thiserror::Error;
+use zbus::blocking::Connection;
+use zbus::proxy;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+
+const SERVICE_ATTRIBUTE: &str = "croaker";
+/// Where `store` puts the key: the collection the user unlocks at login
+const DEFAULT_COLLECTION: &str = "/org/freedesktop/secrets/aliases/default";
+/// Returned instead of a prompt when none is needed
+const NO_PROMPT: &str = "/";
+
+/// A secret as the Secret Service sends it: session, parameters, value and content type
+type Secret = (OwnedObjectPath, Vec<u8>, Vec<u8>, String);
+
+#[derive(Debug, Error)]
+pub enum KeyringError {
+    #[error("Secret Service not available: {0}")]
+    BusError(#[from] zbus::Error),
+    #[error("No API key for account {0:?} in the keyring (store one with `croaker configure`)")]
+    NotFoundError(String),
+    #[error("Unlocking the keyring was dismissed")]
+    DismissedError,
+}
+
+#[proxy(
+    interface = "org.freedesktop.Secret.Service",
+    default_service = "org.freedesktop.secrets",
+    default_path = "/org/freedesktop/secrets"
+)]
+trait Service {
+    fn open_session(&self, algorithm: &str, input: &Value<'_>) -> zbus::Result<(OwnedValue, OwnedObjectPath)>;
+
+    fn search_items(
+        &self,
+        attributes: HashMap<&str, &str>,
+    ) -> zbus::Result<(Vec<OwnedObjectPath>, Vec<OwnedObjectPath>)>;
+
+    fn unlock(&self, objects: &[ObjectPath<'_>]) -> zbus::Result<(Vec<OwnedObjectPath>, OwnedObjectPath)>;
+
+    fn get_secrets(
+        &self,
+        items: &[ObjectPath<'_>],
+        session: &ObjectPath<'_>,
+    ) -> zbus::Result<HashMap<OwnedObjectPath, Secret>>;
+}
+
+#[proxy(interface = "org.freedesktop.Secret.Collection", default_service = "org.freedesktop.secrets")]
+trait Collection {
+    fn create_item(
+        &self,
+        properties: HashMap<&str, Value<'_>>,
+        secret: &Secret,
+        replace: bool,
+    ) -> zbus::Result<(OwnedObjectPath, OwnedObjectPath)>;
+}
+
+#[proxy(interface = "org.freedesktop.Secret.Prompt", default_service = "org.freedesktop.secrets")]
+trait Prompt {
+    fn prompt(&self, window_id: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn completed(&self, dismissed: bool, result: OwnedValue) -> zbus::Result<()>;
+}
+
+fn attributes(account: &str) -> HashMap<&str, &str> {
+    HashMap::from([("service", SERVICE_ATTRIBUTE), ("account", account)])
+}
+
+/// An unencrypted session: the key only travels over the user's own session bus
+fn open_session(service: &ServiceProxyBlocking) -> Result<OwnedObjectPath, KeyringError> {
+    let (_, session) = service.open_session("plain", &Value::from(""))?;
+    Ok(session)
+}
+
+/// Let the keyring ask for its password, if `prompt` is a real one, and wait for the answer.
+fn run_prompt(connection: &Connection, prompt: OwnedObjectPath) -> Result<(), KeyringError> {
+    if prompt.as_str() == NO_PROMPT {
+        return Ok(());
+    }
+    let prompt = PromptProxyBlocking::builder(connection).path(prompt)?.build()?;
+    // Subscribe first so the answer can't arrive before we listen
+    let mut completed = prompt.receive_completed()?;
+    prompt.prompt("")?;
+    let signal = completed.next().ok_or(KeyringError::DismissedError)?;
+    if signal.args()?.dismissed {
+        return Err(KeyringError::DismissedError);
+    }
+    Ok(())
+}
+
+/// Fetch the API key stored for `account`, unlocking the keyring if needed.
+pub fn lookup(account: &str) -> Result<String, KeyringError> {
+    let connection = Connection::session()?;
+    let service = ServiceProxyBlocking::new(&connection)?;
+    let (unlocked, locked) = service.search_items(attributes(account))?;
+    let item = match (unlocked.into_iter().next(), locked.into_iter().next()) {
+        (Some(item), _) => item,
+        (None, Some(item)) => {
+            let (_, prompt) = service.unlock(&[item.as_ref()])?;
+            run_prompt(&connection, prompt)?;
+            item
+        }
+        (None, None) => return Err(KeyringError::NotFoundError(account.to_string())),
+    };
+
+    let session = open_session(&service)?;
+    let mut secrets = service.get_secrets(&[item.as_ref()], &session.as_ref())?;
+    let (_, _, value, _) = secrets
+        .remove(&item)
+        .ok_or_else(|| KeyringError::NotFoundError(account.to_string()))?;
+    Ok(String::from_utf8_lossy(&value).trim().to_string())
+}
+
+/// Store `key` for `account` in the default collection, replacing the one there.
+pub fn store(account: &str, key: &str) -> Result<(), KeyringError> {
+    let connection = Connection::session()?;
+    let service = ServiceProxyBlocking::new(&connection)?;
+    let session = open_session(&service)?;
+    let collection = CollectionProxyBlocking::builder(&connection).path(DEFAULT_COLLECTION)?.build()?;
+
+    let label = format!("croaker API key ({})", account);
+    let attributes: HashMap<String, String> = attributes(account)
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    let properties = HashMap::from([
+        ("org.freedesktop.Secret.Item.Label", Value::from(label)),
+        ("org.freedesktop.Secret.Item.Attributes", Value::from(attributes)),
+    ]);
+    let secret: Secret = (session, Vec::new(), key.as_bytes().to_vec(), "text/plain".to_string());
+    let (_, prompt) = collection.create_item(properties, &secret, true)?;
+    run_prompt(&connection, prompt)
+}
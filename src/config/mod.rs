@@ -1,6 +1,11 @@
+pub mod keyring;
+pub mod validate;
+
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -27,7 +32,7 @@ impl From<toml::de::Error> for ConfigError {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default)]
     pub general: GeneralConfig,
@@ -36,11 +41,92 @@ pub struct Config {
     #[serde(default)]
     pub audio: AudioConfig,
     #[serde(default)]
+    pub transcription: TranscriptionConfig,
+    #[serde(default)]
     pub groq: GroqConfig,
     #[serde(default)]
+    pub vosk: VoskConfig,
+    #[serde(default)]
+    pub mock: MockConfig,
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    #[serde(default)]
+    pub history: HistoryConfig,
+    #[serde(default)]
     pub output: OutputConfig,
     #[serde(default)]
+    pub remote: RemoteConfig,
+    #[serde(default)]
     pub overlay: OverlayConfig,
+    #[serde(default)]
+    pub watchdog: WatchdogConfig,
+    #[serde(default)]
+    pub stats: StatsConfig,
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    #[serde(default)]
+    pub accessibility: AccessibilityConfig,
+    /// Per-application overrides, first matching profile wins
+    #[serde(default)]
+    pub app_profiles: Vec<AppProfile>,
+    /// Named session profiles (`[profiles.email]`), switched at runtime
+    #[serde(default)]
+    pub profiles: BTreeMap<String, SessionProfile>,
+}
+
+/// Settings applied while dictating into specific applications.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppProfile {
+    /// App ids / window classes this profile applies to (case-insensitive)
+    pub apps: Vec<String>,
+    #[serde(default)]
+    pub output_mode: Option<OutputMode>,
+    #[serde(default)]
+    pub paste_keys: Option<String>,
+    #[serde(default)]
+    pub keystroke_delay_ms: Option<u64>,
+    #[serde(default)]
+    pub typing_burst_chars: Option<usize>,
+    #[serde(default)]
+    pub chunk_delay_ms: Option<u64>,
+    #[serde(default)]
+    pub paste_chunk_chars: Option<usize>,
+    /// `false` outputs the raw transcript in this app
+    #[serde(default)]
+    pub cleanup_enabled: Option<bool>,
+    /// Cleanup prompt to use instead of `groq.cleanup_prompt_file`
+    #[serde(default)]
+    pub cleanup_prompt_file: Option<String>,
+    /// Confirm text before it's output in this app (overrides `output.preview`)
+    #[serde(default)]
+    pub preview: Option<bool>,
+}
+
+impl AppProfile {
+    pub fn matches(&self, app: &str) -> bool {
+        self.apps.iter().any(|a| a.eq_ignore_ascii_case(app))
+    }
+}
+
+/// Settings for a kind of dictation ("email", "coding", "notes"), active until another profile
+/// is chosen. Unset fields keep the global settings; app profiles still win in their apps.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionProfile {
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub output_mode: Option<OutputMode>,
+    #[serde(default)]
+    pub cleanup_enabled: Option<bool>,
+    /// Cleanup prompt to use instead of `groq.cleanup_prompt_file`
+    #[serde(default)]
+    pub cleanup_prompt_file: Option<String>,
+    /// Where the "file" output mode appends (instead of `output.file_path`)
+    #[serde(default)]
+    pub file_path: Option<String>,
+    /// What the "command" output mode runs (instead of `output.command`)
+    #[serde(default)]
+    pub command: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,12 +135,59 @@ pub struct GeneralConfig {
     pub language: String,
     #[serde(default = "default_languages")]
     pub languages: Vec<String>,
+    #[serde(default)]
+    pub translate: bool,
+    /// Turn spoken commands ("new line", "comma", "all caps") into formatting
+    #[serde(default)]
+    pub voice_commands: bool,
+    /// Word/regex replacements applied after cleanup; re-read when the file changes
+    #[serde(default = "default_replacements_file")]
+    pub replacements_file: String,
+    /// Turn ":thumbs up:" and "thumbs up emoji" into 👍
+    #[serde(default)]
+    pub emoji: bool,
+    /// Extra emoji names (`[emoji] name = "…"`); re-read when the file changes
+    #[serde(default = "default_emoji_file")]
+    pub emoji_file: String,
+    /// Continue the previous dictation when the next one goes into the same window:
+    /// separating space, lowercase start when the previous one ended mid-sentence
+    #[serde(default = "default_true")]
+    pub smart_join: bool,
+    /// How long after an output the next dictation still counts as a continuation
+    #[serde(default = "default_smart_join_window_secs")]
+    pub smart_join_window_secs: u64,
+    /// Daemon log, rotated daily (croaker.2026-01-31.log, ...); empty for stderr only
+    #[serde(default = "default_log_file")]
+    pub log_file: String,
+    /// Rotated log files kept, the oldest are deleted
+    #[serde(default = "default_log_max_files")]
+    pub log_max_files: usize,
+}
+
+fn default_log_file() -> String {
+    "~/.local/state/croaker/croaker.log".to_string()
+}
+
+fn default_log_max_files() -> usize {
+    7
+}
+
+fn default_smart_join_window_secs() -> u64 {
+    30
 }
 
 fn default_language() -> String {
     "en".to_string()
 }
 
+fn default_replacements_file() -> String {
+    "~/.config/croaker/replacements.toml".to_string()
+}
+
+fn default_emoji_file() -> String {
+    "~/.config/croaker/emoji.toml".to_string()
+}
+
 fn default_languages() -> Vec<String> {
     vec!["en".to_string(), "tr".to_string(), "es".to_string(), "fr".to_string(), "de".to_string()]
 }
@@ -62,39 +195,116 @@ fn default_languages() -> Vec<String> {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HotkeyConfig {
     #[serde(default = "default_push_to_talk_key")]
-    pub push_to_talk_key: String,
+    pub push_to_talk_key: KeyBindings,
     #[serde(default = "default_true")]
     pub push_to_talk_enabled: bool,
     #[serde(default = "default_toggle_shortcut")]
-    pub toggle_shortcut: String,
+    pub toggle_shortcut: KeyBindings,
     #[serde(default = "default_true")]
     pub toggle_enabled: bool,
     #[serde(default = "default_cancel_shortcut")]
-    pub cancel_shortcut: String,
+    pub cancel_shortcut: KeyBindings,
     #[serde(default = "default_output_mode_shortcut")]
-    pub output_mode_shortcut: String,
+    pub output_mode_shortcut: KeyBindings,
     #[serde(default = "default_language_shortcut")]
-    pub language_shortcut: String,
+    pub language_shortcut: KeyBindings,
+    #[serde(default = "default_translate_shortcut")]
+    pub translate_shortcut: KeyBindings,
+    #[serde(default = "default_raw_shortcut")]
+    pub raw_shortcut: KeyBindings,
+    /// Cycles through the `[profiles]`, then back to none
+    #[serde(default = "default_profile_shortcut")]
+    pub profile_shortcut: KeyBindings,
+    /// Only monitor this device for the evdev hotkeys: a path (e.g. /dev/input/by-id/...-event-kbd)
+    /// or part of the device name. Empty: every device that looks like a keyboard
+    #[serde(default)]
+    pub device: String,
+    /// Double-tap push-to-talk to keep recording without holding the key; press again to stop
+    #[serde(default)]
+    pub double_tap_lock: bool,
+    /// Longest press, and longest gap between the presses, that count as a double tap
+    #[serde(default = "default_double_tap_ms")]
+    pub double_tap_ms: u64,
+    /// Push-to-talk presses shorter than this cancel instead of transcribing (0: off)
+    #[serde(default = "default_min_hold_ms")]
+    pub min_hold_ms: u64,
+    /// Bind the toggle/cancel shortcuts with hyprctl/swaymsg instead of the GlobalShortcuts portal
+    #[serde(default)]
+    pub compositor_bindings: bool,
+    /// Game controller button used as push-to-talk (empty: off, needs the `gamepad` feature)
+    #[serde(default)]
+    pub gamepad_button: String,
 }
 
-fn default_push_to_talk_key() -> String {
-    "RightAlt".to_string()
+/// One or more hotkeys for the same action: `"RightAlt"` or `["RightAlt", "KEY_F13"]`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum KeyBindings {
+    One(String),
+    Many(Vec<String>),
 }
 
-fn default_toggle_shortcut() -> String {
-    "Super+Shift+R".to_string()
+impl KeyBindings {
+    /// The bindings, without empty ones
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        let keys = match self {
+            KeyBindings::One(key) => std::slice::from_ref(key),
+            KeyBindings::Many(keys) => keys.as_slice(),
+        };
+        keys.iter().map(String::as_str).filter(|key| !key.trim().is_empty())
+    }
 }
 
-fn default_cancel_shortcut() -> String {
-    "Escape".to_string()
+impl From<&str> for KeyBindings {
+    fn from(key: &str) -> Self {
+        KeyBindings::One(key.to_string())
+    }
+}
+
+impl std::fmt::Display for KeyBindings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.iter().collect::<Vec<_>>().join(", "))
+    }
+}
+
+fn default_push_to_talk_key() -> KeyBindings {
+    KeyBindings::from("RightAlt")
+}
+
+fn default_toggle_shortcut() -> KeyBindings {
+    KeyBindings::from("Super+Shift+R")
+}
+
+fn default_cancel_shortcut() -> KeyBindings {
+    KeyBindings::from("Escape")
+}
+
+fn default_output_mode_shortcut() -> KeyBindings {
+    KeyBindings::from("Shift+RightAlt+O")
+}
+
+fn default_language_shortcut() -> KeyBindings {
+    KeyBindings::from("Shift+RightAlt+L")
 }
 
-fn default_output_mode_shortcut() -> String {
-    "Shift+RightAlt+O".to_string()
+fn default_translate_shortcut() -> KeyBindings {
+    KeyBindings::from("Shift+RightAlt+T")
 }
 
-fn default_language_shortcut() -> String {
-    "Shift+RightAlt+L".to_string()
+fn default_raw_shortcut() -> KeyBindings {
+    KeyBindings::from("Shift+RightAlt+R")
+}
+
+fn default_profile_shortcut() -> KeyBindings {
+    KeyBindings::from("Shift+RightAlt+P")
+}
+
+fn default_double_tap_ms() -> u64 {
+    300
+}
+
+fn default_min_hold_ms() -> u64 {
+    150
 }
 
 fn default_true() -> bool {
@@ -109,6 +319,72 @@ pub struct AudioConfig {
     pub sample_rate: u32,
     #[serde(default = "default_format")]
     pub format: String,
+    #[serde(default = "default_channels")]
+    pub channels: u16,
+    #[serde(default)]
+    pub source: CaptureSource,
+    #[serde(default = "default_preroll_ms")]
+    pub preroll_ms: u32,
+    #[serde(default = "default_upload_codec")]
+    pub upload_codec: UploadCodec,
+    #[serde(default)]
+    pub denoise: bool,
+    #[serde(default)]
+    pub echo_cancel: bool,
+    #[serde(default)]
+    pub in_memory: bool,
+    #[serde(default)]
+    pub normalize: bool,
+    #[serde(default = "default_normalize_target_dbfs")]
+    pub normalize_target_dbfs: f32,
+    #[serde(default = "default_clip_warn_percent")]
+    pub clip_warn_percent: f32,
+    #[serde(default)]
+    pub chunk_seconds: u32,
+    #[serde(default = "default_chunk_overlap_ms")]
+    pub chunk_overlap_ms: u32,
+    #[serde(default)]
+    pub idle_stop_secs: u32,
+    #[serde(default)]
+    pub archive_dir: String,
+}
+
+/// What to record: the microphone, or whatever is playing on the default output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptureSource {
+    #[default]
+    Microphone,
+    Monitor,
+}
+
+impl CaptureSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CaptureSource::Microphone => "microphone",
+            CaptureSource::Monitor => "monitor",
+        }
+    }
+}
+
+impl std::str::FromStr for CaptureSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "microphone" | "mic" => Ok(CaptureSource::Microphone),
+            "monitor" => Ok(CaptureSource::Monitor),
+            other => Err(format!("unknown capture source {:?} (expected \"microphone\" or \"monitor\")", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UploadCodec {
+    Wav,
+    Flac,
+    Opus,
 }
 
 fn default_device() -> String {
@@ -123,12 +399,244 @@ fn default_format() -> String {
     "s16".to_string()
 }
 
+fn default_channels() -> u16 {
+    1
+}
+
+fn default_preroll_ms() -> u32 {
+    // Disabled by default: the pre-roll keeps the microphone open while idle
+    0
+}
+
+fn default_normalize_target_dbfs() -> f32 {
+    -20.0
+}
+
+fn default_clip_warn_percent() -> f32 {
+    1.0
+}
+
+fn default_chunk_overlap_ms() -> u32 {
+    1000
+}
+
+fn default_upload_codec() -> UploadCodec {
+    UploadCodec::Wav
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptionConfig {
+    #[serde(default = "default_provider")]
+    pub provider: String,
+    #[serde(default = "default_true")]
+    pub offline_queue: bool,
+    #[serde(default = "default_spool_dir")]
+    pub spool_dir: String,
+    #[serde(default = "default_retry_interval_secs")]
+    pub retry_interval_secs: u64,
+    /// Recordings a crashed daemon left in the temp directory are transcribed through the
+    /// offline queue on the next start, instead of deleted
+    #[serde(default)]
+    pub recover_orphaned: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoskConfig {
+    /// Unpacked model from https://alphacephei.com/vosk/models
+    #[serde(default = "default_vosk_model_dir")]
+    pub model_dir: String,
+}
+
+fn default_vosk_model_dir() -> String {
+    "~/.local/share/croaker/vosk-model".to_string()
+}
+
+/// Fake backend for `[transcription] provider = "mock"` (`croaker serve --mock`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockConfig {
+    /// What every recording transcribes to
+    #[serde(default = "default_mock_text")]
+    pub text: String,
+    /// How long transcription, and cleanup when enabled, each take
+    #[serde(default = "default_mock_delay_ms")]
+    pub delay_ms: u64,
+}
+
+fn default_mock_text() -> String {
+    "This is a mock transcription.".to_string()
+}
+
+fn default_mock_delay_ms() -> u64 {
+    500
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_true")]
+    pub emails: bool,
+    #[serde(default = "default_true")]
+    pub phone_numbers: bool,
+    #[serde(default = "default_true")]
+    pub credit_cards: bool,
+    /// Extra regexes whose matches are replaced with `[redacted]`
+    #[serde(default)]
+    pub patterns: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_history_db")]
+    pub db_path: String,
+}
+
+/// How long each stage may take before it's considered hung and cancelled (0: no limit).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchdogConfig {
+    #[serde(default = "default_watchdog_recording_secs")]
+    pub recording_secs: u64,
+    #[serde(default = "default_watchdog_processing_secs")]
+    pub processing_secs: u64,
+    #[serde(default = "default_watchdog_output_secs")]
+    pub output_secs: u64,
+}
+
+fn default_watchdog_recording_secs() -> u64 {
+    900
+}
+
+fn default_watchdog_processing_secs() -> u64 {
+    180
+}
+
+fn default_watchdog_output_secs() -> u64 {
+    60
+}
+
+/// Runtime metrics (`croaker stats`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StatsConfig {
+    /// host:port to serve the metrics on for Prometheus, empty for none
+    #[serde(default)]
+    pub prometheus_listen: String,
+}
+
+/// Shell commands run on lifecycle events, empty for none.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HooksConfig {
+    #[serde(default)]
+    pub on_start_recording: String,
+    /// Also run when a recording is cancelled
+    #[serde(default)]
+    pub on_stop_recording: String,
+    /// Gets the text on stdin
+    #[serde(default)]
+    pub on_transcription: String,
+    /// Gets the reason on stdin
+    #[serde(default)]
+    pub on_error: String,
+}
+
+/// Spoken feedback through speech-dispatcher, for using croaker without looking at the screen.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccessibilityConfig {
+    /// Say "Recording", "Processing" and errors as they happen
+    #[serde(default)]
+    pub announce_states: bool,
+    /// Read out each dictation
+    #[serde(default)]
+    pub announce_text: bool,
+    /// Whether the dictation is read out before or after it's written
+    #[serde(default)]
+    pub announce_text_when: ReadBack,
+    #[serde(default)]
+    pub speech_engine: SpeechEngine,
+    /// Piper voice (.onnx) for `speech_engine = "piper"`
+    #[serde(default)]
+    pub piper_model: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadBack {
+    /// As soon as the text is ready, while a preview or countdown waits
+    Before,
+    #[default]
+    After,
+}
+
+/// What speaks: speech-dispatcher (`spd-say`), or Piper played through `pw-play`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SpeechEngine {
+    #[default]
+    SpeechDispatcher,
+    Piper,
+}
+
+/// Sending text to / receiving it from croaker on another machine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteConfig {
+    /// Where `output_mode = "remote"` sends text: host:port or a Unix socket path
+    #[serde(default)]
+    pub address: String,
+    /// Shared secret, the same on both machines
+    #[serde(default)]
+    pub token: String,
+    /// Where `croaker receive` listens
+    #[serde(default = "default_remote_listen")]
+    pub listen: String,
+}
+
+fn default_remote_listen() -> String {
+    "127.0.0.1:7731".to_string()
+}
+
+fn default_history_db() -> String {
+    "~/.local/share/croaker/history.db".to_string()
+}
+
+fn default_provider() -> String {
+    "groq".to_string()
+}
+
+fn default_spool_dir() -> String {
+    "~/.cache/croaker/spool".to_string()
+}
+
+fn default_retry_interval_secs() -> u64 {
+    30
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GroqConfig {
+    #[serde(default)]
+    pub key_source: KeySource,
     #[serde(default = "default_key_file")]
     pub key_file: String,
+    /// Account the key is stored under in the keyring (`key_source = "keyring"`)
+    #[serde(default = "default_keyring_account")]
+    pub keyring_account: String,
+    #[serde(default = "default_base_url")]
+    pub base_url: String,
+    #[serde(default)]
+    pub cleanup_base_url: String,
     #[serde(default = "default_whisper_model")]
     pub whisper_model: String,
+    #[serde(default = "default_translation_model")]
+    pub translation_model: String,
+    /// Sent as Whisper's `prompt` to bias spelling of names and jargon
+    #[serde(default)]
+    pub whisper_prompt: String,
+    /// File with one term per line, appended to `whisper_prompt`
+    #[serde(default)]
+    pub glossary_file: String,
+    /// Drop transcripts whose segments all have a higher no-speech probability (0 disables)
+    #[serde(default = "default_no_speech_threshold")]
+    pub no_speech_threshold: f64,
     #[serde(default = "default_true")]
     pub cleanup_enabled: bool,
     #[serde(default = "default_cleanup_model")]
@@ -137,16 +645,45 @@ pub struct GroqConfig {
     pub cleanup_prompt_file: String,
     #[serde(default = "default_cleanup_temperature")]
     pub cleanup_temperature: f64,
+    /// Stream the cleanup response and output each sentence as soon as it's complete
+    #[serde(default)]
+    pub cleanup_streaming: bool,
+}
+
+/// Where the API key is kept: `groq.key_file`, or the Secret Service keyring
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeySource {
+    #[default]
+    File,
+    Keyring,
 }
 
 fn default_key_file() -> String {
     "~/.config/croaker/groq.key".to_string()
 }
 
+fn default_keyring_account() -> String {
+    "groq".to_string()
+}
+
+fn default_base_url() -> String {
+    "https://api.groq.com/openai/v1".to_string()
+}
+
 fn default_whisper_model() -> String {
     "whisper-large-v3-turbo".to_string()
 }
 
+fn default_translation_model() -> String {
+    // Groq's turbo model doesn't support the translations endpoint
+    "whisper-large-v3".to_string()
+}
+
+fn default_no_speech_threshold() -> f64 {
+    0.6
+}
+
 fn default_cleanup_model() -> String {
     "openai/gpt-oss-120b".to_string()
 }
@@ -165,22 +702,169 @@ pub enum OutputMode {
     Direct,
     Clipboard,
     Both,
+    /// Type the whole text with `wtype`, without touching the clipboard
+    #[serde(rename = "wtype-type")]
+    WtypeType,
+    /// Append to `output.file_path`
+    File,
+    /// Only emit the `TranscriptionReady` D-Bus signal
+    Dbus,
+    /// Pipe the text into `output.command`
+    Command,
+    /// Send the text to `croaker receive` at `remote.address`
+    Remote,
+}
+
+impl OutputMode {
+    /// Name as written in the config file
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OutputMode::Direct => "direct",
+            OutputMode::Clipboard => "clipboard",
+            OutputMode::Both => "both",
+            OutputMode::WtypeType => "wtype-type",
+            OutputMode::File => "file",
+            OutputMode::Dbus => "dbus",
+            OutputMode::Command => "command",
+            OutputMode::Remote => "remote",
+        }
+    }
+
+    /// Whether text can be written piece by piece (long dictation chunks, streamed cleanup).
+    /// The clipboard would only keep the last piece, and a notes file would get one entry per piece.
+    pub fn is_incremental(self) -> bool {
+        !matches!(
+            self,
+            OutputMode::Clipboard | OutputMode::File | OutputMode::Dbus | OutputMode::Command | OutputMode::Remote
+        )
+    }
+
+    /// Whether the text goes into the focused window (typed or pasted).
+    pub fn inserts_text(self) -> bool {
+        matches!(self, OutputMode::Direct | OutputMode::Both | OutputMode::WtypeType)
+    }
+}
+
+impl std::str::FromStr for OutputMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        [
+            OutputMode::Direct,
+            OutputMode::Clipboard,
+            OutputMode::Both,
+            OutputMode::WtypeType,
+            OutputMode::File,
+            OutputMode::Dbus,
+            OutputMode::Command,
+            OutputMode::Remote,
+        ]
+        .into_iter()
+        .find(|mode| mode.as_str() == s)
+        .ok_or_else(|| format!("unknown output mode {:?} (expected direct, clipboard, both, wtype-type, file, dbus, command or remote)", s))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OutputConfig {
     #[serde(default = "default_keystroke_delay")]
     pub keystroke_delay_ms: u64,
-    #[serde(default = "default_true")]
+    /// Type this many characters, then pause `chunk_delay_ms` (0: no pauses)
+    #[serde(default)]
+    pub typing_burst_chars: usize,
+    /// Pause between typing bursts and between paste chunks
+    #[serde(default = "default_chunk_delay")]
+    pub chunk_delay_ms: u64,
+    /// Paste long text in pieces of this many characters (0: all at once)
+    #[serde(default)]
+    pub paste_chunk_chars: usize,
+    /// Put the previous clipboard contents back after pasting (both mode)
+    #[serde(default)]
     pub clipboard_restore: bool,
+    /// How long to wait after pasting before restoring, so the app has read our text
+    #[serde(default = "default_clipboard_restore_delay")]
+    pub clipboard_restore_delay_ms: u64,
     #[serde(default = "default_output_mode")]
     pub output_mode: OutputMode,
+    /// Type through zwp_virtual_keyboard_v1 when the compositor supports it
+    #[serde(default = "default_true")]
+    pub virtual_keyboard: bool,
+    /// Wait after creating the uinput keyboard so the compositor picks it up before the first key
+    #[serde(default = "default_uinput_settle_ms")]
+    pub uinput_settle_ms: u64,
+    /// Show the text with Accept/Edit/Discard actions before outputting it
+    #[serde(default)]
+    pub preview: bool,
+    /// Preview in an editable window, then Insert or Copy only, instead of a notification
+    #[serde(default)]
+    pub preview_window: bool,
+    /// Seconds to count down on the overlay before typing, to switch to the target window
+    #[serde(default)]
+    pub countdown_secs: u32,
+    /// Key combination sent to paste, e.g. "ctrl+v" or "shift+insert"
+    #[serde(default = "default_paste_keys")]
+    pub paste_keys: String,
+    /// Paste keys for specific applications, by app id / window class (case-insensitive)
+    #[serde(default = "default_app_paste_keys")]
+    pub app_paste_keys: HashMap<String, String>,
+    /// XKB layout for typing through uinput; empty asks the compositor
+    #[serde(default)]
+    pub keyboard_layout: String,
+    #[serde(default)]
+    pub keyboard_variant: String,
+    /// Notes file for the "file" output mode; strftime placeholders allowed
+    #[serde(default)]
+    pub file_path: String,
+    /// Entry appended to `file_path`: {text}, {date} and {time} are filled in
+    #[serde(default = "default_file_template")]
+    pub file_template: String,
+    /// Shell command for the "command" output mode, gets the text on stdin
+    #[serde(default)]
+    pub command: String,
+}
+
+fn default_file_template() -> String {
+    "- {time} {text}".to_string()
 }
 
 fn default_keystroke_delay() -> u64 {
     5
 }
 
+fn default_chunk_delay() -> u64 {
+    100
+}
+
+fn default_uinput_settle_ms() -> u64 {
+    200
+}
+
+fn default_clipboard_restore_delay() -> u64 {
+    300
+}
+
+fn default_paste_keys() -> String {
+    "ctrl+v".to_string()
+}
+
+/// App ids of terminal emulators, where Ctrl+V and Ctrl+Z belong to the shell
+const TERMINAL_APPS: &[&str] =
+    &["alacritty", "foot", "kitty", "org.wezfurlong.wezterm", "org.kde.konsole", "konsole", "org.gnome.terminal"];
+
+/// Whether `app` (an app id or window class) is a terminal emulator.
+pub fn is_terminal(app: &str) -> bool {
+    TERMINAL_APPS.iter().any(|terminal| terminal.eq_ignore_ascii_case(app))
+}
+
+fn default_app_paste_keys() -> HashMap<String, String> {
+    // Terminals reserve Ctrl+V for the shell
+    TERMINAL_APPS
+        .iter()
+        .map(|app| (app.to_string(), "ctrl+shift+v".to_string()))
+        .collect()
+}
+
 fn default_output_mode() -> OutputMode {
     OutputMode::Both
 }
@@ -191,12 +875,138 @@ pub struct OverlayConfig {
     pub enabled: bool,
     #[serde(default = "default_backend")]
     pub backend: String,
+    // NOTE: position, size and opacity are for a floating on-screen overlay, which no backend
+    // draws yet (the tray and notifications are placed by the desktop). Setting them only warns
     #[serde(default = "default_position")]
     pub position: String,
     #[serde(default = "default_size")]
     pub size: u32,
     #[serde(default = "default_opacity")]
     pub opacity: f64,
+    /// Seconds an error stays shown before going back to idle
+    #[serde(default = "default_error_secs")]
+    pub error_secs: u64,
+    /// Offer to retry a failed dictation in a notification
+    #[serde(default = "default_true")]
+    pub retry_notification: bool,
+    /// Briefly show the first line and word count of each dictation once it's written
+    #[serde(default = "default_true")]
+    pub show_transcription: bool,
+    /// What the notification backend shows per state (`[overlay.notifications.recording]`)
+    #[serde(default)]
+    pub notifications: NotificationTemplates,
+    /// Pipe the "wob" backend writes to; empty = `$XDG_RUNTIME_DIR/wob.sock`
+    #[serde(default)]
+    pub wob_pipe: String,
+    /// Keyboard LED lit while recording ("scrolllock", "capslock", ...), empty for none.
+    /// Works with `enabled = false` too
+    #[serde(default)]
+    pub keyboard_led: String,
+    /// Tray icon color per state (`[overlay.colors]`)
+    #[serde(default)]
+    pub colors: OverlayColors,
+}
+
+/// Colors as "#rrggbb".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OverlayColors {
+    #[serde(default = "default_idle_color")]
+    pub idle: String,
+    /// Idle with hotkeys paused
+    #[serde(default = "default_paused_color")]
+    pub paused: String,
+    #[serde(default = "default_recording_color")]
+    pub recording: String,
+    #[serde(default = "default_processing_color")]
+    pub processing: String,
+    #[serde(default = "default_outputting_color")]
+    pub outputting: String,
+    #[serde(default = "default_error_color")]
+    pub error: String,
+}
+
+impl Default for OverlayColors {
+    fn default() -> Self {
+        Self {
+            idle: default_idle_color(),
+            paused: default_paused_color(),
+            recording: default_recording_color(),
+            processing: default_processing_color(),
+            outputting: default_outputting_color(),
+            error: default_error_color(),
+        }
+    }
+}
+
+pub fn default_idle_color() -> String {
+    "#808080".to_string()
+}
+
+pub fn default_paused_color() -> String {
+    "#3c3c3c".to_string()
+}
+
+pub fn default_recording_color() -> String {
+    "#ff3c3c".to_string()
+}
+
+pub fn default_processing_color() -> String {
+    "#ffb43c".to_string()
+}
+
+pub fn default_outputting_color() -> String {
+    "#3cc83c".to_string()
+}
+
+pub fn default_error_color() -> String {
+    "#c828a0".to_string()
+}
+
+/// Notification backend messages per state. Unset fields keep the built-in text.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NotificationTemplates {
+    #[serde(default)]
+    pub recording: NotificationTemplate,
+    #[serde(default)]
+    pub processing: NotificationTemplate,
+    #[serde(default)]
+    pub outputting: NotificationTemplate,
+    #[serde(default)]
+    pub error: NotificationTemplate,
+}
+
+/// Title, body and icon of a notification. Title and body take the placeholders {state},
+/// {language}, {mode}, {duration}, {chars} and {reason}
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NotificationTemplate {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    /// Icon name from the icon theme or a path to an image
+    #[serde(default)]
+    pub icon: Option<String>,
+}
+
+impl OverlayConfig {
+    /// Placement settings changed from their defaults, which no backend can honor yet.
+    pub fn unsupported_settings(&self) -> Vec<&'static str> {
+        let mut set = Vec::new();
+        if self.position != default_position() {
+            set.push("position");
+        }
+        if self.size != default_size() {
+            set.push("size");
+        }
+        if self.opacity != default_opacity() {
+            set.push("opacity");
+        }
+        set
+    }
+}
+
+fn default_error_secs() -> u64 {
+    5
 }
 
 fn default_backend() -> String {
@@ -215,24 +1025,20 @@ fn default_opacity() -> f64 {
     0.9
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            general: GeneralConfig::default(),
-            hotkeys: HotkeyConfig::default(),
-            audio: AudioConfig::default(),
-            groq: GroqConfig::default(),
-            output: OutputConfig::default(),
-            overlay: OverlayConfig::default(),
-        }
-    }
-}
-
 impl Default for GeneralConfig {
     fn default() -> Self {
         Self {
             language: default_language(),
             languages: default_languages(),
+            translate: false,
+            voice_commands: false,
+            replacements_file: default_replacements_file(),
+            emoji: false,
+            emoji_file: default_emoji_file(),
+            smart_join: true,
+            smart_join_window_secs: default_smart_join_window_secs(),
+            log_file: default_log_file(),
+            log_max_files: default_log_max_files(),
         }
     }
 }
@@ -247,6 +1053,15 @@ impl Default for HotkeyConfig {
             cancel_shortcut: default_cancel_shortcut(),
             output_mode_shortcut: default_output_mode_shortcut(),
             language_shortcut: default_language_shortcut(),
+            translate_shortcut: default_translate_shortcut(),
+            raw_shortcut: default_raw_shortcut(),
+            profile_shortcut: default_profile_shortcut(),
+            device: String::new(),
+            double_tap_lock: false,
+            double_tap_ms: default_double_tap_ms(),
+            min_hold_ms: default_min_hold_ms(),
+            compositor_bindings: false,
+            gamepad_button: String::new(),
         }
     }
 }
@@ -257,19 +1072,131 @@ impl Default for AudioConfig {
             device: default_device(),
             sample_rate: default_sample_rate(),
             format: default_format(),
+            channels: default_channels(),
+            source: CaptureSource::default(),
+            preroll_ms: default_preroll_ms(),
+            upload_codec: default_upload_codec(),
+            denoise: false,
+            echo_cancel: false,
+            in_memory: false,
+            normalize: false,
+            normalize_target_dbfs: default_normalize_target_dbfs(),
+            clip_warn_percent: default_clip_warn_percent(),
+            chunk_seconds: 0,
+            chunk_overlap_ms: default_chunk_overlap_ms(),
+            idle_stop_secs: 0,
+            archive_dir: String::new(),
+        }
+    }
+}
+
+impl Default for TranscriptionConfig {
+    fn default() -> Self {
+        Self {
+            provider: default_provider(),
+            offline_queue: default_true(),
+            spool_dir: default_spool_dir(),
+            retry_interval_secs: default_retry_interval_secs(),
+            recover_orphaned: false,
+        }
+    }
+}
+
+impl Default for VoskConfig {
+    fn default() -> Self {
+        Self {
+            model_dir: default_vosk_model_dir(),
+        }
+    }
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        Self {
+            text: default_mock_text(),
+            delay_ms: default_mock_delay_ms(),
+        }
+    }
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            emails: default_true(),
+            phone_numbers: default_true(),
+            credit_cards: default_true(),
+            patterns: Vec::new(),
+        }
+    }
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self {
+            address: String::new(),
+            token: String::new(),
+            listen: default_remote_listen(),
+        }
+    }
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_true(),
+            db_path: default_history_db(),
+        }
+    }
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            recording_secs: default_watchdog_recording_secs(),
+            processing_secs: default_watchdog_processing_secs(),
+            output_secs: default_watchdog_output_secs(),
         }
     }
 }
 
+impl GroqConfig {
+    pub fn transcription_url(&self) -> String {
+        format!("{}/audio/transcriptions", self.base_url.trim_end_matches('/'))
+    }
+
+    pub fn translation_url(&self) -> String {
+        format!("{}/audio/translations", self.base_url.trim_end_matches('/'))
+    }
+
+    pub fn chat_completions_url(&self) -> String {
+        let base = if self.cleanup_base_url.is_empty() {
+            &self.base_url
+        } else {
+            &self.cleanup_base_url
+        };
+        format!("{}/chat/completions", base.trim_end_matches('/'))
+    }
+}
+
 impl Default for GroqConfig {
     fn default() -> Self {
         Self {
+            key_source: KeySource::default(),
             key_file: default_key_file(),
+            keyring_account: default_keyring_account(),
+            base_url: default_base_url(),
+            cleanup_base_url: String::new(),
             whisper_model: default_whisper_model(),
+            translation_model: default_translation_model(),
+            whisper_prompt: String::new(),
+            glossary_file: String::new(),
+            no_speech_threshold: default_no_speech_threshold(),
             cleanup_enabled: default_true(),
             cleanup_model: default_cleanup_model(),
             cleanup_prompt_file: default_cleanup_prompt_file(),
             cleanup_temperature: default_cleanup_temperature(),
+            cleanup_streaming: false,
         }
     }
 }
@@ -278,8 +1205,24 @@ impl Default for OutputConfig {
     fn default() -> Self {
         Self {
             keystroke_delay_ms: default_keystroke_delay(),
-            clipboard_restore: default_true(),
+            typing_burst_chars: 0,
+            chunk_delay_ms: default_chunk_delay(),
+            paste_chunk_chars: 0,
+            clipboard_restore: false,
+            clipboard_restore_delay_ms: default_clipboard_restore_delay(),
             output_mode: default_output_mode(),
+            virtual_keyboard: default_true(),
+            uinput_settle_ms: default_uinput_settle_ms(),
+            preview: false,
+            preview_window: false,
+            countdown_secs: 0,
+            paste_keys: default_paste_keys(),
+            app_paste_keys: default_app_paste_keys(),
+            keyboard_layout: String::new(),
+            keyboard_variant: String::new(),
+            file_path: String::new(),
+            file_template: default_file_template(),
+            command: String::new(),
         }
     }
 }
@@ -292,12 +1235,42 @@ impl Default for OverlayConfig {
             position: default_position(),
             size: default_size(),
             opacity: default_opacity(),
+            error_secs: default_error_secs(),
+            retry_notification: true,
+            show_transcription: true,
+            notifications: NotificationTemplates::default(),
+            wob_pipe: String::new(),
+            keyboard_led: String::new(),
+            colors: OverlayColors::default(),
         }
     }
 }
 
+/// Config profile in effect: `croaker serve --profile`, or the last one switched to over the socket
+static CONFIG_PROFILE: Mutex<Option<String>> = Mutex::new(None);
+
+/// The config profile `Config::load` layers over the config, `None` for none.
+pub fn config_profile() -> Option<String> {
+    CONFIG_PROFILE.lock().unwrap().clone()
+}
+
+pub fn set_config_profile(name: Option<String>) {
+    *CONFIG_PROFILE.lock().unwrap() = name;
+}
+
 impl Config {
     pub fn load() -> Result<Self, ConfigError> {
+        Self::load_profile(config_profile().as_deref())
+    }
+
+    /// Load the config with `~/.config/croaker/profiles/<profile>.toml` layered over it.
+    pub fn load_profile(profile: Option<&str>) -> Result<Self, ConfigError> {
+        Self::from_table(Self::load_table(profile)?)
+    }
+
+    /// The config file with the instance overlay, the config profile and `CROAKER_*` variables
+    /// layered over it, before it's deserialized.
+    pub fn load_table(profile: Option<&str>) -> Result<toml::Table, ConfigError> {
         let config_path = Self::config_path()?;
         
         if !config_path.exists() {
@@ -307,19 +1280,95 @@ impl Config {
 
         let contents = fs::read_to_string(&config_path)
             .map_err(|e| ConfigError::ReadError(e.to_string()))?;
-        let mut config: Config = toml::from_str(&contents)
+        let mut table: toml::Table = toml::from_str(&contents)
             .map_err(|e| ConfigError::ParseError(e.to_string()))?;
-        
+        let instance = crate::daemon::instance::name();
+        if let Some(name) = instance {
+            let overlay_path = Self::instance_config_path(name)?;
+            if overlay_path.exists() {
+                let contents = fs::read_to_string(&overlay_path)
+                    .map_err(|e| ConfigError::ReadError(e.to_string()))?;
+                let overlay: toml::Table = toml::from_str(&contents)
+                    .map_err(|e| ConfigError::ParseError(format!("{:?}: {}", overlay_path, e)))?;
+                merge_tables(&mut table, overlay);
+            } else {
+                tracing::info!("No {:?}, instance {:?} uses the main config", overlay_path, name);
+            }
+        }
+        // Unlike an instance's, a profile's file was asked for by name, so it has to be there
+        if let Some(name) = profile {
+            let profile_path = Self::profile_config_path(name)?;
+            let contents = fs::read_to_string(&profile_path)
+                .map_err(|e| ConfigError::ReadError(format!("{:?}: {}", profile_path, e)))?;
+            let overlay: toml::Table = toml::from_str(&contents)
+                .map_err(|e| ConfigError::ParseError(format!("{:?}: {}", profile_path, e)))?;
+            merge_tables(&mut table, overlay);
+        }
+        apply_env_overrides(&mut table, std::env::vars())?;
+        Ok(table)
+    }
+
+    /// Deserialize a table from `load_table` and fill in the paths.
+    pub fn from_table(table: toml::Table) -> Result<Self, ConfigError> {
+        let instance = crate::daemon::instance::name();
+        let mut config: Config = toml::Value::Table(table).try_into()
+            .map_err(|e: toml::de::Error| ConfigError::ParseError(e.to_string()))?;
+
+        // Two daemons sharing a spool would both retry the same recordings
+        if let Some(name) = instance {
+            if config.transcription.spool_dir == default_spool_dir() {
+                config.transcription.spool_dir = format!("{}-{}", default_spool_dir(), name);
+            }
+            if config.general.log_file == default_log_file() {
+                config.general.log_file = default_log_file().replace("croaker.log", &format!("croaker-{}.log", name));
+            }
+        }
+
         // Expand paths
+        config.general.replacements_file = Self::expand_path(&config.general.replacements_file)
+            .map_err(|e| ConfigError::ReadError(format!("Path expansion error: {}", e)))?;
+        config.general.log_file = Self::expand_path(&config.general.log_file)
+            .map_err(|e| ConfigError::ReadError(format!("Path expansion error: {}", e)))?;
+        config.general.emoji_file = Self::expand_path(&config.general.emoji_file)
+            .map_err(|e| ConfigError::ReadError(format!("Path expansion error: {}", e)))?;
         config.groq.key_file = Self::expand_path(&config.groq.key_file)
             .map_err(|e| ConfigError::ReadError(format!("Path expansion error: {}", e)))?;
         config.groq.cleanup_prompt_file = Self::expand_path(&config.groq.cleanup_prompt_file)
             .map_err(|e| ConfigError::ReadError(format!("Path expansion error: {}", e)))?;
+        config.groq.glossary_file = Self::expand_path(&config.groq.glossary_file)
+            .map_err(|e| ConfigError::ReadError(format!("Path expansion error: {}", e)))?;
+        config.audio.archive_dir = Self::expand_path(&config.audio.archive_dir)
+            .map_err(|e| ConfigError::ReadError(format!("Path expansion error: {}", e)))?;
+        config.transcription.spool_dir = Self::expand_path(&config.transcription.spool_dir)
+            .map_err(|e| ConfigError::ReadError(format!("Path expansion error: {}", e)))?;
+        config.vosk.model_dir = Self::expand_path(&config.vosk.model_dir)
+            .map_err(|e| ConfigError::ReadError(format!("Path expansion error: {}", e)))?;
+        config.history.db_path = Self::expand_path(&config.history.db_path)
+            .map_err(|e| ConfigError::ReadError(format!("Path expansion error: {}", e)))?;
+        config.output.file_path = Self::expand_path(&config.output.file_path)
+            .map_err(|e| ConfigError::ReadError(format!("Path expansion error: {}", e)))?;
+        config.overlay.wob_pipe = Self::expand_path(&config.overlay.wob_pipe)
+            .map_err(|e| ConfigError::ReadError(format!("Path expansion error: {}", e)))?;
+        config.accessibility.piper_model = Self::expand_path(&config.accessibility.piper_model)
+            .map_err(|e| ConfigError::ReadError(format!("Path expansion error: {}", e)))?;
+        for profile in &mut config.app_profiles {
+            if let Some(ref mut prompt_file) = profile.cleanup_prompt_file {
+                *prompt_file = Self::expand_path(prompt_file)
+                    .map_err(|e| ConfigError::ReadError(format!("Path expansion error: {}", e)))?;
+            }
+        }
+        for profile in config.profiles.values_mut() {
+            for path in [&mut profile.cleanup_prompt_file, &mut profile.file_path].into_iter().flatten() {
+                *path = Self::expand_path(path)
+                    .map_err(|e| ConfigError::ReadError(format!("Path expansion error: {}", e)))?;
+            }
+        }
 
         // Validate whisper model: transcription endpoint only supports Whisper models.
         // If user accidentally sets this to an LLM (e.g. openai/gpt-oss-120b), Groq returns HTTP 400.
         // We fall back to the default Whisper model to keep croaker functional, and log a clear warning.
-        if !config.groq.whisper_model.to_lowercase().contains("whisper") {
+        // Gateways are free to name their models however they like, so only check against Groq itself
+        if config.groq.base_url == default_base_url() && !config.groq.whisper_model.to_lowercase().contains("whisper") {
             tracing::warn!(
                 "Invalid whisper_model {:?} (does not look like a Whisper model). Falling back to {:?}. \
                  Fix this in ~/.config/croaker/config.toml under [groq].",
@@ -366,7 +1415,7 @@ impl Config {
                             }
                         })
                 })
-                .unwrap_or_else(|| Self::default_prompt_text());
+                .unwrap_or_else(Self::default_prompt_text);
             fs::write(&default_prompt_path, prompt_content)
                 .map_err(|e| ConfigError::ReadError(format!("Failed to write default prompt file: {}", e)))?;
             tracing::info!("Created default prompt file at {:?}", default_prompt_path);
@@ -382,18 +1431,46 @@ impl Config {
                 .map_err(|e| ConfigError::ReadError(format!("Failed to create config directory: {}", e)))?;
         }
 
-        let default_config = r#"# croaker Configuration File
+        let default_config = r##"# croaker Configuration File
 # All options are shown below with their default values.
 # Uncomment and modify any option you want to change.
+# Any key can also be set from the environment as CROAKER_<SECTION>_<KEY>, e.g.
+# CROAKER_GENERAL_LANGUAGE=de; CROAKER_GROQ_API_KEY is used instead of the key file.
 
 [general]
 # Language code for transcription (e.g., "en", "es", "fr")
 language = "en"
 # List of languages to toggle between (use language codes like "en", "tr", "es", "fr", "de", etc.)
 languages = ["en", "tr", "es", "fr", "de"]
+# Translation mode: speak any language and get English text (toggle at runtime with
+# translate_shortcut or `croaker toggle-translate`)
+translate = false
+# Interpret spoken commands: "new line", "new paragraph", "comma", "period", "question mark",
+# "open quote"/"close quote", "all caps <word>", ...
+voice_commands = false
+# Replacements applied after cleanup ([words] word = "Word", [regex] 'pattern' = "replacement").
+# Edits are picked up on the next transcription; a missing file means no replacements
+replacements_file = "~/.config/croaker/replacements.toml"
+# Dictate emoji: ":thumbs up:" (say "colon thumbs up colon") or "thumbs up emoji" becomes 👍.
+# Common names are built in; add or override them in emoji_file ([emoji] "ship it" = "🚢")
+emoji = false
+emoji_file = "~/.config/croaker/emoji.toml"
+# Join back-to-back dictations into the same window: add a space between them and don't
+# capitalize when the previous one ended mid-sentence
+smart_join = true
+# Seconds after an output that the next dictation still continues it
+smart_join_window_secs = 30
+# Daemon log, rotated daily (croaker.2026-01-31.log, ...) and read with `croaker logs [-f]`.
+# Logs at info level unless RUST_LOG or --debug says otherwise. Empty = stderr/journal only
+log_file = "~/.local/state/croaker/croaker.log"
+# Days of logs kept
+log_max_files = 7
 
 [hotkeys]
-# Push-to-talk key (e.g., "RightAlt", "LeftAlt", "RightCtrl", "LeftCtrl")
+# Push-to-talk key (e.g., "RightAlt", "LeftAlt", "RightCtrl", "LeftCtrl"). Any other key or
+# button works too, by evdev name (KEY_ optional) or code: "KEY_F13", "KEY_MICMUTE" (dictation
+# keys), "KP0" (numpad), "KEY_PROG1", "BTN_0" (USB foot pedals), "256". Shortcuts take these names too
+# Every hotkey below also takes a list, e.g. ["RightAlt", "KEY_F13"] for a keyboard and a macro pad
 push_to_talk_key = "RightAlt"
 # Enable push-to-talk mode
 push_to_talk_enabled = true
@@ -403,25 +1480,120 @@ toggle_shortcut = "Super+Shift+R"
 toggle_enabled = true
 # Cancel shortcut
 cancel_shortcut = "Escape"
-# Output mode toggle shortcut (cycles between direct/clipboard/both)
+# Output mode toggle shortcut (cycles between direct/clipboard/both/wtype-type/file)
 output_mode_shortcut = "Shift+RightAlt+O"
 # Language toggle shortcut (cycles through configured languages)
 language_shortcut = "Shift+RightAlt+L"
+# Translation mode toggle shortcut (speak any language, output English)
+translate_shortcut = "Shift+RightAlt+T"
+# Skip LLM cleanup for the next dictation only (press again to undo)
+raw_shortcut = "Shift+RightAlt+R"
+# Session profile shortcut (cycles through [profiles], then back to none)
+profile_shortcut = "Shift+RightAlt+P"
+# Keyboard to watch for the hotkeys above, if detection picks the wrong one: a path such as
+# "/dev/input/by-id/usb-...-event-kbd", or part of the device name. Empty watches every keyboard
+device = ""
+# Double-tap push-to-talk to lock recording on (hands-free); press once more to stop
+double_tap_lock = false
+# A double tap is two presses this short, this close together (ms)
+double_tap_ms = 300
+# Push-to-talk presses shorter than this (ms) are taken as accidental and cancelled (0 = off)
+min_hold_ms = 150
+# Bind toggle_shortcut/cancel_shortcut in Hyprland or Sway over their IPC instead of the
+# GlobalShortcuts portal (for wlroots compositors without it). A cancel shortcut without a
+# modifier isn't bound, it would take the key from every application
+compositor_bindings = false
+# Game controller button held for push-to-talk, e.g. "South" (A on Xbox pads), "LeftTrigger",
+# "Select", "DPadUp". Empty = off. Needs a build with `--features gamepad`
+gamepad_button = ""
 
 [audio]
-# Audio device (use "default" for system default)
+# Audio device (use "default" for system default). Accepts a node name, node id, or part of
+# the name/description (e.g. "yeti"). Run `croaker devices` to list them.
 device = "default"
-# Sample rate in Hz
+# Sample rate in Hz. Higher rates are resampled to 16000 before upload (Whisper uses 16kHz)
 sample_rate = 16000
 # Audio format (s16, s24, s32, f32, f64)
 format = "s16"
+# Number of channels to capture. Multi-channel recordings are downmixed to mono before upload,
+# so only raise this for devices that can't record mono.
+channels = 1
+# What to record: "microphone", or "monitor" to transcribe whatever is playing on the default
+# output (meetings, videos). Can be overridden per recording with `croaker toggle --source=monitor`.
+source = "microphone"
+# Milliseconds of audio kept from before recording starts, so the first syllable isn't clipped.
+# 0 disables it. Note: a non-zero value keeps the microphone open while idle.
+preroll_ms = 0
+# Codec used when uploading to Groq: "wav" (no encoding), "flac" (lossless), "opus" (smallest)
+# flac and opus require ffmpeg and cut upload time considerably on slow connections
+upload_codec = "wav"
+# Run RNNoise noise suppression on the recording before upload (helps with fan/keyboard noise)
+denoise = false
+# Record through PipeWire's echo-cancel module so speaker output isn't picked up while dictating.
+# croaker loads it via pactl if needed; route playback through "croaker-echo-cancel-sink"
+# (e.g. make it the default output) so there's a reference signal to cancel.
+echo_cancel = false
+# Keep recordings in memory instead of a temp WAV file (nothing is left in /tmp after a crash)
+in_memory = false
+# Automatic gain: scale quiet recordings towards a target RMS level before upload
+normalize = false
+# Target RMS level in dBFS (gain is capped at +30 dB and peaks are kept under -1 dBFS)
+normalize_target_dbfs = -20.0
+# Warn (log + tray/notification) when more than this percentage of samples clip, which usually
+# means the microphone gain is too high and transcriptions will suffer. 0 disables the check.
+clip_warn_percent = 1.0
+# Long-form dictation: transcribe and output the recording in chunks of this many seconds while
+# you're still talking, instead of all at once at the end. 0 disables chunking.
+chunk_seconds = 0
+# Audio shared between consecutive chunks so words on the boundary aren't cut (duplicates are dropped)
+chunk_overlap_ms = 1000
+# Hands-free recordings (croaker toggle, double-tapped push-to-talk) stop and are transcribed on
+# their own after this many seconds without speech, for when you forget the mic is on. 0 disables it.
+idle_stop_secs = 0
+# Copy every recording here (as <timestamp>.wav) before it's deleted. Empty disables archiving.
+# Handy for debugging bad transcriptions. e.g. "~/.local/share/croaker/recordings"
+archive_dir = ""
+
+[transcription]
+# Speech-to-text provider: "groq" (Whisper via the Groq API, configured under [groq]),
+# "vosk" (fully offline, configured under [vosk]; needs a build with `--features vosk`) or
+# "mock" (canned text from [mock], no API key or network; for trying out hotkeys and output)
+provider = "groq"
+# When transcription fails because the network is down, keep the recording in spool_dir and
+# retry every retry_interval_secs; the text is output once it goes through.
+offline_queue = true
+spool_dir = "~/.cache/croaker/spool"
+retry_interval_secs = 30
+# Recordings left behind by a crash are deleted when the daemon starts again; set this to
+# transcribe and output them through the offline queue instead (needs offline_queue)
+recover_orphaned = false
 
 [groq]
+# Where the API key comes from: "file" (key_file) or "keyring" (the Secret Service: GNOME
+# Keyring, KWallet, KeePassXC). `croaker configure` stores the key in the keyring.
+key_source = "file"
 # Path to Groq API key file
 key_file = "~/.config/croaker/groq.key"
+# Keyring account of the key, to keep one per instance
+keyring_account = "groq"
+# OpenAI-compatible API base URL. Point this at a gateway (LiteLLM, vLLM, a corporate proxy)
+# to use it instead of Groq; /audio/transcriptions and /chat/completions are appended.
+base_url = "https://api.groq.com/openai/v1"
+# Base URL for the cleanup LLM only. Empty uses base_url.
+cleanup_base_url = ""
 # Whisper model for transcription (use any Groq-supported Whisper model slug)
 # Examples: whisper-large-v3-turbo, whisper-large-v3, whisper-medium, etc.
 whisper_model = "whisper-large-v3-turbo"
+# Whisper model used in translation mode (must support the /audio/translations endpoint)
+translation_model = "whisper-large-v3"
+# Text sent to Whisper as a prompt to bias spelling of names, products and jargon
+# (Whisper only looks at the last ~224 tokens of it)
+whisper_prompt = ""
+# Optional glossary file, one term per line, appended to whisper_prompt
+glossary_file = ""
+# Recordings where every segment's no-speech probability is above this are treated as silence
+# and nothing is output (needs response_format=verbose_json support; 0 disables)
+no_speech_threshold = 0.6
 # Enable LLM cleanup of transcription
 cleanup_enabled = true
 # LLM model for text cleanup (use any Groq-supported model slug)
@@ -433,14 +1605,109 @@ cleanup_prompt_file = "~/.config/croaker/prompts/default.txt"
 # Temperature for cleanup model (0.0 = deterministic, higher = more creative)
 # Lower values (0.0-0.3) are recommended for transcription cleanup
 cleanup_temperature = 0.0
+# Start typing cleaned text sentence by sentence while the LLM is still writing
+# (direct/both output modes; clipboard mode still outputs once at the end)
+cleanup_streaming = false
+
+[vosk]
+# Unpacked Vosk model directory (https://alphacephei.com/vosk/models). Models are single-language,
+# so general.language is ignored with this provider
+model_dir = "~/.local/share/croaker/vosk-model"
+
+[mock]
+# With provider = "mock" (or `croaker serve --mock`) every recording transcribes to this text,
+# after delay_ms; cleanup, when enabled, returns it unchanged after the same delay
+text = "This is a mock transcription."
+delay_ms = 500
+
+[redaction]
+# Mask personal data (e.g. when dictating in a screen-shared meeting). Applied after cleanup,
+# before the text is output or saved to history
+enabled = false
+emails = true
+phone_numbers = true
+credit_cards = true
+# Extra regexes, matches are replaced with [redacted]
+patterns = []
+
+[history]
+# Keep every transcription (raw and cleaned text) in a local SQLite database.
+# Browse it with `croaker history list`, `croaker history search <text>` and `croaker history copy <id>`
+enabled = true
+db_path = "~/.local/share/croaker/history.db"
 
 [output]
 # Delay between keystrokes in milliseconds (for uinput typing)
 keystroke_delay_ms = 5
-# Restore clipboard after pasting (disabled - user preference)
+# For apps that drop characters when typed to quickly (some Electron editors): type this many
+# characters at a time with chunk_delay_ms pauses in between. 0 types everything at once
+typing_burst_chars = 0
+# Pause between typing bursts and between paste chunks
+chunk_delay_ms = 100
+# Paste long text in pieces of this many characters ("both" mode). 0 pastes it at once
+paste_chunk_chars = 0
+# Put the previous clipboard contents back after pasting in "both" mode (any type: text, images,
+# files). Needs the data-control protocol, so not on GNOME
 clipboard_restore = false
-# Output mode: "direct" (type directly), "clipboard" (copy to clipboard only), "both" (do both)
+# Milliseconds to wait after pasting before restoring; raise it if apps paste the old contents
+clipboard_restore_delay_ms = 300
+# Output mode: "direct" (type directly), "clipboard" (copy to clipboard only), "both" (do both),
+# "wtype-type" (type with wtype on Wayland, clipboard untouched; needs the virtual keyboard protocol),
+# "file" (append to file_path), "command" (pipe into command), "remote" (send to croaker on another
+# machine, see [remote]), "dbus" (only emit the TranscriptionReady signal on the session bus;
+# it's emitted in every mode, for scripts that want the text)
 output_mode = "both"
+# Type via the Wayland virtual keyboard protocol when the compositor supports it (Sway, Hyprland,
+# river, ...) instead of uinput (direct mode) or Ctrl+V (both mode)
+virtual_keyboard = true
+# Milliseconds to wait after creating the uinput keyboard at startup; raise it if the first
+# characters typed after starting the daemon go missing
+uinput_settle_ms = 200
+# Show each transcription in a notification with Accept/Edit/Discard before outputting it
+# (needs notify-send with --action support; Edit needs zenity). Long dictations and streamed
+# cleanup are then output in one piece
+preview = false
+# Preview in an editable zenity window instead, to fix up the text before it's output; once
+# it's done a second window asks whether to insert the edited text or only copy it
+preview_window = false
+# Count down this many seconds on the overlay before typing/pasting, so there's time to click
+# into the window the text should go to. Long dictations are then output in one piece
+countdown_secs = 0
+# Key combination used to paste ("ctrl+v", "ctrl+shift+v", "shift+insert", ...)
+paste_keys = "ctrl+v"
+# XKB layout/variant used to type through uinput, e.g. "tr" or "de" + "nodeadkeys".
+# Empty asks the compositor for the active keymap (falls back to XKB_DEFAULT_LAYOUT, then "us")
+keyboard_layout = ""
+keyboard_variant = ""
+# Notes file for output_mode = "file" (strftime placeholders allowed, e.g. an Obsidian daily
+# note: "~/Obsidian/Daily/%Y-%m-%d.md"). Empty leaves "file" out of the output mode toggle
+file_path = ""
+# Entry appended for each transcription: {text}, {date} (YYYY-MM-DD), {time} (HH:MM)
+file_template = "- {time} {text}"
+# Command for output_mode = "command", run with `sh -c`. It gets the cleaned text on stdin and
+# CROAKER_LANGUAGE, CROAKER_MODE (dictation/translate), CROAKER_APP and CROAKER_RAW_TEXT in its
+# environment. Empty leaves "command" out of the output mode toggle
+command = ""
+
+# Paste keys for specific applications, matched against the focused window's app id/class
+# (detected on Sway and Hyprland). Setting this table replaces the built-in terminal list
+[output.app_paste_keys]
+alacritty = "ctrl+shift+v"
+foot = "ctrl+shift+v"
+kitty = "ctrl+shift+v"
+"org.wezfurlong.wezterm" = "ctrl+shift+v"
+"org.kde.konsole" = "ctrl+shift+v"
+konsole = "ctrl+shift+v"
+"org.gnome.terminal" = "ctrl+shift+v"
+
+[remote]
+# Dictate on one machine, type on another: there, run `croaker receive` next to the daemon; here,
+# set output_mode = "remote". The address is host:port or a Unix socket path (e.g. one forwarded
+# with `ssh -R`). Use the same token on both sides. Empty leaves "remote" out of the toggle
+address = ""
+token = ""
+# Where `croaker receive` listens. Keep it on localhost and tunnel over SSH on untrusted networks
+listen = "127.0.0.1:7731"
 
 [overlay]
 # Enable visual feedback
@@ -448,8 +1715,121 @@ enabled = true
 # Feedback backend: "tray" (system tray icon - default), "notification" (desktop notifications)
 # "tray" shows a colored icon in your system tray that changes based on state
 # "notification" shows desktop notifications for each state change
+# "auto" uses the tray when a tray host (StatusNotifierWatcher) is running, notifications otherwise
+# ("gtk" and "layer-shell" aren't implemented yet and act as "auto")
+# "wob" fills the bar of a running wob (recording, then processing step by step, countdowns)
+# "swayosd" shows states and messages through swayosd-client
 backend = "tray"
-"#;
+# Pipe wob reads from ("wob" backend); empty = $XDG_RUNTIME_DIR/wob.sock
+wob_pipe = ""
+# Light a keyboard LED while recording: "scrolllock", "capslock", "numlock", "compose" or "kana".
+# Also with enabled = false. Needs write access to /dev/input (the input group); the compositor
+# may set lock key LEDs back, Scroll Lock is the one it usually leaves alone. Empty = off
+keyboard_led = ""
+# Seconds a failure ("API key invalid", "Network timeout", ...) stays shown before going back to idle
+error_secs = 5
+# When transcription or output fails, a notification with a Retry action tries the same recording
+# (or text) again. Needs a notification server with actions; comes on top of the Error state shown above
+retry_notification = true
+# Briefly show the first line and word count of each dictation once it's written, to tell at a
+# glance whether it came out right
+show_transcription = true
+
+[overlay.colors]
+# Tray icon color per state, "#rrggbb". Pick ones that tell recording from the rest apart for
+# you, e.g. recording = "#0072b2" and processing = "#e69f00" are told apart with any color vision
+idle = "#808080"
+paused = "#3c3c3c"
+recording = "#ff3c3c"
+processing = "#ffb43c"
+outputting = "#3cc83c"
+error = "#c828a0"
+
+# With the "notification" backend, each state's notification can be changed. Placeholders:
+# {state}, {language}, {mode}, {duration} (seconds recorded), {chars} (characters being written)
+# and {reason} (of an error). Unset fields keep the built-in text; there's no icon by default
+# [overlay.notifications.recording]
+# title = "croaker"
+# body = "Recording ({language}, {mode})"
+# icon = "media-record"
+# [overlay.notifications.processing]
+# body = "Transcribing {duration}s of audio..."
+# [overlay.notifications.outputting]
+# body = "Typing {chars} characters..."
+# [overlay.notifications.error]
+# body = "Error: {reason}"
+# icon = "dialog-error"
+
+[watchdog]
+# A stage taking longer than this many seconds is taken as hung (a stuck wtype, a dead network)
+# and cancelled, showing an error before going back to idle. 0 = no limit.
+# Waiting for an answer to the output preview doesn't count. A recording that runs past
+# recording_secs is stopped and transcribed instead.
+recording_secs = 900
+processing_secs = 180
+output_secs = 60
+
+[stats]
+# Serve `croaker stats` metrics (stage latency histograms, error counts) for Prometheus
+# on this address, e.g. "127.0.0.1:9464". Empty = off
+prometheus_listen = ""
+
+[hooks]
+# Shell commands (run with `sh -c`, in the background) on lifecycle events. Each gets
+# CROAKER_EVENT and more CROAKER_* variables; see the README. Empty = nothing runs
+# e.g. on_start_recording = "playerctl pause" and on_stop_recording = "playerctl play"
+on_start_recording = ""
+# After a recording stopped or was cancelled
+on_stop_recording = ""
+# The cleaned text on stdin, CROAKER_RAW_TEXT, CROAKER_LANGUAGE and CROAKER_APP
+on_transcription = ""
+# The reason ("API key invalid", "Network timeout", ...) on stdin and in CROAKER_ERROR
+on_error = ""
+
+[accessibility]
+# Spoken feedback through speech-dispatcher (spd-say), in the voice a screen reader like Orca
+# uses. Say "Recording", "Processing" and errors as they happen
+announce_states = false
+# Read out each dictation, to check it came out right without looking
+announce_text = false
+# "after" it's written, or "before": as soon as it's ready, to listen during a preview or countdown
+announce_text_when = "after"
+# "speech-dispatcher" (spd-say) or "piper" (a local neural voice, played with pw-play)
+speech_engine = "speech-dispatcher"
+# Voice model for piper, e.g. "~/.local/share/piper/en_US-lessac-medium.onnx"
+piper_model = ""
+
+# Per-application profiles, matched against the focused window's app id/class when
+# recording starts (Sway and Hyprland). The first matching profile wins; unset fields
+# keep the global settings. Example:
+# [[app_profiles]]
+# apps = ["Alacritty", "foot"]
+# output_mode = "wtype-type"
+# paste_keys = "ctrl+shift+v"
+# keystroke_delay_ms = 10
+# typing_burst_chars = 40
+# chunk_delay_ms = 150
+# paste_chunk_chars = 500
+# cleanup_enabled = false
+# cleanup_prompt_file = "~/.config/croaker/prompts/terminal.txt"
+#
+# [[app_profiles]]
+# apps = ["Slack", "discord"]
+# preview = true
+
+# Session profiles, switched with hotkeys.profile_shortcut, the tray menu or
+# `croaker profile set <name>`. Unset fields keep the global settings. To change anything else
+# (models, endpoints), use a config profile in ~/.config/croaker/profiles/<name>.toml instead.
+# Example:
+# [profiles.email]
+# cleanup_prompt_file = "~/.config/croaker/prompts/email.txt"
+# output_mode = "clipboard"
+#
+# [profiles.notes]
+# language = "en"
+# output_mode = "file"
+# file_path = "~/notes/inbox.md"
+"##;
 
         fs::write(config_path, default_config)
             .map_err(|e| ConfigError::ReadError(format!("Failed to write config file: {}", e)))?;
@@ -464,7 +1844,32 @@ backend = "tray"
         Ok(config_dir.join("croaker").join("config.toml"))
     }
 
+    /// Settings for `--instance <name>` that override the main config.
+    pub fn instance_config_path(name: &str) -> Result<PathBuf, ConfigError> {
+        Ok(Self::config_path()?.with_file_name("instances").join(format!("{}.toml", name)))
+    }
+
+    pub fn profile_config_path(name: &str) -> Result<PathBuf, ConfigError> {
+        Ok(Self::config_path()?.with_file_name("profiles").join(format!("{}.toml", name)))
+    }
+
     pub fn load_api_key(&self) -> Result<String, ConfigError> {
+        // NOTE: the key from the environment isn't a config field, so `get-config` never shows it
+        if let Ok(key) = std::env::var(API_KEY_VARIABLE) {
+            let key = key.trim();
+            if !key.is_empty() {
+                return Ok(key.to_string());
+            }
+        }
+        if self.groq.key_source == KeySource::Keyring {
+            let key = keyring::lookup(&self.groq.keyring_account)
+                .map_err(|e| ConfigError::KeyReadError(e.to_string()))?;
+            if key.is_empty() {
+                return Err(ConfigError::InvalidKey);
+            }
+            return Ok(key);
+        }
+
         // Expand path if it contains ~
         let expanded_path = if self.groq.key_file.starts_with("~/") {
             Self::expand_path(&self.groq.key_file)
@@ -520,6 +1925,18 @@ backend = "tray"
             .trim().to_string())
     }
 
+    /// Whisper prompt: `whisper_prompt` followed by the glossary terms, if any.
+    pub fn load_whisper_prompt(&self) -> Result<String, ConfigError> {
+        let glossary = if self.groq.glossary_file.is_empty() {
+            String::new()
+        } else {
+            fs::read_to_string(&self.groq.glossary_file)
+                .map_err(|e| ConfigError::ReadError(format!("Failed to read glossary {}: {}", self.groq.glossary_file, e)))?
+        };
+
+        Ok(build_whisper_prompt(&self.groq.whisper_prompt, &glossary))
+    }
+
     fn default_prompt_path() -> Result<PathBuf, std::io::Error> {
         let config_dir = dirs::config_dir()
             .ok_or_else(|| std::io::Error::new(
@@ -535,16 +1952,172 @@ backend = "tray"
     }
 
     fn expand_path(path: &str) -> Result<String, std::io::Error> {
-        if path.starts_with("~/") {
+        if let Some(rest) = path.strip_prefix("~/") {
             let home = dirs::home_dir()
                 .ok_or_else(|| std::io::Error::new(
                     std::io::ErrorKind::NotFound,
                     "Could not find home directory"
                 ))?;
-            Ok(home.join(&path[2..]).to_string_lossy().to_string())
+            Ok(home.join(rest).to_string_lossy().to_string())
         } else {
             Ok(path.to_string())
         }
     }
 }
 
+/// Join the free-form prompt and glossary terms (one per line, `#` comments allowed)
+/// into a single prompt string.
+fn build_whisper_prompt(prompt: &str, glossary: &str) -> String {
+    let terms: Vec<&str> = glossary
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+
+    let prompt = prompt.trim();
+    match (prompt.is_empty(), terms.is_empty()) {
+        (_, true) => prompt.to_string(),
+        (true, false) => terms.join(", "),
+        (false, false) => format!("{} {}", prompt, terms.join(", ")),
+    }
+}
+
+/// Environment variables named `CROAKER_<SECTION>_<KEY>` override config keys
+const ENV_PREFIX: &str = "CROAKER_";
+/// Read by `load_api_key` instead of `groq.key_file`
+const API_KEY_VARIABLE: &str = "CROAKER_GROQ_API_KEY";
+/// Set by croaker for hooks rather than read as settings; a hook running croaker has them
+const HOOK_VARIABLES: &[&str] = &["CROAKER_EVENT", "CROAKER_ERROR", "CROAKER_RAW_TEXT", "CROAKER_LANGUAGE", "CROAKER_APP"];
+
+/// Layer `CROAKER_*` variables over `table`. Names are matched against the keys of the default
+/// config, so `CROAKER_OUTPUT_OUTPUT_MODE` sets `output.output_mode` and
+/// `CROAKER_OVERLAY_COLORS_RECORDING` sets `overlay.colors.recording`. A value is taken as is for
+/// string keys and parsed as TOML (`true`, `0.5`, `["a", "b"]`) for the others.
+fn apply_env_overrides(table: &mut toml::Table, vars: impl Iterator<Item = (String, String)>) -> Result<(), ConfigError> {
+    let defaults = toml::Table::try_from(Config::default())
+        .map_err(|e| ConfigError::ParseError(e.to_string()))?;
+    for (name, raw) in vars {
+        let Some(path) = name.strip_prefix(ENV_PREFIX) else { continue };
+        if name == API_KEY_VARIABLE || HOOK_VARIABLES.contains(&name.as_str()) {
+            continue;
+        }
+        match override_key(&defaults, table, &path.to_lowercase(), &raw) {
+            Ok(true) => tracing::debug!("{} overrides the config file", name),
+            Ok(false) => tracing::warn!("{} doesn't name a config key, ignoring it", name),
+            Err(e) => return Err(ConfigError::ParseError(format!("{}: {}", name, e))),
+        }
+    }
+    Ok(())
+}
+
+/// Set the key `path` names (lowercase, `_` between section and key) in `table`. Keys are tried
+/// longest first, so the longest one that fits wins.
+fn override_key(defaults: &toml::Table, table: &mut toml::Table, path: &str, raw: &str) -> Result<bool, String> {
+    let mut keys: Vec<&String> = defaults.keys().collect();
+    keys.sort_by_key(|key| std::cmp::Reverse(key.len()));
+    for key in keys {
+        let default = &defaults[key.as_str()];
+        if path == key.as_str() {
+            if default.is_table() {
+                return Err(format!("{} is a section, not a key", key));
+            }
+            table.insert(key.clone(), env_value(default, raw)?);
+            return Ok(true);
+        }
+        let Some(rest) = path.strip_prefix(key.as_str()).and_then(|rest| rest.strip_prefix('_')) else { continue };
+        let Some(section_defaults) = default.as_table() else { continue };
+        let section = table.entry(key.clone()).or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        let Some(section) = section.as_table_mut() else {
+            return Err(format!("{} is not a table in the config file", key));
+        };
+        if override_key(section_defaults, section, rest, raw)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// `raw` as a value of the same type as `default`.
+fn env_value(default: &toml::Value, raw: &str) -> Result<toml::Value, String> {
+    if default.is_str() {
+        return Ok(toml::Value::String(raw.to_string()));
+    }
+    let mut parsed: toml::Table = toml::from_str(&format!("value = {}", raw))
+        .map_err(|_| format!("{:?} is not a valid {}", raw, default.type_str()))?;
+    let value = parsed.remove("value").unwrap_or_else(|| toml::Value::String(raw.to_string()));
+    // An integer is fine where a float is expected
+    match (default, value) {
+        (toml::Value::Float(_), toml::Value::Integer(n)) => Ok(toml::Value::Float(n as f64)),
+        (default, value) if value.same_type(default) => Ok(value),
+        (default, _) => Err(format!("{:?} is not a valid {}", raw, default.type_str())),
+    }
+}
+
+/// Layer `overlay` over `base`: tables merge key by key, anything else is replaced.
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge_tables(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_env_overrides, build_whisper_prompt, merge_tables, HotkeyConfig};
+
+    #[test]
+    fn whisper_prompt_appends_glossary_terms() {
+        let glossary = "# names\nKubernetes\n\n  croaker \n";
+        assert_eq!(build_whisper_prompt("Tech talk.", glossary), "Tech talk. Kubernetes, croaker");
+        assert_eq!(build_whisper_prompt("", glossary), "Kubernetes, croaker");
+        assert_eq!(build_whisper_prompt(" Hi ", ""), "Hi");
+    }
+
+    #[test]
+    fn hotkeys_take_one_binding_or_a_list() {
+        let hotkeys: HotkeyConfig =
+            toml::from_str("push_to_talk_key = [\"RightAlt\", \"KEY_F13\"]\ncancel_shortcut = \"\"").unwrap();
+        assert_eq!(hotkeys.push_to_talk_key.iter().collect::<Vec<_>>(), ["RightAlt", "KEY_F13"]);
+        assert_eq!(hotkeys.cancel_shortcut.iter().count(), 0);
+        assert_eq!(hotkeys.toggle_shortcut.to_string(), "Super+Shift+R");
+    }
+
+    #[test]
+    fn instance_overlay_merges_per_key() {
+        let mut base: toml::Table = toml::from_str(
+            "[general]\nlanguage = \"en\"\noutput_mode = \"clipboard\"\n[groq]\nkey_file = \"~/a\"\n",
+        ).unwrap();
+        let overlay: toml::Table = toml::from_str("[general]\nlanguage = \"de\"\n").unwrap();
+        merge_tables(&mut base, overlay);
+        assert_eq!(base["general"]["language"].as_str(), Some("de"));
+        assert_eq!(base["general"]["output_mode"].as_str(), Some("clipboard"));
+        assert_eq!(base["groq"]["key_file"].as_str(), Some("~/a"));
+    }
+
+    #[test]
+    fn env_overrides_set_typed_keys() {
+        let mut table: toml::Table = toml::from_str("[general]\nlanguage = \"en\"\n").unwrap();
+        let vars = [
+            ("CROAKER_GENERAL_LANGUAGE", "de"),
+            ("CROAKER_OUTPUT_OUTPUT_MODE", "clipboard"),
+            ("CROAKER_GROQ_CLEANUP_ENABLED", "false"),
+            ("CROAKER_GROQ_NO_SPEECH_THRESHOLD", "1"),
+            ("CROAKER_OVERLAY_COLORS_RECORDING", "#ff0000"),
+            ("CROAKER_EVENT", "recording"),
+            ("HOME", "/root"),
+        ];
+        apply_env_overrides(&mut table, vars.iter().map(|(k, v)| (k.to_string(), v.to_string()))).unwrap();
+        assert_eq!(table["general"]["language"].as_str(), Some("de"));
+        assert_eq!(table["output"]["output_mode"].as_str(), Some("clipboard"));
+        assert_eq!(table["groq"]["cleanup_enabled"].as_bool(), Some(false));
+        assert_eq!(table["groq"]["no_speech_threshold"].as_float(), Some(1.0));
+        assert_eq!(table["overlay"]["colors"]["recording"].as_str(), Some("#ff0000"));
+
+        let bad = [("CROAKER_GROQ_CLEANUP_ENABLED".to_string(), "maybe".to_string())];
+        assert!(apply_env_overrides(&mut table, bad.into_iter()).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+// `croaker config validate`: load the config the way the daemon does, but report what loading
+// would quietly get wrong. Unknown keys (a typo falls back to the default), bindings and values
+// the daemon only checks when it gets to them, and files that aren't there.
+
+use crate::config::{Config, KeySource, SpeechEngine};
+use crate::output::keys::PasteKeys;
+use std::path::Path;
+
+/// Closest a known key has to be to an unknown one to be suggested, by Jaro-Winkler similarity
+const SUGGESTION_SIMILARITY: f64 = 0.8;
+
+#[derive(Debug, Default)]
+pub struct Report {
+    /// The daemon won't start, or won't do what the config says
+    pub errors: Vec<String>,
+    /// Probably not what was meant
+    pub warnings: Vec<String>,
+}
+
+/// Check the config file, the instance overlay, the config profile `profile` and `CROAKER_*`
+/// variables.
+pub fn validate(profile: Option<&str>) -> Report {
+    let mut report = Report::default();
+    let table = match Config::load_table(profile) {
+        Ok(table) => table,
+        Err(e) => {
+            report.errors.push(e.to_string());
+            return report;
+        }
+    };
+
+    let mut unknown = Vec::new();
+    let parsed: Result<Config, _> =
+        serde_ignored::deserialize(toml::Value::Table(table.clone()), |path| unknown.push(path.to_string()));
+    if let Err(e) = parsed {
+        report.errors.push(e.to_string());
+        return report;
+    }
+    let defaults = toml::Table::try_from(Config::default()).unwrap_or_default();
+    for key in unknown {
+        let message = match suggest(&defaults, &key) {
+            Some(known) => format!("Unknown key {} is ignored (did you mean {}?)", key, known),
+            None => format!("Unknown key {} is ignored", key),
+        };
+        report.errors.push(message);
+    }
+
+    match Config::from_table(table) {
+        Ok(config) => check(&config, &mut report),
+        Err(e) => report.errors.push(e.to_string()),
+    }
+    report
+}
+
+/// The known key next to `path` that looks most like its last part.
+fn suggest(defaults: &toml::Table, path: &str) -> Option<String> {
+    let (section, key) = match path.rsplit_once('.') {
+        Some((section, key)) => (Some(section), key),
+        None => (None, path),
+    };
+    let mut table = defaults;
+    for part in section.into_iter().flat_map(|section| section.split('.')) {
+        table = table.get(part)?.as_table()?;
+    }
+    let (best, similarity) = table
+        .keys()
+        .map(|known| (known, strsim::jaro_winkler(key, known)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    (similarity >= SUGGESTION_SIMILARITY).then(|| match section {
+        Some(section) => format!("{}.{}", section, best),
+        None => best.clone(),
+    })
+}
+
+fn check(config: &Config, report: &mut Report) {
+    for (key, e) in crate::input::evdev::EvdevMonitor::check_bindings(&config.hotkeys) {
+        report.errors.push(format!("{}: {}", key, e));
+    }
+    #[cfg(feature = "gamepad")]
+    if !config.hotkeys.gamepad_button.is_empty()
+        && crate::input::gamepad::parse_button(&config.hotkeys.gamepad_button).is_none()
+    {
+        report.errors.push(format!("hotkeys.gamepad_button: unknown button {:?}", config.hotkeys.gamepad_button));
+    }
+    if config.overlay.enabled {
+        if let Err(e) = crate::overlay::check_backend(&config.overlay.backend) {
+            report.errors.push(format!("overlay.backend: {}", e));
+        } else if crate::overlay::is_floating(&config.overlay.backend) {
+            report.warnings.push(format!(
+                "overlay.backend = {:?} isn't implemented yet, the tray or notifications are used as for \"auto\"",
+                config.overlay.backend
+            ));
+        }
+    }
+    if !config.overlay.keyboard_led.is_empty() {
+        if let Err(e) = crate::output::led::parse_led(&config.overlay.keyboard_led) {
+            report.errors.push(format!("overlay.keyboard_led: {}", e));
+        }
+    }
+
+    let colors = &config.overlay.colors;
+    for (name, value) in [
+        ("idle", &colors.idle),
+        ("paused", &colors.paused),
+        ("recording", &colors.recording),
+        ("processing", &colors.processing),
+        ("outputting", &colors.outputting),
+        ("error", &colors.error),
+    ] {
+        if crate::overlay::parse_color(value).is_none() {
+            report.warnings.push(format!("overlay.colors.{} = {:?} isn't a \"#rrggbb\" color, the default is used", name, value));
+        }
+    }
+
+    if let Err(e) = config.output.paste_keys.parse::<PasteKeys>() {
+        report.warnings.push(format!("output.paste_keys: {}, Ctrl+V is used", e));
+    }
+    for (app, keys) in &config.output.app_paste_keys {
+        if let Err(e) = keys.parse::<PasteKeys>() {
+            report.warnings.push(format!("output.app_paste_keys.{}: {}, it's ignored", app, e));
+        }
+    }
+
+    check_files(config, report);
+}
+
+fn check_files(config: &Config, report: &mut Report) {
+    let needs_key = config.transcription.provider == "groq" || config.groq.cleanup_enabled;
+    if needs_key && config.transcription.provider != "mock" {
+        if let Err(e) = config.load_api_key() {
+            let source = match config.groq.key_source {
+                KeySource::File => "groq.key_file",
+                KeySource::Keyring => "groq.key_source",
+            };
+            report.errors.push(format!("{}: {}", source, e));
+        }
+    }
+
+    let missing = |path: &str| !path.is_empty() && !Path::new(path).exists();
+    if config.groq.cleanup_enabled && missing(&config.groq.cleanup_prompt_file) {
+        report.warnings.push(format!(
+            "groq.cleanup_prompt_file {} doesn't exist, the built-in prompt is used",
+            config.groq.cleanup_prompt_file
+        ));
+    }
+    if missing(&config.groq.glossary_file) {
+        report.warnings.push(format!("groq.glossary_file {} doesn't exist", config.groq.glossary_file));
+    }
+    if missing(&config.general.replacements_file) {
+        report.warnings.push(format!("general.replacements_file {} doesn't exist", config.general.replacements_file));
+    }
+    if config.general.emoji && missing(&config.general.emoji_file) {
+        report.warnings.push(format!("general.emoji_file {} doesn't exist", config.general.emoji_file));
+    }
+    if config.transcription.provider == "vosk" && missing(&config.vosk.model_dir) {
+        report.errors.push(format!("vosk.model_dir {} doesn't exist", config.vosk.model_dir));
+    }
+    if config.accessibility.speech_engine == SpeechEngine::Piper {
+        if config.accessibility.piper_model.is_empty() {
+            report.errors.push("accessibility.piper_model must be set for speech_engine = \"piper\"".to_string());
+        } else if missing(&config.accessibility.piper_model) {
+            report.errors.push(format!("accessibility.piper_model {} doesn't exist", config.accessibility.piper_model));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggests_close_keys_in_the_same_section() {
+        let defaults = toml::Table::try_from(Config::default()).unwrap();
+        assert_eq!(suggest(&defaults, "output.keystroke_delay").as_deref(), Some("output.keystroke_delay_ms"));
+        assert_eq!(suggest(&defaults, "generl").as_deref(), Some("general"));
+        assert_eq!(suggest(&defaults, "output.zzz"), None);
+        assert_eq!(suggest(&defaults, "nowhere.language"), None);
+    }
+}
@@ -0,0 +1,132 @@
+// Spoken feedback (`[accessibility]`): state changes and the text written are read out through
+// speech-dispatcher's `spd-say`, the same speech service Orca uses, so they come in the voice
+// and at the rate the user already has set up. Piper can be used instead for a local neural
+// voice. Runs in the background like hooks.
+
+use crate::config::{AccessibilityConfig, SpeechEngine};
+use crate::daemon::state::DaemonState;
+use std::sync::Mutex;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command as TokioCommand;
+use tokio::task::JoinHandle;
+
+/// What Piper is saying, cut off when there's something newer to say
+static PIPER: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+
+/// speech-dispatcher's message priorities, most urgent first. `Message` cuts off `Text` still
+/// being spoken; `Important` is never interrupted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    Important,
+    Message,
+    Text,
+}
+
+impl Priority {
+    fn as_str(self) -> &'static str {
+        match self {
+            Priority::Important => "important",
+            Priority::Message => "message",
+            Priority::Text => "text",
+        }
+    }
+}
+
+/// What to say for entering `state`, if anything. Idle is left out: either the text is read
+/// out or a cancel was asked for.
+pub fn state_message(state: &DaemonState) -> Option<(String, Priority)> {
+    match state {
+        DaemonState::Recording => Some(("Recording".to_string(), Priority::Message)),
+        DaemonState::Processing => Some(("Processing".to_string(), Priority::Message)),
+        DaemonState::Outputting => None,
+        DaemonState::Error(reason) => Some((format!("Error: {}", reason), Priority::Important)),
+        DaemonState::Idle => None,
+    }
+}
+
+/// Say `text` without waiting for it to be spoken. `language` picks a speech-dispatcher voice
+/// for dictated text; a Piper model speaks one language anyway.
+pub fn say(config: &AccessibilityConfig, text: String, priority: Priority, language: Option<String>) {
+    match config.speech_engine {
+        SpeechEngine::SpeechDispatcher => {
+            tokio::spawn(async move {
+                if let Err(e) = spd_say(&text, priority, language.as_deref()).await {
+                    tracing::warn!("Can't speak through speech-dispatcher: {}", e);
+                }
+            });
+        }
+        SpeechEngine::Piper => {
+            let model = config.piper_model.clone();
+            let mut playing = PIPER.lock().unwrap();
+            if let Some(previous) = playing.take() {
+                previous.abort();
+            }
+            *playing = Some(tokio::spawn(async move {
+                if let Err(e) = piper(&model, &text).await {
+                    tracing::warn!("Can't speak through piper: {}", e);
+                }
+            }));
+        }
+    }
+}
+
+async fn spd_say(text: &str, priority: Priority, language: Option<&str>) -> Result<(), String> {
+    let mut command = TokioCommand::new("spd-say");
+    command.args(["--application-name", "croaker", "--priority", priority.as_str()]);
+    if let Some(language) = language {
+        command.args(["--language", language]);
+    }
+    let output = command
+        .arg("--")
+        .arg(text)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .output()
+        .await
+        .map_err(|e| format!("{} (install speech-dispatcher)", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(())
+}
+
+/// Synthesize `text` to a temporary WAV with Piper, then play it. Both processes are killed
+/// when the task is aborted.
+async fn piper(model: &str, text: &str) -> Result<(), String> {
+    if model.is_empty() {
+        return Err("accessibility.piper_model is not set".to_string());
+    }
+    let wav = tempfile::Builder::new()
+        .prefix("croaker-speech-")
+        .suffix(".wav")
+        .tempfile()
+        .map_err(|e| e.to_string())?;
+
+    let mut child = TokioCommand::new("piper")
+        .args(["--model", model, "--output_file"])
+        .arg(wav.path())
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("{} (is piper installed?)", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes()).await.map_err(|e| e.to_string())?;
+    }
+    let output = child.wait_with_output().await.map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    let status = TokioCommand::new("pw-play")
+        .arg(wav.path())
+        .kill_on_drop(true)
+        .status()
+        .await
+        .map_err(|e| format!("pw-play: {}", e))?;
+    if !status.success() {
+        return Err(format!("pw-play exited with {}", status));
+    }
+    Ok(())
+}
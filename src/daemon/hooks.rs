@@ -0,0 +1,85 @@
+// User commands run on lifecycle events (`[hooks]`): mute music while recording, log
+// transcripts, push a notification on failure. Each runs through `sh -c` in the background,
+// so a slow hook never holds up dictation; what it's about is in CROAKER_* variables and
+// on stdin.
+
+use crate::config::HooksConfig;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command as TokioCommand;
+
+/// A hook still running after this long is killed
+const HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    StartRecording,
+    /// Recording ended, stopped or cancelled
+    StopRecording,
+    /// Stdin: the cleaned text
+    Transcription,
+    /// Stdin: the reason shown in the Error state
+    Error,
+}
+
+impl Hook {
+    fn name(self) -> &'static str {
+        match self {
+            Hook::StartRecording => "start_recording",
+            Hook::StopRecording => "stop_recording",
+            Hook::Transcription => "transcription",
+            Hook::Error => "error",
+        }
+    }
+
+    fn command(self, config: &HooksConfig) -> &str {
+        match self {
+            Hook::StartRecording => &config.on_start_recording,
+            Hook::StopRecording => &config.on_stop_recording,
+            Hook::Transcription => &config.on_transcription,
+            Hook::Error => &config.on_error,
+        }
+    }
+}
+
+/// Run the command configured for `hook`, if any, without waiting for it. `env` names are
+/// given without the CROAKER_ prefix.
+pub fn fire(config: &HooksConfig, hook: Hook, input: String, env: Vec<(&'static str, String)>) {
+    let command = hook.command(config);
+    if command.is_empty() {
+        return;
+    }
+    let command = command.to_string();
+    tokio::spawn(async move {
+        if let Err(e) = run(&command, hook, &input, &env).await {
+            tracing::warn!("on_{} hook {:?} failed: {}", hook.name(), command, e);
+        }
+    });
+}
+
+async fn run(command: &str, hook: Hook, input: &str, env: &[(&'static str, String)]) -> Result<(), String> {
+    let mut child = TokioCommand::new("sh")
+        .args(["-c", command])
+        .env("CROAKER_EVENT", hook.name())
+        .envs(env.iter().map(|(name, value)| (format!("CROAKER_{}", name), value)))
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that doesn't read stdin closes it early, that's fine
+        let _ = stdin.write_all(input.as_bytes()).await;
+    }
+    let output = tokio::time::timeout(HOOK_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| format!("still running after {:?}, killed", HOOK_TIMEOUT))?
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!("{}: {}", output.status, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    tracing::debug!("Ran on_{} hook", hook.name());
+    Ok(())
+}
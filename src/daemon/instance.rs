@@ -0,0 +1,175 @@
+// One daemon per user. Two would both grab the keyboard and type every dictation twice, so
+// `croaker serve` holds an exclusive lock on a pidfile next to the control socket for as long
+// as it runs. The lock goes away with the process, so a crash never leaves a stale pidfile
+// blocking the next start. `croaker serve --replace` stops the running daemon and takes over.
+//
+// `croaker --instance work serve` runs a second, independent daemon: every file two daemons
+// can't share (socket, pidfile, spooled and in-flight recordings, log) is named after the
+// instance, and `~/.config/croaker/instances/work.toml` is layered over the main config.
+
+use std::fs::File;
+use std::io::{Read, Seek, Write};
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// How long a replaced daemon gets to exit after SIGTERM before it is killed
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+static NAME: OnceLock<String> = OnceLock::new();
+
+/// Act as the named instance from now on. Call before anything asks for a path.
+pub fn set_name(name: String) {
+    let _ = NAME.set(name);
+}
+
+/// The `--instance` name, `None` for the default daemon.
+pub fn name() -> Option<&'static str> {
+    NAME.get().map(String::as_str)
+}
+
+/// `croaker`, or `croaker-<name>` for a named instance: the stem of every per-instance file.
+pub fn file_stem() -> String {
+    stem_for(name())
+}
+
+pub(super) fn stem_for(name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!("croaker-{}", name),
+        None => "croaker".to_string(),
+    }
+}
+
+/// clap parser for `--instance`. The name ends up in file and unit names, so it's kept to
+/// letters, digits, `-` and `_`.
+pub fn parse_name(name: &str) -> Result<String, String> {
+    if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        Ok(name.to_string())
+    } else {
+        Err("use only letters, digits, '-' and '_'".to_string())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum InstanceError {
+    #[error("croaker is already running (pid {0}), use `croaker serve --replace` to take over")]
+    RunningError(i32),
+    #[error("Failed to stop the running daemon (pid {0})")]
+    ReplaceError(i32),
+    #[error("Failed to lock the pidfile: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+/// The locked pidfile. Keep it alive for the lifetime of the daemon.
+pub struct Instance {
+    file: File,
+    path: PathBuf,
+}
+
+impl Instance {
+    pub fn pidfile_path() -> Result<PathBuf, std::io::Error> {
+        Ok(crate::input::socket::SocketServer::socket_path()?.with_file_name(format!("{}.pid", file_stem())))
+    }
+
+    /// Become the only daemon, stopping the running one first if `replace` is set.
+    pub fn acquire(replace: bool) -> Result<Self, InstanceError> {
+        Self::acquire_at(&Self::pidfile_path()?, replace)
+    }
+
+    fn acquire_at(path: &Path, replace: bool) -> Result<Self, InstanceError> {
+        let mut file = std::fs::OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+        if !try_lock(&file)? {
+            let pid = read_pid(&mut file).ok_or(InstanceError::RunningError(0))?;
+            if !replace {
+                return Err(InstanceError::RunningError(pid));
+            }
+            tracing::info!("Replacing the running daemon (pid {})", pid);
+            stop(&file, pid)?;
+        }
+
+        file.set_len(0)?;
+        file.rewind()?;
+        writeln!(file, "{}", std::process::id())?;
+        Ok(Self { file, path: path.to_path_buf() })
+    }
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        // NOTE: a replacing daemon may already have written its own pid here, only remove ours
+        if read_pid(&mut self.file) == Some(std::process::id() as i32) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Ask `pid` to exit and wait for its lock, killing it if it doesn't let go in time.
+fn stop(file: &File, pid: i32) -> Result<(), InstanceError> {
+    for signal in [libc::SIGTERM, libc::SIGKILL] {
+        // SAFETY: kill has no memory safety requirements
+        if unsafe { libc::kill(pid, signal) } < 0 {
+            tracing::warn!("Failed to signal pid {}: {}", pid, std::io::Error::last_os_error());
+        }
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+        while Instant::now() < deadline {
+            if try_lock(file)? {
+                return Ok(());
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        tracing::warn!("Daemon (pid {}) didn't exit in {:?}", pid, SHUTDOWN_TIMEOUT);
+    }
+    Err(InstanceError::ReplaceError(pid))
+}
+
+fn try_lock(file: &File) -> Result<bool, std::io::Error> {
+    // SAFETY: the descriptor is open for as long as `file` is borrowed
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        return Ok(true);
+    }
+    let error = std::io::Error::last_os_error();
+    if error.raw_os_error() == Some(libc::EWOULDBLOCK) {
+        Ok(false)
+    } else {
+        Err(error)
+    }
+}
+
+fn read_pid(file: &mut File) -> Option<i32> {
+    let mut contents = String::new();
+    file.rewind().ok()?;
+    file.read_to_string(&mut contents).ok()?;
+    contents.trim().parse().ok().filter(|pid| *pid > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_a_second_instance() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("croaker.pid");
+
+        let first = Instance::acquire_at(&path, false).unwrap();
+        let pid = std::process::id() as i32;
+        assert!(matches!(Instance::acquire_at(&path, false), Err(InstanceError::RunningError(p)) if p == pid));
+
+        drop(first);
+        assert!(!path.exists());
+        assert!(Instance::acquire_at(&path, false).is_ok());
+    }
+
+    #[test]
+    fn instance_names_stay_file_safe() {
+        assert_eq!(stem_for(None), "croaker");
+        assert_eq!(stem_for(Some("work")), "croaker-work");
+        assert!(parse_name("work_2").is_ok());
+        assert!(parse_name("").is_err());
+        assert!(parse_name("../work").is_err());
+        assert!(parse_name("a.b").is_err());
+    }
+}
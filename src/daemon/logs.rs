@@ -0,0 +1,96 @@
+// The daemon's log file (`general.log_file`), rotated daily so logs survive reboots without
+// journald. "croaker.log" is written as croaker.2026-01-31.log, croaker.2026-02-01.log, ...
+// (dates in UTC), keeping the newest `general.log_max_files`. `croaker logs` reads it back
+// through the socket, so the CLI doesn't have to know where it is.
+
+use crate::config::GeneralConfig;
+use std::path::{Path, PathBuf};
+use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+
+/// Writer for the log file, or `None` when it's turned off or can't be created. Logging stops
+/// when the guard is dropped.
+pub fn file_writer(config: &GeneralConfig) -> Option<(NonBlocking, WorkerGuard)> {
+    if config.log_file.is_empty() {
+        return None;
+    }
+    let (dir, prefix, suffix) = split(Path::new(&config.log_file));
+    let mut builder = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(prefix)
+        .max_log_files(config.log_max_files.max(1));
+    if let Some(suffix) = suffix {
+        builder = builder.filename_suffix(suffix);
+    }
+    // The appender complains about a missing directory before creating it
+    let appender = std::fs::create_dir_all(&dir)
+        .map_err(|e| e.to_string())
+        .and_then(|_| builder.build(&dir).map_err(|e| e.to_string()));
+    match appender {
+        Ok(appender) => Some(tracing_appender::non_blocking(appender)),
+        Err(e) => {
+            // Logging isn't set up yet
+            eprintln!("Not writing a log file to {}: {}", dir.display(), e);
+            None
+        }
+    }
+}
+
+/// The file being written today: the newest rotated file for `log_file`.
+pub fn current_file(log_file: &Path) -> Option<PathBuf> {
+    let (dir, prefix, suffix) = split(log_file);
+    let suffix = suffix.map(|suffix| format!(".{}", suffix)).unwrap_or_default();
+    std::fs::read_dir(&dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.strip_prefix(&format!("{}.", prefix))
+                .and_then(|rest| rest.strip_suffix(suffix.as_str()))
+                .is_some_and(is_date)
+        })
+        .map(|entry| entry.path())
+        // ISO dates sort by name
+        .max()
+}
+
+/// The last `lines` lines of `contents`.
+pub fn tail(contents: &str, lines: usize) -> String {
+    let all: Vec<&str> = contents.lines().collect();
+    let mut tail = all[all.len().saturating_sub(lines)..].join("\n");
+    if !tail.is_empty() {
+        tail.push('\n');
+    }
+    tail
+}
+
+/// Directory, file name prefix and extension of the configured path.
+fn split(log_file: &Path) -> (PathBuf, String, Option<String>) {
+    let dir = log_file.parent().map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from("."));
+    let prefix = log_file.file_stem().map_or("croaker".into(), |stem| stem.to_string_lossy().into_owned());
+    let suffix = log_file.extension().map(|ext| ext.to_string_lossy().into_owned());
+    (dir, prefix, suffix)
+}
+
+fn is_date(date: &str) -> bool {
+    date.len() == 10 && date.chars().enumerate().all(|(i, c)| if i == 4 || i == 7 { c == '-' } else { c.is_ascii_digit() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_newest_rotated_file() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["croaker.2026-01-31.log", "croaker.2026-02-01.log", "croaker.log.bak", "other.2026-03-01.log"] {
+            std::fs::write(dir.path().join(name), "").unwrap();
+        }
+        let current = current_file(&dir.path().join("croaker.log")).unwrap();
+        assert_eq!(current.file_name().unwrap(), "croaker.2026-02-01.log");
+
+        assert_eq!(tail("one\ntwo\nthree\n", 2), "two\nthree\n");
+        assert_eq!(tail("", 5), "");
+    }
+}
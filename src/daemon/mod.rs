@@ -1,4 +1,8 @@
+pub mod announce;
+pub mod hooks;
+pub mod instance;
+pub mod logs;
+pub mod orphans;
+pub mod service;
 pub mod state;
-
-pub use state::{DaemonState, StateEvent};
-
+pub mod stats;
@@ -0,0 +1,87 @@
+// Recordings a crashed or killed daemon left in the temp directory. They're normally deleted
+// once transcribed, so any still there at startup, while we hold the instance lock, belong to
+// no live daemon. Deleted, or with `transcription.recover_orphaned` handed to the offline
+// queue, which transcribes and outputs them like recordings made while offline.
+
+use crate::audio::{temp_prefix, wav};
+use crate::config::Config;
+use crate::transcribe::spool::Spool;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// Deal with the leftovers in the temp directory. Returns how many were queued for transcription.
+pub async fn recover(config: &Config) -> usize {
+    let spool = (config.transcription.recover_orphaned && config.transcription.offline_queue)
+        .then(|| Spool::new(&config.transcription.spool_dir));
+    let mut queued = 0;
+    for path in find(&std::env::temp_dir(), &temp_prefix()) {
+        if let Some(ref spool) = spool {
+            match queue(spool, &path, config).await {
+                Ok(true) => {
+                    tracing::info!("Queued {:?} from an earlier session for transcription", path);
+                    queued += 1;
+                }
+                Ok(false) => tracing::info!("Deleting empty recording {:?} from an earlier session", path),
+                Err(e) => tracing::warn!("Failed to queue {:?} from an earlier session, deleting it: {}", path, e),
+            }
+        } else {
+            tracing::info!("Deleting recording {:?} from an earlier session", path);
+        }
+        if let Err(e) = tokio::fs::remove_file(&path).await {
+            tracing::warn!("Failed to delete {:?}: {}", path, e);
+        }
+    }
+    queued
+}
+
+/// Copy a leftover into the spool, unless there's no audio in it.
+async fn queue(spool: &Spool, path: &Path, config: &Config) -> Result<bool, std::io::Error> {
+    let mut bytes = tokio::fs::read(path).await?;
+    // A killed recorder doesn't get to write the final sizes
+    if wav::repair_header(&mut bytes).is_none() || wav::duration_ms(&bytes).unwrap_or(0) == 0 {
+        return Ok(false);
+    }
+    spool.save(&bytes, &config.general.language, config.general.translate).await?;
+    Ok(true)
+}
+
+/// Our temp recordings in `dir`; the temp directory is shared with other users' daemons
+/// and our other instances.
+fn find(dir: &Path, prefix: &str) -> Vec<PathBuf> {
+    // SAFETY: getuid has no preconditions and can't fail
+    let uid = unsafe { libc::getuid() };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut found: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with(prefix) && name.ends_with(".wav")
+        })
+        .filter(|entry| entry.metadata().is_ok_and(|meta| meta.is_file() && meta.uid() == uid))
+        .map(|entry| entry.path())
+        .collect();
+    found.sort();
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_leftover_recordings() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["croaker.rec-a1b2c3.wav", "croaker.rec-x.txt", "croaker-work.rec-d4e5f6.wav", ".tmpQ1w2e3", "other.wav"] {
+            std::fs::write(dir.path().join(name), "").unwrap();
+        }
+        std::fs::create_dir(dir.path().join("croaker.rec-dir.wav")).unwrap();
+
+        let found = find(dir.path(), "croaker.rec-");
+        assert_eq!(found, vec![dir.path().join("croaker.rec-a1b2c3.wav")]);
+        let found = find(dir.path(), "croaker-work.rec-");
+        assert_eq!(found, vec![dir.path().join("croaker-work.rec-d4e5f6.wav")]);
+    }
+}
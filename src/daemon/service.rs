@@ -0,0 +1,152 @@
+// systemd integration: `croaker install-service` writes and enables a user service plus a
+// socket unit for the control socket, and `croaker serve` started by that socket unit takes
+// over the listening socket systemd passes it (socket activation). With the socket unit
+// enabled, `croaker toggle` starts the daemon on demand. A named instance gets its own pair
+// of units, `croaker-work.service` and `croaker-work.socket`.
+
+use std::os::fd::FromRawFd;
+use std::os::unix::net::UnixListener;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use super::instance::stem_for;
+use thiserror::Error;
+
+/// First descriptor passed by systemd (`SD_LISTEN_FDS_START`)
+const LISTEN_FDS_START: i32 = 3;
+
+#[derive(Debug, Error)]
+pub enum ServiceError {
+    #[error("Could not find the config directory")]
+    NoConfigDirError,
+    #[error("Failed to write unit file: {0}")]
+    WriteError(#[from] std::io::Error),
+    #[error("systemctl --user {0} failed: {1}")]
+    SystemctlError(String, String),
+}
+
+/// The control socket systemd opened for us, if we were started by `croaker.socket`, and how
+/// many sockets it passed (only the first is used).
+///
+/// The `LISTEN_*` variables are meant for this process only, not for anything we spawn, so
+/// they're cleared here. Changing the environment is only sound while no other thread runs:
+/// call this first thing in `main`, before the runtime or the log writer starts.
+pub fn take_inherited_listener() -> Option<(UnixListener, i32)> {
+    let pid = std::env::var("LISTEN_PID").ok();
+    let fds = std::env::var("LISTEN_FDS").ok();
+    std::env::remove_var("LISTEN_PID");
+    std::env::remove_var("LISTEN_FDS");
+    std::env::remove_var("LISTEN_FDNAMES");
+    let pid = pid?.parse::<u32>().ok()?;
+    let fds = fds?.parse::<i32>().ok()?;
+    if pid != std::process::id() || fds < 1 {
+        return None;
+    }
+    // SAFETY: with LISTEN_PID naming us, systemd guarantees descriptor 3 is an open socket
+    // handed over to this process, and nothing else owns it
+    let listener = unsafe { UnixListener::from_raw_fd(LISTEN_FDS_START) };
+    Some((listener, fds))
+}
+
+/// Write the user units for the binary at `exe` and enable them. Returns the files written.
+pub fn install(exe: &Path, socket_activation: bool) -> Result<Vec<PathBuf>, ServiceError> {
+    let dir = dirs::config_dir().ok_or(ServiceError::NoConfigDirError)?.join("systemd/user");
+    std::fs::create_dir_all(&dir)?;
+
+    let instance = super::instance::name();
+    let service_name = format!("{}.service", super::instance::file_stem());
+    let socket_name = format!("{}.socket", super::instance::file_stem());
+    let mut written = vec![dir.join(&service_name)];
+    std::fs::write(&written[0], service_unit(exe, socket_activation, instance))?;
+    if socket_activation {
+        written.push(dir.join(&socket_name));
+        std::fs::write(&written[1], socket_unit(instance))?;
+    }
+
+    systemctl(&["daemon-reload"])?;
+    let mut enable = vec!["enable", "--now", service_name.as_str()];
+    if socket_activation {
+        enable.insert(2, socket_name.as_str());
+    }
+    systemctl(&enable)?;
+    Ok(written)
+}
+
+fn systemctl(args: &[&str]) -> Result<(), ServiceError> {
+    let output = Command::new("systemctl").arg("--user").args(args).output()?;
+    if !output.status.success() {
+        return Err(ServiceError::SystemctlError(
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn service_unit(exe: &Path, socket_activation: bool, instance: Option<&str>) -> String {
+    let socket = if socket_activation {
+        format!("Requires={socket}.socket\nAfter={socket}.socket\n", socket = stem_for(instance))
+    } else {
+        String::new()
+    };
+    let serve = match instance {
+        Some(name) => format!("--instance {} serve", name),
+        None => "serve".to_string(),
+    };
+    format!(
+        "[Unit]
+Description=croaker speech-to-text daemon
+After=graphical-session.target sound.target
+{socket}
+[Service]
+Type=simple
+ExecStart={exe} {serve}
+Restart=on-failure
+RestartSec=5
+Environment=RUST_LOG=info
+# Ensure DBUS is available for tray/notifications
+Environment=\"DBUS_SESSION_BUS_ADDRESS=unix:path=%t/bus\"
+
+[Install]
+WantedBy=default.target
+",
+        exe = exe.display(),
+    )
+}
+
+/// `%C` is the user's cache directory, where `SocketServer::socket_path` puts the socket.
+fn socket_unit(instance: Option<&str>) -> String {
+    format!(
+        "[Unit]
+Description=croaker control socket
+
+[Socket]
+ListenStream=%C/croaker/{stem}.sock
+SocketMode=0600
+
+[Install]
+WantedBy=sockets.target
+",
+        stem = stem_for(instance),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn service_unit_ties_in_the_socket() {
+        let unit = service_unit(Path::new("/usr/local/bin/croaker"), true, None);
+        assert!(unit.contains("ExecStart=/usr/local/bin/croaker serve\n"));
+        assert!(unit.contains("Requires=croaker.socket\n"));
+        assert!(!service_unit(Path::new("/usr/bin/croaker"), false, None).contains("croaker.socket"));
+    }
+
+    #[test]
+    fn instance_units_are_named_after_it() {
+        let unit = service_unit(Path::new("/usr/bin/croaker"), true, Some("work"));
+        assert!(unit.contains("ExecStart=/usr/bin/croaker --instance work serve\n"));
+        assert!(unit.contains("Requires=croaker-work.socket\n"));
+        assert!(socket_unit(Some("work")).contains("ListenStream=%C/croaker/croaker-work.sock\n"));
+    }
+}
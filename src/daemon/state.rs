@@ -1,30 +1,431 @@
-use crate::audio::AudioRecorder;
-use crate::config::Config;
+use crate::audio::{self, AudioRecorder, Recording};
+use crate::config::{AppProfile, CaptureSource, Config, ReadBack, SessionProfile};
+use crate::daemon::hooks::{self, Hook};
+use crate::daemon::announce;
+use crate::daemon::stats::{Stage, Stats};
+use crate::history::{History, NewEntry};
+use crate::input::socket::LEVEL_FLOOR_DBFS;
 use crate::output::clipboard::ClipboardOutput;
+use crate::output::dbus::DbusOutput;
+use crate::output::virtual_keyboard::VirtualKeyboardOutput;
+use crate::output::keymap::Keymap;
+use crate::output::led::KeyboardLed;
+use crate::output::keys::{Modifier, PasteKeys};
+use crate::output::pacing::{self, Pacing};
+use crate::output::preview::Decision;
 use crate::output::uinput::UinputKeyboard;
-use crate::transcribe::{CleanupClient, WhisperClient};
+use crate::transcribe::backend::{create_backend, TranscriptionError};
+use crate::transcribe::{CleanupClient, TranscriptionBackend};
+use crate::transcribe::emoji::Emoji;
+use crate::transcribe::redact::Redactor;
+use crate::transcribe::replace::Replacements;
+use crate::transcribe::spool::Spool;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use unicode_segmentation::UnicodeSegmentation;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How often the input level of a recording is measured, for `subscribe` and
+/// `audio.idle_stop_secs`
+const LEVEL_CHECK_INTERVAL: Duration = Duration::from_millis(200);
+/// Quieter than this counts as no speech. Well above a typical mic's noise floor, well below talking
+const SPEECH_DBFS: f32 = -45.0;
+/// Tries a chunk of a long dictation gets before the dictation fails, a second apart per try
+const CHUNK_ATTEMPTS: u32 = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum DaemonState {
     Idle,
     Recording,
     Processing,
     Outputting,
+    /// Something failed; shown for `overlay.error_secs`, then back to Idle
+    Error(String),
+}
+
+/// What a dictation in the Processing state is waiting for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// Uploading the recording and waiting for the transcript; one request, so one step
+    Transcribing,
+    CleaningUp,
+}
+
+impl Step {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Step::Transcribing => "transcribing",
+            Step::CleaningUp => "cleaning up",
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum StateEvent {
     StartRecording,
     StopRecording,
+    /// Start recording when idle (with one-off overrides from `croaker toggle --source/--language`),
+    /// stop when recording. What happened is sent back if a reply channel is given
+    Toggle(RecordOptions, Option<oneshot::Sender<ToggleAction>>),
     Cancel,
-    ProcessingComplete(String),
+    /// A processing task finished: (job, text)
+    ProcessingComplete(u64, Transcript),
+    /// A processing task failed, with the reason to show, or heard nothing (already reported).
+    /// What's needed to try again comes along if it can be retried
+    ProcessingFailed(u64, Option<String>, Option<Retry>),
+    /// A sentence of streamed cleanup output, ready to be written while still processing: (job, text)
+    CleanupFragment(u64, String),
+    /// A processing task moved on to the next step: (job, step)
+    Progress(u64, Step),
     OutputComplete,
     ToggleOutputMode,
     ToggleLanguage,
+    ToggleTranslate,
+    /// Switch to the next session profile (`[profiles]`), then back to none
+    CycleProfile,
+    /// Stop listening to hotkeys (`croaker pause`), or start again (`croaker resume`)
+    Pause(bool),
+    /// Skip LLM cleanup for the next recording (sent again, undoes it)
+    SkipCleanupNext,
+    /// Time to transcribe the next chunk of a long recording
+    ChunkDue,
+    /// A chunk finished transcribing: (session, chunk index, text)
+    ChunkTranscribed(u64, usize, Transcript),
+    /// A chunk still failed after `CHUNK_ATTEMPTS`: (session, chunk index, reason)
+    ChunkFailed(u64, usize, String),
+    /// A recording spooled while offline has been transcribed
+    SpoolTranscribed(Transcript),
+    /// Text sent from another machine's croaker (`croaker receive`)
+    Received(Transcript),
+    /// Output the last transcription again
+    Repeat,
+    /// Re-run LLM cleanup on the last raw transcript, optionally with another prompt file
+    Recleanup(Option<PathBuf>),
+    /// Remove the text inserted by the last dictation
+    Undo,
+    /// The user answered the output preview
+    PreviewResolved(Decision),
+    /// The countdown before output ran out; write this text
+    CountdownElapsed(String),
+    /// Change a setting at runtime; the error, if any, is sent back
+    Set(Setting, oneshot::Sender<Result<(), String>>),
+    /// The config profile asked for with `Set(Setting::ConfigProfile)` has been loaded, or
+    /// couldn't be; the reply goes back once it's applied
+    ConfigProfileLoaded(Option<String>, Result<Box<LoadedConfig>, String>, oneshot::Sender<Result<(), String>>),
+    /// Send back the config as currently in effect
+    GetConfig(oneshot::Sender<Config>),
+    /// No second push-to-talk press followed the numbered tap (`hotkeys.double_tap_lock`)
+    TapWindowElapsed(u64),
+    /// Time to leave the numbered error (`overlay.error_secs`)
+    ErrorElapsed(u64),
+    /// The numbered stage ran past its `[watchdog]` deadline
+    StageTimeout(u64),
+    /// Time to measure the input level of the recording, and see whether a hands-free one has
+    /// gone quiet (`audio.idle_stop_secs`)
+    LevelCheck,
+    /// Try the dictation that failed last again (the Retry action of its notification)
+    Retry,
+    /// Stop the daemon (`croaker quit`, the tray's Quit, SIGTERM)
+    Shutdown,
+}
+
+impl StateEvent {
+    /// Something the user started, which takes over from an error still on screen.
+    fn is_user_action(&self) -> bool {
+        matches!(
+            self,
+            StateEvent::StartRecording
+                | StateEvent::Cancel
+                | StateEvent::Repeat
+                | StateEvent::Recleanup(_)
+                | StateEvent::Undo
+                | StateEvent::Retry
+        )
+    }
+}
+
+/// What a failed dictation left behind to try again with.
+#[derive(Clone)]
+pub enum Retry {
+    /// The recording, when transcription or cleanup failed
+    Audio { wav: Vec<u8>, language: String, translate: bool },
+    /// The text, when writing it out failed
+    Output(String),
+}
+
+// Recordings run to megabytes, keep them out of event logs
+impl std::fmt::Debug for Retry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Retry::Audio { wav, language, .. } => write!(f, "Audio({} bytes, {})", wav.len(), language),
+            Retry::Output(text) => write!(f, "Output({} chars)", text.chars().count()),
+        }
+    }
+}
+
+/// Settings that can be changed while the daemon runs (`croaker set-language`, ...).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Setting {
+    Language(String),
+    OutputMode(crate::config::OutputMode),
+    Cleanup(bool),
+    /// Switch to a session profile by name, or back to the global settings
+    Profile(Option<String>),
+    /// Reload the config with a config profile layered over it, or without one
+    ConfigProfile(Option<String>),
+}
+
+/// Outcome of recent work, shared with the socket server for `croaker status`.
+#[derive(Debug, Clone, Default)]
+pub struct Activity {
+    pub last_error: Option<String>,
+    /// Characters in the last transcription
+    pub last_transcript_chars: Option<usize>,
+}
+
+/// What a `Toggle` event did, reported back to `croaker toggle`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToggleAction {
+    Started,
+    Stopped,
+    /// Busy processing or outputting the previous dictation
+    Ignored(DaemonState),
+}
+
+/// What a `Toggle` does in a given state, apart from the state machine so it can be tested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ToggleStep {
+    /// Start recording, taking over from an error still on screen
+    Start,
+    /// Queue the dictation being processed and start recording
+    QueueAndStart,
+    Stop,
+    Ignore,
+}
+
+impl ToggleStep {
+    /// `can_queue`: the dictation being processed can wait in the queue (`StateMachine::can_queue`).
+    fn new(state: &DaemonState, can_queue: bool) -> Self {
+        match state {
+            DaemonState::Idle | DaemonState::Error(_) => ToggleStep::Start,
+            DaemonState::Recording => ToggleStep::Stop,
+            DaemonState::Processing if can_queue => ToggleStep::QueueAndStart,
+            DaemonState::Processing | DaemonState::Outputting => ToggleStep::Ignore,
+        }
+    }
+}
+
+impl std::fmt::Display for ToggleAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ToggleAction::Started => write!(f, "started"),
+            ToggleAction::Stopped => write!(f, "stopped"),
+            ToggleAction::Ignored(state) => write!(f, "ignored ({:?})", state),
+        }
+    }
+}
+
+/// Overrides for a single recording; unset fields use the configured defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordOptions {
+    pub source: Option<CaptureSource>,
+    /// Transcribe this utterance in this language without switching the current one
+    pub language: Option<String>,
+}
+
+/// Text produced from one recording.
+#[derive(Debug, Clone, Default)]
+pub struct Transcript {
+    /// Transcription before LLM cleanup
+    pub raw: String,
+    /// Text that gets output
+    pub text: String,
+}
+
+/// What the last dictation put into the focused application, so `croaker undo` can take it back.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Insertion {
+    /// Characters typed key by key, removed with Backspace. Counted in grapheme clusters, since
+    /// one Backspace removes a whole emoji or accented letter
+    typed: usize,
+    /// Clipboard pastes, removed with Ctrl+Z
+    pastes: usize,
+    /// Characters pasted, removed with Backspace where Ctrl+Z isn't undo (terminals)
+    pasted: usize,
+}
+
+/// Push-to-talk presses of the current recording, for `hotkeys.double_tap_lock`.
+#[derive(Debug, Default)]
+struct PushToTalk {
+    pressed_at: Option<Instant>,
+    /// How long the key was held, once released
+    held: Option<Duration>,
+    /// Released quickly, waiting to see whether a second press follows
+    tapped: bool,
+    /// Taps so far, so a stale `TapWindowElapsed` is ignored
+    taps: u64,
+    /// Double-tapped: keep recording until the next press
+    locked: bool,
+    /// The key is down. Nothing queued is typed meanwhile, as a held AltGr (the default
+    /// RightAlt) or other modifier would turn keystrokes and Ctrl+V into something else
+    down: bool,
+}
+
+/// A dictation still being processed after a newer recording started. It's written once every
+/// earlier one has been, so dictations come out in the order they were spoken.
+struct QueuedDictation {
+    job: u64,
+    token: CancellationToken,
+    target: Option<Target>,
+    /// What it inserted, kept apart from the current dictation's
+    inserted: Insertion,
+    /// Cleanup is streamed: what isn't in `held` has been written already
+    streamed: bool,
+    /// Streamed sentences waiting for earlier dictations to be written
+    held: String,
+    /// Set once processing is done, empty if it failed
+    transcript: Option<Transcript>,
+}
+
+/// Text of a queued dictation that can be written now.
+struct ReadyText {
+    queued: QueuedDictation,
+    text: String,
+    /// `None` while it's still being processed: `text` is what it streamed so far, and it goes
+    /// back to the front of the queue with `DictationQueue::resume`
+    transcript: Option<Transcript>,
+}
+
+/// The queued dictations, oldest first. Results arrive in any order; they're handed out for
+/// writing in the order the dictations were spoken.
+#[derive(Default)]
+struct DictationQueue {
+    queued: VecDeque<QueuedDictation>,
+}
+
+impl DictationQueue {
+    fn push(&mut self, queued: QueuedDictation) {
+        self.queued.push_back(queued);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.queued.is_empty()
+    }
+
+    fn contains(&self, job: u64) -> bool {
+        self.queued.iter().any(|queued| queued.job == job)
+    }
+
+    /// Stop processing every queued dictation and forget them.
+    fn cancel(&mut self) {
+        for queued in self.queued.drain(..) {
+            queued.token.cancel();
+        }
+    }
+
+    /// Hold a streamed sentence of `job`. False if it isn't queued (it was cancelled).
+    fn fragment(&mut self, job: u64, text: &str) -> bool {
+        let Some(queued) = self.queued.iter_mut().find(|queued| queued.job == job) else {
+            return false;
+        };
+        queued.streamed = true;
+        queued.held.push_str(text);
+        true
+    }
+
+    /// Mark `job` done, with an empty transcript if it failed. False if it isn't queued.
+    fn complete(&mut self, job: u64, transcript: Transcript) -> bool {
+        let Some(queued) = self.queued.iter_mut().find(|queued| queued.job == job) else {
+            return false;
+        };
+        queued.transcript = Some(transcript);
+        true
+    }
+
+    /// Take what can be written now, oldest first: the dictations that are done, up to the
+    /// first one still being processed, and of that one the sentences streamed so far.
+    fn take_ready(&mut self) -> Vec<ReadyText> {
+        let mut ready = Vec::new();
+        while let Some(mut queued) = self.queued.pop_front() {
+            let held = std::mem::take(&mut queued.held);
+            match queued.transcript.take() {
+                Some(transcript) => {
+                    let text = if queued.streamed { held } else { transcript.text.clone() };
+                    ready.push(ReadyText { queued, text, transcript: Some(transcript) });
+                }
+                None => {
+                    ready.push(ReadyText { queued, text: held, transcript: None });
+                    break;
+                }
+            }
+        }
+        ready
+    }
+
+    /// Put back the dictation `take_ready` handed out while still being processed.
+    fn resume(&mut self, queued: QueuedDictation) {
+        self.queued.push_front(queued);
+    }
+}
+
+/// The last text inserted, so the next dictation into the same window can continue it.
+struct OutputContext {
+    app: Option<String>,
+    text: String,
+    at: Instant,
+}
+
+/// Bookkeeping for chunked long-form dictation.
+///
+/// Chunks are transcribed concurrently but emitted strictly in order, so
+/// results are parked in `results` until every earlier chunk has been output.
+#[derive(Default)]
+struct ChunkState {
+    /// Bumped on every recording so late results from a cancelled one are ignored
+    session: u64,
+    ticker: Option<JoinHandle<()>>,
+    tasks: Vec<JoinHandle<()>>,
+    /// Milliseconds of audio already handed to a chunk. Kept as time rather than a
+    /// sample offset because the final recording may be downmixed or resampled.
+    cursor_ms: u64,
+    next_index: usize,
+    emit_index: usize,
+    final_index: Option<usize>,
+    results: BTreeMap<usize, Transcript>,
+    /// Text of the last emitted chunk, used to drop words repeated in the overlap
+    previous: String,
+    /// Everything emitted so far
+    transcript: String,
+    /// Raw transcripts of the emitted chunks. Overlapping words aren't removed here,
+    /// the cleanup LLM deals with them if this is ever re-cleaned.
+    raw: String,
+}
+
+impl ChunkState {
+    fn active(&self) -> bool {
+        self.next_index > 0
+    }
+
+    fn reset(&mut self) {
+        if let Some(ticker) = self.ticker.take() {
+            ticker.abort();
+        }
+        for task in self.tasks.drain(..) {
+            task.abort();
+        }
+        *self = Self {
+            session: self.session + 1,
+            ..Self::default()
+        };
+    }
 }
 
 #[derive(Debug, Error)]
@@ -32,68 +433,492 @@ pub enum StateError {
     #[error("Audio error: {0}")]
     AudioError(#[from] crate::audio::AudioError),
     #[error("Transcription error: {0}")]
-    TranscriptionError(#[from] crate::transcribe::whisper::WhisperError),
+    TranscriptionError(#[from] TranscriptionError),
     #[error("Cleanup error: {0}")]
     CleanupError(#[from] crate::transcribe::cleanup::CleanupError),
     #[error("Output error: {0}")]
     OutputError(#[from] crate::output::uinput::UinputError),
     #[error("Clipboard error: {0}")]
     ClipboardError(#[from] crate::output::clipboard::ClipboardError),
+    #[error("No speech detected")]
+    NoSpeech,
     #[error("Invalid state transition")]
     InvalidTransition,
+    #[error("{0} took too long")]
+    Timeout(&'static str),
+}
+
+impl StateError {
+    /// Short reason for the Error state, what the user can do something about.
+    fn summary(&self) -> String {
+        use crate::transcribe::cleanup::CleanupError;
+        use crate::transcribe::whisper::WhisperError;
+        match self {
+            StateError::TranscriptionError(TranscriptionError::WhisperError(WhisperError::Timeout(_))) => {
+                "Network timeout".to_string()
+            }
+            StateError::TranscriptionError(e) if e.is_network() => "Network unreachable".to_string(),
+            StateError::CleanupError(CleanupError::RequestError(e)) if e.is_timeout() => "Network timeout".to_string(),
+            StateError::CleanupError(CleanupError::RequestError(_)) => "Network unreachable".to_string(),
+            StateError::TranscriptionError(TranscriptionError::WhisperError(WhisperError::ApiError(message)))
+            | StateError::CleanupError(CleanupError::ApiError(message)) => {
+                api_summary(message).map_or_else(|| self.to_string(), str::to_string)
+            }
+            StateError::AudioError(_) => "Recording failed".to_string(),
+            StateError::OutputError(_) | StateError::ClipboardError(_) => "Output failed".to_string(),
+            e => e.to_string(),
+        }
+    }
+
+    /// Stage to count the failure against in `croaker stats`.
+    fn stage(&self) -> Option<Stage> {
+        match self {
+            StateError::AudioError(_) => Some(Stage::Record),
+            StateError::TranscriptionError(_) => Some(Stage::Transcription),
+            StateError::CleanupError(_) => Some(Stage::Cleanup),
+            StateError::OutputError(_) | StateError::ClipboardError(_) | StateError::Timeout("Output") => Some(Stage::Output),
+            _ => None,
+        }
+    }
+}
+
+/// Reason for an API error message ("HTTP 401 Unauthorized: ...") that has an obvious one.
+fn api_summary(message: &str) -> Option<&'static str> {
+    let status = message.strip_prefix("HTTP ").and_then(|rest| rest.get(..3));
+    match status {
+        Some("401") | Some("403") => Some("API key invalid"),
+        Some("429") => Some("Rate limited by the API"),
+        Some(code) if code.starts_with('5') => Some("API unavailable"),
+        _ if message.contains("timed out") => Some("Network timeout"),
+        _ => None,
+    }
+}
+
+/// How one recording gets cleaned up.
+#[derive(Debug, Clone)]
+enum CleanupStep {
+    /// Output the raw transcript (`raw-next`, or an app profile with cleanup disabled)
+    Skip,
+    Default,
+    /// An app profile's prompt file instead of the configured one
+    Prompt(PathBuf),
+}
+
+/// The application being dictated into and its profile, looked up once per dictation.
+#[derive(Debug, Clone, Default)]
+struct Target {
+    app: Option<String>,
+    profile: Option<AppProfile>,
+}
+
+/// Everything that turns a recording into final text. Cheap to clone into spawned tasks.
+#[derive(Clone)]
+struct Pipeline {
+    transcriber: Arc<dyn TranscriptionBackend>,
+    cleanup_client: CleanupClient,
+    voice_commands: bool,
+    emoji: Option<Arc<Emoji>>,
+    replacements: Option<Arc<Replacements>>,
+    redactor: Option<Arc<Redactor>>,
+    history: Option<History>,
+    stats: Arc<std::sync::Mutex<Stats>>,
+}
+
+/// A config with the processing pieces built from it, loaded off the event loop for a config
+/// profile switch.
+pub struct LoadedConfig {
+    config: Config,
+    transcriber: Arc<dyn TranscriptionBackend>,
+    cleanup_client: CleanupClient,
+    emoji: Option<Arc<Emoji>>,
+    replacements: Option<Arc<Replacements>>,
+    redactor: Option<Arc<Redactor>>,
+}
+
+impl LoadedConfig {
+    /// Load the config with the config profile `profile`; blocks on files and the keyring.
+    fn load(profile: Option<&str>, mock: bool) -> Result<Self, String> {
+        let mut config = Config::load_profile(profile).map_err(|e| e.to_string())?;
+        if mock {
+            config.transcription.provider = "mock".to_string();
+        }
+        let api_key = api_key(&config).map_err(|e| e.to_string())?;
+        let transcriber = create_backend(&config, &api_key).map_err(|e| e.to_string())?;
+        let cleanup_client = CleanupClient::new(config.clone(), api_key).map_err(|e| e.to_string())?;
+        Ok(Self {
+            transcriber,
+            cleanup_client,
+            emoji: config.general.emoji.then(|| Arc::new(Emoji::new(&config.general.emoji_file))),
+            replacements: (!config.general.replacements_file.is_empty())
+                .then(|| Arc::new(Replacements::new(&config.general.replacements_file))),
+            redactor: config.redaction.enabled.then(|| Arc::new(Redactor::new(&config.redaction))),
+            config,
+        })
+    }
+}
+
+impl std::fmt::Debug for LoadedConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoadedConfig").field("provider", &self.transcriber.name()).finish_non_exhaustive()
+    }
+}
+
+/// Where the foreground dictation's processing task reports to.
+struct Report<'a> {
+    job: u64,
+    events: &'a mpsc::Sender<StateEvent>,
+    /// Send cleaned sentences as `CleanupFragment`s as they arrive
+    stream: bool,
+}
+
+impl Report<'_> {
+    async fn step(&self, step: Step) {
+        let _ = self.events.send(StateEvent::Progress(self.job, step)).await;
+    }
+}
+
+impl Pipeline {
+    /// With `report`, each step is sent as `Progress`. If it streams, cleaned sentences are
+    /// sent as `CleanupFragment`s as they arrive and the returned transcript only records what
+    /// was sent.
+    async fn process(
+        &self,
+        recording: &Recording,
+        language: &str,
+        translate: bool,
+        cleanup: &CleanupStep,
+        report: Option<Report<'_>>,
+    ) -> Result<Transcript, StateError> {
+        if let Some(ref report) = report {
+            report.step(Step::Transcribing).await;
+        }
+        // Transcribe with current language, or straight to English in translation mode
+        let started = Instant::now();
+        let raw_text = if translate {
+            self.transcriber.translate(recording).await
+        } else {
+            self.transcriber.transcribe(recording, language).await
+        };
+        let raw_text = self.timed(Stage::Transcription, started, raw_text.map_err(StateError::from))?;
+
+        // Nothing said: don't spend a cleanup request on it, and don't output anything
+        if crate::transcribe::nospeech::is_no_speech(&raw_text) {
+            tracing::info!("No speech detected (raw transcript {:?})", raw_text);
+            return Err(StateError::NoSpeech);
+        }
+
+        // Cleanup, unless the user asked for the raw text this time
+        let prompt = match cleanup {
+            CleanupStep::Prompt(path) => match tokio::fs::read_to_string(path).await {
+                Ok(prompt) => Some(prompt.trim().to_string()),
+                Err(e) => {
+                    tracing::warn!("Failed to read prompt file {:?}, using the default prompt: {}", path, e);
+                    None
+                }
+            },
+            _ => None,
+        };
+        if let (Some(ref report), false) = (&report, matches!(cleanup, CleanupStep::Skip)) {
+            report.step(Step::CleaningUp).await;
+        }
+        let started = Instant::now();
+        let transcript = match (cleanup, report) {
+            (CleanupStep::Skip, _) => {
+                tracing::info!("Skipping cleanup for this dictation");
+                self.finish(raw_text.clone(), raw_text)
+            }
+            (_, Some(Report { job, events, stream: true })) => {
+                let transcript = self.stream_cleanup(raw_text, prompt.as_deref(), job, events).await;
+                self.timed(Stage::Cleanup, started, transcript)?
+            }
+            (_, _) => {
+                let cleaned_text = match prompt {
+                    Some(ref prompt) => self.cleanup_client.cleanup_with_prompt(&raw_text, Some(prompt)).await,
+                    None => self.cleanup_client.cleanup(&raw_text).await,
+                };
+                let cleaned_text = self.timed(Stage::Cleanup, started, cleaned_text.map_err(StateError::from))?;
+                self.finish(raw_text, cleaned_text)
+            }
+        };
+
+        if let Some(ref history) = self.history {
+            let entry = NewEntry {
+                duration_ms: recording.read().await.ok().and_then(|wav| audio::wav::duration_ms(&wav)),
+                language: language.to_string(),
+                model: self.transcriber.model(translate).to_string(),
+                raw_text: transcript.raw.clone(),
+                cleaned_text: transcript.text.clone(),
+            };
+            let history = history.clone();
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) = history.record(&entry) {
+                    tracing::warn!("Failed to save transcription to history: {}", e);
+                }
+            });
+        }
+
+        Ok(transcript)
+    }
+
+    /// Run cleanup in streaming mode, post-processing and forwarding each sentence.
+    async fn stream_cleanup(
+        &self,
+        raw_text: String,
+        prompt: Option<&str>,
+        job: u64,
+        events: &mpsc::Sender<StateEvent>,
+    ) -> Result<Transcript, StateError> {
+        let (sentence_tx, mut sentence_rx) = mpsc::channel::<String>(16);
+        let cleanup = self.cleanup_client.cleanup_streaming(&raw_text, prompt, sentence_tx);
+        let forward = async {
+            let mut text = String::new();
+            while let Some(sentence) = sentence_rx.recv().await {
+                let fragment = self.post_process(&sentence);
+                text.push_str(&fragment);
+                let _ = events.send(StateEvent::CleanupFragment(job, fragment)).await;
+            }
+            text
+        };
+        let (cleaned, text) = tokio::join!(cleanup, forward);
+        cleaned?;
+
+        let raw = match self.redactor {
+            Some(ref redactor) => redactor.redact(&raw_text),
+            None => raw_text,
+        };
+        Ok(Transcript { raw, text })
+    }
+
+    /// Count a finished request in `croaker stats`: its time if it worked, else the failure.
+    fn timed<T>(&self, stage: Stage, started: Instant, result: Result<T, StateError>) -> Result<T, StateError> {
+        let mut stats = self.stats.lock().unwrap();
+        match result {
+            Ok(_) => stats.observe(stage, started.elapsed()),
+            Err(_) => stats.error(stage.as_str()),
+        }
+        result
+    }
+
+    /// Apply voice commands, emoji, user replacements and redaction to freshly cleaned text.
+    fn finish(&self, raw_text: String, cleaned_text: String) -> Transcript {
+        // Redact both, the raw text is kept in history and for `recleanup`
+        let raw = match self.redactor {
+            Some(ref redactor) => redactor.redact(&raw_text),
+            None => raw_text,
+        };
+        Transcript {
+            raw,
+            text: self.post_process(&cleaned_text),
+        }
+    }
+
+    fn post_process(&self, cleaned: &str) -> String {
+        // Streamed sentences carry the space that separates them from the next one
+        let body = cleaned.trim_end();
+        let trailing = &cleaned[body.len()..];
+
+        let mut text = body.to_string();
+        if self.voice_commands {
+            text = crate::transcribe::commands::apply_voice_commands(&text);
+        }
+        if let Some(ref emoji) = self.emoji {
+            text = emoji.apply(&text);
+        }
+        if let Some(ref replacements) = self.replacements {
+            text = replacements.apply(&text);
+        }
+        if let Some(ref redactor) = self.redactor {
+            text = redactor.redact(&text);
+        }
+        text.push_str(trailing);
+        text
+    }
 }
 
 pub struct StateMachine {
     state: DaemonState,
     config: Config,
     audio_recorder: AudioRecorder,
-    whisper_client: WhisperClient,
-    cleanup_client: CleanupClient,
+    pipeline: Pipeline,
     keyboard: Arc<UinputKeyboard>,
     clipboard: ClipboardOutput,
+    /// Set when the compositor supports zwp_virtual_keyboard_v1 (and `output.virtual_keyboard` is on)
+    virtual_keyboard: Option<VirtualKeyboardOutput>,
+    /// Lit while recording (`overlay.keyboard_led`)
+    keyboard_led: Option<KeyboardLed>,
+    default_paste_keys: PasteKeys,
+    /// Lowercased app id -> paste keys
+    app_paste_keys: HashMap<String, PasteKeys>,
     overlay_tx: Option<std::sync::mpsc::Sender<crate::overlay::OverlayMessage>>,
     event_tx: mpsc::Sender<StateEvent>,
     event_rx: mpsc::Receiver<StateEvent>,
     state_tx: Option<mpsc::Sender<DaemonState>>,
-    current_language_index: usize,
-    current_output_mode: crate::config::OutputMode,
+    /// Shared with the socket server so `croaker status` can report it
+    current_language: Arc<std::sync::Mutex<String>>,
+    /// Shared with the socket server so `croaker status` can report it
+    current_output_mode: Arc<std::sync::Mutex<crate::config::OutputMode>>,
+    /// Shared with the socket server so `croaker status` can report it
+    translate: Arc<AtomicBool>,
+    /// Shared with the socket server so `croaker status` can report it
+    activity: Arc<std::sync::Mutex<Activity>>,
+    /// Input level while recording, followed by the socket server
+    audio_level: tokio::sync::watch::Sender<Option<f32>>,
+    /// Active session profile; shared with the socket server so `croaker status` can report it
+    profile: Arc<std::sync::Mutex<Option<String>>>,
+    /// Hotkeys are ignored while set; shared with the hotkey gate and the socket server
+    paused: Arc<AtomicBool>,
+    /// Shared with the pipeline and the socket server for `croaker stats`
+    stats: Arc<std::sync::Mutex<Stats>>,
+    /// When the current recording started, for the record stage of `croaker stats`
+    recording_started: Option<Instant>,
+    chunks: ChunkState,
+    spool: Option<Spool>,
+    /// Spooled transcriptions that arrived while busy, output once idle again
+    deferred: VecDeque<Transcript>,
+    /// Last transcription output, kept for `repeat` and `recleanup`
+    last: Option<Transcript>,
+    /// Cancels the in-flight transcription/cleanup task
+    processing: Option<CancellationToken>,
+    /// Id of the last processing task started, so results of queued or cancelled ones are told apart
+    job: u64,
+    /// Earlier dictations still being processed when a new recording started, oldest first
+    queue: DictationQueue,
+    /// Errors shown so far, so a stale `ErrorElapsed` leaves a newer one alone
+    errors: u64,
+    /// Bumped on every state change, so a `StageTimeout` of an earlier stage is ignored
+    stage: u64,
+    watchdog: Option<JoinHandle<()>>,
+    /// A transcript was remembered since the last output, show it once written
+    fresh_transcript: bool,
+    /// Sends `LevelCheck` while recording
+    level_ticker: Option<JoinHandle<()>>,
+    /// The last failed dictation, until it's retried or a new recording starts
+    retry: Option<Retry>,
+    /// Waits for the Retry action of the last failure's notification
+    retry_prompt: Option<JoinHandle<()>>,
+    /// Set by `SkipCleanupNext`, moved to `skip_cleanup` when the next recording starts
+    skip_cleanup_next: bool,
+    skip_cleanup: bool,
+    /// Language given with `croaker toggle --language` for the current recording
+    language_override: Option<String>,
+    /// Focused application for the current dictation, cleared when idle again
+    target: Option<Target>,
+    /// Part of the current transcription was already written from streamed cleanup
+    streamed: bool,
+    /// Text inserted by the current or last dictation, reset when the next one starts
+    inserted: Insertion,
+    /// Set by every insertion, for `general.smart_join`
+    last_output: Option<OutputContext>,
+    /// Session bus connection for the `TranscriptionReady` signal, set up in `run`
+    dbus: Option<DbusOutput>,
+    push_to_talk: PushToTalk,
+    /// A config profile is being loaded (`load_config_profile`)
+    loading_config_profile: bool,
 }
 
 impl StateMachine {
     pub fn new(config: Config) -> Result<Self, StateError> {
-        let api_key = config.load_api_key()
-            .map_err(|e| StateError::TranscriptionError(crate::transcribe::whisper::WhisperError::ApiError(e.to_string())))?;
+        let api_key = api_key(&config).map_err(|e| {
+            StateError::TranscriptionError(crate::transcribe::whisper::WhisperError::ApiError(e.to_string()).into())
+        })?;
 
-        let whisper_client = WhisperClient::new(config.clone(), api_key.clone());
+        let transcriber = create_backend(&config, &api_key)?;
+        tracing::info!("Transcription provider: {}", transcriber.name());
         let cleanup_client = CleanupClient::new(config.clone(), api_key)
-            .map_err(|e| StateError::CleanupError(e))?;
+            .map_err(StateError::CleanupError)?;
         
-        let keyboard = Arc::new(UinputKeyboard::new(&config)?);
-        let clipboard = ClipboardOutput::new(keyboard.clone(), config.output.clipboard_restore);
+        let keymap = Keymap::load(&config.output.keyboard_layout, &config.output.keyboard_variant);
+        if keymap.is_none() {
+            tracing::info!("No XKB keymap available, uinput typing is limited to ASCII on a US layout");
+        }
+        let keyboard = Arc::new(UinputKeyboard::new(keymap, config.output.uinput_settle_ms)?);
+        let clipboard = ClipboardOutput::new(
+            keyboard.clone(),
+            config.output.clipboard_restore,
+            config.output.clipboard_restore_delay_ms,
+        );
 
         let (event_tx, event_rx) = mpsc::channel(32);
+        let stats = Arc::new(std::sync::Mutex::new(Stats::default()));
 
         let config_clone = config.clone();
         
-        // Find initial language index
-        let current_language_index = config.general.languages.iter()
-            .position(|l| l == &config.general.language)
-            .unwrap_or(0);
+        // Start on the configured language if it's in the toggle list, else on the first entry
+        let current_language = if config.general.languages.contains(&config.general.language) {
+            config.general.language.clone()
+        } else {
+            config.general.languages.first().cloned().unwrap_or_else(|| config.general.language.clone())
+        };
         
         Ok(Self {
             state: DaemonState::Idle,
             config: config.clone(),
             audio_recorder: AudioRecorder::new(config_clone),
-            whisper_client,
-            cleanup_client,
+            pipeline: Pipeline {
+                transcriber,
+                cleanup_client,
+                voice_commands: config.general.voice_commands,
+                emoji: config.general.emoji.then(|| Arc::new(Emoji::new(&config.general.emoji_file))),
+                replacements: (!config.general.replacements_file.is_empty())
+                    .then(|| Arc::new(Replacements::new(&config.general.replacements_file))),
+                redactor: config.redaction.enabled.then(|| Arc::new(Redactor::new(&config.redaction))),
+                history: config.history.enabled.then(|| History::new(&config.history.db_path)),
+                stats: stats.clone(),
+            },
             keyboard,
             clipboard,
+            virtual_keyboard: if config.output.virtual_keyboard {
+                VirtualKeyboardOutput::detect()
+            } else {
+                None
+            },
+            keyboard_led: (!config.overlay.keyboard_led.is_empty())
+                .then(|| KeyboardLed::open(&config.overlay.keyboard_led))
+                .and_then(|led| led.map_err(|e| tracing::warn!("No recording LED: {}", e)).ok()),
+            default_paste_keys: parse_paste_keys(&config.output.paste_keys).unwrap_or_default(),
+            app_paste_keys: config
+                .output
+                .app_paste_keys
+                .iter()
+                .filter_map(|(app, keys)| Some((app.to_lowercase(), parse_paste_keys(keys)?)))
+                .collect(),
             overlay_tx: None,
             event_tx,
             event_rx,
             state_tx: None,
-            current_language_index,
-            current_output_mode: config.output.output_mode,
+            current_language: Arc::new(std::sync::Mutex::new(current_language)),
+            current_output_mode: Arc::new(std::sync::Mutex::new(config.output.output_mode)),
+            translate: Arc::new(AtomicBool::new(config.general.translate)),
+            activity: Arc::new(std::sync::Mutex::new(Activity::default())),
+            audio_level: tokio::sync::watch::Sender::new(None),
+            profile: Arc::new(std::sync::Mutex::new(None)),
+            paused: Arc::new(AtomicBool::new(false)),
+            stats,
+            recording_started: None,
+            chunks: ChunkState::default(),
+            spool: config.transcription.offline_queue.then(|| Spool::new(&config.transcription.spool_dir)),
+            deferred: VecDeque::new(),
+            last: None,
+            processing: None,
+            job: 0,
+            queue: DictationQueue::default(),
+            errors: 0,
+            stage: 0,
+            watchdog: None,
+            fresh_transcript: false,
+            level_ticker: None,
+            retry: None,
+            retry_prompt: None,
+            skip_cleanup_next: false,
+            skip_cleanup: false,
+            language_override: None,
+            target: None,
+            streamed: false,
+            inserted: Insertion::default(),
+            last_output: None,
+            dbus: None,
+            push_to_talk: PushToTalk::default(),
+            loading_config_profile: false,
         })
     }
 
@@ -105,8 +930,48 @@ impl StateMachine {
         self.overlay_tx = Some(overlay_tx);
     }
 
-    pub fn state(&self) -> DaemonState {
-        self.state
+    /// Name of the program used for audio capture, for `croaker status`.
+    pub fn recorder_name(&self) -> &'static str {
+        self.audio_recorder.backend().map(|b| b.program()).unwrap_or("none")
+    }
+
+    /// Flag reflecting whether translation mode is on.
+    pub fn translate_flag(&self) -> Arc<AtomicBool> {
+        self.translate.clone()
+    }
+
+    /// Language currently transcribed in (`toggle-language` cycles it).
+    pub fn language_handle(&self) -> Arc<std::sync::Mutex<String>> {
+        self.current_language.clone()
+    }
+
+    /// Runtime output mode (before app profiles).
+    pub fn activity_handle(&self) -> Arc<std::sync::Mutex<Activity>> {
+        self.activity.clone()
+    }
+
+    /// Input level while recording, for `croaker status` and `subscribe`.
+    pub fn audio_level_handle(&self) -> tokio::sync::watch::Receiver<Option<f32>> {
+        self.audio_level.subscribe()
+    }
+
+    /// Active session profile, for `croaker status`.
+    pub fn profile_handle(&self) -> Arc<std::sync::Mutex<Option<String>>> {
+        self.profile.clone()
+    }
+
+    /// Flag set while hotkeys are paused (`croaker pause`).
+    pub fn paused_flag(&self) -> Arc<AtomicBool> {
+        self.paused.clone()
+    }
+
+    /// Stage latencies and error counts, for `croaker stats`.
+    pub fn stats_handle(&self) -> Arc<std::sync::Mutex<Stats>> {
+        self.stats.clone()
+    }
+
+    pub fn output_mode_handle(&self) -> Arc<std::sync::Mutex<crate::config::OutputMode>> {
+        self.current_output_mode.clone()
     }
 
     pub fn event_sender(&self) -> mpsc::Sender<StateEvent> {
@@ -114,206 +979,1710 @@ impl StateMachine {
     }
 
     fn update_state(&mut self, new_state: DaemonState) {
+        if self.state == DaemonState::Idle && new_state != DaemonState::Idle {
+            self.inserted = Insertion::default();
+        }
+        let idle = new_state == DaemonState::Idle;
+        match (&self.state, &new_state) {
+            (DaemonState::Recording, DaemonState::Recording) => {}
+            (_, DaemonState::Recording) => hooks::fire(&self.config.hooks, Hook::StartRecording, String::new(), Vec::new()),
+            (DaemonState::Recording, _) => hooks::fire(&self.config.hooks, Hook::StopRecording, String::new(), Vec::new()),
+            (_, DaemonState::Error(reason)) => {
+                hooks::fire(&self.config.hooks, Hook::Error, reason.clone(), vec![("ERROR", reason.clone())]);
+            }
+            _ => {}
+        }
+        if self.config.accessibility.announce_states && new_state != self.state {
+            if let Some((message, priority)) = announce::state_message(&new_state) {
+                announce::say(&self.config.accessibility, message, priority, None);
+            }
+        }
+        if let Some(ref led) = self.keyboard_led {
+            let recording = new_state == DaemonState::Recording;
+            if recording != (self.state == DaemonState::Recording) {
+                led.set(recording);
+            }
+        }
+        if new_state != DaemonState::Recording {
+            // Cleared quietly, the state change reaches subscribers anyway
+            self.audio_level.send_if_modified(|level| {
+                *level = None;
+                false
+            });
+        }
         self.state = new_state;
+        if idle {
+            self.target = None;
+        }
+        self.arm_watchdog();
+        self.arm_level_check();
         if let Some(ref state_tx) = self.state_tx {
-            let _ = state_tx.try_send(self.state);
+            let _ = state_tx.try_send(self.state.clone());
         }
         
         // Update overlay via channel
         if let Some(ref overlay_tx) = self.overlay_tx {
-            let _ = overlay_tx.send(crate::overlay::OverlayMessage::State(self.state));
+            let _ = overlay_tx.send(crate::overlay::OverlayMessage::State(self.state.clone()));
+        }
+    }
+
+    /// Start the `[watchdog]` deadline of the stage just entered, replacing the previous one.
+    fn arm_watchdog(&mut self) {
+        self.stage += 1;
+        if let Some(watchdog) = self.watchdog.take() {
+            watchdog.abort();
+        }
+        let watchdog = &self.config.watchdog;
+        let secs = match self.state {
+            DaemonState::Recording => watchdog.recording_secs,
+            DaemonState::Processing => watchdog.processing_secs,
+            DaemonState::Outputting => watchdog.output_secs,
+            DaemonState::Idle | DaemonState::Error(_) => 0,
+        };
+        if secs == 0 {
+            return;
+        }
+        let stage = self.stage;
+        let event_tx = self.event_tx.clone();
+        self.watchdog = Some(tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(secs)).await;
+            let _ = event_tx.send(StateEvent::StageTimeout(stage)).await;
+        }));
+    }
+
+    /// Measure the input level while recording, stopping when any other stage starts.
+    fn arm_level_check(&mut self) {
+        let recording = self.state == DaemonState::Recording;
+        if recording && self.level_ticker.is_some() {
+            return;
+        }
+        if let Some(ticker) = self.level_ticker.take() {
+            ticker.abort();
+        }
+        if !recording {
+            return;
+        }
+        let event_tx = self.event_tx.clone();
+        self.level_ticker = Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + LEVEL_CHECK_INTERVAL, LEVEL_CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                if event_tx.send(StateEvent::LevelCheck).await.is_err() {
+                    break;
+                }
+            }
+        }));
+    }
+
+    /// Publish the input level of the recording, then stop a hands-free one that has heard no
+    /// speech for `audio.idle_stop_secs`. One with push-to-talk held down is left alone:
+    /// someone is clearly still there.
+    async fn check_level(&mut self) -> Result<(), StateError> {
+        let Some(tail) = self.audio_recorder.read_tail(SPEECH_DBFS).await else {
+            return Ok(());
+        };
+        let (level, silent_ms) = (tail.level_dbfs().max(LEVEL_FLOOR_DBFS), tail.silent_ms());
+        self.audio_level.send_replace(Some(level));
+        if let Some(ref overlay_tx) = self.overlay_tx {
+            let _ = overlay_tx.send(crate::overlay::OverlayMessage::AudioLevel(level));
+        }
+
+        let hands_free = self.push_to_talk.pressed_at.is_none() || self.push_to_talk.locked;
+        let limit_secs = self.config.audio.idle_stop_secs;
+        if !hands_free || limit_secs == 0 || silent_ms < limit_secs as u64 * 1000 {
+            return Ok(());
+        }
+        tracing::info!("No speech for {}s, stopping the recording", limit_secs);
+        if let Some(ref overlay_tx) = self.overlay_tx {
+            let _ = overlay_tx.send(crate::overlay::OverlayMessage::Notice(format!(
+                "Stopped after {}s of silence",
+                limit_secs
+            )));
         }
+        if self.push_to_talk.locked {
+            self.set_locked(false);
+        }
+        self.stop_recording().await
     }
 
     pub async fn handle_event(&mut self, event: StateEvent) -> Result<(), StateError> {
-        match (self.state, &event) {
+        // Handled apart from the match below because the reply channel has to be moved out
+        let event = match event {
+            StateEvent::Toggle(options, reply) => return self.toggle(options, reply).await,
+            StateEvent::Set(Setting::ConfigProfile(name), reply) => {
+                self.load_config_profile(name, reply);
+                return Ok(());
+            }
+            StateEvent::Set(setting, reply) => {
+                let _ = reply.send(self.apply_setting(setting));
+                return Ok(());
+            }
+            StateEvent::ConfigProfileLoaded(name, loaded, reply) => {
+                self.loading_config_profile = false;
+                let _ = reply.send(loaded.and_then(|loaded| self.switch_config_profile(name, *loaded)));
+                return Ok(());
+            }
+            StateEvent::GetConfig(reply) => {
+                let _ = reply.send(self.effective_config());
+                return Ok(());
+            }
+            event => event,
+        };
+
+        // An error stays on screen until its time is up or the user starts something else
+        if matches!(self.state, DaemonState::Error(_)) && event.is_user_action() {
+            self.update_state(DaemonState::Idle);
+            if matches!(event, StateEvent::Cancel) {
+                return self.output_deferred().await;
+            }
+        }
+
+        match event {
+            StateEvent::StartRecording => self.push_to_talk.down = true,
+            StateEvent::StopRecording => self.push_to_talk.down = false,
+            _ => {}
+        }
+
+        match (&self.state, &event) {
             (DaemonState::Idle, StateEvent::StartRecording) => {
-                self.start_recording().await?;
+                self.start_recording(RecordOptions::default()).await?;
+                self.push_to_talk.pressed_at = Some(Instant::now());
+            }
+            (DaemonState::Processing, StateEvent::StartRecording) if self.can_queue() => {
+                self.queue_current();
+                self.start_recording(RecordOptions::default()).await?;
+                self.push_to_talk.pressed_at = Some(Instant::now());
+            }
+            (DaemonState::Recording, StateEvent::StartRecording) => {
+                self.push_to_talk_pressed_again().await?;
             }
             (DaemonState::Recording, StateEvent::StopRecording) => {
-                self.stop_recording().await?;
+                self.push_to_talk_released().await?;
+            }
+            (DaemonState::Recording, StateEvent::TapWindowElapsed(tap)) => {
+                let push_to_talk = &self.push_to_talk;
+                if push_to_talk.tapped && !push_to_talk.locked && *tap == push_to_talk.taps {
+                    self.push_to_talk_done(push_to_talk.held).await?;
+                }
             }
+            (_, StateEvent::TapWindowElapsed(_)) => {}
             (DaemonState::Recording, StateEvent::Cancel) |
             (DaemonState::Processing, StateEvent::Cancel) |
             (DaemonState::Outputting, StateEvent::Cancel) => {
                 self.cancel().await?;
             }
-            (DaemonState::Processing, StateEvent::ProcessingComplete(text)) => {
-                self.output_text(text).await?;
+            (DaemonState::Processing, StateEvent::CleanupFragment(job, text)) if self.is_current(*job) && self.queue.is_empty() => {
+                self.streamed = true;
+                self.write_output(text).await?;
             }
-            (DaemonState::Outputting, StateEvent::OutputComplete) => {
-                self.update_state(DaemonState::Idle);
+            (DaemonState::Processing, StateEvent::ProcessingComplete(job, transcript))
+                if self.is_current(*job) && self.queue.is_empty() && self.preview_enabled() =>
+            {
+                self.processing = None;
+                self.remember(transcript.clone());
+                self.preview(transcript.text.clone());
             }
-            (_, StateEvent::ToggleOutputMode) => {
-                self.toggle_output_mode().await?;
+            (DaemonState::Processing, StateEvent::ProcessingComplete(job, transcript)) if self.is_current(*job) && self.queue.is_empty() => {
+                self.processing = None;
+                self.remember(transcript.clone());
+                // Every sentence of a streamed cleanup has been written already
+                let text = if std::mem::take(&mut self.streamed) { "" } else { transcript.text.as_str() };
+                self.output_after_countdown(text).await?;
             }
-            (_, StateEvent::ToggleLanguage) => {
-                self.toggle_language().await?;
+            (_, StateEvent::ProcessingFailed(job, Some(reason), retry)) if self.is_current(*job) && self.queue.is_empty() => {
+                self.fail(reason.clone(), retry.clone()).await;
             }
-            // Ignore StartRecording/StopRecording when processing - user might press key while processing
-            (DaemonState::Processing, StateEvent::StartRecording) |
-            (DaemonState::Processing, StateEvent::StopRecording) |
-            (DaemonState::Outputting, StateEvent::StartRecording) |
-            (DaemonState::Outputting, StateEvent::StopRecording) => {
-                tracing::debug!("Ignoring key event while {:?} - user pressed key during processing", self.state);
-                // Don't error - just ignore it
+            (_, StateEvent::ProcessingFailed(job, reason, _)) if self.is_current(*job) => {
+                self.warn_failed(reason.as_deref());
+                self.drop_current().await?;
             }
-            _ => {
-                tracing::warn!("Invalid state transition: {:?} -> {:?}", self.state, event);
-                return Err(StateError::InvalidTransition);
+            // Queued behind earlier dictations, or cancelled
+            (_, StateEvent::CleanupFragment(job, text)) => {
+                self.queued_fragment(*job, text).await?;
             }
-        }
-
-        Ok(())
-    }
+            (DaemonState::Processing, StateEvent::Progress(job, step)) if self.is_current(*job) => {
+                if let Some(ref overlay_tx) = self.overlay_tx {
+                    let _ = overlay_tx.send(crate::overlay::OverlayMessage::Progress(*step));
+                }
+            }
+            (_, StateEvent::Progress(..)) => {}
+            (_, StateEvent::ProcessingComplete(job, transcript)) => {
+                self.queued_complete(*job, transcript.clone()).await?;
+            }
+            (_, StateEvent::ProcessingFailed(job, reason, _)) => {
+                if self.queue.contains(*job) {
+                    self.warn_failed(reason.as_deref());
+                }
+                self.queued_complete(*job, Transcript::default()).await?;
+            }
+            (DaemonState::Error(_), StateEvent::ErrorElapsed(error)) if *error == self.errors => {
+                self.update_state(DaemonState::Idle);
+                self.output_deferred().await?;
+            }
+            (_, StateEvent::ErrorElapsed(_)) => {}
+            // A forgotten recording is still a dictation: stop it and transcribe what was said
+            (DaemonState::Recording, StateEvent::StageTimeout(stage)) if *stage == self.stage => {
+                let limit_secs = self.config.watchdog.recording_secs;
+                tracing::warn!("Recording hit its {}s limit, stopping it", limit_secs);
+                if let Some(ref overlay_tx) = self.overlay_tx {
+                    let _ = overlay_tx.send(crate::overlay::OverlayMessage::Notice(format!(
+                        "Recording stopped after {}s",
+                        limit_secs
+                    )));
+                }
+                if self.push_to_talk.locked {
+                    self.set_locked(false);
+                }
+                self.stop_recording().await?;
+            }
+            (_, StateEvent::StageTimeout(stage)) if *stage == self.stage => {
+                let stage = match self.state {
+                    DaemonState::Processing => "Processing",
+                    _ => "Output",
+                };
+                tracing::error!("{} hit its watchdog deadline, cancelling", stage);
+                self.stats.lock().unwrap().error("watchdog");
+                self.fail(StateError::Timeout(stage).summary(), None).await;
+            }
+            (_, StateEvent::StageTimeout(_)) => {}
+            (DaemonState::Outputting, StateEvent::PreviewResolved(decision)) => {
+                self.processing = None;
+                match decision {
+                    Decision::Accept(text) => {
+                        if let Some(ref mut last) = self.last {
+                            last.text = text.clone();
+                        }
+                        self.output_after_countdown(text).await?;
+                    }
+                    Decision::Copy(text) | Decision::CopyOnly(text) => {
+                        self.clipboard.copy_to_clipboard(text).await?;
+                        let notice = match decision {
+                            Decision::CopyOnly(_) => "Preview unavailable, text copied to clipboard",
+                            _ => "Text copied to clipboard",
+                        };
+                        if let Some(ref overlay_tx) = self.overlay_tx {
+                            let _ = overlay_tx.send(crate::overlay::OverlayMessage::Notice(notice.to_string()));
+                        }
+                        self.update_state(DaemonState::Idle);
+                        self.output_deferred().await?;
+                    }
+                    Decision::Discard => {
+                        tracing::info!("Output discarded from preview");
+                        self.update_state(DaemonState::Idle);
+                        self.output_deferred().await?;
+                    }
+                }
+            }
+            (_, StateEvent::PreviewResolved(_)) => {
+                tracing::debug!("Dropping preview answer of cancelled output");
+            }
+            (DaemonState::Outputting, StateEvent::CountdownElapsed(text)) => {
+                self.processing = None;
+                // The countdown is there to switch windows, so the profile is that of the new one
+                self.target = None;
+                self.output_text(text).await?;
+            }
+            (_, StateEvent::CountdownElapsed(_)) => {
+                tracing::debug!("Dropping countdown of cancelled output");
+            }
+            (DaemonState::Outputting, StateEvent::OutputComplete) => {
+                self.update_state(DaemonState::Idle);
+                self.show_transcription();
+                self.output_deferred().await?;
+            }
+            (DaemonState::Idle, StateEvent::SpoolTranscribed(transcript)) |
+            (DaemonState::Idle, StateEvent::Received(transcript)) => {
+                self.remember(transcript.clone());
+                self.output_text(&transcript.text).await?;
+            }
+            (_, StateEvent::SpoolTranscribed(transcript)) | (_, StateEvent::Received(transcript)) => {
+                tracing::debug!("Deferring {:?} while {:?}", event, self.state);
+                self.deferred.push_back(transcript.clone());
+            }
+            (DaemonState::Idle, StateEvent::Repeat) => {
+                self.repeat().await?;
+            }
+            (DaemonState::Idle, StateEvent::Recleanup(prompt_file)) => {
+                self.recleanup(prompt_file.clone());
+            }
+            (DaemonState::Idle, StateEvent::Undo) => {
+                self.undo().await?;
+            }
+            (DaemonState::Idle, StateEvent::Retry) => {
+                self.retry().await?;
+            }
+            (_, StateEvent::Repeat) | (_, StateEvent::Recleanup(_)) | (_, StateEvent::Undo) | (_, StateEvent::Retry) => {
+                tracing::debug!("Ignoring {:?} while {:?}", event, self.state);
+            }
+            (_, StateEvent::ToggleOutputMode) => {
+                self.toggle_output_mode().await?;
+            }
+            (_, StateEvent::ToggleLanguage) => {
+                self.toggle_language().await?;
+            }
+            (_, StateEvent::ToggleTranslate) => {
+                self.toggle_translate();
+            }
+            (_, StateEvent::CycleProfile) => {
+                self.cycle_profile();
+            }
+            (_, StateEvent::Pause(paused)) => {
+                self.pause(*paused).await?;
+            }
+            (_, StateEvent::SkipCleanupNext) => {
+                self.skip_cleanup_next = !self.skip_cleanup_next;
+                let message = if self.skip_cleanup_next { "Next dictation: raw (no cleanup)" } else { "Next dictation: cleaned up" };
+                tracing::info!("{}", message);
+                if let Some(ref overlay_tx) = self.overlay_tx {
+                    let _ = overlay_tx.send(crate::overlay::OverlayMessage::Notice(message.to_string()));
+                }
+            }
+            (DaemonState::Recording, StateEvent::LevelCheck) => {
+                self.check_level().await?;
+            }
+            (_, StateEvent::LevelCheck) => {}
+            (DaemonState::Recording, StateEvent::ChunkDue) => {
+                self.transcribe_next_chunk().await;
+            }
+            (DaemonState::Recording, StateEvent::ChunkTranscribed(session, index, transcript)) |
+            (DaemonState::Processing, StateEvent::ChunkTranscribed(session, index, transcript)) => {
+                if *session == self.chunks.session {
+                    self.chunks.results.insert(*index, transcript.clone());
+                    self.emit_chunks().await?;
+                }
+            }
+            // Text missing from the middle would go unnoticed, so the whole dictation fails. What
+            // was recorded so far can be retried in one piece
+            (DaemonState::Recording, StateEvent::ChunkFailed(session, index, reason)) |
+            (DaemonState::Processing, StateEvent::ChunkFailed(session, index, reason)) if *session == self.chunks.session => {
+                let retry = self.audio_recorder.snapshot().await.map(|(format, samples)| Retry::Audio {
+                    wav: audio::wav::write_wav(&format, &samples),
+                    language: self.recording_language(),
+                    translate: self.translate.load(Ordering::Relaxed),
+                });
+                let reason = format!("Part {} of the dictation was lost: {}", index + 1, reason);
+                self.activity.lock().unwrap().last_error = Some(reason.clone());
+                self.stats.lock().unwrap().error("chunk");
+                self.fail(reason, retry).await;
+            }
+            // Stale chunk events from a recording that has already finished or been cancelled
+            (_, StateEvent::ChunkDue) | (_, StateEvent::ChunkTranscribed(..)) | (_, StateEvent::ChunkFailed(..)) => {
+                tracing::debug!("Ignoring chunk event while {:?}", self.state);
+            }
+            // Ignore StartRecording/StopRecording when processing - user might press key while processing
+            (DaemonState::Processing, StateEvent::StartRecording) |
+            (DaemonState::Processing, StateEvent::StopRecording) |
+            (DaemonState::Outputting, StateEvent::StartRecording) |
+            (DaemonState::Outputting, StateEvent::StopRecording) => {
+                tracing::debug!("Ignoring key event while {:?} - user pressed key during processing", self.state);
+                // Don't error - just ignore it
+            }
+            _ => {
+                tracing::warn!("Invalid state transition: {:?} -> {:?}", self.state, event);
+                return Err(StateError::InvalidTransition);
+            }
+        }
+
+        // Queued dictations that finished while the push-to-talk key was down
+        if matches!(event, StateEvent::StopRecording)
+            && matches!(self.state, DaemonState::Recording | DaemonState::Processing)
+        {
+            self.flush_queue().await?;
+        }
+        Ok(())
+    }
+
+    async fn toggle(
+        &mut self,
+        options: RecordOptions,
+        reply: Option<oneshot::Sender<ToggleAction>>,
+    ) -> Result<(), StateError> {
+        let action = match ToggleStep::new(&self.state, self.can_queue()) {
+            ToggleStep::Start => {
+                // An error stays on screen until the user starts something else, as in handle_event
+                if matches!(self.state, DaemonState::Error(_)) {
+                    self.update_state(DaemonState::Idle);
+                }
+                self.start_recording(options).await?;
+                ToggleAction::Started
+            }
+            ToggleStep::Stop => {
+                self.stop_recording().await?;
+                ToggleAction::Stopped
+            }
+            ToggleStep::QueueAndStart => {
+                self.queue_current();
+                self.start_recording(options).await?;
+                ToggleAction::Started
+            }
+            ToggleStep::Ignore => {
+                tracing::debug!("Ignoring toggle while {:?}", self.state);
+                ToggleAction::Ignored(self.state.clone())
+            }
+        };
+        if let Some(reply) = reply {
+            let _ = reply.send(action);
+        }
+        Ok(())
+    }
+
+    async fn start_recording(&mut self, options: RecordOptions) -> Result<(), StateError> {
+        tracing::info!("Starting recording");
+        let source = options.source.unwrap_or(self.config.audio.source);
+        self.audio_recorder.start(source).await?;
+        self.chunks.reset();
+        self.retry = None;
+        self.fresh_transcript = false;
+        self.push_to_talk =
+            PushToTalk { taps: self.push_to_talk.taps, down: self.push_to_talk.down, ..PushToTalk::default() };
+        self.skip_cleanup = std::mem::take(&mut self.skip_cleanup_next);
+        if let Some(ref language) = options.language {
+            tracing::info!("Recording in {} for this utterance only", language);
+        }
+        self.language_override = options.language;
+        self.recording_started = Some(Instant::now());
+        // The window focused when dictation starts is the one the text is meant for
+        self.target = None;
+        self.target().await;
+        if source == CaptureSource::Microphone {
+            self.warn_if_muted();
+        }
+
+        let chunk_seconds = self.config.audio.chunk_seconds;
+        if chunk_seconds > 0 {
+            let event_tx = self.event_tx.clone();
+            self.chunks.ticker = Some(tokio::spawn(async move {
+                let period = std::time::Duration::from_secs(chunk_seconds as u64);
+                let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+                loop {
+                    interval.tick().await;
+                    if event_tx.send(StateEvent::ChunkDue).await.is_err() {
+                        break;
+                    }
+                }
+            }));
+        }
+
+        self.update_state(DaemonState::Recording);
+        Ok(())
+    }
+
+    /// Warn straight away if the source is muted, instead of producing an empty transcript.
+    fn warn_if_muted(&self) {
+        let device = self.config.audio.device.clone();
+        let overlay_tx = self.overlay_tx.clone();
+        tokio::task::spawn_blocking(move || {
+            if audio::devices::source_muted(&device) == Some(true) {
+                tracing::warn!("Microphone {:?} is muted", device);
+                if let Some(overlay_tx) = overlay_tx {
+                    let _ = overlay_tx.send(crate::overlay::OverlayMessage::Warning(
+                        "Microphone is muted - nothing will be transcribed".to_string(),
+                    ));
+                }
+            }
+        });
+    }
+
+    fn warn_if_clipping(&self) {
+        let threshold = self.config.audio.clip_warn_percent;
+        let Some(fraction) = self.audio_recorder.clipped_fraction() else {
+            return;
+        };
+        let percent = fraction * 100.0;
+        if threshold <= 0.0 || percent <= threshold {
+            return;
+        }
+
+        tracing::warn!("{:.1}% of samples clipped (threshold {}%); the microphone gain is probably too high", percent, threshold);
+        if let Some(ref overlay_tx) = self.overlay_tx {
+            let _ = overlay_tx.send(crate::overlay::OverlayMessage::Warning(format!(
+                "Audio is clipping ({:.1}% of samples) - lower the microphone gain",
+                percent
+            )));
+        }
+    }
+
+    /// A push-to-talk press while recording: the second half of a double tap locks recording
+    /// on, and a press while locked stops it.
+    async fn push_to_talk_pressed_again(&mut self) -> Result<(), StateError> {
+        if self.push_to_talk.locked {
+            self.set_locked(false);
+            return self.stop_recording().await;
+        }
+        if self.push_to_talk.tapped {
+            self.push_to_talk.tapped = false;
+            self.set_locked(true);
+        }
+        Ok(())
+    }
+
+    async fn push_to_talk_released(&mut self) -> Result<(), StateError> {
+        let hotkeys = &self.config.hotkeys;
+        let held = self.push_to_talk.pressed_at.map(|at| at.elapsed());
+        if self.push_to_talk.locked {
+            // Release of the second tap
+            return Ok(());
+        }
+        let window = Duration::from_millis(hotkeys.double_tap_ms);
+        if hotkeys.double_tap_lock && !self.push_to_talk.tapped && held.is_some_and(|held| held < window) {
+            // Maybe the first half of a double tap; stop only if no second press follows
+            self.push_to_talk.tapped = true;
+            self.push_to_talk.held = held;
+            self.push_to_talk.taps += 1;
+            let tap = self.push_to_talk.taps;
+            let event_tx = self.event_tx.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(window).await;
+                let _ = event_tx.send(StateEvent::TapWindowElapsed(tap)).await;
+            });
+            return Ok(());
+        }
+        self.push_to_talk_done(held).await
+    }
+
+    /// End a push-to-talk recording. One shorter than `hotkeys.min_hold_ms` was most likely an
+    /// accidental tap, so it's cancelled rather than transcribed.
+    async fn push_to_talk_done(&mut self, held: Option<Duration>) -> Result<(), StateError> {
+        let min_hold = Duration::from_millis(self.config.hotkeys.min_hold_ms);
+        if let Some(held) = held.filter(|held| *held < min_hold) {
+            tracing::info!("Push-to-talk held for only {:?}, cancelling", held);
+            return self.drop_current().await;
+        }
+        self.stop_recording().await
+    }
+
+    fn set_locked(&mut self, locked: bool) {
+        self.push_to_talk.locked = locked;
+        tracing::info!("Recording {}", if locked { "locked on" } else { "unlocked" });
+        if let Some(ref overlay_tx) = self.overlay_tx {
+            let _ = overlay_tx.send(crate::overlay::OverlayMessage::Locked(locked));
+        }
+    }
+
+    async fn stop_recording(&mut self) -> Result<(), StateError> {
+        tracing::info!("Stopping recording");
+        if let Some(ticker) = self.chunks.ticker.take() {
+            ticker.abort();
+        }
+        let recording = self.audio_recorder.stop().await?;
+        if let Some(started) = self.recording_started.take() {
+            self.stats.lock().unwrap().observe(Stage::Record, started.elapsed());
+        }
+        self.update_state(DaemonState::Processing);
+        self.warn_if_clipping();
+
+        if self.chunks.active() {
+            // Earlier chunks are already done or in flight; only the tail is left
+            let tail = match recording.read().await {
+                Ok(bytes) => audio::wav::read_samples(&bytes)
+                    .and_then(|(format, samples)| self.chunk_wav(&format, &samples)),
+                Err(e) => {
+                    tracing::warn!("Failed to read recording for final chunk: {}", e);
+                    None
+                }
+            };
+            recording.remove().await;
+
+            let index = self.chunks.next_index;
+            self.chunks.final_index = Some(index);
+            match tail {
+                Some(wav) => self.spawn_chunk(index, Recording::Memory(wav)),
+                None => {
+                    self.chunks.results.insert(index, Transcript::default());
+                    self.emit_chunks().await?;
+                }
+            }
+            return Ok(());
+        }
+
+        let language = self.recording_language();
+        let translate = self.translate.load(Ordering::Relaxed);
+        self.spawn_processing(recording, language, translate);
+        Ok(())
+    }
+
+    /// Transcribe and clean up `recording` in the background; the result comes back as
+    /// `ProcessingComplete` or `ProcessingFailed`.
+    fn spawn_processing(&mut self, recording: Recording, current_language: String, translate: bool) {
+        let pipeline = self.pipeline.clone();
+        let event_tx = self.event_tx.clone();
+        let cleanup = self.cleanup_step();
+        // Clipboard and file modes want the whole text at once (see `OutputMode::is_incremental`)
+        let stream = self.config.groq.cleanup_streaming
+            && self.output_mode().is_incremental()
+            && !self.holds_output();
+        let spool = self.spool.clone();
+        let overlay_tx = self.overlay_tx.clone();
+        let activity = self.activity.clone();
+        let token = CancellationToken::new();
+        self.processing = Some(token.clone());
+        self.job += 1;
+        let job = self.job;
+
+        tokio::spawn(async move {
+            // Dropping the request future aborts the HTTP call, so nothing is pasted after a cancel
+            let result = tokio::select! {
+                _ = token.cancelled() => {
+                    tracing::info!("Processing cancelled");
+                    recording.remove().await;
+                    return;
+                }
+                result = pipeline.process(&recording, &current_language, translate, &cleanup, Some(Report { job, events: &event_tx, stream })) => result,
+            };
+
+            match result {
+                Ok(transcript) => {
+                    let _ = event_tx.send(StateEvent::ProcessingComplete(job, transcript)).await;
+                }
+                Err(StateError::NoSpeech) => {
+                    if let Some(overlay_tx) = overlay_tx {
+                        let _ = overlay_tx.send(crate::overlay::OverlayMessage::Notice("Nothing heard".to_string()));
+                    }
+                    let _ = event_tx.send(StateEvent::ProcessingFailed(job, None, None)).await;
+                }
+                Err(StateError::TranscriptionError(e)) if e.is_network() && spool.is_some() => {
+                    tracing::warn!("Transcription failed ({}), spooling recording for retry", e);
+                    let spooled = match recording.read().await {
+                        Ok(wav) => spool.as_ref().unwrap().save(&wav, &current_language, translate).await,
+                        Err(e) => Err(e),
+                    };
+                    let message = match spooled {
+                        Ok(path) => {
+                            tracing::info!("Recording spooled to {:?}", path);
+                            "Offline - recording saved, it will be transcribed when the network is back".to_string()
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to spool recording: {}", e);
+                            format!("Offline and the recording couldn't be saved: {}", e)
+                        }
+                    };
+                    if let Some(overlay_tx) = overlay_tx {
+                        let _ = overlay_tx.send(crate::overlay::OverlayMessage::Warning(message));
+                    }
+                    let _ = event_tx.send(StateEvent::ProcessingFailed(job, None, None)).await;
+                }
+                Err(e) => {
+                    tracing::error!("Processing failed: {}", e);
+                    activity.lock().unwrap().last_error = Some(e.to_string());
+                    let retry = recording.read().await.ok().map(|wav| Retry::Audio {
+                        wav,
+                        language: current_language.clone(),
+                        translate,
+                    });
+                    let _ = event_tx.send(StateEvent::ProcessingFailed(job, Some(e.summary()), retry)).await;
+                }
+            }
+
+            // Cleanup temp file
+            recording.remove().await;
+        });
+    }
+
+    fn current_language(&self) -> String {
+        self.current_language.lock().unwrap().clone()
+    }
+
+    /// Language of the recording in progress: the one-off override, if any, else the current one
+    fn recording_language(&self) -> String {
+        self.language_override.clone().unwrap_or_else(|| self.current_language())
+    }
+
+    /// Cut the audio after the chunk cursor (plus the configured overlap) into a WAV file.
+    fn chunk_wav(&mut self, format: &audio::wav::WavFormat, samples: &[f32]) -> Option<Vec<u8>> {
+        let channels = format.channels.max(1) as usize;
+        let rate = format.sample_rate.max(1) as u64;
+        let frames = (samples.len() / channels) as u64;
+        let end_ms = frames * 1000 / rate;
+        if end_ms <= self.chunks.cursor_ms {
+            return None;
+        }
+
+        let start_ms = self.chunks.cursor_ms.saturating_sub(self.config.audio.chunk_overlap_ms as u64);
+        let start = (start_ms * rate / 1000) as usize * channels;
+        let end = frames as usize * channels;
+        self.chunks.cursor_ms = end_ms;
+        Some(audio::wav::write_wav(format, &samples[start.min(end)..end]))
+    }
+
+    async fn transcribe_next_chunk(&mut self) {
+        let Some((format, samples)) = self.audio_recorder.snapshot().await else {
+            tracing::debug!("No audio available for chunk yet");
+            return;
+        };
+        let Some(mut wav) = self.chunk_wav(&format, &samples) else {
+            return;
+        };
+
+        // The final recording gets filtered in stop(); chunks cut from the live capture need it here
+        if audio::needs_filters(&self.config.audio) {
+            match audio::apply_filters(wav.clone(), &self.config.audio).await {
+                Ok(filtered) => wav = filtered,
+                Err(e) => tracing::warn!("Failed to filter chunk, sending it unfiltered: {}", e),
+            }
+        }
+
+        let index = self.chunks.next_index;
+        self.chunks.next_index += 1;
+        tracing::info!("Transcribing chunk {} ({} bytes)", index, wav.len());
+        self.spawn_chunk(index, Recording::Memory(wav));
+    }
+
+    fn spawn_chunk(&mut self, index: usize, recording: Recording) {
+        let pipeline = self.pipeline.clone();
+        let event_tx = self.event_tx.clone();
+        let session = self.chunks.session;
+        let language = self.recording_language();
+        let translate = self.translate.load(Ordering::Relaxed);
+        let cleanup = self.cleanup_step();
+
+        self.chunks.tasks.push(tokio::spawn(async move {
+            let mut attempt = 1;
+            // NOTE: each chunk of a long dictation is its own history entry
+            let event = loop {
+                match pipeline.process(&recording, &language, translate, &cleanup, None).await {
+                    Ok(transcript) => break StateEvent::ChunkTranscribed(session, index, transcript),
+                    // A pause in a long dictation, not worth a notification
+                    Err(StateError::NoSpeech) => break StateEvent::ChunkTranscribed(session, index, Transcript::default()),
+                    Err(e) if attempt < CHUNK_ATTEMPTS => {
+                        tracing::warn!("Chunk {} failed ({}), trying again", index, e);
+                        tokio::time::sleep(Duration::from_secs(attempt as u64)).await;
+                        attempt += 1;
+                    }
+                    Err(e) => {
+                        tracing::error!("Chunk {} failed {} times: {}", index, CHUNK_ATTEMPTS, e);
+                        break StateEvent::ChunkFailed(session, index, e.summary());
+                    }
+                }
+            };
+            let _ = event_tx.send(event).await;
+        }));
+    }
+
+    /// Output every chunk whose predecessors have all been output.
+    async fn emit_chunks(&mut self) -> Result<(), StateError> {
+        // Clipboard and file modes want the whole text at once, so hold it until the end
+        let incremental = self.output_mode().is_incremental() && !self.holds_output();
+
+        while let Some(Transcript { raw, text }) = self.chunks.results.remove(&self.chunks.emit_index) {
+            let index = self.chunks.emit_index;
+            self.chunks.emit_index += 1;
+
+            let mut fragment = crate::transcribe::merge::strip_overlap(&self.chunks.previous, &text);
+            if !fragment.is_empty() && !self.chunks.transcript.is_empty() {
+                fragment.insert(0, ' ');
+            }
+            if !text.trim().is_empty() {
+                self.chunks.previous = text;
+            }
+            self.chunks.transcript.push_str(&fragment);
+            if !raw.trim().is_empty() {
+                if !self.chunks.raw.is_empty() {
+                    self.chunks.raw.push(' ');
+                }
+                self.chunks.raw.push_str(raw.trim());
+            }
+
+            if self.chunks.final_index == Some(index) {
+                let transcript = Transcript {
+                    raw: std::mem::take(&mut self.chunks.raw),
+                    text: std::mem::take(&mut self.chunks.transcript),
+                };
+                let remaining = if incremental { fragment } else { transcript.text.clone() };
+                self.chunks.reset();
+                self.remember(transcript);
+                self.output_text(&remaining).await?;
+                break;
+            }
+            if incremental && !fragment.is_empty() {
+                tracing::info!("Outputting chunk {}: {} chars", index, fragment.len());
+                self.write_output(&fragment).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Keep a finished transcription for `repeat`/`recleanup` and announce it on D-Bus.
+    fn remember(&mut self, transcript: Transcript) {
+        if transcript.text.trim().is_empty() {
+            return;
+        }
+        self.fresh_transcript = true;
+        let language = self.transcript_language();
+        self.read_back(&transcript.text, ReadBack::Before);
+        if let Some(ref dbus) = self.dbus {
+            let dbus = dbus.clone();
+            let language = language.clone();
+            let (text, raw) = (transcript.text.clone(), transcript.raw.clone());
+            tokio::spawn(async move {
+                if let Err(e) = dbus.emit(&text, &raw, &language).await {
+                    tracing::warn!("Failed to emit TranscriptionReady: {}", e);
+                }
+            });
+        }
+        self.activity.lock().unwrap().last_transcript_chars = Some(transcript.text.chars().count());
+        hooks::fire(
+            &self.config.hooks,
+            Hook::Transcription,
+            transcript.text.clone(),
+            vec![
+                ("RAW_TEXT", transcript.raw.clone()),
+                ("LANGUAGE", language),
+                ("APP", self.target.as_ref().and_then(|target| target.app.clone()).unwrap_or_default()),
+            ],
+        );
+        self.last = Some(transcript);
+    }
+
+    async fn repeat(&mut self) -> Result<(), StateError> {
+        let Some(text) = self.last.as_ref().map(|last| last.text.clone()) else {
+            tracing::info!("Nothing to repeat yet");
+            return Ok(());
+        };
+        tracing::info!("Repeating last transcription");
+        self.output_text(&text).await
+    }
+
+    /// Tell the overlay how long the text about to be written is, before entering Outputting.
+    fn announce_output(&self, text: &str) {
+        if let Some(ref overlay_tx) = self.overlay_tx {
+            let _ = overlay_tx.send(crate::overlay::OverlayMessage::OutputLength(text.chars().count()));
+        }
+    }
+
+    /// Language the last dictation came out in.
+    fn transcript_language(&self) -> String {
+        if self.translate.load(Ordering::Relaxed) { "en".to_string() } else { self.recording_language() }
+    }
+
+    /// Read a dictation out (`accessibility.announce_text`) if `when` is when it's wanted.
+    fn read_back(&self, text: &str, when: ReadBack) {
+        let accessibility = &self.config.accessibility;
+        if !accessibility.announce_text || accessibility.announce_text_when != when || text.trim().is_empty() {
+            return;
+        }
+        announce::say(accessibility, text.to_string(), announce::Priority::Text, Some(self.transcript_language()));
+    }
+
+    /// Show the start of what was just written on the overlay (`overlay.show_transcription`)
+    /// and read it out (`accessibility.announce_text`).
+    fn show_transcription(&mut self) {
+        if !std::mem::take(&mut self.fresh_transcript) {
+            return;
+        }
+        let Some(ref last) = self.last else {
+            return;
+        };
+        self.read_back(&last.text, ReadBack::After);
+        if let (true, Some(ref overlay_tx)) = (self.config.overlay.show_transcription, &self.overlay_tx) {
+            let _ = overlay_tx.send(crate::overlay::OverlayMessage::Transcribed(last.text.clone()));
+        }
+    }
+
+    /// Remove what the last dictation inserted: Backspace for typed characters, Ctrl+Z per paste.
+    /// Take back what the last dictation inserted, if the window it went into still has focus.
+    /// Terminals get Backspace for pasted text too: Ctrl+Z there suspends the foreground job.
+    async fn undo(&mut self) -> Result<(), StateError> {
+        if self.inserted == Insertion::default() {
+            tracing::info!("Nothing to undo");
+            return Ok(());
+        }
+        let app = self.last_output.as_ref().and_then(|last| last.app.clone());
+        let focused = crate::output::focus::focused_app().await;
+        if !same_app(app.as_deref(), focused.as_deref()) {
+            let message = format!(
+                "Not undoing: the dictation went into {}, {} has focus now",
+                app.as_deref().unwrap_or("another window"),
+                focused.as_deref().unwrap_or("an unknown window"),
+            );
+            tracing::warn!("{}", message);
+            if let Some(ref overlay_tx) = self.overlay_tx {
+                let _ = overlay_tx.send(crate::overlay::OverlayMessage::Warning(message));
+            }
+            return Ok(());
+        }
+
+        let inserted = std::mem::take(&mut self.inserted);
+        self.last_output = None;
+        let terminal = app.as_deref().is_some_and(crate::config::is_terminal);
+        tracing::info!(
+            "Undoing last output: {} typed chars, {} pastes ({} chars){}",
+            inserted.typed,
+            inserted.pastes,
+            inserted.pasted,
+            if terminal { " in a terminal" } else { "" }
+        );
+        let delay_ms = self.config.output.keystroke_delay_ms;
+        if terminal {
+            self.keyboard.backspace(inserted.typed + inserted.pasted, delay_ms).await?;
+            return Ok(());
+        }
+        self.keyboard.backspace(inserted.typed, delay_ms).await?;
+        let undo_keys = PasteKeys { modifiers: vec![Modifier::Ctrl], key: "z".to_string() };
+        for _ in 0..inserted.pastes {
+            self.keyboard.send_combo(&undo_keys).await?;
+        }
+        Ok(())
+    }
+
+    /// Clean up the last raw transcript again, with `prompt_file` instead of the configured prompt if given.
+    fn recleanup(&mut self, prompt_file: Option<PathBuf>) {
+        let Some(raw) = self.last.as_ref().map(|last| last.raw.clone()) else {
+            tracing::info!("Nothing to re-clean yet");
+            return;
+        };
+        tracing::info!("Re-running cleanup on last transcript (prompt: {:?})", prompt_file);
+        self.update_state(DaemonState::Processing);
+
+        let pipeline = self.pipeline.clone();
+        let event_tx = self.event_tx.clone();
+        let overlay_tx = self.overlay_tx.clone();
+        let token = CancellationToken::new();
+        self.processing = Some(token.clone());
+        self.job += 1;
+        let job = self.job;
+
+        tokio::spawn(async move {
+            let prompt = match prompt_file {
+                Some(path) => match tokio::fs::read_to_string(&path).await {
+                    Ok(prompt) => Some(prompt.trim().to_string()),
+                    Err(e) => {
+                        tracing::error!("Failed to read prompt file {:?}: {}", path, e);
+                        if let Some(overlay_tx) = overlay_tx {
+                            let _ = overlay_tx.send(crate::overlay::OverlayMessage::Warning(format!(
+                                "Couldn't read prompt file {}: {}",
+                                path.display(),
+                                e
+                            )));
+                        }
+                        let _ = event_tx.send(StateEvent::ProcessingFailed(job, None, None)).await;
+                        return;
+                    }
+                },
+                None => None,
+            };
+
+            let result = tokio::select! {
+                _ = token.cancelled() => {
+                    tracing::info!("Re-cleanup cancelled");
+                    return;
+                }
+                result = pipeline.cleanup_client.cleanup_with_prompt(&raw, prompt.as_deref()) => result,
+            };
+            match result {
+                Ok(text) => {
+                    let transcript = pipeline.finish(raw, text);
+                    let _ = event_tx.send(StateEvent::ProcessingComplete(job, transcript)).await;
+                }
+                Err(e) => {
+                    tracing::error!("Re-cleanup failed: {}", e);
+                    let _ = event_tx.send(StateEvent::ProcessingFailed(job, Some(StateError::from(e).summary()), None)).await;
+                }
+            }
+        });
+    }
+
+    /// Show `text` for confirmation without blocking the event loop; the answer comes back
+    /// as `PreviewResolved`. If no preview can be shown, the text is only copied to the
+    /// clipboard, so nothing is sent unchecked.
+    fn preview(&mut self, text: String) {
+        if text.is_empty() {
+            let _ = self.event_tx.try_send(StateEvent::OutputComplete);
+            self.update_state(DaemonState::Outputting);
+            return;
+        }
+        tracing::info!("Waiting for preview confirmation");
+        self.announce_output(&text);
+        self.update_state(DaemonState::Outputting);
+        // The user takes as long as they like to answer
+        if let Some(watchdog) = self.watchdog.take() {
+            watchdog.abort();
+        }
+
+        let event_tx = self.event_tx.clone();
+        let token = CancellationToken::new();
+        self.processing = Some(token.clone());
+
+        let window = self.config.output.preview_window;
+
+        tokio::spawn(async move {
+            let decision = tokio::select! {
+                _ = token.cancelled() => return,
+                decision = async {
+                    if window {
+                        crate::output::preview::review(&text).await
+                    } else {
+                        crate::output::preview::confirm(&text).await
+                    }
+                } => decision,
+            };
+            let decision = decision.unwrap_or_else(|e| {
+                tracing::warn!("Can't show output preview, copying to clipboard instead: {}", e);
+                Decision::CopyOnly(text)
+            });
+            let _ = event_tx.send(StateEvent::PreviewResolved(decision)).await;
+        });
+    }
+
+    /// Output `text` after counting down `output.countdown_secs` on the overlay, so the user
+    /// can focus the window it should go to. The countdown runs in a task (cancellable like
+    /// processing) and comes back as `CountdownElapsed`.
+    async fn output_after_countdown(&mut self, text: &str) -> Result<(), StateError> {
+        let seconds = self.config.output.countdown_secs;
+        if seconds == 0 || text.is_empty() || !self.output_mode().inserts_text() {
+            return self.output_text(text).await;
+        }
+        tracing::info!("Outputting in {}s", seconds);
+        self.announce_output(text);
+        self.update_state(DaemonState::Outputting);
+
+        let event_tx = self.event_tx.clone();
+        let overlay_tx = self.overlay_tx.clone();
+        let token = CancellationToken::new();
+        self.processing = Some(token.clone());
+        let text = text.to_string();
+
+        tokio::spawn(async move {
+            for left in (1..=seconds).rev() {
+                if let Some(ref overlay_tx) = overlay_tx {
+                    let _ = overlay_tx.send(crate::overlay::OverlayMessage::Countdown(left));
+                }
+                tokio::select! {
+                    _ = token.cancelled() => return,
+                    _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+                }
+            }
+            let _ = event_tx.send(StateEvent::CountdownElapsed(text)).await;
+        });
+        Ok(())
+    }
+
+    async fn output_text(&mut self, text: &str) -> Result<(), StateError> {
+        tracing::info!("Outputting text: {} chars (mode: {:?})", text.len(), self.output_mode());
+        self.announce_output(text);
+        self.update_state(DaemonState::Outputting);
+
+        // The last chunk of a long dictation can be empty when everything was already typed
+        if !text.is_empty() {
+            self.write_output(text).await?;
+        }
+
+        // Signal completion
+        let _ = self.event_tx.send(StateEvent::OutputComplete).await;
+        Ok(())
+    }
+
+    /// Deliver text using the current output mode, without touching the state.
+    async fn write_output(&mut self, text: &str) -> Result<(), StateError> {
+        let target = self.target().await;
+        let text = self.join_previous(&target, text);
+        let before = self.inserted;
+        let started = Instant::now();
+        // Delivery runs inside the event loop, so the StageTimeout event couldn't get through
+        // if it hung; it gets the same deadline directly
+        match self.config.watchdog.output_secs {
+            0 => self.deliver(&text, &target).await?,
+            secs => tokio::time::timeout(Duration::from_secs(secs), self.deliver(&text, &target))
+                .await
+                .map_err(|_| StateError::Timeout("Output"))??,
+        }
+        self.stats.lock().unwrap().observe(Stage::Output, started.elapsed());
+        if self.inserted != before {
+            self.last_output = Some(OutputContext { app: target.app, text, at: Instant::now() });
+        }
+        Ok(())
+    }
+
+    /// Continue the previous dictation when this one goes into the same window shortly after:
+    /// add a space and fix the first letter's case. Only the first insertion of a dictation is
+    /// adjusted; chunks and streamed sentences after it already carry their spacing.
+    fn join_previous(&self, target: &Target, text: &str) -> String {
+        let general = &self.config.general;
+        match self.last_output {
+            Some(ref last)
+                if general.smart_join
+                    && self.output_mode().inserts_text()
+                    && self.inserted == Insertion::default()
+                    && last.app == target.app
+                    && last.at.elapsed() < Duration::from_secs(general.smart_join_window_secs) =>
+            {
+                crate::transcribe::join::join(&last.text, text)
+            }
+            _ => text.to_string(),
+        }
+    }
+
+    async fn deliver(&mut self, text: &str, target: &Target) -> Result<(), StateError> {
+        let delay_ms = target
+            .profile
+            .as_ref()
+            .and_then(|p| p.keystroke_delay_ms)
+            .unwrap_or(self.config.output.keystroke_delay_ms);
+        let (pacing, paste_chunk) = self.pacing(target);
+        match self.output_mode() {
+            crate::config::OutputMode::Direct => {
+                // Try direct typing first, fallback to clipboard if it fails
+                tracing::debug!("Using direct output mode");
+                let mut rest = text;
+                if let Some(ref virtual_keyboard) = self.virtual_keyboard {
+                    match pacing::in_bursts(rest, pacing, |burst| virtual_keyboard.type_text(burst, delay_ms)).await {
+                        Ok(()) => {
+                            self.inserted.typed += graphemes(text);
+                            return Ok(());
+                        }
+                        Err(interrupted) => {
+                            tracing::warn!("Virtual keyboard failed, falling back to uinput: {}", interrupted.error);
+                            rest = self.untyped(rest, interrupted.typed);
+                        }
+                    }
+                }
+                match pacing::in_bursts(rest, pacing, |burst| self.keyboard.type_text(burst, delay_ms)).await {
+                    Ok(()) => self.inserted.typed += graphemes(rest),
+                    Err(interrupted) => {
+                        match interrupted.error {
+                            crate::output::uinput::UinputError::UnsupportedChar(_) => {
+                                tracing::debug!("Falling back to clipboard (unsupported char)")
+                            }
+                            ref e => tracing::warn!("uinput failed, falling back to clipboard: {}", e),
+                        }
+                        let rest = self.untyped(rest, interrupted.typed);
+                        self.clipboard.copy_to_clipboard(rest).await?;
+                    }
+                }
+            }
+            crate::config::OutputMode::WtypeType => {
+                // Type through the compositor, fall back to clipboard if it refuses
+                tracing::debug!("Using wtype output mode");
+                match pacing::in_bursts(text, pacing, |burst| crate::output::wtype::type_text(burst, delay_ms)).await {
+                    Ok(()) => self.inserted.typed += graphemes(text),
+                    Err(interrupted) => {
+                        tracing::warn!("wtype failed, falling back to clipboard: {}", interrupted.error);
+                        let rest = self.untyped(text, interrupted.typed);
+                        self.clipboard.copy_to_clipboard(rest).await?;
+                    }
+                }
+            }
+            crate::config::OutputMode::File => {
+                let result = crate::output::file::append(
+                    &self.output_file_path(),
+                    &self.config.output.file_template,
+                    text,
+                )
+                .await;
+                if let Err(e) = result {
+                    tracing::warn!("{}, falling back to clipboard", e);
+                    self.clipboard.copy_to_clipboard(text).await?;
+                }
+            }
+            crate::config::OutputMode::Command => {
+                let translate = self.translate.load(Ordering::Relaxed);
+                let language = if translate { "en".to_string() } else { self.recording_language() };
+                let env = crate::output::command::CommandEnv {
+                    language: &language,
+                    mode: if translate { "translate" } else { "dictation" },
+                    app: target.app.as_deref(),
+                    raw_text: self.last.as_ref().map_or("", |last| last.raw.as_str()),
+                };
+                if let Err(e) = crate::output::command::run(&self.output_command(), text, &env).await {
+                    tracing::warn!("{}, falling back to clipboard", e);
+                    self.clipboard.copy_to_clipboard(text).await?;
+                }
+            }
+            crate::config::OutputMode::Remote => {
+                let remote = &self.config.remote;
+                if let Err(e) = crate::output::remote::send(&remote.address, &remote.token, text).await {
+                    tracing::warn!("{}, falling back to clipboard", e);
+                    self.clipboard.copy_to_clipboard(text).await?;
+                }
+            }
+            crate::config::OutputMode::Dbus => {
+                // `remember` already emitted the signal
+                tracing::debug!("D-Bus output mode, not writing anything");
+            }
+            crate::config::OutputMode::Clipboard => {
+                // Only copy to clipboard, don't paste
+                tracing::debug!("Using clipboard-only output mode");
+                self.clipboard.copy_to_clipboard(text).await?;
+            }
+            crate::config::OutputMode::Both => {
+                // Copy to clipboard first
+                tracing::debug!("Using both output modes - copying to clipboard");
+                if let Err(e) = self.clipboard.save_current().await {
+                    tracing::warn!("Couldn't save clipboard, it won't be restored: {}", e);
+                }
+                self.clipboard.copy_to_clipboard(text).await?;
+
+                // Typing through the compositor handles any character, no paste needed
+                let mut rest = text;
+                if let Some(ref virtual_keyboard) = self.virtual_keyboard {
+                    match pacing::in_bursts(rest, pacing, |burst| virtual_keyboard.type_text(burst, delay_ms)).await {
+                        Ok(()) => {
+                            self.inserted.typed += graphemes(text);
+                            // As after a paste, the clipboard goes back to what it held
+                            if let Err(e) = self.clipboard.restore().await {
+                                tracing::warn!("Failed to restore clipboard: {}", e);
+                            }
+                            return Ok(());
+                        }
+                        Err(interrupted) => {
+                            tracing::warn!("Virtual keyboard failed, falling back to paste: {}", interrupted.error);
+                            rest = self.untyped(rest, interrupted.typed);
+                        }
+                    }
+                }
+                let paste_keys = self.paste_keys(target);
+                
+                // Then try to paste/type
+                let is_wayland = std::env::var("XDG_SESSION_TYPE")
+                    .map(|s| s == "wayland")
+                    .unwrap_or(false);
+                
+                let has_non_ascii = !rest.is_ascii();
+
+                if is_wayland || has_non_ascii {
+                    // Use clipboard paste method (works reliably on Wayland)
+                    tracing::debug!("Using clipboard paste method (Wayland={}, non-ASCII={})", is_wayland, has_non_ascii);
+                } else {
+                    // Try direct typing, fallback to paste if it fails
+                    tracing::debug!("Trying direct typing");
+                    match pacing::in_bursts(rest, pacing, |burst| self.keyboard.type_text(burst, delay_ms)).await {
+                        Ok(()) => {
+                            self.inserted.typed += graphemes(rest);
+                            return Ok(());
+                        }
+                        Err(interrupted) => {
+                            match interrupted.error {
+                                crate::output::uinput::UinputError::UnsupportedChar(_) => {
+                                    tracing::debug!("Falling back to clipboard paste (unsupported char)")
+                                }
+                                ref e => tracing::warn!("uinput failed, falling back to clipboard paste: {}", e),
+                            }
+                            rest = self.untyped(rest, interrupted.typed);
+                        }
+                    }
+                }
+                if rest.len() == text.len() {
+                    self.paste_in_chunks(text, &paste_keys, paste_chunk).await?;
+                } else {
+                    // Part of it was typed already; paste only the rest, then leave the whole text
+                    // in the clipboard as usual
+                    self.clipboard.copy_to_clipboard(rest).await?;
+                    self.paste_in_chunks(rest, &paste_keys, paste_chunk).await?;
+                    if !self.config.output.clipboard_restore {
+                        self.clipboard.copy_to_clipboard(text).await?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Focused application and its profile, looked up on first use in each dictation.
+    async fn target(&mut self) -> Target {
+        if let Some(ref target) = self.target {
+            return target.clone();
+        }
+        let app = if self.config.app_profiles.is_empty() && self.app_paste_keys.is_empty() {
+            None
+        } else {
+            crate::output::focus::focused_app().await
+        };
+        let profile = app
+            .as_ref()
+            .and_then(|app| self.config.app_profiles.iter().find(|p| p.matches(app)).cloned());
+        if let Some(ref app) = app {
+            tracing::debug!("Focused app: {} (profile: {})", app, profile.is_some());
+        }
+        let target = Target { app, profile };
+        self.target = Some(target.clone());
+        target
+    }
+
+    /// Output mode for the current dictation: the app profile's, else the runtime setting.
+    fn output_mode(&self) -> crate::config::OutputMode {
+        self.target
+            .as_ref()
+            .and_then(|t| t.profile.as_ref())
+            .and_then(|p| p.output_mode)
+            .unwrap_or_else(|| *self.current_output_mode.lock().unwrap())
+    }
+
+    /// Whether to confirm text before output: the app profile's setting, else `output.preview`.
+    fn preview_enabled(&self) -> bool {
+        self.target
+            .as_ref()
+            .and_then(|t| t.profile.as_ref())
+            .and_then(|p| p.preview)
+            .unwrap_or(self.config.output.preview)
+    }
+
+    /// Whether the text is held back until it's complete: it's previewed or counted down first.
+    fn holds_output(&self) -> bool {
+        self.preview_enabled() || self.config.output.countdown_secs > 0
+    }
+
+    fn cleanup_step(&self) -> CleanupStep {
+        let profile = self.target.as_ref().and_then(|t| t.profile.as_ref());
+        if self.skip_cleanup || profile.and_then(|p| p.cleanup_enabled) == Some(false) {
+            return CleanupStep::Skip;
+        }
+        let prompt_file = profile
+            .and_then(|p| p.cleanup_prompt_file.clone())
+            .or_else(|| self.session_profile().and_then(|p| p.cleanup_prompt_file));
+        match prompt_file {
+            Some(path) => CleanupStep::Prompt(PathBuf::from(path)),
+            None => CleanupStep::Default,
+        }
+    }
+
+    /// Settings of the active session profile, if any.
+    fn session_profile(&self) -> Option<SessionProfile> {
+        let name = self.profile.lock().unwrap();
+        name.as_ref().and_then(|name| self.config.profiles.get(name)).cloned()
+    }
+
+    /// Where the "file" output mode appends: the session profile's file, else `output.file_path`.
+    fn output_file_path(&self) -> String {
+        self.session_profile()
+            .and_then(|p| p.file_path)
+            .unwrap_or_else(|| self.config.output.file_path.clone())
+    }
+
+    /// What the "command" output mode runs: the session profile's command, else `output.command`.
+    fn output_command(&self) -> String {
+        self.session_profile()
+            .and_then(|p| p.command)
+            .unwrap_or_else(|| self.config.output.command.clone())
+    }
+
+    /// Typing bursts and paste chunk size for the focused application, or the configured ones.
+    fn pacing(&self, target: &Target) -> (Pacing, usize) {
+        let output = &self.config.output;
+        let profile = target.profile.as_ref();
+        let pacing = Pacing {
+            burst_chars: profile.and_then(|p| p.typing_burst_chars).unwrap_or(output.typing_burst_chars),
+            chunk_delay_ms: profile.and_then(|p| p.chunk_delay_ms).unwrap_or(output.chunk_delay_ms),
+        };
+        let paste_chunk = profile.and_then(|p| p.paste_chunk_chars).unwrap_or(output.paste_chunk_chars);
+        (pacing, paste_chunk)
+    }
+
+    /// Count the part of `text` an interrupted typing got through, and return the rest.
+    fn untyped<'t>(&mut self, text: &'t str, typed: usize) -> &'t str {
+        self.inserted.typed += graphemes(&text[..typed]);
+        &text[typed..]
+    }
+
+    /// Paste `text`, which is already in the clipboard. With `chunk_chars` set, long text is
+    /// copied and pasted a piece at a time instead.
+    async fn paste_in_chunks(&mut self, text: &str, keys: &PasteKeys, chunk_chars: usize) -> Result<(), StateError> {
+        let pieces = pacing::chunks(text, chunk_chars);
+        if pieces.len() == 1 {
+            self.clipboard.paste(keys).await?;
+            self.inserted.pastes += 1;
+            self.inserted.pasted += graphemes(text);
+            return Ok(());
+        }
+        tracing::debug!("Pasting in {} chunks", pieces.len());
+        let delay = std::time::Duration::from_millis(self.config.output.chunk_delay_ms);
+        for (i, piece) in pieces.iter().enumerate() {
+            if i > 0 {
+                tokio::time::sleep(delay).await;
+                // The previous paste restored the user's clipboard, save it again
+                if let Err(e) = self.clipboard.save_current().await {
+                    tracing::warn!("Couldn't save clipboard, it won't be restored: {}", e);
+                }
+            }
+            self.clipboard.copy_to_clipboard(piece).await?;
+            self.clipboard.paste(keys).await?;
+            self.inserted.pastes += 1;
+            self.inserted.pasted += graphemes(piece);
+        }
+        // Both mode leaves the whole text in the clipboard, not the last piece
+        if !self.config.output.clipboard_restore {
+            self.clipboard.copy_to_clipboard(text).await?;
+        }
+        Ok(())
+    }
+
+    /// Paste keys for the focused application, or the configured default.
+    fn paste_keys(&self, target: &Target) -> PasteKeys {
+        if let Some(keys) = target.profile.as_ref().and_then(|p| p.paste_keys.as_deref()).and_then(parse_paste_keys) {
+            return keys;
+        }
+        if let Some(keys) = target.app.as_ref().and_then(|app| self.app_paste_keys.get(&app.to_lowercase())) {
+            tracing::debug!("Using paste keys {} for {:?}", keys, target.app);
+            return keys.clone();
+        }
+        self.default_paste_keys.clone()
+    }
+
+    async fn toggle_output_mode(&mut self) -> Result<(), StateError> {
+        let mut current = self.current_output_mode.lock().unwrap();
+        *current = match *current {
+            crate::config::OutputMode::Direct => crate::config::OutputMode::Clipboard,
+            crate::config::OutputMode::Clipboard => crate::config::OutputMode::Both,
+            crate::config::OutputMode::Both => crate::config::OutputMode::WtypeType,
+            crate::config::OutputMode::WtypeType if !self.output_file_path().is_empty() => {
+                crate::config::OutputMode::File
+            }
+            crate::config::OutputMode::WtypeType | crate::config::OutputMode::File
+                if !self.output_command().is_empty() =>
+            {
+                crate::config::OutputMode::Command
+            }
+            crate::config::OutputMode::WtypeType
+            | crate::config::OutputMode::File
+            | crate::config::OutputMode::Command
+                if !self.config.remote.address.is_empty() =>
+            {
+                crate::config::OutputMode::Remote
+            }
+            // D-Bus only is set in the config, toggling leaves it
+            crate::config::OutputMode::WtypeType
+            | crate::config::OutputMode::File
+            | crate::config::OutputMode::Command
+            | crate::config::OutputMode::Remote
+            | crate::config::OutputMode::Dbus => crate::config::OutputMode::Direct,
+        };
+        
+        let mode = *current;
+        drop(current);
+        self.announce_output_mode(mode);
+        
+        Ok(())
+    }
+
+    fn announce_output_mode(&self, mode: crate::config::OutputMode) {
+        let mode_str = match mode {
+            crate::config::OutputMode::Direct => "Direct",
+            crate::config::OutputMode::Clipboard => "Clipboard",
+            crate::config::OutputMode::Both => "Both",
+            crate::config::OutputMode::WtypeType => "Wtype",
+            crate::config::OutputMode::File => "File",
+            crate::config::OutputMode::Dbus => "D-Bus",
+            crate::config::OutputMode::Command => "Command",
+            crate::config::OutputMode::Remote => "Remote",
+        };
+        tracing::info!("Output mode changed to: {}", mode_str);
+        
+        // Update overlay (overlay handles the visual feedback)
+        if let Some(ref overlay_tx) = self.overlay_tx {
+            let _ = overlay_tx.send(crate::overlay::OverlayMessage::OutputMode(mode_str.to_string()));
+        }
+    }
 
-    async fn start_recording(&mut self) -> Result<(), StateError> {
-        tracing::info!("Starting recording");
-        self.audio_recorder.start().await?;
-        self.update_state(DaemonState::Recording);
+    /// Change a setting for the rest of the session (`croaker set-*`). The error explains
+    /// why the value can't be used and is sent back to the client.
+    fn apply_setting(&mut self, setting: Setting) -> Result<(), String> {
+        match setting {
+            Setting::Language(code) => {
+                let code = code.trim().to_lowercase();
+                if code.is_empty() || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+                    return Err(format!("{:?} isn't a language code (e.g. \"en\", \"tr\")", code));
+                }
+                *self.current_language.lock().unwrap() = code.clone();
+                tracing::info!("Language changed to: {}", code);
+                if let Some(ref overlay_tx) = self.overlay_tx {
+                    let _ = overlay_tx.send(crate::overlay::OverlayMessage::Language(code));
+                }
+            }
+            Setting::OutputMode(mode) => {
+                self.check_output_mode(mode, &self.session_profile().unwrap_or_default())?;
+                *self.current_output_mode.lock().unwrap() = mode;
+                self.announce_output_mode(mode);
+            }
+            Setting::Cleanup(enabled) => {
+                let cleanup = &self.pipeline.cleanup_client;
+                if enabled && !cleanup.has_api_key() {
+                    return Err("cleanup needs a Groq API key, none was loaded at startup".to_string());
+                }
+                cleanup.set_enabled(enabled);
+                let message = if enabled { "Cleanup on" } else { "Cleanup off" };
+                tracing::info!("{}", message);
+                if let Some(ref overlay_tx) = self.overlay_tx {
+                    let _ = overlay_tx.send(crate::overlay::OverlayMessage::Notice(message.to_string()));
+                }
+            }
+            Setting::Profile(name) => self.switch_profile(name)?,
+            // Loaded off the event loop, as the keyring may ask for its password
+            Setting::ConfigProfile(_) => return Err("config profiles are switched by load_config_profile".to_string()),
+        }
         Ok(())
     }
 
-    async fn stop_recording(&mut self) -> Result<(), StateError> {
-        tracing::info!("Stopping recording");
-        let wav_path = self.audio_recorder.stop().await?;
-        self.update_state(DaemonState::Processing);
-
-        // Spawn transcription task
-        let whisper_client = Arc::new(self.whisper_client.clone());
-        let cleanup_client = Arc::new(self.cleanup_client.clone());
+    /// Start loading the config with the config profile `name` layered over it (`None`: without
+    /// one). Loading reads files and may wait for the keyring to be unlocked, so it runs on a
+    /// blocking thread and comes back as `ConfigProfileLoaded`; `reply` is answered from there.
+    fn load_config_profile(&mut self, name: Option<String>, reply: oneshot::Sender<Result<(), String>>) {
+        if let Err(e) = self.can_switch_config_profile() {
+            let _ = reply.send(Err(e));
+            return;
+        }
+        if let Some(ref name) = name {
+            if let Err(e) = crate::daemon::instance::parse_name(name) {
+                let _ = reply.send(Err(format!("bad config profile name {:?}: {}", name, e)));
+                return;
+            }
+        }
+        self.loading_config_profile = true;
+        // NOTE: `serve --mock` replaced the provider after loading, keep it that way
+        let mock = self.config.transcription.provider == "mock";
         let event_tx = self.event_tx.clone();
-        let current_language = self.config.general.languages.get(self.current_language_index)
-            .cloned()
-            .unwrap_or_else(|| self.config.general.language.clone());
-
         tokio::spawn(async move {
-            let result = Self::process_audio(
-                &*whisper_client,
-                &*cleanup_client,
-                wav_path,
-                &current_language
-            ).await;
-            
-            match result {
-                Ok(text) => {
-                    let _ = event_tx.send(StateEvent::ProcessingComplete(text)).await;
-                }
-                Err(e) => {
-                    tracing::error!("Processing failed: {}", e);
-                    let _ = event_tx.send(StateEvent::Cancel).await;
-                }
-            }
+            let profile = name.clone();
+            let loaded = tokio::task::spawn_blocking(move || LoadedConfig::load(profile.as_deref(), mock))
+                .await
+                .unwrap_or_else(|e| Err(format!("loading the config failed: {}", e)));
+            let _ = event_tx.send(StateEvent::ConfigProfileLoaded(name, loaded.map(Box::new), reply)).await;
         });
+    }
 
+    /// Config profiles are only switched between dictations, one at a time.
+    fn can_switch_config_profile(&self) -> Result<(), String> {
+        if self.loading_config_profile {
+            return Err("another config profile is still loading".to_string());
+        }
+        if self.state != DaemonState::Idle || !self.queue.is_empty() {
+            return Err("finish or cancel the current dictation first".to_string());
+        }
         Ok(())
     }
 
-    async fn process_audio(
-        whisper_client: &WhisperClient,
-        cleanup_client: &CleanupClient,
-        wav_path: PathBuf,
-        language: &str,
-    ) -> Result<String, StateError> {
-        // Transcribe with current language
-        let raw_text = whisper_client.transcribe_with_language(&wav_path, language).await?;
+    /// Put the config loaded for the config profile `name` in place, rebuilding what processing
+    /// and output are made from: transcription backend, cleanup client, text filters and paste
+    /// keys. Hotkeys, audio capture and the overlay keep their settings until a restart. The
+    /// session profile is dropped, as the new config may not have it.
+    fn switch_config_profile(&mut self, name: Option<String>, loaded: LoadedConfig) -> Result<(), String> {
+        // A dictation may have started while it was loading
+        if self.state != DaemonState::Idle || !self.queue.is_empty() {
+            return Err("finish or cancel the current dictation first".to_string());
+        }
+        let LoadedConfig { config, transcriber, cleanup_client, emoji, replacements, redactor } = loaded;
 
-        // Cleanup
-        let cleaned_text = cleanup_client.cleanup(&raw_text).await?;
+        self.pipeline.transcriber = transcriber;
+        self.pipeline.cleanup_client = cleanup_client;
+        self.pipeline.voice_commands = config.general.voice_commands;
+        self.pipeline.emoji = emoji;
+        self.pipeline.replacements = replacements;
+        self.pipeline.redactor = redactor;
+        self.default_paste_keys = parse_paste_keys(&config.output.paste_keys).unwrap_or_default();
+        self.app_paste_keys = config
+            .output
+            .app_paste_keys
+            .iter()
+            .filter_map(|(app, keys)| Some((app.to_lowercase(), parse_paste_keys(keys)?)))
+            .collect();
+        self.translate.store(config.general.translate, Ordering::Relaxed);
+        self.config = config;
+        crate::config::set_config_profile(name.clone());
+        tracing::info!("Config profile: {} (transcription provider {})", name.as_deref().unwrap_or("none"), self.pipeline.transcriber.name());
 
-        // Cleanup temp file
-        if let Err(e) = tokio::fs::remove_file(&wav_path).await {
-            tracing::warn!("Failed to remove audio file: {}", e);
+        // Language, output mode and cleanup go back to the new config's
+        if let Err(e) = self.switch_profile(None) {
+            tracing::warn!("Config profile {}: {}", name.as_deref().unwrap_or("none"), e);
         }
-
-        Ok(cleaned_text)
+        if let Some(ref overlay_tx) = self.overlay_tx {
+            let message = format!("Config profile: {}", name.as_deref().unwrap_or("none"));
+            let _ = overlay_tx.send(crate::overlay::OverlayMessage::Notice(message));
+        }
+        Ok(())
     }
 
-    async fn output_text(&mut self, text: &str) -> Result<(), StateError> {
-        tracing::info!("Outputting text: {} chars (mode: {:?})", text.len(), self.current_output_mode);
-        self.update_state(DaemonState::Outputting);
-
-        match self.current_output_mode {
-            crate::config::OutputMode::Direct => {
-                // Try direct typing first, fallback to clipboard if it fails
-                tracing::debug!("Using direct output mode");
-                match self.keyboard.type_text(text).await {
-                    Ok(()) => {}
-                    Err(crate::output::uinput::UinputError::UnsupportedChar(_)) => {
-                        tracing::debug!("Falling back to clipboard (unsupported char)");
-                        self.clipboard.copy_to_clipboard(text).await?;
-                    }
-                    Err(e) => {
-                        tracing::warn!("uinput failed, falling back to clipboard: {}", e);
-                        self.clipboard.copy_to_clipboard(text).await?;
-                    }
-                }
+    /// Whether `mode` has somewhere to write to with `profile` active.
+    fn check_output_mode(&self, mode: crate::config::OutputMode, profile: &SessionProfile) -> Result<(), String> {
+        let output = &self.config.output;
+        let missing = match mode {
+            crate::config::OutputMode::File if profile.file_path.as_ref().unwrap_or(&output.file_path).is_empty() => {
+                Some("output.file_path")
             }
-            crate::config::OutputMode::Clipboard => {
-                // Only copy to clipboard, don't paste
-                tracing::debug!("Using clipboard-only output mode");
-                self.clipboard.copy_to_clipboard(text).await?;
+            crate::config::OutputMode::Command if profile.command.as_ref().unwrap_or(&output.command).is_empty() => {
+                Some("output.command")
             }
-            crate::config::OutputMode::Both => {
-                // Copy to clipboard first
-                tracing::debug!("Using both output modes - copying to clipboard");
-                self.clipboard.copy_to_clipboard(text).await?;
-                
-                // Then try to paste/type
-                let is_wayland = std::env::var("XDG_SESSION_TYPE")
-                    .map(|s| s == "wayland")
-                    .unwrap_or(false);
-                
-                let has_non_ascii = text.chars().any(|c| !c.is_ascii());
+            crate::config::OutputMode::Remote if self.config.remote.address.is_empty() => Some("remote.address"),
+            _ => None,
+        };
+        match missing {
+            Some(key) => Err(format!("output mode {} needs {} in the config", mode.as_str(), key)),
+            None => Ok(()),
+        }
+    }
 
-                if is_wayland || has_non_ascii {
-                    // Use clipboard paste method (works reliably on Wayland)
-                    tracing::debug!("Using clipboard paste method (Wayland={}, non-ASCII={})", is_wayland, has_non_ascii);
-                    self.clipboard.paste().await?;
-                } else {
-                    // Try direct typing, fallback to paste if it fails
-                    tracing::debug!("Trying direct typing");
-                    match self.keyboard.type_text(text).await {
-                        Ok(()) => {}
-                        Err(crate::output::uinput::UinputError::UnsupportedChar(_)) => {
-                            tracing::debug!("Falling back to clipboard paste (unsupported char)");
-                            self.clipboard.paste().await?;
-                        }
-                        Err(e) => {
-                            tracing::warn!("uinput failed, falling back to clipboard paste: {}", e);
-                            self.clipboard.paste().await?;
-                        }
-                    }
-                }
-            }
+    /// Make `name` the active session profile (`None`: back to the global settings). Its
+    /// language, output mode and cleanup switch replace the runtime ones; what it leaves unset
+    /// goes back to the config.
+    fn switch_profile(&mut self, name: Option<String>) -> Result<(), String> {
+        let profile = match name {
+            Some(ref name) => self.config.profiles.get(name).cloned().ok_or_else(|| {
+                let names: Vec<&str> = self.config.profiles.keys().map(String::as_str).collect();
+                let names = if names.is_empty() { "none".to_string() } else { names.join(", ") };
+                format!("no profile named {:?} (configured: {})", name, names)
+            })?,
+            None => SessionProfile::default(),
+        };
+        let mode = profile.output_mode.unwrap_or(self.config.output.output_mode);
+        self.check_output_mode(mode, &profile)?;
+        let cleanup = &self.pipeline.cleanup_client;
+        if profile.cleanup_enabled == Some(true) && !cleanup.has_api_key() {
+            return Err("the profile turns cleanup on, which needs a Groq API key, none was loaded at startup".to_string());
         }
 
-        // Signal completion
-        let _ = self.event_tx.send(StateEvent::OutputComplete).await;
+        *self.profile.lock().unwrap() = name.clone();
+        cleanup.set_enabled(profile.cleanup_enabled.unwrap_or(self.config.groq.cleanup_enabled) && cleanup.has_api_key());
+
+        let language = profile.language.map(|l| l.to_lowercase()).unwrap_or_else(|| self.config.general.language.clone());
+        let language_changed = std::mem::replace(&mut *self.current_language.lock().unwrap(), language.clone()) != language;
+        let mode_changed = std::mem::replace(&mut *self.current_output_mode.lock().unwrap(), mode) != mode;
+
+        tracing::info!("Session profile: {}", name.as_deref().unwrap_or("none"));
+        if let Some(ref overlay_tx) = self.overlay_tx {
+            let _ = overlay_tx.send(crate::overlay::OverlayMessage::Profile(name));
+            if language_changed {
+                let _ = overlay_tx.send(crate::overlay::OverlayMessage::Language(language));
+            }
+        }
+        if mode_changed {
+            self.announce_output_mode(mode);
+        }
         Ok(())
     }
 
-    async fn toggle_output_mode(&mut self) -> Result<(), StateError> {
-        self.current_output_mode = match self.current_output_mode {
-            crate::config::OutputMode::Direct => crate::config::OutputMode::Clipboard,
-            crate::config::OutputMode::Clipboard => crate::config::OutputMode::Both,
-            crate::config::OutputMode::Both => crate::config::OutputMode::Direct,
-        };
-        
-        let mode_str = match self.current_output_mode {
-            crate::config::OutputMode::Direct => "Direct",
-            crate::config::OutputMode::Clipboard => "Clipboard",
-            crate::config::OutputMode::Both => "Both",
+    /// Next session profile in name order, none after the last.
+    fn cycle_profile(&mut self) {
+        if self.config.profiles.is_empty() {
+            tracing::warn!("No profiles configured");
+            return;
+        }
+        let current = self.profile.lock().unwrap().clone();
+        let mut names = self.config.profiles.keys();
+        let next = match current {
+            Some(current) => names.skip_while(|name| **name != current).nth(1),
+            None => names.next(),
         };
-        
-        tracing::info!("Output mode changed to: {}", mode_str);
-        
-        // Update overlay (overlay handles the visual feedback)
-        if let Some(ref overlay_tx) = self.overlay_tx {
-            let _ = overlay_tx.send(crate::overlay::OverlayMessage::OutputMode(mode_str.to_string()));
+        if let Err(e) = self.switch_profile(next.cloned()) {
+            tracing::warn!("Can't switch profile: {}", e);
+            if let Some(ref overlay_tx) = self.overlay_tx {
+                let _ = overlay_tx.send(crate::overlay::OverlayMessage::Warning(e));
+            }
         }
-        
-        Ok(())
+    }
+
+    /// The config with the settings changed at runtime, for `croaker get-config`. Secrets are blanked.
+    fn effective_config(&self) -> Config {
+        let mut config = self.config.clone();
+        config.general.language = self.current_language();
+        config.general.translate = self.translate.load(Ordering::Relaxed);
+        config.output.output_mode = *self.current_output_mode.lock().unwrap();
+        config.groq.cleanup_enabled = self.pipeline.cleanup_client.is_enabled();
+        config.output.file_path = self.output_file_path();
+        config.output.command = self.output_command();
+        if let Some(prompt_file) = self.session_profile().and_then(|p| p.cleanup_prompt_file) {
+            config.groq.cleanup_prompt_file = prompt_file;
+        }
+        if !config.remote.token.is_empty() {
+            config.remote.token = "<redacted>".to_string();
+        }
+        config
     }
 
     async fn toggle_language(&mut self) -> Result<(), StateError> {
@@ -323,8 +2692,13 @@ impl StateMachine {
         }
         
         // Cycle to next language
-        self.current_language_index = (self.current_language_index + 1) % self.config.general.languages.len();
-        let new_language = &self.config.general.languages[self.current_language_index];
+        let languages = &self.config.general.languages;
+        let new_language = {
+            let mut current = self.current_language.lock().unwrap();
+            let index = languages.iter().position(|l| *l == *current).map_or(0, |i| (i + 1) % languages.len());
+            *current = languages[index].clone();
+            current.clone()
+        };
         
         tracing::info!("Language changed to: {}", new_language);
         
@@ -336,22 +2710,330 @@ impl StateMachine {
         Ok(())
     }
 
+    fn toggle_translate(&mut self) {
+        let enabled = !self.translate.load(Ordering::Relaxed);
+        self.translate.store(enabled, Ordering::Relaxed);
+
+        tracing::info!("Translation mode {}", if enabled { "enabled" } else { "disabled" });
+
+        // Update overlay (overlay handles the visual feedback)
+        if let Some(ref overlay_tx) = self.overlay_tx {
+            let _ = overlay_tx.send(crate::overlay::OverlayMessage::Translate(enabled));
+        }
+    }
+
+    async fn pause(&mut self, paused: bool) -> Result<(), StateError> {
+        if self.paused.swap(paused, Ordering::Relaxed) == paused {
+            return Ok(());
+        }
+        tracing::info!("Hotkeys {}", if paused { "paused" } else { "resumed" });
+        // NOTE: a push-to-talk release would be dropped from now on, so don't wait for it
+        if paused && self.state == DaemonState::Recording {
+            self.cancel().await?;
+        }
+        if let Some(ref overlay_tx) = self.overlay_tx {
+            let _ = overlay_tx.send(crate::overlay::OverlayMessage::Paused(paused));
+        }
+        Ok(())
+    }
+
     async fn cancel(&mut self) -> Result<(), StateError> {
         tracing::info!("Cancelling current operation");
-        
+        self.queue.cancel();
+        self.drop_current().await
+    }
+
+    /// Drop the current recording or dictation. Earlier ones still queued carry on.
+    async fn drop_current(&mut self) -> Result<(), StateError> {
+        self.abort_current().await;
+        if !self.queue.is_empty() {
+            self.update_state(DaemonState::Processing);
+            return Ok(());
+        }
+        self.update_state(DaemonState::Idle);
+        self.output_deferred().await
+    }
+
+    /// Stop recording and processing for the current dictation, leaving the state as it is.
+    async fn abort_current(&mut self) {
         // Cleanup audio
         self.audio_recorder.cleanup(None).await;
+        self.chunks.reset();
+        self.streamed = false;
+        self.fresh_transcript = false;
+        if let Some(token) = self.processing.take() {
+            token.cancel();
+        }
         
-        // Clipboard restoration disabled - user requested removal
-        
-        self.update_state(DaemonState::Idle);
+        // NOTE: the clipboard is restored right after each paste, nothing to undo here
+    }
+
+    /// Drop everything in progress and show `reason` as the Error state, which goes back to
+    /// Idle after `overlay.error_secs` (or as soon as the user starts something else). With
+    /// something to `retry`, a notification offers to try again.
+    async fn fail(&mut self, reason: String, retry: Option<Retry>) {
+        self.queue.cancel();
+        self.abort_current().await;
+        tracing::warn!("Showing error: {}", reason);
+        self.offer_retry(&reason, retry);
+        self.update_state(DaemonState::Error(reason));
+
+        self.errors += 1;
+        let error = self.errors;
+        let event_tx = self.event_tx.clone();
+        let shown = Duration::from_secs(self.config.overlay.error_secs);
+        tokio::spawn(async move {
+            tokio::time::sleep(shown).await;
+            let _ = event_tx.send(StateEvent::ErrorElapsed(error)).await;
+        });
+    }
+
+    /// Keep what's needed to retry and show the `overlay.retry_notification`. It replaces the
+    /// offer of an earlier failure, which can no longer be retried.
+    fn offer_retry(&mut self, reason: &str, retry: Option<Retry>) {
+        if let Some(prompt) = self.retry_prompt.take() {
+            prompt.abort();
+        }
+        self.retry = retry;
+        if self.retry.is_none() || !self.config.overlay.retry_notification {
+            return;
+        }
+        let event_tx = self.event_tx.clone();
+        let reason = reason.to_string();
+        self.retry_prompt = Some(tokio::spawn(async move {
+            match crate::overlay::notification::ask_retry(&reason).await {
+                Ok(true) => {
+                    let _ = event_tx.send(StateEvent::Retry).await;
+                }
+                Ok(false) => {}
+                Err(e) => tracing::warn!("Can't offer a retry: {}", e),
+            }
+        }));
+    }
+
+    /// Run the failed dictation again: the recording through transcription and cleanup, or
+    /// the text through output.
+    async fn retry(&mut self) -> Result<(), StateError> {
+        match self.retry.take() {
+            Some(Retry::Audio { wav, language, translate }) => {
+                tracing::info!("Retrying the failed dictation");
+                self.update_state(DaemonState::Processing);
+                self.spawn_processing(Recording::Memory(wav), language, translate);
+                Ok(())
+            }
+            Some(Retry::Output(text)) => {
+                tracing::info!("Retrying output of the failed dictation");
+                self.output_text(&text).await
+            }
+            None => {
+                tracing::info!("Nothing to retry");
+                Ok(())
+            }
+        }
+    }
+
+    /// Drop whatever is in progress, deleting its recording, before the daemon exits.
+    async fn shutdown(&mut self) {
+        tracing::info!("Shutting down ({:?})", self.state);
+        self.queue.cancel();
+        self.abort_current().await;
+        if let Some(watchdog) = self.watchdog.take() {
+            watchdog.abort();
+        }
+        if let Some(ticker) = self.level_ticker.take() {
+            ticker.abort();
+        }
+        // The state isn't left on the way out, so the recording LED has to go off here
+        if let (Some(ref led), DaemonState::Recording) = (&self.keyboard_led, &self.state) {
+            led.set(false);
+        }
+    }
+
+    /// A queued dictation failed while something else is on screen, so only mention it.
+    fn warn_failed(&self, reason: Option<&str>) {
+        if let (Some(reason), Some(ref overlay_tx)) = (reason, &self.overlay_tx) {
+            let _ = overlay_tx.send(crate::overlay::OverlayMessage::Warning(format!("Dictation failed: {}", reason)));
+        }
+    }
+
+    /// Whether `job` is the dictation being processed in the foreground.
+    fn is_current(&self, job: u64) -> bool {
+        self.state == DaemonState::Processing && self.processing.is_some() && job == self.job
+    }
+
+    /// Whether a recording can start while the current dictation is still being processed.
+    /// Chunked dictation, preview and countdown need the output to themselves, so they wait.
+    fn can_queue(&self) -> bool {
+        self.config.audio.chunk_seconds == 0 && !self.holds_output()
+    }
+
+    /// Move the dictation being processed to the queue, out of the way of a new recording.
+    fn queue_current(&mut self) {
+        let Some(token) = self.processing.take() else {
+            return;
+        };
+        tracing::info!("Dictation {} queued behind a new recording", self.job);
+        self.queue.push(QueuedDictation {
+            job: self.job,
+            token,
+            target: self.target.take(),
+            inserted: std::mem::take(&mut self.inserted),
+            streamed: std::mem::take(&mut self.streamed),
+            held: String::new(),
+            transcript: None,
+        });
+    }
+
+    async fn queued_fragment(&mut self, job: u64, text: &str) -> Result<(), StateError> {
+        if self.is_current(job) {
+            self.queue_current();
+        }
+        if !self.queue.fragment(job, text) {
+            tracing::debug!("Dropping result of cancelled processing");
+            return Ok(());
+        }
+        self.flush_queue().await
+    }
+
+    async fn queued_complete(&mut self, job: u64, transcript: Transcript) -> Result<(), StateError> {
+        if self.is_current(job) {
+            self.queue_current();
+        }
+        if !self.queue.complete(job, transcript) {
+            tracing::debug!("Dropping result of cancelled processing");
+            return Ok(());
+        }
+        self.flush_queue().await
+    }
+
+    /// Write the queued dictations that are done, oldest first, up to the first one still
+    /// being processed; of that one only the sentences streamed so far. Nothing is written while
+    /// the push-to-talk key is down; its release flushes the queue.
+    async fn flush_queue(&mut self) -> Result<(), StateError> {
+        if self.push_to_talk.down {
+            return Ok(());
+        }
+        for ReadyText { mut queued, text, transcript } in self.queue.take_ready() {
+            match transcript {
+                Some(transcript) => {
+                    tracing::info!("Outputting queued dictation {}: {} chars", queued.job, text.len());
+                    self.remember(transcript);
+                    self.write_queued(&mut queued, &text).await?;
+                }
+                None => {
+                    let written = self.write_queued(&mut queued, &text).await;
+                    self.queue.resume(queued);
+                    return written;
+                }
+            }
+        }
+
+        // Nothing is left to wait for
+        if self.state == DaemonState::Processing && self.processing.is_none() {
+            self.update_state(DaemonState::Idle);
+            return self.output_deferred().await;
+        }
         Ok(())
     }
 
+    /// Write text of a queued dictation into the window it was meant for, possibly while the
+    /// next one is recording hands-free or latched on. It gets its own `inserted`, so `undo` and smart joining treat
+    /// the current dictation apart from it.
+    async fn write_queued(&mut self, queued: &mut QueuedDictation, text: &str) -> Result<(), StateError> {
+        if text.is_empty() {
+            return Ok(());
+        }
+        let target = std::mem::replace(&mut self.target, queued.target.take());
+        let inserted = std::mem::replace(&mut self.inserted, queued.inserted);
+        let written = self.write_output(text).await;
+        queued.target = std::mem::replace(&mut self.target, target);
+        queued.inserted = std::mem::replace(&mut self.inserted, inserted);
+        written
+    }
+
+    async fn output_deferred(&mut self) -> Result<(), StateError> {
+        match self.deferred.pop_front() {
+            Some(transcript) => {
+                self.remember(transcript.clone());
+                self.output_text(&transcript.text).await
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Retry spooled recordings in the background until they go through.
+    fn spawn_spool_retry(&self) {
+        let Some(spool) = self.spool.clone() else {
+            return;
+        };
+        let pipeline = self.pipeline.clone();
+        let event_tx = self.event_tx.clone();
+        let interval = std::time::Duration::from_secs(self.config.transcription.retry_interval_secs.max(1));
+
+        tokio::spawn(async move {
+            loop {
+                for entry in spool.pending().await {
+                    let recording = Recording::File(entry.wav.clone());
+                    match pipeline.process(&recording, &entry.meta.language, entry.meta.translate, &CleanupStep::Default, None).await {
+                        Ok(transcript) => {
+                            tracing::info!("Transcribed spooled recording {:?} (recorded {})", entry.wav, entry.meta.recorded_at);
+                            spool.remove(&entry).await;
+                            if event_tx.send(StateEvent::SpoolTranscribed(transcript)).await.is_err() {
+                                return;
+                            }
+                        }
+                        Err(StateError::NoSpeech) => {
+                            tracing::info!("Spooled recording {:?} was silent, discarding it", entry.wav);
+                            spool.remove(&entry).await;
+                        }
+                        Err(StateError::TranscriptionError(e)) if e.is_network() => {
+                            tracing::debug!("Still offline, retrying spooled recordings later: {}", e);
+                            break;
+                        }
+                        Err(StateError::CleanupError(crate::transcribe::cleanup::CleanupError::RequestError(e))) => {
+                            tracing::debug!("Cleanup unreachable, retrying spooled recordings later: {}", e);
+                            break;
+                        }
+                        Err(e) => {
+                            tracing::error!("Spooled recording {:?} failed, not retrying: {}", entry.wav, e);
+                            spool.mark_failed(&entry).await;
+                        }
+                    }
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+
     pub async fn run(mut self) -> Result<(), StateError> {
+        self.spawn_spool_retry();
+        self.dbus = match DbusOutput::connect().await {
+            Ok(dbus) => Some(dbus),
+            Err(e) => {
+                tracing::warn!("Not emitting transcriptions on D-Bus: {}", e);
+                None
+            }
+        };
+
         while let Some(event) = self.event_rx.recv().await {
+            if matches!(event, StateEvent::Shutdown) {
+                self.shutdown().await;
+                break;
+            }
             if let Err(e) = self.handle_event(event).await {
                 tracing::error!("State machine error: {}", e);
+                self.activity.lock().unwrap().last_error = Some(e.to_string());
+                if let Some(stage) = e.stage() {
+                    self.stats.lock().unwrap().error(stage.as_str());
+                }
+                // Otherwise the failed step would be left on screen (say "Processing...") for good
+                if !matches!(e, StateError::InvalidTransition) {
+                    // Text that couldn't be written is still there to write again
+                    let retry = (e.stage() == Some(Stage::Output))
+                        .then(|| self.last.as_ref().map(|last| Retry::Output(last.text.clone())))
+                        .flatten();
+                    self.fail(e.summary(), retry).await;
+                }
             }
         }
 
@@ -359,4 +3041,146 @@ impl StateMachine {
     }
 }
 
+/// Characters as the user sees them, which is what one Backspace removes.
+fn graphemes(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+/// Whether the window that got the output (`output`) is the one focused now. When the
+/// compositor can't tell either, there's nothing to compare and the undo goes ahead.
+fn same_app(output: Option<&str>, focused: Option<&str>) -> bool {
+    match (output, focused) {
+        (Some(output), Some(focused)) => output.eq_ignore_ascii_case(focused),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// The API key, or an empty one when nothing needs it: offline providers only need it for LLM
+/// cleanup, the mock one fakes that too.
+fn api_key(config: &Config) -> Result<String, crate::config::ConfigError> {
+    let needs_key = config.transcription.provider != "mock"
+        && (config.transcription.provider == "groq" || config.groq.cleanup_enabled);
+    match config.load_api_key() {
+        Ok(key) => Ok(key),
+        Err(e) if !needs_key => {
+            tracing::debug!("No API key loaded ({}), not needed with provider {}", e, config.transcription.provider);
+            Ok(String::new())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn parse_paste_keys(keys: &str) -> Option<PasteKeys> {
+    keys.parse()
+        .map_err(|e| tracing::warn!("{}, ignoring it", e))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggle_during_an_error_starts_a_recording() {
+        let error = DaemonState::Error("Transcription failed".to_string());
+        assert_eq!(ToggleStep::new(&error, false), ToggleStep::Start);
+        assert_eq!(ToggleStep::new(&DaemonState::Recording, true), ToggleStep::Stop);
+        assert_eq!(ToggleStep::new(&DaemonState::Processing, true), ToggleStep::QueueAndStart);
+        assert_eq!(ToggleStep::new(&DaemonState::Processing, false), ToggleStep::Ignore);
+    }
+
+    #[test]
+    fn undo_counts_graphemes_and_checks_focus() {
+        assert_eq!(graphemes("ok 👍🏽 café"), 9);
+        assert_eq!(graphemes("👨‍👩‍👧"), 1);
+        assert!(same_app(Some("foot"), Some("Foot")));
+        assert!(!same_app(Some("foot"), Some("firefox")));
+        assert!(!same_app(Some("foot"), None));
+        assert!(same_app(None, None));
+    }
+
+    #[test]
+    fn summarizes_api_errors() {
+        assert_eq!(api_summary("HTTP 401 Unauthorized: {\"error\": ...}"), Some("API key invalid"));
+        assert_eq!(api_summary("HTTP 429 Too Many Requests: slow down"), Some("Rate limited by the API"));
+        assert_eq!(api_summary("HTTP 503 Service Unavailable: "), Some("API unavailable"));
+        assert_eq!(api_summary("Request timed out after 30 seconds"), Some("Network timeout"));
+        assert_eq!(api_summary("HTTP 400 Bad Request: bad audio"), None);
+    }
+
+    fn queue_of(jobs: &[u64]) -> DictationQueue {
+        let mut queue = DictationQueue::default();
+        for &job in jobs {
+            queue.push(QueuedDictation {
+                job,
+                token: CancellationToken::new(),
+                target: None,
+                inserted: Insertion::default(),
+                streamed: false,
+                held: String::new(),
+                transcript: None,
+            });
+        }
+        queue
+    }
+
+    fn transcript(text: &str) -> Transcript {
+        Transcript { raw: text.to_string(), text: text.to_string() }
+    }
+
+    /// Each ready dictation as (job, text, whether it's finished); the unfinished one is put back.
+    fn flush(queue: &mut DictationQueue) -> Vec<(u64, String, bool)> {
+        let mut written = Vec::new();
+        for ready in queue.take_ready() {
+            written.push((ready.queued.job, ready.text, ready.transcript.is_some()));
+            if ready.transcript.is_none() {
+                queue.resume(ready.queued);
+            }
+        }
+        written
+    }
+
+    #[test]
+    fn queue_writes_dictations_in_the_order_they_were_spoken() {
+        let mut queue = queue_of(&[1, 2, 3]);
+        assert!(queue.complete(3, transcript("third")));
+        assert_eq!(flush(&mut queue), vec![(1, String::new(), false)]);
 
+        // A streamed sentence of the oldest one goes out at once, the rest is held
+        assert!(queue.fragment(1, "first. "));
+        assert!(queue.fragment(2, "second. "));
+        assert_eq!(flush(&mut queue), vec![(1, "first. ".to_string(), false)]);
+
+        assert!(queue.complete(1, transcript("first.")));
+        assert_eq!(flush(&mut queue), vec![(1, String::new(), true), (2, "second. ".to_string(), false)]);
+
+        assert!(queue.complete(2, transcript("second.")));
+        assert_eq!(flush(&mut queue), vec![(2, String::new(), true), (3, "third".to_string(), true)]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn failed_queued_dictation_does_not_hold_up_later_ones() {
+        let mut queue = queue_of(&[1, 2]);
+        assert!(queue.complete(2, transcript("second")));
+        // Failures complete with an empty transcript
+        assert!(queue.complete(1, Transcript::default()));
+        assert_eq!(flush(&mut queue), vec![(1, String::new(), true), (2, "second".to_string(), true)]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn cancelling_the_queue_drops_late_results() {
+        let mut queue = queue_of(&[1, 2]);
+        let token = queue.queued[0].token.clone();
+        assert!(queue.contains(1));
+        queue.cancel();
+        assert!(token.is_cancelled());
+        assert!(queue.is_empty());
+        assert!(!queue.contains(1));
+        assert!(!queue.fragment(1, "late. "));
+        assert!(!queue.complete(2, transcript("late")));
+        assert!(flush(&mut queue).is_empty());
+    }
+}
@@ -0,0 +1,213 @@
+// Runtime metrics: how long each stage of a dictation takes and how often it fails, since the
+// daemon started. Shared with the socket server for `croaker stats`, and optionally served in
+// the Prometheus text format (`stats.prometheus_listen`).
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Upper bounds of the latency buckets, in milliseconds; one more bucket counts anything above.
+const BUCKETS_MS: &[u64] = &[100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000, 60_000, 300_000];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Speaking, from start to stop of the recording
+    Record,
+    /// The transcription request, including encoding and uploading the audio
+    Transcription,
+    Cleanup,
+    /// Typing or pasting one piece of text
+    Output,
+}
+
+impl Stage {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Stage::Record => "record",
+            Stage::Transcription => "transcription",
+            Stage::Cleanup => "cleanup",
+            Stage::Output => "output",
+        }
+    }
+}
+
+/// Latency histogram of one stage.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Histogram {
+    pub count: u64,
+    pub sum_ms: u64,
+    pub max_ms: u64,
+    /// Count per bucket of `BUCKETS_MS`, plus the overflow bucket
+    pub buckets: Vec<u64>,
+}
+
+impl Histogram {
+    fn observe(&mut self, ms: u64) {
+        self.buckets.resize(BUCKETS_MS.len() + 1, 0);
+        let bucket = BUCKETS_MS.iter().position(|bound| ms <= *bound).unwrap_or(BUCKETS_MS.len());
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.sum_ms += ms;
+        self.max_ms = self.max_ms.max(ms);
+    }
+
+    pub fn mean_ms(&self) -> u64 {
+        self.sum_ms.checked_div(self.count).unwrap_or(0)
+    }
+
+    /// Upper bound of the bucket holding the `q` quantile, capped at the slowest one seen.
+    pub fn quantile_ms(&self, q: f64) -> u64 {
+        let rank = (q * self.count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return BUCKETS_MS.get(bucket).map_or(self.max_ms, |bound| (*bound).min(self.max_ms));
+            }
+        }
+        self.max_ms
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Stats {
+    pub uptime_secs: u64,
+    /// By stage name
+    pub stages: BTreeMap<String, Histogram>,
+    /// Failures by stage name, and "watchdog" for stages cancelled as hung
+    pub errors: BTreeMap<String, u64>,
+}
+
+impl Stats {
+    pub fn observe(&mut self, stage: Stage, elapsed: Duration) {
+        let ms = elapsed.as_millis().min(u64::MAX as u128) as u64;
+        self.stages.entry(stage.as_str().to_string()).or_default().observe(ms);
+    }
+
+    pub fn error(&mut self, kind: &str) {
+        *self.errors.entry(kind.to_string()).or_default() += 1;
+    }
+
+    /// The Prometheus text exposition format.
+    pub fn prometheus(&self) -> String {
+        let mut out = String::new();
+        out.push_str("# HELP croaker_uptime_seconds Seconds since the daemon started\n");
+        out.push_str("# TYPE croaker_uptime_seconds gauge\n");
+        let _ = writeln!(out, "croaker_uptime_seconds {}", self.uptime_secs);
+
+        out.push_str("# HELP croaker_stage_duration_seconds Time spent per dictation stage\n");
+        out.push_str("# TYPE croaker_stage_duration_seconds histogram\n");
+        for (stage, histogram) in &self.stages {
+            let mut cumulative = 0;
+            for (bucket, count) in histogram.buckets.iter().enumerate() {
+                cumulative += count;
+                let le = BUCKETS_MS.get(bucket).map_or("+Inf".to_string(), |bound| seconds(*bound));
+                let _ = writeln!(
+                    out,
+                    "croaker_stage_duration_seconds_bucket{{stage=\"{}\",le=\"{}\"}} {}",
+                    stage, le, cumulative
+                );
+            }
+            let _ = writeln!(out, "croaker_stage_duration_seconds_sum{{stage=\"{}\"}} {}", stage, seconds(histogram.sum_ms));
+            let _ = writeln!(out, "croaker_stage_duration_seconds_count{{stage=\"{}\"}} {}", stage, histogram.count);
+        }
+
+        out.push_str("# HELP croaker_errors_total Failures per stage\n");
+        out.push_str("# TYPE croaker_errors_total counter\n");
+        for (kind, count) in &self.errors {
+            let _ = writeln!(out, "croaker_errors_total{{stage=\"{}\"}} {}", kind, count);
+        }
+        out
+    }
+}
+
+fn seconds(ms: u64) -> String {
+    format!("{}", ms as f64 / 1000.0)
+}
+
+/// The table `croaker stats` prints.
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Uptime: {}s", self.uptime_secs)?;
+        if self.stages.is_empty() {
+            writeln!(f, "No dictations yet")?;
+        } else {
+            writeln!(f, "{:<14} {:>6} {:>9} {:>9} {:>9} {:>9}", "stage", "count", "mean", "p50", "p95", "max")?;
+            for stage in [Stage::Record, Stage::Transcription, Stage::Cleanup, Stage::Output] {
+                let Some(histogram) = self.stages.get(stage.as_str()) else {
+                    continue;
+                };
+                writeln!(
+                    f,
+                    "{:<14} {:>6} {:>7}ms {:>7}ms {:>7}ms {:>7}ms",
+                    stage.as_str(),
+                    histogram.count,
+                    histogram.mean_ms(),
+                    histogram.quantile_ms(0.5),
+                    histogram.quantile_ms(0.95),
+                    histogram.max_ms
+                )?;
+            }
+        }
+        if self.errors.is_empty() {
+            write!(f, "Errors: none")
+        } else {
+            let errors: Vec<String> = self.errors.iter().map(|(kind, count)| format!("{} {}", kind, count)).collect();
+            write!(f, "Errors: {}", errors.join(", "))
+        }
+    }
+}
+
+/// Answer every HTTP request on `address` with the metrics, whatever the path.
+pub async fn serve_prometheus(address: &str, stats: Arc<Mutex<Stats>>, started: std::time::Instant) -> std::io::Result<()> {
+    let listener = TcpListener::bind(address).await?;
+    tracing::info!("Serving Prometheus metrics on http://{}/metrics", address);
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let body = {
+            let mut stats = stats.lock().unwrap();
+            stats.uptime_secs = started.elapsed().as_secs();
+            stats.prometheus()
+        };
+        tokio::spawn(async move {
+            // The request itself doesn't matter, but read it so the client isn't reset
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request).await;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_latencies() {
+        let mut stats = Stats::default();
+        for ms in [80, 400, 450, 900, 70_000] {
+            stats.observe(Stage::Transcription, Duration::from_millis(ms));
+        }
+        stats.error("cleanup");
+
+        let histogram = &stats.stages["transcription"];
+        assert_eq!(histogram.count, 5);
+        assert_eq!(histogram.mean_ms(), 14_366);
+        assert_eq!(histogram.quantile_ms(0.5), 500);
+        assert_eq!(histogram.quantile_ms(0.95), 70_000);
+
+        let text = stats.prometheus();
+        assert!(text.contains("croaker_stage_duration_seconds_bucket{stage=\"transcription\",le=\"0.5\"} 3\n"));
+        assert!(text.contains("croaker_stage_duration_seconds_bucket{stage=\"transcription\",le=\"+Inf\"} 5\n"));
+        assert!(text.contains("croaker_errors_total{stage=\"cleanup\"} 1\n"));
+    }
+}
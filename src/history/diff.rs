@@ -0,0 +1,70 @@
+// Word-level diff between the raw and cleaned transcript, in the same
+// `[-removed-]{+added+}` notation as `git diff --word-diff=plain`.
+
+pub fn word_diff(before: &str, after: &str) -> String {
+    let a: Vec<&str> = before.split_whitespace().collect();
+    let b: Vec<&str> = after.split_whitespace().collect();
+
+    // Longest common subsequence table, lcs[i][j] for a[i..] and b[j..].
+    // Transcripts are a few hundred words at most, so O(n*m) is fine.
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut parts = Vec::new();
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    let flush = |parts: &mut Vec<String>, removed: &mut Vec<&str>, added: &mut Vec<&str>| {
+        if !removed.is_empty() {
+            parts.push(format!("[-{}-]", removed.join(" ")));
+            removed.clear();
+        }
+        if !added.is_empty() {
+            parts.push(format!("{{+{}+}}", added.join(" ")));
+            added.clear();
+        }
+    };
+
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            flush(&mut parts, &mut removed, &mut added);
+            parts.push(a[i].to_string());
+            i += 1;
+            j += 1;
+        } else if j < b.len() && (i == a.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            added.push(b[j]);
+            j += 1;
+        } else {
+            removed.push(a[i]);
+            i += 1;
+        }
+    }
+    flush(&mut parts, &mut removed, &mut added);
+
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::word_diff;
+
+    #[test]
+    fn marks_removed_and_added_words() {
+        assert_eq!(
+            word_diff("um so the api is down", "So the API is down."),
+            "[-um so-] {+So+} the [-api-] {+API+} is [-down-] {+down.+}"
+        );
+    }
+
+    #[test]
+    fn identical_text_has_no_markers() {
+        assert_eq!(word_diff("Hello world.", "Hello world."), "Hello world.");
+    }
+}
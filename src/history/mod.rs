@@ -0,0 +1,190 @@
+pub mod diff;
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum HistoryError {
+    #[error("History database error: {0}")]
+    DatabaseError(#[from] rusqlite::Error),
+    #[error("Failed to create history directory: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+/// A transcription about to be stored.
+#[derive(Debug, Clone)]
+pub struct NewEntry {
+    pub duration_ms: Option<u64>,
+    pub language: String,
+    pub model: String,
+    pub raw_text: String,
+    pub cleaned_text: String,
+}
+
+/// A stored transcription.
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub id: i64,
+    /// RFC 3339 local time
+    pub created_at: String,
+    pub duration_ms: Option<u64>,
+    pub language: String,
+    pub model: String,
+    pub raw_text: String,
+    pub cleaned_text: String,
+}
+
+/// Transcription history in a local SQLite database.
+///
+/// Every call opens its own connection, so the store is cheap to clone into
+/// background tasks and the CLI can read it while the daemon is writing.
+#[derive(Debug, Clone)]
+pub struct History {
+    path: PathBuf,
+}
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS transcriptions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at TEXT NOT NULL,
+    duration_ms INTEGER,
+    language TEXT NOT NULL,
+    model TEXT NOT NULL,
+    raw_text TEXT NOT NULL,
+    cleaned_text TEXT NOT NULL
+);
+";
+
+const COLUMNS: &str = "id, created_at, duration_ms, language, model, raw_text, cleaned_text";
+
+impl History {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    fn connect(&self) -> Result<Connection, HistoryError> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(&self.path)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(conn)
+    }
+
+    pub fn record(&self, entry: &NewEntry) -> Result<i64, HistoryError> {
+        let conn = self.connect()?;
+        conn.execute(
+            "INSERT INTO transcriptions (created_at, duration_ms, language, model, raw_text, cleaned_text)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                chrono::Local::now().to_rfc3339(),
+                entry.duration_ms.map(|ms| ms as i64),
+                entry.language,
+                entry.model,
+                entry.raw_text,
+                entry.cleaned_text,
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Most recent entries first.
+    pub fn list(&self, limit: usize) -> Result<Vec<HistoryEntry>, HistoryError> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM transcriptions ORDER BY id DESC LIMIT ?1",
+            COLUMNS
+        ))?;
+        let entries = stmt
+            .query_map(params![limit as i64], entry_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(entries)
+    }
+
+    /// Case-insensitive substring search over raw and cleaned text, most recent first.
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<HistoryEntry>, HistoryError> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM transcriptions
+             WHERE cleaned_text LIKE ?1 ESCAPE '\\' OR raw_text LIKE ?1 ESCAPE '\\'
+             ORDER BY id DESC LIMIT ?2",
+            COLUMNS
+        ))?;
+        let entries = stmt
+            .query_map(params![like_pattern(query), limit as i64], entry_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(entries)
+    }
+
+    pub fn get(&self, id: i64) -> Result<Option<HistoryEntry>, HistoryError> {
+        let conn = self.connect()?;
+        let entry = conn
+            .query_row(
+                &format!("SELECT {} FROM transcriptions WHERE id = ?1", COLUMNS),
+                params![id],
+                entry_from_row,
+            )
+            .optional()?;
+        Ok(entry)
+    }
+}
+
+fn entry_from_row(row: &Row) -> rusqlite::Result<HistoryEntry> {
+    Ok(HistoryEntry {
+        id: row.get(0)?,
+        created_at: row.get(1)?,
+        duration_ms: row.get::<_, Option<i64>>(2)?.map(|ms| ms as u64),
+        language: row.get(3)?,
+        model: row.get(4)?,
+        raw_text: row.get(5)?,
+        cleaned_text: row.get(6)?,
+    })
+}
+
+/// Wrap `query` in `%` wildcards, escaping the LIKE metacharacters it contains.
+fn like_pattern(query: &str) -> String {
+    let mut pattern = String::with_capacity(query.len() + 2);
+    pattern.push('%');
+    for c in query.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn like_pattern_escapes_wildcards() {
+        assert_eq!(like_pattern("50%_off"), "%50\\%\\_off%");
+    }
+
+    #[test]
+    fn record_and_search_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::new(dir.path().join("history.db"));
+        let entry = NewEntry {
+            duration_ms: Some(1500),
+            language: "en".to_string(),
+            model: "whisper-large-v3-turbo".to_string(),
+            raw_text: "um hello world".to_string(),
+            cleaned_text: "Hello world.".to_string(),
+        };
+        let id = history.record(&entry).unwrap();
+
+        let found = history.search("HELLO", 10).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, id);
+        assert_eq!(found[0].duration_ms, Some(1500));
+        assert!(history.search("goodbye", 10).unwrap().is_empty());
+        assert_eq!(history.get(id).unwrap().unwrap().cleaned_text, "Hello world.");
+    }
+}
@@ -0,0 +1,136 @@
+// Toggle/cancel shortcuts bound straight in the compositor over its IPC (`hotkeys.compositor_bindings`),
+// for wlroots compositors without the GlobalShortcuts portal. The bindings run `croaker toggle`
+// and `croaker cancel`, which reach the daemon through the control socket.
+//
+// They are runtime bindings: reloading the compositor config drops them until croaker restarts.
+
+use crate::config::{Config, KeyBindings};
+use std::path::Path;
+use thiserror::Error;
+use tokio::process::Command as TokioCommand;
+
+#[derive(Debug, Error)]
+pub enum CompositorError {
+    #[error("Neither Hyprland nor Sway is running")]
+    NoCompositorError,
+    #[error("{0} failed: {1}")]
+    CommandError(&'static str, String),
+}
+
+/// Compositors with an IPC croaker knows how to use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compositor {
+    Hyprland,
+    Sway,
+}
+
+impl Compositor {
+    pub fn detect() -> Option<Self> {
+        if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+            Some(Compositor::Hyprland)
+        } else if std::env::var_os("SWAYSOCK").is_some() {
+            Some(Compositor::Sway)
+        } else {
+            None
+        }
+    }
+
+    /// The compositor's bind command for `shortcut` (e.g. "Super+Shift+R") running `command`.
+    fn bind_command(self, shortcut: &str, command: &str) -> (&'static str, Vec<String>) {
+        let parts: Vec<&str> = shortcut.split('+').map(str::trim).collect();
+        let (key, modifiers) = parts.split_last().expect("split yields at least one part");
+        match self {
+            Compositor::Hyprland => {
+                let modifiers: Vec<&str> = modifiers
+                    .iter()
+                    .map(|m| match m.to_lowercase().as_str() {
+                        "super" | "meta" | "logo" | "win" => "SUPER",
+                        "shift" => "SHIFT",
+                        "ctrl" | "control" => "CTRL",
+                        "alt" => "ALT",
+                        _ => m,
+                    })
+                    .collect();
+                let bind = format!("{}, {}, exec, {}", modifiers.join(" "), key, command);
+                ("hyprctl", vec!["keyword".to_string(), "bind".to_string(), bind])
+            }
+            Compositor::Sway => {
+                let mut combo: Vec<String> = modifiers
+                    .iter()
+                    .map(|m| match m.to_lowercase().as_str() {
+                        "super" | "meta" | "logo" | "win" => "Mod4".to_string(),
+                        "shift" => "Shift".to_string(),
+                        "ctrl" | "control" => "Ctrl".to_string(),
+                        "alt" => "Mod1".to_string(),
+                        _ => m.to_string(),
+                    })
+                    .collect();
+                combo.push(if key.chars().count() == 1 { key.to_lowercase() } else { key.to_string() });
+                ("swaymsg", vec![format!("bindsym {} exec {}", combo.join("+"), command)])
+            }
+        }
+    }
+}
+
+/// Bind `hotkeys.toggle_shortcut` and `hotkeys.cancel_shortcut` to the croaker binary at `exe`.
+pub async fn bind_shortcuts(config: &Config, exe: &Path) -> Result<Compositor, CompositorError> {
+    let compositor = Compositor::detect().ok_or(CompositorError::NoCompositorError)?;
+    let exe = shell_quote(&exe.display().to_string());
+    bind(compositor, &config.hotkeys.toggle_shortcut, &format!("{} toggle", exe)).await?;
+
+    // A bare key like the default Escape would be taken from every application
+    let (cancel, global): (Vec<&str>, Vec<&str>) =
+        config.hotkeys.cancel_shortcut.iter().partition(|shortcut| shortcut.contains('+'));
+    for shortcut in global {
+        tracing::warn!("Not binding cancel shortcut {:?} in the compositor, it needs a modifier", shortcut);
+    }
+    for shortcut in cancel {
+        bind(compositor, &KeyBindings::from(shortcut), &format!("{} cancel", exe)).await?;
+    }
+    Ok(compositor)
+}
+
+async fn bind(compositor: Compositor, shortcuts: &KeyBindings, command: &str) -> Result<(), CompositorError> {
+    for shortcut in shortcuts.iter() {
+        let (program, args) = compositor.bind_command(shortcut, command);
+        let output = TokioCommand::new(program)
+            .args(&args)
+            .output()
+            .await
+            .map_err(|e| CompositorError::CommandError(program, e.to_string()))?;
+        // hyprctl exits 0 and prints the error instead of "ok"
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if !output.status.success() || (program == "hyprctl" && stdout.trim() != "ok") {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(CompositorError::CommandError(program, format!("{}{}", stdout.trim(), stderr.trim())));
+        }
+        tracing::info!("Bound {} to `{}` in {:?}", shortcut, command, compositor);
+    }
+    Ok(())
+}
+
+fn shell_quote(arg: &str) -> String {
+    if arg.chars().all(|c| c.is_ascii_alphanumeric() || "/._-".contains(c)) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_bind_commands() {
+        let (program, args) = Compositor::Hyprland.bind_command("Super+Shift+R", "croaker toggle");
+        assert_eq!(program, "hyprctl");
+        assert_eq!(args, ["keyword", "bind", "SUPER SHIFT, R, exec, croaker toggle"]);
+
+        let (program, args) = Compositor::Sway.bind_command("Super+Shift+R", "croaker toggle");
+        assert_eq!(program, "swaymsg");
+        assert_eq!(args, ["bindsym Mod4+Shift+r exec croaker toggle"]);
+
+        assert_eq!(shell_quote("/home/me/my bin/croaker"), "'/home/me/my bin/croaker'");
+    }
+}
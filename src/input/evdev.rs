@@ -1,7 +1,11 @@
-use crate::config::Config;
+use crate::config::{Config, HotkeyConfig, KeyBindings};
 use crate::daemon::state::StateEvent;
 use evdev::{Device, Key};
-use std::path::Path;
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::os::fd::RawFd;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use thiserror::Error;
 use tokio::sync::mpsc;
 
@@ -24,19 +28,40 @@ struct ParsedShortcut {
     main_key_code: u16, // O, L, etc.
 }
 
+/// Keys to react to, shared by every monitored keyboard.
+#[derive(Debug, Clone)]
+struct Bindings {
+    key_codes: Vec<u16>,
+    output_mode_shortcuts: Vec<ParsedShortcut>,
+    language_shortcuts: Vec<ParsedShortcut>,
+    translate_shortcuts: Vec<ParsedShortcut>,
+    raw_shortcuts: Vec<ParsedShortcut>,
+    profile_shortcuts: Vec<ParsedShortcut>,
+}
+
 pub struct EvdevMonitor {
-    device_paths: Vec<std::path::PathBuf>,
-    key_code: u16,
-    output_mode_shortcut: Option<ParsedShortcut>,
-    language_shortcut: Option<ParsedShortcut>,
+    /// Keyboards found at startup; more are picked up while monitoring
+    device_paths: Vec<PathBuf>,
+    /// `hotkeys.device`
+    selector: String,
+    bindings: Bindings,
     event_tx: mpsc::Sender<StateEvent>,
 }
 
 impl EvdevMonitor {
     pub fn new(config: &Config, event_tx: mpsc::Sender<StateEvent>) -> Result<Self, EvdevError> {
         tracing::debug!("Creating EvdevMonitor");
+        // Parse key names to key codes
+        let key_codes = config
+            .hotkeys
+            .push_to_talk_key
+            .iter()
+            .map(Self::parse_key_name)
+            .collect::<Result<Vec<_>, _>>()?;
+        tracing::info!("Push-to-talk key(s) '{}' parsed to code(s): {:?}", config.hotkeys.push_to_talk_key, key_codes);
+
         // Find keyboard device
-        let device_paths = Self::find_keyboard_devices()?;
+        let device_paths = Self::find_keyboard_devices(&config.hotkeys.device, &trigger_keys(&key_codes))?;
         
         // Verify we can open all detected keyboards and log capabilities
         tracing::info!("Detected {} keyboard device(s)", device_paths.len());
@@ -58,6 +83,9 @@ impl EvdevMonitor {
                     ("LeftShift", Key::KEY_LEFTSHIFT),
                     ("O", Key::KEY_O),
                     ("L", Key::KEY_L),
+                    ("T", Key::KEY_T),
+                    ("R", Key::KEY_R),
+                    ("P", Key::KEY_P),
                 ];
 
                 tracing::info!("Keyboard key code diagnostics for {:?}:", device_name);
@@ -71,44 +99,130 @@ impl EvdevMonitor {
             }
         }
 
-        // Parse key name to key code
-        let key_code = Self::parse_key_name(&config.hotkeys.push_to_talk_key)?;
-        tracing::info!("Push-to-talk key '{}' parsed to code: {}", config.hotkeys.push_to_talk_key, key_code);
-
         // Parse shortcuts from config
-        let output_mode_shortcut = Self::parse_shortcut(&config.hotkeys.output_mode_shortcut)?;
-        let language_shortcut = Self::parse_shortcut(&config.hotkeys.language_shortcut)?;
+        let output_mode_shortcuts = Self::parse_shortcuts(&config.hotkeys.output_mode_shortcut)?;
+        let language_shortcuts = Self::parse_shortcuts(&config.hotkeys.language_shortcut)?;
+        let translate_shortcuts = Self::parse_shortcuts(&config.hotkeys.translate_shortcut)?;
+        let raw_shortcuts = Self::parse_shortcuts(&config.hotkeys.raw_shortcut)?;
+        let profile_shortcuts = Self::parse_shortcuts(&config.hotkeys.profile_shortcut)?;
 
-        if let Some(ref shortcut) = output_mode_shortcut {
+        for shortcut in &output_mode_shortcuts {
             tracing::info!("Output mode shortcut: {:?}", shortcut);
         }
-        if let Some(ref shortcut) = language_shortcut {
+        for shortcut in &language_shortcuts {
             tracing::info!("Language shortcut: {:?}", shortcut);
         }
+        for shortcut in &translate_shortcuts {
+            tracing::info!("Translate shortcut: {:?}", shortcut);
+        }
+        for shortcut in &raw_shortcuts {
+            tracing::info!("Raw output shortcut: {:?}", shortcut);
+        }
+        for shortcut in &profile_shortcuts {
+            tracing::info!("Profile shortcut: {:?}", shortcut);
+        }
 
         Ok(Self {
             device_paths,
-            key_code,
-            output_mode_shortcut,
-            language_shortcut,
+            selector: config.hotkeys.device.clone(),
+            bindings: Bindings {
+                key_codes,
+                output_mode_shortcuts,
+                language_shortcuts,
+                translate_shortcuts,
+                raw_shortcuts,
+                profile_shortcuts,
+            },
             event_tx,
         })
     }
 
-    fn find_keyboard_devices() -> Result<Vec<std::path::PathBuf>, EvdevError> {
+    /// Parse the push-to-talk keys and shortcuts the way `new` does, without opening a device.
+    /// Returns the config key of each binding that doesn't parse.
+    pub fn check_bindings(hotkeys: &HotkeyConfig) -> Vec<(&'static str, EvdevError)> {
+        let mut errors = Vec::new();
+        if let Some(e) = hotkeys.push_to_talk_key.iter().map(Self::parse_key_name).find_map(Result::err) {
+            errors.push(("hotkeys.push_to_talk_key", e));
+        }
+        let shortcuts = [
+            ("hotkeys.output_mode_shortcut", &hotkeys.output_mode_shortcut),
+            ("hotkeys.language_shortcut", &hotkeys.language_shortcut),
+            ("hotkeys.translate_shortcut", &hotkeys.translate_shortcut),
+            ("hotkeys.raw_shortcut", &hotkeys.raw_shortcut),
+            ("hotkeys.profile_shortcut", &hotkeys.profile_shortcut),
+        ];
+        for (key, bindings) in shortcuts {
+            if let Err(e) = Self::parse_shortcuts(bindings) {
+                errors.push((key, e));
+            }
+        }
+        errors
+    }
+
+    fn find_keyboard_devices(selector: &str, triggers: &[Key]) -> Result<Vec<PathBuf>, EvdevError> {
         tracing::info!("Starting keyboard device detection");
+        let candidates = Self::scan_keyboards(selector, triggers);
+
+        if candidates.is_empty() && !selector.is_empty() {
+            tracing::warn!("No input device matches hotkeys.device = {:?}", selector);
+            return Err(EvdevError::NoDevice);
+        }
+        if candidates.is_empty() {
+            tracing::warn!("No suitable input device found in {}", INPUT_DIR);
+            tracing::warn!("This may be due to:");
+            tracing::warn!("  1. Not being in the 'input' group (run: sudo usermod -aG input $USER)");
+            tracing::warn!("  2. Need to log out and back in after adding to input group");
+            tracing::warn!("  3. System may need different device detection logic");
+            tracing::warn!("  4. Try running: sudo croaker serve (temporary test)");
+            return Err(EvdevError::NoDevice);
+        }
 
+        for (path, name, key_count) in &candidates {
+            tracing::info!("✅ Found keyboard device candidate: {:?} (name: {:?}, {} keys)", path, name, key_count);
+        }
+
+        Ok(candidates.into_iter().map(|(p, _, _)| p).collect())
+    }
+
+    /// Every readable /dev/input/event* that looks like a keyboard or has one of the `triggers`,
+    /// or that `selector` (`hotkeys.device`) picks when set: (path, name, key count).
+    fn scan_keyboards(selector: &str, triggers: &[Key]) -> Vec<(PathBuf, String, usize)> {
         // Don't assume event0 is the keyboard - search through all devices
-        // and find one that actually has keyboard keys
-        let mut candidates: Vec<(std::path::PathBuf, String, usize)> = Vec::new();
-        for i in 0..32 {
-            let path_str = format!("/dev/input/event{}", i);
-            let path = Path::new(&path_str);
+        // and find the ones that actually have keyboard keys
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(INPUT_DIR)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.file_name().to_string_lossy().starts_with("event"))
+                    .map(|entry| entry.path())
+                    .collect()
+            })
+            .unwrap_or_default();
+        paths.sort();
+
+        let mut candidates: Vec<(PathBuf, String, usize)> = Vec::new();
+        for path in &paths {
+            let path = path.as_path();
             match Device::open(path) {
                 Ok(device) => {
                     let device_name = device.name().unwrap_or("unknown");
                     tracing::debug!("Checking device {:?} (name: {:?})", path, device_name);
 
+                    // The user picked the device, so don't second-guess it (a macro pad may not look like a keyboard)
+                    if !selector.is_empty() {
+                        if selects(selector, path, device_name) {
+                            let key_count = device.supported_keys().map_or(0, |keys| keys.iter().count());
+                            candidates.push((path.to_path_buf(), device_name.to_string(), key_count));
+                        }
+                        continue;
+                    }
+
+                    // A foot pedal or other trigger device, whatever its name or other keys
+                    if let Some(keys) = device.supported_keys().filter(|keys| triggers.iter().any(|t| keys.contains(*t))) {
+                        candidates.push((path.to_path_buf(), device_name.to_string(), keys.iter().count()));
+                        continue;
+                    }
+
                     let name_lower = device_name.to_lowercase();
 
                     // Skip obvious non-keyboard devices
@@ -155,24 +269,9 @@ impl EvdevMonitor {
             }
         }
 
-        if candidates.is_empty() {
-            tracing::warn!("No suitable input device found. Checked /dev/input/event0-31");
-            tracing::warn!("This may be due to:");
-            tracing::warn!("  1. Not being in the 'input' group (run: sudo usermod -aG input $USER)");
-            tracing::warn!("  2. Need to log out and back in after adding to input group");
-            tracing::warn!("  3. System may need different device detection logic");
-            tracing::warn!("  4. Try running: sudo croaker serve (temporary test)");
-            return Err(EvdevError::NoDevice);
-        }
-
-        // Prefer "real" keyboards but monitor all candidates.
-        // Sort by key count desc so the most feature-complete device appears first in logs.
-        candidates.sort_by(|a, b| b.2.cmp(&a.2));
-        for (path, name, key_count) in &candidates {
-            tracing::info!("✅ Found keyboard device candidate: {:?} (name: {:?}, {} keys)", path, name, key_count);
-        }
-
-        Ok(candidates.into_iter().map(|(p, _, _)| p).collect())
+        // Most keys first, so the most feature-complete device appears first in logs
+        candidates.sort_by_key(|c| std::cmp::Reverse(c.2));
+        candidates
     }
 
     fn parse_key_name(name: &str) -> Result<u16, EvdevError> {
@@ -184,13 +283,34 @@ impl EvdevMonitor {
             "leftctrl" | "ctrl_l" => Key::KEY_LEFTCTRL,
             "rightshift" | "shift_r" => Key::KEY_RIGHTSHIFT,
             "leftshift" | "shift_l" => Key::KEY_LEFTSHIFT,
-            _ => return Err(EvdevError::ParseKeyError(format!("Unknown key: {}", name))),
+            // Any other key or button (foot pedals send e.g. BTN_0 or KEY_F13): its evdev
+            // name, with or without KEY_, or the numeric code
+            other => match other.trim().parse::<u16>() {
+                Ok(code) => Key::new(code),
+                Err(_) => {
+                    let upper = other.trim().to_uppercase();
+                    upper
+                        .parse::<Key>()
+                        .or_else(|_| format!("KEY_{}", upper).parse::<Key>())
+                        .map_err(|_| {
+                            EvdevError::ParseKeyError(format!(
+                                "Unknown key: {} (use RightAlt, an evdev name like KEY_F13 or BTN_0, or a key code)",
+                                name
+                            ))
+                        })?
+                }
+            },
         };
         let code = key.code();
         tracing::debug!("Parsed key '{}' to code {}", name, code);
         Ok(code)
     }
 
+    fn parse_shortcuts(shortcuts: &KeyBindings) -> Result<Vec<ParsedShortcut>, EvdevError> {
+        let parsed = shortcuts.iter().map(Self::parse_shortcut).collect::<Result<Vec<_>, _>>()?;
+        Ok(parsed.into_iter().flatten().collect())
+    }
+
     fn parse_shortcut(shortcut_str: &str) -> Result<Option<ParsedShortcut>, EvdevError> {
         if shortcut_str.is_empty() {
             return Ok(None);
@@ -269,7 +389,11 @@ impl EvdevMonitor {
                             return Err(EvdevError::ParseShortcutError(format!("Unknown key in shortcut: {}", part)));
                         }
                     } else {
-                        return Err(EvdevError::ParseShortcutError(format!("Unknown modifier/key in shortcut: {}", part)));
+                        // Numpad, F13-F24, media keys, ...: any evdev key name
+                        let code = Self::parse_key_name(part).map_err(|_| {
+                            EvdevError::ParseShortcutError(format!("Unknown modifier/key in shortcut: {}", part))
+                        })?;
+                        main_key_code = Some(code);
                     }
                 }
             }
@@ -286,181 +410,365 @@ impl EvdevMonitor {
         }
     }
 
+    /// Watch every keyboard until an error, including ones plugged in later. A keyboard that
+    /// goes away is dropped and picked up again when it comes back.
     pub async fn monitor(&mut self) -> Result<(), EvdevError> {
         tracing::info!(
-            "Starting evdev monitor for key code: {} on device(s): {:?}",
-            self.key_code,
+            "Starting evdev monitor for key code(s): {:?} on device(s): {:?}",
+            self.bindings.key_codes,
             self.device_paths
         );
 
-        let key_code = self.key_code;
-        let device_paths = self.device_paths.clone();
-        let event_tx = self.event_tx.clone();
-        let output_mode_shortcut = self.output_mode_shortcut.clone();
-        let language_shortcut = self.language_shortcut.clone();
-
-        // Run evdev monitoring in blocking tasks since Device doesn't implement Send
-        let mut handles = Vec::new();
-        for device_path in device_paths {
-            let event_tx = event_tx.clone();
-            let output_mode_shortcut = output_mode_shortcut.clone();
-            let language_shortcut = language_shortcut.clone();
-            handles.push(tokio::task::spawn_blocking(move || -> Result<(), EvdevError> {
-                let mut device = Device::open(&device_path)?;
-                // Copy out the name so we don't hold an immutable borrow of `device` while fetching events.
-                let device_name = device.name().unwrap_or("unknown").to_string();
-                tracing::info!("Opened device for monitoring: {:?} (name: {:?})", device_path, device_name);
-            
-                // Track modifier states for shortcut detection (per device)
-                let mut shift_pressed = false;
-                let mut modifier_pressed: Option<u16> = None; // Track which modifier is pressed (RightAlt, LeftAlt, RightCtrl, etc.)
-                let mut is_recording = false;
-            
-                // Use evdev::Key enum to get correct key codes for this system
-                let key_leftshift = Key::KEY_LEFTSHIFT.code();
-                let key_rightshift = Key::KEY_RIGHTSHIFT.code();
-                let key_rightalt = Key::KEY_RIGHTALT.code();
-                let key_leftalt = Key::KEY_LEFTALT.code();
-                let key_rightctrl = Key::KEY_RIGHTCTRL.code();
-                let key_leftctrl = Key::KEY_LEFTCTRL.code();
-            
-                tracing::info!("Monitoring device {:?}. Push-to-talk key code: {}", device_name, key_code);
-                tracing::info!("Modifier key codes - Shift: L={} R={}, Alt: L={} R={}, Ctrl: L={} R={}", 
-                    key_leftshift, key_rightshift, key_leftalt, key_rightalt, key_leftctrl, key_rightctrl);
-                if let Some(ref shortcut) = output_mode_shortcut {
-                    tracing::info!("Output mode shortcut configured - modifier code: {:?}, main key code: {}", 
-                        shortcut.modifier_key_code, shortcut.main_key_code);
+        let (found_tx, mut found_rx) = mpsc::channel::<PathBuf>(8);
+        let selector = self.selector.clone();
+        let triggers = trigger_keys(&self.bindings.key_codes);
+        for path in &self.device_paths {
+            let _ = found_tx.send(path.clone()).await;
+        }
+        // Rescan /dev/input whenever something in it changes
+        tokio::task::spawn_blocking(move || {
+            let watch = InputDirWatch::new();
+            if let Err(ref e) = watch {
+                tracing::warn!("Can't watch {} for new keyboards ({}), checking every {:?}", INPUT_DIR, e, RESCAN_INTERVAL);
+            }
+            loop {
+                match watch {
+                    Ok(ref watch) => watch.wait(),
+                    Err(_) => std::thread::sleep(RESCAN_INTERVAL),
                 }
-                if let Some(ref shortcut) = language_shortcut {
-                    tracing::info!("Language shortcut configured - modifier code: {:?}, main key code: {}", 
-                        shortcut.modifier_key_code, shortcut.main_key_code);
+                // udev sets the permissions just after creating the node
+                std::thread::sleep(std::time::Duration::from_millis(200));
+                for (path, _, _) in Self::scan_keyboards(&selector, &triggers) {
+                    if found_tx.blocking_send(path).is_err() {
+                        return;
+                    }
                 }
-            
-                loop {
-                    match device.fetch_events() {
-                        Ok(events) => {
-                            for event in events {
-                                if event.event_type() == evdev::EventType::KEY {
-                                    let event_key_code = event.code();
-                                    let event_value = event.value();
-                                    
-                                    // Trace only the push-to-talk key to avoid log spam
-                                    if event_key_code == key_code {
-                                        tracing::debug!(
-                                            "PTT key event (device={:?}): code={} value={} shift_pressed={} modifier_pressed={:?} is_recording={}",
-                                            device_name,
-                                            event_key_code,
-                                            event_value,
-                                            shift_pressed,
-                                            modifier_pressed,
-                                            is_recording
-                                        );
+            }
+        });
+
+        // Device doesn't implement Send, so each keyboard is read in its own blocking task
+        let monitored = Arc::new(Mutex::new(HashSet::new()));
+        while let Some(path) = found_rx.recv().await {
+            if !monitored.lock().unwrap().insert(path.clone()) {
+                continue;
+            }
+            let bindings = self.bindings.clone();
+            let event_tx = self.event_tx.clone();
+            let monitored = monitored.clone();
+            tokio::task::spawn_blocking(move || {
+                let _ = Self::watch_device(&path, &bindings, &event_tx);
+                monitored.lock().unwrap().remove(&path);
+            });
+        }
+
+        Ok(())
+    }
+
+    fn watch_device(device_path: &Path, bindings: &Bindings, event_tx: &mpsc::Sender<StateEvent>) -> Result<(), EvdevError> {
+        let key_codes = &bindings.key_codes;
+        let output_mode_shortcuts = &bindings.output_mode_shortcuts;
+        let language_shortcuts = &bindings.language_shortcuts;
+        let translate_shortcuts = &bindings.translate_shortcuts;
+        let raw_shortcuts = &bindings.raw_shortcuts;
+        let profile_shortcuts = &bindings.profile_shortcuts;
+
+        let mut device = Device::open(device_path)?;
+        // Copy out the name so we don't hold an immutable borrow of `device` while fetching events.
+        let device_name = device.name().unwrap_or("unknown").to_string();
+        tracing::info!("Opened device for monitoring: {:?} (name: {:?})", device_path, device_name);
+    
+        // Track modifier states for shortcut detection (per device)
+        let mut shift_pressed = false;
+        let mut modifier_pressed: Option<u16> = None; // Track which modifier is pressed (RightAlt, LeftAlt, RightCtrl, etc.)
+        let mut is_recording = false;
+    
+        // Use evdev::Key enum to get correct key codes for this system
+        let key_leftshift = Key::KEY_LEFTSHIFT.code();
+        let key_rightshift = Key::KEY_RIGHTSHIFT.code();
+        let key_rightalt = Key::KEY_RIGHTALT.code();
+        let key_leftalt = Key::KEY_LEFTALT.code();
+        let key_rightctrl = Key::KEY_RIGHTCTRL.code();
+        let key_leftctrl = Key::KEY_LEFTCTRL.code();
+    
+        tracing::info!("Monitoring device {:?}. Push-to-talk key code(s): {:?}", device_name, key_codes);
+        tracing::info!("Modifier key codes - Shift: L={} R={}, Alt: L={} R={}, Ctrl: L={} R={}", 
+            key_leftshift, key_rightshift, key_leftalt, key_rightalt, key_leftctrl, key_rightctrl);
+        for shortcut in output_mode_shortcuts {
+            tracing::info!("Output mode shortcut configured - modifier code: {:?}, main key code: {}", 
+                shortcut.modifier_key_code, shortcut.main_key_code);
+        }
+        for shortcut in language_shortcuts {
+            tracing::info!("Language shortcut configured - modifier code: {:?}, main key code: {}", 
+                shortcut.modifier_key_code, shortcut.main_key_code);
+        }
+    
+        loop {
+            match device.fetch_events() {
+                Ok(events) => {
+                    for event in events {
+                        if event.event_type() == evdev::EventType::KEY {
+                            let event_key_code = event.code();
+                            let event_value = event.value();
+                            
+                            // Trace only the push-to-talk key to avoid log spam
+                            if key_codes.contains(&event_key_code) {
+                                tracing::debug!(
+                                    "PTT key event (device={:?}): code={} value={} shift_pressed={} modifier_pressed={:?} is_recording={}",
+                                    device_name,
+                                    event_key_code,
+                                    event_value,
+                                    shift_pressed,
+                                    modifier_pressed,
+                                    is_recording
+                                );
+                            }
+                            
+                            // Track modifier states (1=press, 0=release, ignore 2=repeat)
+                            match event_key_code {
+                                code if code == key_leftshift || code == key_rightshift => {
+                                    if event_value == 1 {
+                                        shift_pressed = true;
+                                    } else if event_value == 0 {
+                                        shift_pressed = false;
                                     }
-                                    
-                                    // Track modifier states (1=press, 0=release, ignore 2=repeat)
-                                    match event_key_code {
-                                        code if code == key_leftshift || code == key_rightshift => {
-                                            if event_value == 1 {
-                                                shift_pressed = true;
-                                            } else if event_value == 0 {
-                                                shift_pressed = false;
+                                }
+                                code if code == key_rightalt || code == key_leftalt || 
+                                       code == key_rightctrl || code == key_leftctrl => {
+                                    if event_value == 1 {
+                                        modifier_pressed = Some(event_key_code);
+                                        tracing::debug!("Modifier key pressed (device={:?}): code={}, key_codes={:?}, shift_pressed={}, is_recording={}", 
+                                            device_name, event_key_code, key_codes, shift_pressed, is_recording);
+                                        // Only start recording if this is our push-to-talk key and Shift is NOT pressed
+                                        if key_codes.contains(&event_key_code) {
+                                            if !shift_pressed && !is_recording {
+                                                tracing::info!("Push-to-talk: start recording (device={:?}, key code: {})", device_name, event_key_code);
+                                                is_recording = true;
+                                                let _ = event_tx.try_send(StateEvent::StartRecording);
+                                            } else {
+                                                tracing::debug!("Push-to-talk key pressed but not starting (device={:?}): shift_pressed={}, is_recording={}", device_name, shift_pressed, is_recording);
+                                            }
+                                        }
+                                    } else if event_value == 0 {
+                                        tracing::debug!("Modifier key released (device={:?}): code={}, key_codes={:?}, is_recording={}", 
+                                            device_name, event_key_code, key_codes, is_recording);
+                                        if modifier_pressed == Some(event_key_code) {
+                                            modifier_pressed = None;
+                                        }
+                                        // Stop recording if we were recording and this is our push-to-talk key
+                                        if key_codes.contains(&event_key_code) && is_recording {
+                                            tracing::info!("Push-to-talk: stop recording (device={:?}, key code: {})", device_name, event_key_code);
+                                            is_recording = false;
+                                            let _ = event_tx.try_send(StateEvent::StopRecording);
+                                        }
+                                    }
+                                }
+                                _ => {
+                                    // Check for output mode shortcut
+                                    for shortcut in output_mode_shortcuts {
+                                        if event_key_code == shortcut.main_key_code && event_value == 1 {
+                                            let shift_ok = !shortcut.needs_shift || shift_pressed;
+                                            let modifier_ok = shortcut.modifier_key_code.is_none() || 
+                                                modifier_pressed == shortcut.modifier_key_code;
+                                            if shift_ok && modifier_ok {
+                                                tracing::info!("Shortcut: Toggle output mode (device={:?})", device_name);
+                                                let _ = event_tx.try_send(StateEvent::ToggleOutputMode);
                                             }
                                         }
-                                        code if code == key_rightalt || code == key_leftalt || 
-                                               code == key_rightctrl || code == key_leftctrl => {
-                                            if event_value == 1 {
-                                                modifier_pressed = Some(event_key_code);
-                                                tracing::debug!("Modifier key pressed (device={:?}): code={}, key_code={}, shift_pressed={}, is_recording={}", 
-                                                    device_name, event_key_code, key_code, shift_pressed, is_recording);
-                                                // Only start recording if this is our push-to-talk key and Shift is NOT pressed
-                                                if event_key_code == key_code {
-                                                    if !shift_pressed && !is_recording {
-                                                        tracing::info!("Push-to-talk: start recording (device={:?}, key code: {}, expected: {})", device_name, event_key_code, key_code);
-                                                        is_recording = true;
-                                                        let _ = event_tx.try_send(StateEvent::StartRecording);
-                                                    } else {
-                                                        tracing::debug!("Push-to-talk key pressed but not starting (device={:?}): shift_pressed={}, is_recording={}", device_name, shift_pressed, is_recording);
-                                                    }
-                                                }
-                                            } else if event_value == 0 {
-                                                tracing::debug!("Modifier key released (device={:?}): code={}, key_code={}, is_recording={}", 
-                                                    device_name, event_key_code, key_code, is_recording);
-                                                if modifier_pressed == Some(event_key_code) {
-                                                    modifier_pressed = None;
-                                                }
-                                                // Stop recording if we were recording and this is our push-to-talk key
-                                                if event_key_code == key_code && is_recording {
-                                                    tracing::info!("Push-to-talk: stop recording (device={:?}, key code: {})", device_name, event_key_code);
-                                                    is_recording = false;
-                                                    let _ = event_tx.try_send(StateEvent::StopRecording);
-                                                }
+                                    }
+                                    
+                                    // Check for language shortcut
+                                    for shortcut in language_shortcuts {
+                                        if event_key_code == shortcut.main_key_code && event_value == 1 {
+                                            let shift_ok = !shortcut.needs_shift || shift_pressed;
+                                            let modifier_ok = shortcut.modifier_key_code.is_none() || 
+                                                modifier_pressed == shortcut.modifier_key_code;
+                                            if shift_ok && modifier_ok {
+                                                tracing::info!("Shortcut: Toggle language (device={:?})", device_name);
+                                                let _ = event_tx.try_send(StateEvent::ToggleLanguage);
                                             }
                                         }
-                                        _ => {
-                                            // Check for output mode shortcut
-                                            if let Some(ref shortcut) = output_mode_shortcut {
-                                                if event_key_code == shortcut.main_key_code && event_value == 1 {
-                                                    let shift_ok = !shortcut.needs_shift || shift_pressed;
-                                                    let modifier_ok = shortcut.modifier_key_code.is_none() || 
-                                                        modifier_pressed == shortcut.modifier_key_code;
-                                                    if shift_ok && modifier_ok {
-                                                        tracing::info!("Shortcut: Toggle output mode (device={:?})", device_name);
-                                                        let _ = event_tx.try_send(StateEvent::ToggleOutputMode);
-                                                    }
-                                                }
+                                    }
+                                    
+                                    // Check for translate shortcut
+                                    for shortcut in translate_shortcuts {
+                                        if event_key_code == shortcut.main_key_code && event_value == 1 {
+                                            let shift_ok = !shortcut.needs_shift || shift_pressed;
+                                            let modifier_ok = shortcut.modifier_key_code.is_none() || 
+                                                modifier_pressed == shortcut.modifier_key_code;
+                                            if shift_ok && modifier_ok {
+                                                tracing::info!("Shortcut: Toggle translate (device={:?})", device_name);
+                                                let _ = event_tx.try_send(StateEvent::ToggleTranslate);
                                             }
-                                            
-                                            // Check for language shortcut
-                                            if let Some(ref shortcut) = language_shortcut {
-                                                if event_key_code == shortcut.main_key_code && event_value == 1 {
-                                                    let shift_ok = !shortcut.needs_shift || shift_pressed;
-                                                    let modifier_ok = shortcut.modifier_key_code.is_none() || 
-                                                        modifier_pressed == shortcut.modifier_key_code;
-                                                    if shift_ok && modifier_ok {
-                                                        tracing::info!("Shortcut: Toggle language (device={:?})", device_name);
-                                                        let _ = event_tx.try_send(StateEvent::ToggleLanguage);
-                                                    }
-                                                }
+                                        }
+                                    }
+                                    
+                                    // Check for raw output shortcut
+                                    for shortcut in raw_shortcuts {
+                                        if event_key_code == shortcut.main_key_code && event_value == 1 {
+                                            let shift_ok = !shortcut.needs_shift || shift_pressed;
+                                            let modifier_ok = shortcut.modifier_key_code.is_none() || 
+                                                modifier_pressed == shortcut.modifier_key_code;
+                                            if shift_ok && modifier_ok {
+                                                tracing::info!("Shortcut: Skip cleanup for next dictation (device={:?})", device_name);
+                                                let _ = event_tx.try_send(StateEvent::SkipCleanupNext);
                                             }
-                                            
-                                            // Check if it's our push-to-talk key (for keys that aren't modifiers)
-                                            if event_key_code == key_code && 
-                                               key_code != key_rightalt && 
-                                               key_code != key_leftalt &&
-                                               key_code != key_rightctrl &&
-                                               key_code != key_leftctrl {
-                                                if event_value == 1 && !is_recording {
-                                                    tracing::info!("Push-to-talk key pressed (device={:?}, code {})", device_name, event_key_code);
-                                                    is_recording = true;
-                                                    let _ = event_tx.try_send(StateEvent::StartRecording);
-                                                } else if event_value == 0 && is_recording {
-                                                    tracing::info!("Push-to-talk key released (device={:?}, code {})", device_name, event_key_code);
-                                                    is_recording = false;
-                                                    let _ = event_tx.try_send(StateEvent::StopRecording);
-                                                }
+                                        }
+                                    }
+                                    
+                                    // Check for profile shortcut
+                                    for shortcut in profile_shortcuts {
+                                        if event_key_code == shortcut.main_key_code && event_value == 1 {
+                                            let shift_ok = !shortcut.needs_shift || shift_pressed;
+                                            let modifier_ok = shortcut.modifier_key_code.is_none() || 
+                                                modifier_pressed == shortcut.modifier_key_code;
+                                            if shift_ok && modifier_ok {
+                                                tracing::info!("Shortcut: Next session profile (device={:?})", device_name);
+                                                let _ = event_tx.try_send(StateEvent::CycleProfile);
                                             }
                                         }
                                     }
+                                    
+                                    // Check if it's our push-to-talk key (for keys that aren't modifiers)
+                                    if key_codes.contains(&event_key_code) && 
+                                       event_key_code != key_rightalt && 
+                                       event_key_code != key_leftalt &&
+                                       event_key_code != key_rightctrl &&
+                                       event_key_code != key_leftctrl {
+                                        if event_value == 1 && !is_recording {
+                                            tracing::info!("Push-to-talk key pressed (device={:?}, code {})", device_name, event_key_code);
+                                            is_recording = true;
+                                            let _ = event_tx.try_send(StateEvent::StartRecording);
+                                        } else if event_value == 0 && is_recording {
+                                            tracing::info!("Push-to-talk key released (device={:?}, code {})", device_name, event_key_code);
+                                            is_recording = false;
+                                            let _ = event_tx.try_send(StateEvent::StopRecording);
+                                        }
+                                    }
                                 }
                             }
                         }
-                        Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                            std::thread::sleep(std::time::Duration::from_millis(10));
-                        }
-                        Err(e) => {
-                            tracing::error!("evdev error (device={:?}): {}", device_name, e);
-                            return Err(EvdevError::OpenError(e));
-                        }
                     }
                 }
-            }));
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    std::thread::sleep(std::time::Duration::from_millis(10));
+                }
+                Err(e) => {
+                    tracing::warn!("Stopped reading {:?} ({:?}): {}", device_name, device_path, e);
+                    // Unplugged with push-to-talk held, there will be no release
+                    if is_recording {
+                        let _ = event_tx.try_send(StateEvent::StopRecording);
+                    }
+                    return Err(EvdevError::OpenError(e));
+                }
+            }
         }
+    }
+}
 
-        // Keep join handles alive; this async function should never return during normal operation.
-        let _handles = handles;
-        std::future::pending::<()>().await;
+/// The push-to-talk keys that aren't modifiers every keyboard has. Devices with one of these
+/// keys are watched even when they don't look like keyboards.
+fn trigger_keys(codes: &[u16]) -> Vec<Key> {
+    let modifiers = [
+        Key::KEY_RIGHTALT,
+        Key::KEY_LEFTALT,
+        Key::KEY_RIGHTCTRL,
+        Key::KEY_LEFTCTRL,
+        Key::KEY_RIGHTSHIFT,
+        Key::KEY_LEFTSHIFT,
+    ];
+    codes.iter().map(|code| Key::new(*code)).filter(|key| !modifiers.contains(key)).collect()
+}
 
-        Ok(())
+/// Whether `hotkeys.device` picks this device: a path (symlinks such as /dev/input/by-id/...
+/// are resolved) or part of the device name, ignoring case.
+fn selects(selector: &str, path: &Path, name: &str) -> bool {
+    if selector.starts_with('/') {
+        std::fs::canonicalize(selector).is_ok_and(|target| target == path)
+    } else {
+        name.to_lowercase().contains(&selector.to_lowercase())
     }
 }
 
+const INPUT_DIR: &str = "/dev/input";
+/// How often to look for new keyboards when inotify isn't available
+const RESCAN_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// inotify watch on /dev/input for device nodes being created or getting their permissions.
+struct InputDirWatch {
+    fd: RawFd,
+}
+
+impl InputDirWatch {
+    fn new() -> std::io::Result<Self> {
+        let dir = CString::new(INPUT_DIR).expect("no NUL in path");
+        // SAFETY: plain syscalls; the descriptor is owned by the returned value and closed on drop
+        unsafe {
+            let fd = libc::inotify_init1(libc::IN_CLOEXEC);
+            if fd < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            if libc::inotify_add_watch(fd, dir.as_ptr(), libc::IN_CREATE | libc::IN_ATTRIB) < 0 {
+                let e = std::io::Error::last_os_error();
+                libc::close(fd);
+                return Err(e);
+            }
+            Ok(Self { fd })
+        }
+    }
+
+    /// Block until something changes. The events themselves aren't needed, the caller rescans.
+    fn wait(&self) {
+        let mut buffer = [0u8; 4096];
+        // SAFETY: reads into a buffer we own, at most its length
+        let read = unsafe { libc::read(self.fd, buffer.as_mut_ptr().cast(), buffer.len()) };
+        if read < 0 {
+            tracing::debug!("inotify read failed: {}", std::io::Error::last_os_error());
+            std::thread::sleep(RESCAN_INTERVAL);
+        }
+    }
+}
+
+impl Drop for InputDirWatch {
+    fn drop(&mut self) {
+        // SAFETY: the descriptor came from inotify_init1 and is closed only here
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_trigger_keys() {
+        assert_eq!(EvdevMonitor::parse_key_name("RightAlt").unwrap(), Key::KEY_RIGHTALT.code());
+        assert_eq!(EvdevMonitor::parse_key_name("btn_0").unwrap(), Key::BTN_0.code());
+        assert_eq!(EvdevMonitor::parse_key_name("F13").unwrap(), Key::KEY_F13.code());
+        assert_eq!(EvdevMonitor::parse_key_name("256").unwrap(), 256);
+        assert_eq!(EvdevMonitor::parse_key_name("kp0").unwrap(), Key::KEY_KP0.code());
+        assert_eq!(EvdevMonitor::parse_key_name("KEY_MICMUTE").unwrap(), Key::KEY_MICMUTE.code());
+        assert_eq!(EvdevMonitor::parse_key_name("F24").unwrap(), Key::KEY_F24.code());
+        assert_eq!(EvdevMonitor::parse_key_name("Prog1").unwrap(), Key::KEY_PROG1.code());
+        let shortcut = EvdevMonitor::parse_shortcut("RightCtrl+KPENTER").unwrap().unwrap();
+        assert_eq!(shortcut.main_key_code, Key::KEY_KPENTER.code());
+        assert!(EvdevMonitor::parse_key_name("pedal").is_err());
+        assert_eq!(trigger_keys(&[Key::KEY_RIGHTALT.code(), Key::BTN_0.code()]), vec![Key::BTN_0]);
+    }
+
+    #[test]
+    fn selects_devices_by_path_or_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = dir.path().join("event7");
+        std::fs::write(&node, "").unwrap();
+        let link = dir.path().join("usb-Keychron_K2-event-kbd");
+        std::os::unix::fs::symlink(&node, &link).unwrap();
+        let node = std::fs::canonicalize(&node).unwrap();
+
+        assert!(selects(link.to_str().unwrap(), &node, "Keychron K2"));
+        assert!(!selects(link.to_str().unwrap(), Path::new("/dev/input/event3"), "Keychron K2"));
+        assert!(selects("keychron", &node, "Keychron K2"));
+        assert!(!selects("macro pad", &node, "Keychron K2"));
+    }
+}
@@ -0,0 +1,132 @@
+// Push-to-talk from a game controller button (`hotkeys.gamepad_button`), for couch/HTPC setups
+// where there's no keyboard at hand. Behind the `gamepad` cargo feature since gilrs links
+// against libudev. Any connected controller works, including ones plugged in later.
+
+use crate::config::Config;
+use crate::daemon::state::StateEvent;
+use gilrs::{Button, EventType, GamepadId, Gilrs};
+use thiserror::Error;
+use tokio::sync::mpsc;
+use tokio::time::Duration;
+
+/// How often the blocking event loop checks whether the daemon is still there
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// gilrs button names, plus the Xbox-style face button letters.
+const BUTTONS: &[(&str, Button)] = &[
+    ("south", Button::South),
+    ("a", Button::South),
+    ("east", Button::East),
+    ("b", Button::East),
+    ("north", Button::North),
+    ("y", Button::North),
+    ("west", Button::West),
+    ("x", Button::West),
+    ("lefttrigger", Button::LeftTrigger),
+    ("lb", Button::LeftTrigger),
+    ("lefttrigger2", Button::LeftTrigger2),
+    ("lt", Button::LeftTrigger2),
+    ("righttrigger", Button::RightTrigger),
+    ("rb", Button::RightTrigger),
+    ("righttrigger2", Button::RightTrigger2),
+    ("rt", Button::RightTrigger2),
+    ("select", Button::Select),
+    ("start", Button::Start),
+    ("mode", Button::Mode),
+    ("leftthumb", Button::LeftThumb),
+    ("rightthumb", Button::RightThumb),
+    ("dpadup", Button::DPadUp),
+    ("dpaddown", Button::DPadDown),
+    ("dpadleft", Button::DPadLeft),
+    ("dpadright", Button::DPadRight),
+];
+
+#[derive(Debug, Error)]
+pub enum GamepadError {
+    #[error("Unknown gamepad button: {0}")]
+    UnknownButtonError(String),
+    #[error("Failed to start gamepad support: {0}")]
+    InitError(String),
+}
+
+#[derive(Clone)]
+pub struct GamepadMonitor {
+    button: Button,
+    event_tx: mpsc::Sender<StateEvent>,
+}
+
+impl GamepadMonitor {
+    pub fn new(config: &Config, event_tx: mpsc::Sender<StateEvent>) -> Result<Self, GamepadError> {
+        let name = &config.hotkeys.gamepad_button;
+        let button = parse_button(name).ok_or_else(|| GamepadError::UnknownButtonError(name.clone()))?;
+        Ok(Self { button, event_tx })
+    }
+
+    pub async fn monitor(self) -> Result<(), GamepadError> {
+        tokio::task::spawn_blocking(move || self.watch())
+            .await
+            .map_err(|e| GamepadError::InitError(e.to_string()))?
+    }
+
+    fn watch(self) -> Result<(), GamepadError> {
+        let mut gilrs = Gilrs::new().map_err(|e| GamepadError::InitError(e.to_string()))?;
+        for (_, gamepad) in gilrs.gamepads() {
+            tracing::info!("Gamepad push-to-talk on {} ({:?})", gamepad.name(), self.button);
+        }
+
+        // The controller holding the button, so another one can't stop its recording
+        let mut held: Option<GamepadId> = None;
+        while !self.event_tx.is_closed() {
+            let Some(event) = gilrs.next_event_blocking(Some(POLL_INTERVAL)) else {
+                continue;
+            };
+            match event.event {
+                EventType::ButtonPressed(button, _) if button == self.button && held.is_none() => {
+                    tracing::info!("Gamepad push-to-talk pressed");
+                    held = Some(event.id);
+                    let _ = self.event_tx.try_send(StateEvent::StartRecording);
+                }
+                EventType::ButtonReleased(button, _) if button == self.button && held == Some(event.id) => {
+                    tracing::info!("Gamepad push-to-talk released");
+                    held = None;
+                    let _ = self.event_tx.try_send(StateEvent::StopRecording);
+                }
+                // Unplugged (or out of battery) with the button held, there will be no release
+                EventType::Disconnected if held == Some(event.id) => {
+                    tracing::warn!("Gamepad disconnected during push-to-talk");
+                    held = None;
+                    let _ = self.event_tx.try_send(StateEvent::StopRecording);
+                }
+                EventType::Connected => {
+                    tracing::info!("Gamepad connected: {}", gilrs.gamepad(event.id).name());
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Button for a config name, ignoring case, spaces, dashes and underscores ("DPad-Up", "left_trigger").
+pub fn parse_button(name: &str) -> Option<Button> {
+    let name = name
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '_'))
+        .collect::<String>()
+        .to_lowercase();
+    BUTTONS.iter().find(|(alias, _)| *alias == name).map(|(_, button)| *button)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_button_names() {
+        assert_eq!(parse_button("South"), Some(Button::South));
+        assert_eq!(parse_button("A"), Some(Button::South));
+        assert_eq!(parse_button("left_trigger2"), Some(Button::LeftTrigger2));
+        assert_eq!(parse_button("DPad-Up"), Some(Button::DPadUp));
+        assert_eq!(parse_button("Turbo"), None);
+    }
+}
@@ -1,6 +1,30 @@
+pub mod compositor;
 pub mod evdev;
+#[cfg(feature = "gamepad")]
+pub mod gamepad;
 pub mod portal;
 pub mod socket;
 
-pub use socket::SocketServer;
+use crate::daemon::state::StateEvent;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
 
+/// Sender for the hotkey monitors (evdev, portal, gamepad): events go on to the state machine,
+/// except while `paused` (`croaker pause`), when they're dropped. The socket doesn't go through
+/// here, so `croaker resume` still works.
+pub fn hotkey_sender(event_tx: mpsc::Sender<StateEvent>, paused: Arc<AtomicBool>) -> mpsc::Sender<StateEvent> {
+    let (hotkey_tx, mut hotkey_rx) = mpsc::channel(32);
+    tokio::spawn(async move {
+        while let Some(event) = hotkey_rx.recv().await {
+            if paused.load(Ordering::Relaxed) {
+                tracing::debug!("Hotkeys paused, dropping {:?}", event);
+                continue;
+            }
+            if event_tx.send(event).await.is_err() {
+                break;
+            }
+        }
+    });
+    hotkey_tx
+}
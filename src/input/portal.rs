@@ -1,48 +1,67 @@
-use crate::config::Config;
-use crate::daemon::state::StateEvent;
-use std::sync::Arc;
+use crate::config::{Config, KeyBindings};
+use crate::daemon::state::{RecordOptions, StateEvent};
+use futures_util::StreamExt;
+use std::collections::HashMap;
 use thiserror::Error;
 use tokio::sync::mpsc;
-use zbus::dbus_proxy;
+use zbus::proxy;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
 use zbus::Connection;
 
 #[derive(Debug, Error)]
 pub enum PortalError {
     #[error("D-Bus connection error: {0}")]
     ConnectionError(#[from] zbus::Error),
-    #[error("Failed to register shortcut: {0}")]
-    RegisterError(String),
+    #[error("Portal request {0} failed (response {1})")]
+    RequestError(&'static str, u32),
+    #[error("Portal session was closed")]
+    SessionClosedError,
 }
 
-#[dbus_proxy(
+#[proxy(
     interface = "org.freedesktop.portal.GlobalShortcuts",
     default_service = "org.freedesktop.portal.Desktop",
     default_path = "/org/freedesktop/portal/desktop"
 )]
 trait GlobalShortcuts {
-    fn create_session(
-        &self,
-        options: std::collections::HashMap<&str, zbus::zvariant::Value<'_>>,
-    ) -> zbus::Result<zbus::zvariant::OwnedObjectPath>;
-
-    fn list_shortcuts(&self, session_handle: &zbus::zvariant::ObjectPath<'_>) -> zbus::Result<Vec<(String, Vec<String>)>>;
+    fn create_session(&self, options: HashMap<&str, Value<'_>>) -> zbus::Result<OwnedObjectPath>;
 
     fn bind_shortcuts(
         &self,
-        session_handle: &zbus::zvariant::ObjectPath<'_>,
-        shortcuts: std::collections::HashMap<&str, std::collections::HashMap<&str, zbus::zvariant::Value<'_>>>,
+        session_handle: &ObjectPath<'_>,
+        shortcuts: &[(&str, HashMap<&str, Value<'_>>)],
         parent_window: &str,
-    ) -> zbus::Result<zbus::zvariant::OwnedObjectPath>;
+        options: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<OwnedObjectPath>;
+
+    #[zbus(signal)]
+    fn activated(
+        &self,
+        session_handle: ObjectPath<'_>,
+        shortcut_id: &str,
+        timestamp: u64,
+        options: HashMap<&str, OwnedValue>,
+    ) -> zbus::Result<()>;
+}
+
+/// Every portal method answers asynchronously through a Request object's `Response` signal.
+#[proxy(interface = "org.freedesktop.portal.Request", default_service = "org.freedesktop.portal.Desktop")]
+trait Request {
+    #[zbus(signal)]
+    fn response(&self, response: u32, results: HashMap<&str, OwnedValue>) -> zbus::Result<()>;
+}
 
-    #[dbus_proxy(signal)]
-    fn activated(&self, shortcut: &str, timestamp: u64, options: std::collections::HashMap<&str, zbus::zvariant::Value<'_>>) -> zbus::Result<()>;
+#[proxy(interface = "org.freedesktop.portal.Session", default_service = "org.freedesktop.portal.Desktop")]
+trait Session {
+    #[zbus(signal)]
+    fn closed(&self, details: HashMap<&str, OwnedValue>) -> zbus::Result<()>;
 }
 
 pub struct PortalMonitor {
     connection: Connection,
     event_tx: mpsc::Sender<StateEvent>,
-    toggle_shortcut: String,
-    cancel_shortcut: String,
+    toggle_shortcut: KeyBindings,
+    cancel_shortcut: KeyBindings,
 }
 
 impl PortalMonitor {
@@ -57,42 +76,156 @@ impl PortalMonitor {
         })
     }
 
+    /// Bind the shortcuts and forward their activations until the session ends. Returns
+    /// `SessionClosedError` when the compositor closes the session, so the caller can start over.
     pub async fn register_shortcuts(&mut self) -> Result<(), PortalError> {
         let proxy = GlobalShortcutsProxy::new(&self.connection).await?;
+        let token = format!("croaker{}", std::process::id());
+
+        let mut options = HashMap::new();
+        options.insert("handle_token", Value::from(token.as_str()));
+        options.insert("session_handle_token", Value::from(token.as_str()));
+        let mut response = self.expect_response(&token).await?;
+        proxy.create_session(options).await?;
+        let results = Self::wait(&mut response, "CreateSession").await?;
+        let session_handle = results
+            .get("session_handle")
+            .and_then(|handle| String::try_from(handle.try_clone().ok()?).ok())
+            .and_then(|handle| OwnedObjectPath::try_from(handle).ok())
+            .unwrap_or(self.handle_path("session", &token)?);
+        tracing::info!("Created portal session: {}", session_handle.as_str());
+
+        // Subscribe before binding, the compositor may close the session while asking the user
+        let session = SessionProxy::builder(&self.connection)
+            .path(session_handle.clone())?
+            .build()
+            .await?;
+        let mut closed = session.receive_closed().await?;
+        let mut activated = proxy.receive_activated().await?;
+
+        // Each binding is its own portal shortcut: "toggle", "toggle-2", ...
+        let ids: Vec<(String, &str, &str)> = shortcut_ids("toggle", &self.toggle_shortcut)
+            .map(|(id, trigger)| (id, "Toggle recording", trigger))
+            .chain(shortcut_ids("cancel", &self.cancel_shortcut).map(|(id, trigger)| (id, "Cancel recording", trigger)))
+            .collect();
+        let shortcuts: Vec<_> = ids
+            .iter()
+            .map(|(id, description, trigger)| (id.as_str(), Self::binding(description, trigger)))
+            .collect();
+        let bind_token = format!("{}bind", token);
+        let mut options = HashMap::new();
+        options.insert("handle_token", Value::from(bind_token.as_str()));
+        let mut response = self.expect_response(&bind_token).await?;
+        proxy.bind_shortcuts(&session_handle, &shortcuts, "", options).await?;
+        Self::wait(&mut response, "BindShortcuts").await?;
+        tracing::info!("Registered portal shortcuts");
 
-        // Create session - handle_token is optional per freedesktop portal spec
-        // Try without it first, as some implementations (like GNOME) may have issues with it
-        let options = std::collections::HashMap::new();
-        
-        let session_handle = proxy.create_session(options).await?;
-        tracing::info!("Created portal session: {:?}", session_handle);
-
-        // Bind shortcuts
-        let mut shortcuts = std::collections::HashMap::new();
-        
-        let mut toggle_binding = std::collections::HashMap::new();
-        toggle_binding.insert("shortcut", zbus::zvariant::Value::new(self.toggle_shortcut.clone()));
-        toggle_binding.insert("description", zbus::zvariant::Value::new("Toggle recording"));
-        shortcuts.insert("toggle", toggle_binding);
-
-        let mut cancel_binding = std::collections::HashMap::new();
-        cancel_binding.insert("shortcut", zbus::zvariant::Value::new(self.cancel_shortcut.clone()));
-        cancel_binding.insert("description", zbus::zvariant::Value::new("Cancel recording"));
-        shortcuts.insert("cancel", cancel_binding);
-
-        let _binding_handle = proxy.bind_shortcuts(&session_handle, shortcuts, "").await?;
-        tracing::info!("Registered shortcuts");
-
-        // TODO: Portal signal handling needs proper zbus signal subscription
-        // For now, portal shortcuts registration is done but signal handling
-        // needs to be implemented with proper zbus signal API
-        tracing::warn!("Portal shortcuts registered but signal handling not yet implemented");
-        tracing::warn!("Push-to-talk mode will work, but toggle shortcuts may not function");
-        
-        // Keep connection alive
         loop {
-            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+            tokio::select! {
+                signal = activated.next() => {
+                    let Some(signal) = signal else {
+                        return Err(PortalError::SessionClosedError);
+                    };
+                    let args = signal.args()?;
+                    if args.session_handle.as_str() != session_handle.as_str() {
+                        continue;
+                    }
+                    let action = args.shortcut_id.split('-').next().unwrap_or_default();
+                    let event = match action {
+                        "toggle" => StateEvent::Toggle(RecordOptions::default(), None),
+                        "cancel" => StateEvent::Cancel,
+                        id => {
+                            tracing::debug!("Ignoring unknown portal shortcut {}", id);
+                            continue;
+                        }
+                    };
+                    tracing::info!("Portal shortcut: {}", args.shortcut_id);
+                    let _ = self.event_tx.send(event).await;
+                }
+                _ = closed.next() => {
+                    tracing::warn!("Compositor closed the portal shortcuts session");
+                    return Err(PortalError::SessionClosedError);
+                }
+            }
         }
     }
+
+    fn binding<'a>(description: &'a str, shortcut: &str) -> HashMap<&'static str, Value<'a>> {
+        let mut binding = HashMap::new();
+        binding.insert("description", Value::from(description));
+        binding.insert("preferred_trigger", Value::from(preferred_trigger(shortcut)));
+        binding
+    }
+
+    /// Object path the portal will use for a request or session created with `token`.
+    fn handle_path(&self, kind: &str, token: &str) -> Result<OwnedObjectPath, PortalError> {
+        let sender = self
+            .connection
+            .unique_name()
+            .map(|name| name.trim_start_matches(':').replace('.', "_"))
+            .unwrap_or_default();
+        let path = format!("/org/freedesktop/portal/desktop/{}/{}/{}", kind, sender, token);
+        Ok(OwnedObjectPath::try_from(path).map_err(zbus::Error::from)?)
+    }
+
+    /// Subscribe to a request's `Response` before making the call, so it can't be missed.
+    async fn expect_response(&self, token: &str) -> Result<ResponseStream<'static>, PortalError> {
+        let request = RequestProxy::builder(&self.connection)
+            .path(self.handle_path("request", token)?)?
+            .build()
+            .await?;
+        Ok(request.receive_response().await?)
+    }
+
+    async fn wait(
+        response: &mut ResponseStream<'static>,
+        method: &'static str,
+    ) -> Result<HashMap<String, OwnedValue>, PortalError> {
+        let signal = response.next().await.ok_or(PortalError::RequestError(method, 2))?;
+        let args = signal.args()?;
+        if args.response != 0 {
+            return Err(PortalError::RequestError(method, args.response));
+        }
+        Ok(args
+            .results
+            .iter()
+            .filter_map(|(key, value)| Some((key.to_string(), value.try_clone().ok()?)))
+            .collect())
+    }
+}
+
+/// Portal shortcut ids for the bindings of one action: `action`, then `action-2`, `action-3`, ...
+fn shortcut_ids<'a>(action: &'a str, bindings: &'a KeyBindings) -> impl Iterator<Item = (String, &'a str)> {
+    bindings.iter().enumerate().map(move |(i, trigger)| {
+        let id = if i == 0 { action.to_string() } else { format!("{}-{}", action, i + 1) };
+        (id, trigger)
+    })
 }
 
+/// Convert a config shortcut like "Super+Shift+R" into the portal's trigger format ("LOGO+SHIFT+r").
+fn preferred_trigger(shortcut: &str) -> String {
+    shortcut
+        .split('+')
+        .map(|part| match part.trim().to_lowercase().as_str() {
+            "super" | "meta" | "logo" | "win" => "LOGO".to_string(),
+            "shift" => "SHIFT".to_string(),
+            "ctrl" | "control" => "CTRL".to_string(),
+            "alt" => "ALT".to_string(),
+            key if key.chars().count() == 1 => key.to_string(),
+            _ => part.trim().to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::preferred_trigger;
+
+    #[test]
+    fn converts_shortcuts_to_triggers() {
+        assert_eq!(preferred_trigger("Super+Shift+R"), "LOGO+SHIFT+r");
+        assert_eq!(preferred_trigger("ctrl+alt+Space"), "CTRL+ALT+Space");
+        assert_eq!(preferred_trigger("Escape"), "Escape");
+    }
+}
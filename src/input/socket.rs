@@ -1,9 +1,14 @@
-use crate::daemon::state::{DaemonState, StateEvent};
+use crate::config::{CaptureSource, OutputMode};
+use crate::daemon::stats::Stats;
+use crate::daemon::state::{Activity, DaemonState, RecordOptions, Setting, StateEvent, Transcript};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::{mpsc, Mutex};
+use std::time::Instant;
+use tokio::sync::{broadcast, mpsc, oneshot, watch, Mutex};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
 use tokio::net::{UnixListener, UnixStream};
 
 #[derive(Debug, Error)]
@@ -16,57 +21,498 @@ pub enum SocketError {
 
 #[derive(Debug, Clone)]
 pub enum Command {
-    Toggle,
+    /// `toggle`, optionally followed by `source=<microphone|monitor>` and/or `language=<code>`
+    Toggle(RecordOptions),
     Cancel,
     Status,
     ToggleOutputMode,
     ToggleLanguage,
+    ToggleTranslate,
+    Repeat,
+    RawNext,
+    /// `recleanup`, optionally followed by `prompt=<absolute path>`
+    Recleanup(Option<PathBuf>),
+    Undo,
+    /// `output <JSON string>`: output text from elsewhere (`croaker receive`)
+    Output(String),
+    /// Send the status now and again on every state change, until the client disconnects
+    Subscribe,
+    /// `set-language <code>`
+    SetLanguage(String),
+    /// `set-output-mode <mode>`, with the mode as written in the config
+    SetOutputMode(OutputMode),
+    /// `set-cleanup <on|off>`
+    SetCleanup(bool),
+    /// `set-profile <name>`: switch to a session profile
+    SetProfile(String),
+    /// Back to the global settings
+    ClearProfile,
+    /// `set-config-profile <name>`: reload the config with `profiles/<name>.toml` over it
+    SetConfigProfile(String),
+    /// Reload the plain config
+    ClearConfigProfile,
+    /// Ignore hotkeys until `resume`
+    Pause,
+    Resume,
+    /// Stop the daemon
+    Quit,
+    /// The config as currently in effect, as JSON
+    GetConfig,
+    /// Stage latencies and error counts since the daemon started
+    Stats,
+    /// `logs`, optionally followed by `lines=<n>` and/or `follow`: the end of the log file,
+    /// and with `follow` every line written after it until the client disconnects
+    Logs { lines: usize, follow: bool },
 }
 
 impl Command {
     fn parse(line: &str) -> Result<Self, SocketError> {
         let line = line.trim();
-        match line {
-            "toggle" => Ok(Command::Toggle),
-            "cancel" => Ok(Command::Cancel),
-            "status" => Ok(Command::Status),
-            "toggle-output-mode" => Ok(Command::ToggleOutputMode),
-            "toggle-language" => Ok(Command::ToggleLanguage),
-            _ => Err(SocketError::ParseError(format!("Unknown command: {}", line))),
+        if let Some(args) = line.strip_prefix("toggle ") {
+            let mut options = RecordOptions::default();
+            for arg in args.split_whitespace() {
+                if let Some(source) = arg.strip_prefix("source=") {
+                    options.source = Some(source.parse::<CaptureSource>().map_err(SocketError::ParseError)?);
+                } else if let Some(language) = arg.strip_prefix("language=").filter(|l| !l.is_empty()) {
+                    options.language = Some(language.to_lowercase());
+                } else {
+                    return Err(SocketError::ParseError(format!("Unknown toggle argument: {}", arg)));
+                }
+            }
+            return Ok(Command::Toggle(options));
+        }
+        if let Some(args) = line.strip_prefix("recleanup ") {
+            let path = args
+                .trim()
+                .strip_prefix("prompt=")
+                .ok_or_else(|| SocketError::ParseError(format!("Unknown recleanup argument: {}", args)))?;
+            return Ok(Command::Recleanup(Some(PathBuf::from(path))));
+        }
+        if let Some(code) = line.strip_prefix("set-language ") {
+            return Ok(Command::SetLanguage(code.trim().to_string()));
+        }
+        if let Some(name) = line.strip_prefix("set-profile ") {
+            return Ok(Command::SetProfile(name.trim().to_string()));
+        }
+        if let Some(name) = line.strip_prefix("set-config-profile ") {
+            return Ok(Command::SetConfigProfile(name.trim().to_string()));
+        }
+        if let Some(mode) = line.strip_prefix("set-output-mode ") {
+            return Ok(Command::SetOutputMode(mode.parse::<OutputMode>().map_err(SocketError::ParseError)?));
+        }
+        if let Some(value) = line.strip_prefix("set-cleanup ") {
+            return match value.trim() {
+                "on" => Ok(Command::SetCleanup(true)),
+                "off" => Ok(Command::SetCleanup(false)),
+                value => Err(SocketError::ParseError(format!("set-cleanup takes on or off, not {:?}", value))),
+            };
+        }
+        if let Some(args) = line.strip_prefix("logs ") {
+            let (mut lines, mut follow) = (DEFAULT_LOG_LINES, false);
+            for arg in args.split_whitespace() {
+                if let Some(count) = arg.strip_prefix("lines=") {
+                    lines = count.parse().map_err(|_| SocketError::ParseError(format!("Bad line count: {}", count)))?;
+                } else if arg == "follow" {
+                    follow = true;
+                } else {
+                    return Err(SocketError::ParseError(format!("Unknown logs argument: {}", arg)));
+                }
+            }
+            return Ok(Command::Logs { lines, follow });
+        }
+        if let Some(text) = line.strip_prefix("output ") {
+            // JSON-quoted so the text can contain line breaks
+            let text = serde_json::from_str::<String>(text)
+                .map_err(|e| SocketError::ParseError(format!("Bad output text: {}", e)))?;
+            return Ok(Command::Output(text));
+        }
+
+        Self::without_args(line).ok_or_else(|| SocketError::ParseError(format!("Unknown command: {}", line)))
+    }
+
+    /// Commands that take no arguments, by name.
+    fn without_args(name: &str) -> Option<Self> {
+        match name {
+            "toggle" => Some(Command::Toggle(RecordOptions::default())),
+            "cancel" => Some(Command::Cancel),
+            "status" => Some(Command::Status),
+            "toggle-output-mode" => Some(Command::ToggleOutputMode),
+            "toggle-language" => Some(Command::ToggleLanguage),
+            "toggle-translate" => Some(Command::ToggleTranslate),
+            "repeat" => Some(Command::Repeat),
+            "raw-next" => Some(Command::RawNext),
+            "recleanup" => Some(Command::Recleanup(None)),
+            "undo" => Some(Command::Undo),
+            "subscribe" => Some(Command::Subscribe),
+            "get-config" => Some(Command::GetConfig),
+            "clear-profile" => Some(Command::ClearProfile),
+            "clear-config-profile" => Some(Command::ClearConfigProfile),
+            "pause" => Some(Command::Pause),
+            "resume" => Some(Command::Resume),
+            "quit" => Some(Command::Quit),
+            "stats" => Some(Command::Stats),
+            "logs" => Some(Command::Logs { lines: DEFAULT_LOG_LINES, follow: false }),
+            _ => None,
+        }
+    }
+
+    fn from_request(request: &Request) -> Result<Self, SocketError> {
+        if let Some(version) = request.v.filter(|v| *v != PROTOCOL_VERSION) {
+            return Err(SocketError::ParseError(format!("Unsupported protocol version {}", version)));
+        }
+        let allowed: &[&str] = match request.command.as_str() {
+            "toggle" => &["source", "language"],
+            "recleanup" => &["prompt"],
+            "output" => &["text"],
+            "set-language" => &["language"],
+            "set-output-mode" => &["mode"],
+            "set-cleanup" => &["enabled"],
+            "set-profile" | "set-config-profile" => &["name"],
+            "logs" => &["lines", "follow"],
+            _ => &[],
+        };
+        if let Some(name) = request.args.keys().find(|name| !allowed.contains(&name.as_str())) {
+            return Err(SocketError::ParseError(format!("Unknown {} argument: {}", request.command, name)));
+        }
+
+        match request.command.as_str() {
+            "toggle" => Ok(Command::Toggle(RecordOptions {
+                source: request
+                    .string_arg("source")?
+                    .map(|source| source.parse::<CaptureSource>().map_err(SocketError::ParseError))
+                    .transpose()?,
+                language: request.string_arg("language")?.filter(|l| !l.is_empty()).map(str::to_lowercase),
+            })),
+            "recleanup" => Ok(Command::Recleanup(request.string_arg("prompt")?.map(PathBuf::from))),
+            "output" => {
+                let text = request
+                    .string_arg("text")?
+                    .ok_or_else(|| SocketError::ParseError("output needs a text argument".to_string()))?;
+                Ok(Command::Output(text.to_string()))
+            }
+            "set-language" => {
+                let language = request
+                    .string_arg("language")?
+                    .ok_or_else(|| SocketError::ParseError("set-language needs a language argument".to_string()))?;
+                Ok(Command::SetLanguage(language.to_string()))
+            }
+            "set-output-mode" => {
+                let mode = request
+                    .string_arg("mode")?
+                    .ok_or_else(|| SocketError::ParseError("set-output-mode needs a mode argument".to_string()))?;
+                Ok(Command::SetOutputMode(mode.parse::<OutputMode>().map_err(SocketError::ParseError)?))
+            }
+            "set-profile" => {
+                let name = request
+                    .string_arg("name")?
+                    .ok_or_else(|| SocketError::ParseError("set-profile needs a name argument".to_string()))?;
+                Ok(Command::SetProfile(name.to_string()))
+            }
+            "set-config-profile" => {
+                let name = request
+                    .string_arg("name")?
+                    .ok_or_else(|| SocketError::ParseError("set-config-profile needs a name argument".to_string()))?;
+                Ok(Command::SetConfigProfile(name.to_string()))
+            }
+            "logs" => {
+                let lines = match request.args.get("lines") {
+                    None | Some(serde_json::Value::Null) => DEFAULT_LOG_LINES,
+                    Some(value) => value
+                        .as_u64()
+                        .ok_or_else(|| SocketError::ParseError("lines must be a number".to_string()))?
+                        as usize,
+                };
+                let follow = match request.args.get("follow") {
+                    None | Some(serde_json::Value::Null) => false,
+                    Some(serde_json::Value::Bool(follow)) => *follow,
+                    Some(_) => return Err(SocketError::ParseError("follow must be a boolean".to_string())),
+                };
+                Ok(Command::Logs { lines, follow })
+            }
+            "set-cleanup" => match request.args.get("enabled") {
+                Some(serde_json::Value::Bool(enabled)) => Ok(Command::SetCleanup(*enabled)),
+                _ => Err(SocketError::ParseError("set-cleanup needs a boolean enabled argument".to_string())),
+            },
+            name => Self::without_args(name)
+                .ok_or_else(|| SocketError::ParseError(format!("Unknown command: {}", name))),
         }
     }
 }
 
+/// Version of the JSON protocol, sent as `v` in requests and responses.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Lines of the log file `logs` sends when not told how many
+const DEFAULT_LOG_LINES: usize = 100;
+
+/// How often a followed log file is checked for new lines
+const LOG_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// One line of the JSON protocol: `{"v": 1, "id": 7, "command": "toggle", "args": {"language": "tr"}}`.
+/// `v`, `id` and `args` are optional; `id` is echoed back so clients can match responses.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Request {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub v: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<serde_json::Value>,
+    pub command: String,
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub args: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Request {
+    pub fn new(command: &str) -> Self {
+        Self {
+            v: Some(PROTOCOL_VERSION),
+            id: None,
+            command: command.to_string(),
+            args: serde_json::Map::new(),
+        }
+    }
+
+    pub fn arg(mut self, name: &str, value: impl Into<serde_json::Value>) -> Self {
+        self.args.insert(name.to_string(), value.into());
+        self
+    }
+
+    fn string_arg(&self, name: &str) -> Result<Option<&str>, SocketError> {
+        match self.args.get(name) {
+            None | Some(serde_json::Value::Null) => Ok(None),
+            Some(serde_json::Value::String(value)) => Ok(Some(value)),
+            Some(_) => Err(SocketError::ParseError(format!("{} must be a string", name))),
+        }
+    }
+}
+
+/// Pushed to subscribers after the `subscribe` response: `{"v": 1, "event": "status", "status": {...}}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub v: u32,
+    pub event: String,
+    pub status: Status,
+}
+
+/// Input levels at or below this are reported as this, so silence still serializes as a number
+pub const LEVEL_FLOOR_DBFS: f32 = -60.0;
+
+/// Pushed to JSON subscribers between status events while recording, about five times a second:
+/// `{"v": 1, "event": "level", "level": -23.5}`, the input level in dBFS.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LevelEvent {
+    pub v: u32,
+    pub event: String,
+    pub level: f32,
+}
+
+/// Pushed after the `logs` response when following: `{"v": 1, "event": "log", "line": "..."}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEvent {
+    pub v: u32,
+    pub event: String,
+    pub line: String,
+}
+
+/// Result of `status`, and what subscribers get on every state change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Status {
+    pub state: DaemonState,
+    pub recorder: String,
+    pub language: String,
+    pub translate: bool,
+    pub output_mode: OutputMode,
+    /// Active session profile
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Hotkeys are paused (`croaker pause`)
+    #[serde(default)]
+    pub paused: bool,
+    pub uptime_secs: u64,
+    pub last_error: Option<String>,
+    /// Characters in the last transcription
+    pub last_transcript_chars: Option<usize>,
+    /// Input level in dBFS while recording
+    #[serde(default)]
+    pub audio_level: Option<f32>,
+}
+
+/// The plain-text status line, as older clients get it.
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} (recorder: {}, language: {}, translate: {}, output: {}",
+            self.state,
+            self.recorder,
+            self.language,
+            if self.translate { "on" } else { "off" },
+            self.output_mode.as_str()
+        )?;
+        if let Some(ref profile) = self.profile {
+            write!(f, ", profile: {}", profile)?;
+        }
+        if self.paused {
+            write!(f, ", hotkeys paused")?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Answer to a `Request`: `{"v": 1, "id": 7, "ok": true, "result": "started"}`, or `"ok": false`
+/// with an `error` message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Response {
+    pub v: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<serde_json::Value>,
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Response {
+    fn new(id: Option<serde_json::Value>, result: Result<serde_json::Value, SocketError>) -> Self {
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        Self { v: PROTOCOL_VERSION, id, ok: error.is_none(), result, error }
+    }
+}
+
 pub struct SocketServer {
     path: PathBuf,
+    /// The socket systemd passed, listened on instead of binding `path`
+    inherited: Option<std::os::unix::net::UnixListener>,
+    client: Client,
+}
+
+/// Subscribers that fall this far behind skip to the current status
+const STATE_CHANGES_BUFFER: usize = 16;
+
+/// What a connection needs to run commands; the shared handles keep `status` current on
+/// connections that send several requests.
+#[derive(Clone)]
+struct Client {
     event_tx: mpsc::Sender<StateEvent>,
     current_state: Arc<Mutex<DaemonState>>,
+    recorder: &'static str,
+    translate: Arc<AtomicBool>,
+    output_mode: Arc<std::sync::Mutex<OutputMode>>,
+    language: Arc<std::sync::Mutex<String>>,
+    activity: Arc<std::sync::Mutex<Activity>>,
+    profile: Arc<std::sync::Mutex<Option<String>>>,
+    paused: Arc<AtomicBool>,
+    /// Input level while recording, for `status` and the `level` events of `subscribe`
+    audio_level: watch::Receiver<Option<f32>>,
+    stats: Arc<std::sync::Mutex<Stats>>,
+    /// `general.log_file`, if the daemon writes one
+    log_file: Option<PathBuf>,
+    started: Instant,
+    /// Every state change, for `subscribe`
+    changes: broadcast::Sender<DaemonState>,
 }
 
 impl SocketServer {
     pub fn new(event_tx: mpsc::Sender<StateEvent>) -> (Self, mpsc::Sender<DaemonState>) {
         let socket_path = Self::socket_path().expect("Failed to get socket path");
-        let (state_tx, mut state_rx) = mpsc::channel(1);
+        // The state machine uses try_send, so leave room for a quick Recording -> Processing -> ...
+        let (state_tx, mut state_rx) = mpsc::channel::<DaemonState>(STATE_CHANGES_BUFFER);
         let current_state = Arc::new(Mutex::new(DaemonState::Idle));
+        let (changes, _) = broadcast::channel(STATE_CHANGES_BUFFER);
 
         // Spawn task to update current state
         let state_clone = current_state.clone();
+        let changes_clone = changes.clone();
         tokio::spawn(async move {
             while let Some(state) = state_rx.recv().await {
-                *state_clone.lock().await = state;
+                *state_clone.lock().await = state.clone();
+                let _ = changes_clone.send(state);
             }
         });
 
         (
             Self {
                 path: socket_path,
-                event_tx,
-                current_state,
+                inherited: None,
+                client: Client {
+                    event_tx,
+                    current_state,
+                    recorder: "unknown",
+                    translate: Arc::new(AtomicBool::new(false)),
+                    output_mode: Arc::new(std::sync::Mutex::new(OutputMode::Both)),
+                    language: Arc::new(std::sync::Mutex::new(String::new())),
+                    activity: Arc::new(std::sync::Mutex::new(Activity::default())),
+                    profile: Arc::new(std::sync::Mutex::new(None)),
+                    paused: Arc::new(AtomicBool::new(false)),
+                    audio_level: watch::channel(None).1,
+                    stats: Arc::new(std::sync::Mutex::new(Stats::default())),
+                    log_file: None,
+                    started: Instant::now(),
+                    changes,
+                },
             },
             state_tx,
         )
     }
 
+    /// Record which audio recorder is in use so `status` can report it.
+    pub fn set_recorder(&mut self, recorder: &'static str) {
+        self.client.recorder = recorder;
+    }
+
+    /// Share the state machine's translation flag so `status` can report it.
+    pub fn set_translate_flag(&mut self, translate: Arc<AtomicBool>) {
+        self.client.translate = translate;
+    }
+
+    /// Share the state machine's current language so `status` can report it.
+    pub fn set_language(&mut self, language: Arc<std::sync::Mutex<String>>) {
+        self.client.language = language;
+    }
+
+    /// Share the state machine's output mode so `status` can report it.
+    pub fn set_output_mode(&mut self, output_mode: Arc<std::sync::Mutex<OutputMode>>) {
+        self.client.output_mode = output_mode;
+    }
+
+    /// Share the state machine's last error and transcript length so `status` can report them.
+    pub fn set_activity(&mut self, activity: Arc<std::sync::Mutex<Activity>>) {
+        self.client.activity = activity;
+    }
+
+    /// Share the state machine's session profile so `status` can report it.
+    pub fn set_profile(&mut self, profile: Arc<std::sync::Mutex<Option<String>>>) {
+        self.client.profile = profile;
+    }
+
+    /// Share the state machine's pause flag so `status` can report it.
+    pub fn set_paused_flag(&mut self, paused: Arc<AtomicBool>) {
+        self.client.paused = paused;
+    }
+
+    /// Follow the state machine's input level so `status` and `subscribe` can report it.
+    pub fn set_audio_level(&mut self, audio_level: watch::Receiver<Option<f32>>) {
+        self.client.audio_level = audio_level;
+    }
+
+    /// Share the state machine's stage latencies and error counts for `stats`.
+    pub fn set_stats(&mut self, stats: Arc<std::sync::Mutex<Stats>>) {
+        self.client.stats = stats;
+    }
+
+    /// Listen on the socket systemd passed (`service::take_inherited_listener`).
+    pub fn set_inherited_listener(&mut self, listener: std::os::unix::net::UnixListener) {
+        self.inherited = Some(listener);
+    }
+
+    /// The configured log file, for `logs`.
+    pub fn set_log_file(&mut self, log_file: Option<PathBuf>) {
+        self.client.log_file = log_file;
+    }
+
     pub fn socket_path() -> Result<PathBuf, std::io::Error> {
         let cache_dir = dirs::cache_dir()
             .ok_or_else(|| std::io::Error::new(
@@ -77,25 +523,40 @@ impl SocketServer {
         let croaker_dir = cache_dir.join("croaker");
         std::fs::create_dir_all(&croaker_dir)?;
         
-        Ok(croaker_dir.join("croaker.sock"))
+        Ok(croaker_dir.join(format!("{}.sock", crate::daemon::instance::file_stem())))
     }
 
     pub async fn listen(&mut self) -> Result<(), SocketError> {
-        // Remove existing socket if present
-        if self.path.exists() {
-            let _ = std::fs::remove_file(&self.path);
-        }
-
-        let listener = UnixListener::bind(&self.path)?;
-        tracing::info!("Listening on socket: {:?}", self.path);
+        let listener = match self.inherited.take() {
+            Some(listener) => {
+                listener.set_nonblocking(true)?;
+                tracing::info!("Listening on the socket passed by systemd");
+                UnixListener::from_std(listener)?
+            }
+            None => {
+                if self.path.exists() {
+                    // NOTE: only the instance lock holder gets here, so a socket that still
+                    // answers isn't a daemon's; say systemd's croaker.socket while the daemon was
+                    // started by hand. Leave it, binding fails below
+                    if std::os::unix::net::UnixStream::connect(&self.path).is_ok() {
+                        tracing::warn!("Socket {:?} is in use by another process", self.path);
+                    } else {
+                        tracing::info!("Removing stale socket {:?}", self.path);
+                        let _ = std::fs::remove_file(&self.path);
+                    }
+                }
+                let listener = UnixListener::bind(&self.path)?;
+                tracing::info!("Listening on socket: {:?}", self.path);
+                listener
+            }
+        };
 
         loop {
             match listener.accept().await {
                 Ok((stream, _)) => {
-                    let event_tx = self.event_tx.clone();
-                    let current_state = self.current_state.clone();
+                    let client = self.client.clone();
                     tokio::spawn(async move {
-                        if let Err(e) = Self::handle_client(stream, event_tx, current_state).await {
+                        if let Err(e) = client.handle(stream).await {
                             tracing::warn!("Client error: {}", e);
                         }
                     });
@@ -106,53 +567,393 @@ impl SocketServer {
             }
         }
     }
+}
 
-    async fn handle_client(
-        mut stream: UnixStream,
-        event_tx: mpsc::Sender<StateEvent>,
-        current_state: Arc<Mutex<DaemonState>>,
-    ) -> Result<(), SocketError> {
+impl Client {
+    /// A connection starting with `{` speaks the JSON protocol and may send any number of
+    /// requests, one per line. Anything else is a plain line command, answered in plain text.
+    async fn handle(self, mut stream: UnixStream) -> Result<(), SocketError> {
         let (read_half, mut write_half) = stream.split();
         let mut reader = BufReader::new(read_half);
         let mut line = String::new();
 
         reader.read_line(&mut line).await?;
-        let command = Command::parse(&line)?;
-
-        match command {
-            Command::Toggle => {
-                // Send toggle event
-                event_tx.send(StateEvent::StartRecording).await
-                    .map_err(|e| SocketError::ParseError(e.to_string()))?;
-                
-                // Wait for state change to determine if we started or stopped
-                // For now, just acknowledge
-                write_half.write_all(b"ok\n").await?;
-            }
-            Command::Cancel => {
-                event_tx.send(StateEvent::Cancel).await
-                    .map_err(|e| SocketError::ParseError(e.to_string()))?;
-                write_half.write_all(b"ok\n").await?;
+        if !line.trim_start().starts_with('{') {
+            let reply = match Command::parse(&line)? {
+                Command::Subscribe => return self.stream_status(&mut reader, &mut write_half, false).await,
+                Command::Logs { lines, follow: true } => {
+                    return self.stream_logs(&mut reader, &mut write_half, lines, false).await
+                }
+                Command::Status => self.status().await.to_string(),
+                Command::Stats => self.stats().to_string(),
+                Command::Logs { lines, .. } => self.logs(lines).await?.trim_end().to_string(),
+                command => match self.execute(command).await? {
+                    serde_json::Value::String(reply) => reply,
+                    reply => reply.to_string(),
+                },
+            };
+            write_half.write_all(format!("{}\n", reply).as_bytes()).await?;
+            return Ok(());
+        }
+
+        loop {
+            if !line.trim().is_empty() {
+                let mut subscribe = false;
+                let mut follow = None;
+                let response = match serde_json::from_str::<Request>(&line) {
+                    Ok(request) => {
+                        let result = match Command::from_request(&request) {
+                            Ok(Command::Subscribe) => {
+                                subscribe = true;
+                                Ok(serde_json::Value::from("subscribed"))
+                            }
+                            Ok(Command::Logs { lines, follow: true }) => match self.log_file() {
+                                Ok(_) => {
+                                    follow = Some(lines);
+                                    Ok(serde_json::Value::from("following"))
+                                }
+                                Err(e) => Err(e),
+                            },
+                            Ok(command) => self.execute(command).await,
+                            Err(e) => Err(e),
+                        };
+                        Response::new(request.id, result)
+                    }
+                    Err(e) => Response::new(None, Err(SocketError::ParseError(format!("Malformed request: {}", e)))),
+                };
+                let mut json = serde_json::to_string(&response).expect("response serializes");
+                json.push('\n');
+                write_half.write_all(json.as_bytes()).await?;
+                if subscribe {
+                    return self.stream_status(&mut reader, &mut write_half, true).await;
+                }
+                if let Some(lines) = follow {
+                    return self.stream_logs(&mut reader, &mut write_half, lines, true).await;
+                }
+            }
+            line.clear();
+            if reader.read_line(&mut line).await? == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn status(&self) -> Status {
+        let activity = self.activity.lock().unwrap().clone();
+        Status {
+            state: self.current_state.lock().await.clone(),
+            recorder: self.recorder.to_string(),
+            language: self.language.lock().unwrap().clone(),
+            translate: self.translate.load(Ordering::Relaxed),
+            output_mode: *self.output_mode.lock().unwrap(),
+            profile: self.profile.lock().unwrap().clone(),
+            paused: self.paused.load(Ordering::Relaxed),
+            uptime_secs: self.started.elapsed().as_secs(),
+            last_error: activity.last_error,
+            last_transcript_chars: activity.last_transcript_chars,
+            audio_level: *self.audio_level.borrow(),
+        }
+    }
+
+    fn stats(&self) -> Stats {
+        let mut stats = self.stats.lock().unwrap().clone();
+        stats.uptime_secs = self.started.elapsed().as_secs();
+        stats
+    }
+
+    /// Today's log file.
+    fn log_file(&self) -> Result<PathBuf, SocketError> {
+        let log_file = self
+            .log_file
+            .as_ref()
+            .ok_or_else(|| SocketError::ParseError("No log file, general.log_file is empty".to_string()))?;
+        crate::daemon::logs::current_file(log_file)
+            .ok_or_else(|| SocketError::ParseError(format!("No log file found for {}", log_file.display())))
+    }
+
+    async fn logs(&self, lines: usize) -> Result<String, SocketError> {
+        let contents = tokio::fs::read(self.log_file()?).await?;
+        Ok(crate::daemon::logs::tail(&String::from_utf8_lossy(&contents), lines))
+    }
+
+    /// Write the last `lines` lines of the log, then every line added to it (moving on to the
+    /// next file when it rotates), until the client hangs up. Lines are JSON `LogEvent`s, or
+    /// plain text for plain clients.
+    async fn stream_logs<R, W>(&self, reader: &mut R, writer: &mut W, lines: usize, json: bool) -> Result<(), SocketError>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut path = self.log_file()?;
+        let contents = tokio::fs::read(&path).await?;
+        let mut offset = contents.len() as u64;
+        let mut pending = crate::daemon::logs::tail(&String::from_utf8_lossy(&contents), lines);
+        let mut ignored = String::new();
+        loop {
+            // Only whole lines, a partly written one waits for the next round
+            let complete = pending.rfind('\n').map_or(0, |end| end + 1);
+            for line in pending[..complete].lines() {
+                let mut out = if json {
+                    let event = LogEvent { v: PROTOCOL_VERSION, event: "log".to_string(), line: line.to_string() };
+                    serde_json::to_string(&event).expect("event serializes")
+                } else {
+                    line.to_string()
+                };
+                out.push('\n');
+                writer.write_all(out.as_bytes()).await?;
+            }
+            pending.drain(..complete);
+
+            tokio::select! {
+                _ = tokio::time::sleep(LOG_POLL_INTERVAL) => {}
+                // Nothing more is expected from the client, just notice when it's gone
+                read = reader.read_line(&mut ignored) => {
+                    if read? == 0 {
+                        return Ok(());
+                    }
+                    ignored.clear();
+                }
+            }
+
+            let contents = tokio::fs::read(&path).await?;
+            if (contents.len() as u64) > offset {
+                pending.push_str(&String::from_utf8_lossy(&contents[offset as usize..]));
+                offset = contents.len() as u64;
+            } else if let Ok(current) = self.log_file() {
+                if current != path {
+                    path = current;
+                    offset = 0;
+                }
+            }
+        }
+    }
+
+    /// Write the status, then again after every state change, until the client hangs up.
+    /// Status lines are JSON `Event`s, or the plain-text status line for plain clients; JSON
+    /// clients also get a `LevelEvent` whenever the input level changes.
+    async fn stream_status<R, W>(&self, reader: &mut R, writer: &mut W, json: bool) -> Result<(), SocketError>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut changes = self.changes.subscribe();
+        let mut audio_level = self.audio_level.clone();
+        let mut ignored = String::new();
+        loop {
+            let status = self.status().await;
+            let mut line = if json {
+                let event = Event { v: PROTOCOL_VERSION, event: "status".to_string(), status };
+                serde_json::to_string(&event).expect("event serializes")
+            } else {
+                status.to_string()
+            };
+            line.push('\n');
+            writer.write_all(line.as_bytes()).await?;
+
+            loop {
+                tokio::select! {
+                    change = changes.recv() => match change {
+                        // After a lag the current status is all that matters
+                        Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => break,
+                        Err(broadcast::error::RecvError::Closed) => return Ok(()),
+                    },
+                    Ok(()) = audio_level.changed(), if json => {
+                        let level = *audio_level.borrow_and_update();
+                        if let Some(level) = level {
+                            let event = LevelEvent { v: PROTOCOL_VERSION, event: "level".to_string(), level };
+                            let mut line = serde_json::to_string(&event).expect("event serializes");
+                            line.push('\n');
+                            writer.write_all(line.as_bytes()).await?;
+                        }
+                    },
+                    // Nothing more is expected from a subscriber, just notice when it's gone
+                    read = reader.read_line(&mut ignored) => {
+                        if read? == 0 {
+                            return Ok(());
+                        }
+                        ignored.clear();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Run a command and return its result.
+    async fn execute(&self, command: Command) -> Result<serde_json::Value, SocketError> {
+        let event = match command {
+            Command::Toggle(options) => {
+                let (reply_tx, reply_rx) = oneshot::channel();
+                self.send(StateEvent::Toggle(options, Some(reply_tx))).await?;
+
+                // Dropped without an answer when starting or stopping failed
+                return reply_rx
+                    .await
+                    .map(|action| serde_json::Value::from(action.to_string()))
+                    .map_err(|_| SocketError::ParseError("toggle failed, see the daemon log".to_string()));
             }
             Command::Status => {
-                // Get current state
-                let state = *current_state.lock().await;
-                let state_str = format!("{:?}\n", state);
-                write_half.write_all(state_str.as_bytes()).await?;
+                return Ok(serde_json::to_value(self.status().await).expect("status serializes"));
+            }
+            Command::Stats => {
+                return Ok(serde_json::to_value(self.stats()).expect("stats serialize"));
+            }
+            Command::Logs { follow: true, .. } => {
+                return Err(SocketError::ParseError("logs follow can't be combined with other requests".to_string()));
             }
-            Command::ToggleOutputMode => {
-                event_tx.send(StateEvent::ToggleOutputMode).await
-                    .map_err(|e| SocketError::ParseError(e.to_string()))?;
-                write_half.write_all(b"ok\n").await?;
+            Command::Logs { lines, .. } => return Ok(serde_json::Value::from(self.logs(lines).await?)),
+            // Handled by `handle`, which owns the connection
+            Command::Subscribe => {
+                return Err(SocketError::ParseError("subscribe can't be combined with other requests".to_string()));
             }
-            Command::ToggleLanguage => {
-                event_tx.send(StateEvent::ToggleLanguage).await
-                    .map_err(|e| SocketError::ParseError(e.to_string()))?;
-                write_half.write_all(b"ok\n").await?;
+            Command::SetLanguage(code) => return self.set(Setting::Language(code)).await,
+            Command::SetOutputMode(mode) => return self.set(Setting::OutputMode(mode)).await,
+            Command::SetCleanup(enabled) => return self.set(Setting::Cleanup(enabled)).await,
+            Command::SetProfile(name) => return self.set(Setting::Profile(Some(name))).await,
+            Command::ClearProfile => return self.set(Setting::Profile(None)).await,
+            Command::SetConfigProfile(name) => return self.set(Setting::ConfigProfile(Some(name))).await,
+            Command::ClearConfigProfile => return self.set(Setting::ConfigProfile(None)).await,
+            Command::GetConfig => {
+                let (reply_tx, reply_rx) = oneshot::channel();
+                self.send(StateEvent::GetConfig(reply_tx)).await?;
+                let config = reply_rx
+                    .await
+                    .map_err(|_| SocketError::ParseError("the daemon didn't answer".to_string()))?;
+                return Ok(serde_json::to_value(config).expect("config serializes"));
             }
+            Command::Cancel => StateEvent::Cancel,
+            Command::ToggleOutputMode => StateEvent::ToggleOutputMode,
+            Command::ToggleLanguage => StateEvent::ToggleLanguage,
+            Command::ToggleTranslate => StateEvent::ToggleTranslate,
+            Command::Repeat => StateEvent::Repeat,
+            Command::RawNext => StateEvent::SkipCleanupNext,
+            Command::Recleanup(prompt_file) => StateEvent::Recleanup(prompt_file),
+            Command::Undo => StateEvent::Undo,
+            Command::Pause => StateEvent::Pause(true),
+            Command::Resume => StateEvent::Pause(false),
+            Command::Quit => StateEvent::Shutdown,
+            Command::Output(text) => StateEvent::Received(Transcript { raw: text.clone(), text }),
+        };
+        self.send(event).await?;
+        Ok(serde_json::Value::from("ok"))
+    }
+
+    async fn set(&self, setting: Setting) -> Result<serde_json::Value, SocketError> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.send(StateEvent::Set(setting, reply_tx)).await?;
+        match reply_rx.await {
+            Ok(Ok(())) => Ok(serde_json::Value::from("ok")),
+            Ok(Err(e)) => Err(SocketError::ParseError(e)),
+            Err(_) => Err(SocketError::ParseError("the daemon didn't answer".to_string())),
         }
+    }
 
-        Ok(())
+    async fn send(&self, event: StateEvent) -> Result<(), SocketError> {
+        self.event_tx.send(event).await
+            .map_err(|e| SocketError::ParseError(e.to_string()))
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_toggle_overrides() {
+        let Command::Toggle(options) = Command::parse("toggle source=monitor language=TR\n").unwrap() else {
+            panic!("expected toggle");
+        };
+        assert_eq!(options.source, Some(CaptureSource::Monitor));
+        assert_eq!(options.language.as_deref(), Some("tr"));
+        assert!(Command::parse("toggle lang=tr").is_err());
+    }
+
+    #[test]
+    fn parses_output_text() {
+        let Command::Output(text) = Command::parse("output \"line one\\nline two\"\n").unwrap() else {
+            panic!("expected output");
+        };
+        assert_eq!(text, "line one\nline two");
+        assert!(Command::parse("output line one").is_err());
+    }
+
+    #[test]
+    fn parses_settings() {
+        assert!(matches!(Command::parse("set-language tr"), Ok(Command::SetLanguage(code)) if code == "tr"));
+        assert!(matches!(
+            Command::parse("set-output-mode wtype-type\n"),
+            Ok(Command::SetOutputMode(OutputMode::WtypeType))
+        ));
+        assert!(matches!(Command::parse("set-cleanup off"), Ok(Command::SetCleanup(false))));
+        assert!(Command::parse("set-cleanup maybe").is_err());
+        assert!(Command::parse("set-output-mode typewriter").is_err());
+
+        let request = Request::new("set-cleanup").arg("enabled", true);
+        assert!(matches!(Command::from_request(&request), Ok(Command::SetCleanup(true))));
+        assert!(Command::from_request(&Request::new("set-cleanup").arg("enabled", "on")).is_err());
+
+        assert!(matches!(Command::parse("set-profile email\n"), Ok(Command::SetProfile(name)) if name == "email"));
+        assert!(matches!(Command::parse("clear-profile"), Ok(Command::ClearProfile)));
+        assert!(matches!(Command::parse("set-config-profile work"), Ok(Command::SetConfigProfile(name)) if name == "work"));
+        let request = Request::new("set-profile").arg("name", "notes");
+        assert!(matches!(Command::from_request(&request), Ok(Command::SetProfile(name)) if name == "notes"));
+        assert!(Command::from_request(&Request::new("set-profile")).is_err());
+    }
+
+    #[test]
+    fn parses_logs() {
+        assert!(matches!(Command::parse("logs\n"), Ok(Command::Logs { lines: 100, follow: false })));
+        assert!(matches!(Command::parse("logs lines=20 follow"), Ok(Command::Logs { lines: 20, follow: true })));
+        assert!(Command::parse("logs lines=many").is_err());
+
+        let request = Request::new("logs").arg("lines", 5).arg("follow", true);
+        assert!(matches!(Command::from_request(&request), Ok(Command::Logs { lines: 5, follow: true })));
+        assert!(Command::from_request(&Request::new("logs").arg("follow", "yes")).is_err());
+    }
+
+    #[test]
+    fn parses_json_requests() {
+        let request: Request =
+            serde_json::from_str(r#"{"v": 1, "id": 3, "command": "toggle", "args": {"language": "TR"}}"#).unwrap();
+        let Command::Toggle(options) = Command::from_request(&request).unwrap() else {
+            panic!("expected toggle");
+        };
+        assert_eq!(options.language.as_deref(), Some("tr"));
+
+        let request: Request = serde_json::from_str(r#"{"command": "status", "args": {"verbose": true}}"#).unwrap();
+        assert!(Command::from_request(&request).is_err());
+        assert!(Command::from_request(&Request { v: Some(2), ..Request::new("status") }).is_err());
+    }
+
+    #[test]
+    fn status_serializes_config_names() {
+        let status = Status {
+            state: DaemonState::Recording,
+            recorder: "pw-record".to_string(),
+            language: "tr".to_string(),
+            translate: false,
+            output_mode: OutputMode::WtypeType,
+            profile: None,
+            paused: false,
+            uptime_secs: 5,
+            last_error: None,
+            last_transcript_chars: Some(12),
+            audio_level: Some(-23.0),
+        };
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["state"], "recording");
+        assert_eq!(json["output_mode"], "wtype-type");
+        assert_eq!(status.to_string(), "Recording (recorder: pw-record, language: tr, translate: off, output: wtype-type)");
+
+        let status = Status { profile: Some("email".to_string()), paused: true, ..status };
+        assert_eq!(
+            status.to_string(),
+            "Recording (recorder: pw-record, language: tr, translate: off, output: wtype-type, profile: email, hotkeys paused)"
+        );
+        // Older daemons don't send the newer fields
+        let mut json = serde_json::to_value(&status).unwrap();
+        json.as_object_mut().unwrap().retain(|key, _| key != "profile" && key != "paused" && key != "audio_level");
+        let status = serde_json::from_value::<Status>(json).unwrap();
+        assert!(!status.paused);
+        assert_eq!(status.audio_level, None);
+    }
+}
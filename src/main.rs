@@ -1,19 +1,25 @@
+// NOTE: Error enums intentionally use `FooError` variant names throughout.
+#![allow(clippy::enum_variant_names)]
+
 mod audio;
 mod config;
 mod daemon;
+mod history;
 mod input;
 mod output;
 mod overlay;
 mod transcribe;
+mod tui;
 
 use clap::{Parser, Subcommand};
-use config::Config;
+use config::{keyring, Config, KeySource};
 use daemon::state::{DaemonState, StateEvent, StateMachine};
-use input::{evdev::EvdevMonitor, portal::PortalMonitor, socket::SocketServer};
+use input::{evdev::EvdevMonitor, portal::PortalMonitor, socket::{Event, LogEvent, Request, Response, SocketServer, Status}};
 use overlay::create_overlay;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
 use tokio::net::UnixStream;
 use tokio::time::{sleep, Duration};
+use tracing_subscriber::prelude::*;
 
 #[derive(Parser)]
 #[command(name = "croaker")]
@@ -23,6 +29,10 @@ struct Cli {
     #[arg(long, global = true)]
     debug: bool,
 
+    /// Run or talk to a separate named daemon, configured by ~/.config/croaker/instances/<name>.toml
+    #[arg(long, global = true, value_parser = daemon::instance::parse_name)]
+    instance: Option<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -30,23 +40,203 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Start the daemon
-    Serve,
+    Serve {
+        /// Stop an already running daemon and take over from it
+        #[arg(long)]
+        replace: bool,
+        /// Transcribe every recording to the canned `[mock]` text, without an API key or network
+        #[arg(long)]
+        mock: bool,
+        /// Layer ~/.config/croaker/profiles/<name>.toml over the config
+        #[arg(long, value_parser = daemon::instance::parse_name)]
+        profile: Option<String>,
+    },
     /// Toggle recording on/off
-    Toggle,
+    Toggle {
+        /// Record from this source instead of `audio.source` ("microphone" or "monitor")
+        #[arg(long)]
+        source: Option<config::CaptureSource>,
+        /// Transcribe this utterance in another language (e.g. "tr") without switching the current one
+        #[arg(long)]
+        language: Option<String>,
+    },
     /// Cancel current operation
     Cancel,
     /// Get current status
-    Status,
+    Status {
+        /// Print the status as JSON (state, language, output mode, uptime, last error, ...)
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show stage latencies and error counts since the daemon started
+    Stats {
+        /// Print the histograms as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show the end of the daemon's log file (`general.log_file`)
+    Logs {
+        /// Keep printing lines as they're logged, until interrupted
+        #[arg(short, long)]
+        follow: bool,
+        /// How many of the last lines to show
+        #[arg(short = 'n', long, default_value_t = 100)]
+        lines: usize,
+    },
+    /// Print the status now and on every state change, until interrupted
+    Watch {
+        /// One JSON object per line
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show the status and last transcriptions in the terminal, with keys for toggle, cancel, ...
+    Tui,
     /// Toggle output mode (direct/clipboard/both)
     ToggleOutputMode,
     /// Toggle language (cycles through configured languages)
     ToggleLanguage,
+    /// Toggle translation mode (speak any language, output English)
+    ToggleTranslate,
+    /// Skip LLM cleanup for the next dictation and output the raw transcript
+    RawNext,
+    /// Ignore hotkeys (evdev, portal, gamepad) until `croaker resume`, e.g. while gaming
+    Pause,
+    /// Listen to hotkeys again after `croaker pause`
+    Resume,
+    /// Stop the daemon, dropping a recording or dictation in progress
+    Quit,
+    /// Switch the transcription language until the daemon restarts
+    SetLanguage {
+        /// Language code, e.g. "en" or "tr"
+        code: String,
+    },
+    /// Switch the output mode until the daemon restarts
+    SetOutputMode {
+        /// direct, clipboard, both, wtype-type, file, dbus, command or remote
+        mode: config::OutputMode,
+    },
+    /// Turn LLM cleanup on or off until the daemon restarts
+    SetCleanup {
+        #[arg(value_parser = ["on", "off"])]
+        state: String,
+    },
+    /// Print the config the daemon is running with, including changes made with set-*
+    GetConfig,
+    /// Check the config file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Switch between session profiles (`[profiles]` in the config)
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommand,
+    },
+    /// Show the last transcription from history
+    Last {
+        /// Show what the cleanup changed (`[-removed-]{+added+}`)
+        #[arg(long)]
+        diff: bool,
+        /// Show the raw transcript instead of the cleaned text
+        #[arg(long, conflicts_with = "diff")]
+        raw: bool,
+    },
+    /// Output the last transcription again
+    Repeat,
+    /// Re-run LLM cleanup on the last raw transcript and output the result
+    Recleanup {
+        /// Use this prompt file instead of `groq.cleanup_prompt_file`
+        #[arg(long)]
+        prompt: Option<std::path::PathBuf>,
+    },
+    /// Remove the text the last dictation typed or pasted
+    Undo,
+    /// Receive text from another machine's croaker (`output_mode = "remote"`) and output it here.
+    /// Needs the daemon running on this machine
+    Receive {
+        /// Address to listen on instead of `remote.listen` (host:port, or a Unix socket path)
+        #[arg(long)]
+        listen: Option<String>,
+    },
+    /// Install and enable a systemd user service for the daemon
+    InstallService {
+        /// Don't add the socket unit that starts the daemon on the first croaker command
+        #[arg(long)]
+        no_socket: bool,
+    },
     /// Interactive configuration wizard
     Configure,
+    /// List audio capture devices (values for `audio.device`)
+    Devices,
+    /// Browse past transcriptions
+    History {
+        #[command(subcommand)]
+        command: HistoryCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProfileCommand {
+    /// Use a profile's language, output mode and cleanup until switched again
+    Set {
+        name: String,
+    },
+    /// Go back to the global settings
+    Clear,
+    /// List the configured profiles, marking the active one
+    List,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Report unknown keys, bindings that don't parse and missing files
+    Validate {
+        /// Check the config with ~/.config/croaker/profiles/<name>.toml layered over it
+        #[arg(long, value_parser = daemon::instance::parse_name)]
+        profile: Option<String>,
+    },
+    /// Switch the daemon to a config profile, or back to the plain config without a name
+    Use {
+        #[arg(value_parser = daemon::instance::parse_name)]
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum HistoryCommand {
+    /// Show the most recent transcriptions
+    List {
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Find transcriptions containing some text
+    Search {
+        query: String,
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Copy a past transcription to the clipboard
+    Copy {
+        id: i64,
+        /// Copy the raw transcript instead of the cleaned-up text
+        #[arg(long)]
+        raw: bool,
+    },
 }
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
+    // Before any thread starts, as it clears LISTEN_* from the environment
+    let inherited = match cli.command {
+        Commands::Serve { .. } => daemon::service::take_inherited_listener(),
+        _ => None,
+    };
+    if let Some(name) = cli.instance {
+        daemon::instance::set_name(name);
+    }
+    if let Commands::Serve { ref profile, .. } = cli.command {
+        config::set_config_profile(profile.clone());
+    }
 
     let env_filter = if cli.debug {
         tracing_subscriber::EnvFilter::new("debug")
@@ -54,92 +244,248 @@ fn main() -> anyhow::Result<()> {
         tracing_subscriber::EnvFilter::from_default_env()
     };
 
-    tracing_subscriber::fmt()
-        .with_env_filter(env_filter)
+    // Only the daemon writes the log file; it's kept at info even when stderr is quieter
+    let (log_file, _log_guard) = match cli.command {
+        Commands::Serve { .. } => Config::load()
+            .ok()
+            .and_then(|config| daemon::logs::file_writer(&config.general))
+            .unzip(),
+        _ => (None, None),
+    };
+    let file_filter = if cli.debug || std::env::var_os("RUST_LOG").is_some() {
+        env_filter.to_string()
+    } else {
+        "info".to_string()
+    };
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_filter(env_filter))
+        .with(log_file.map(|writer| {
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(writer)
+                .with_filter(tracing_subscriber::EnvFilter::new(file_filter))
+        }))
         .init();
 
     match cli.command {
-        Commands::Serve => {
-            serve()?;
+        Commands::Serve { replace, mock, .. } => {
+            serve(replace, mock, inherited)?;
         }
-        Commands::Toggle => {
-            tokio::runtime::Runtime::new()?.block_on(send_command("toggle"))?;
+        Commands::Toggle { source, language } => {
+            let mut request = Request::new("toggle");
+            if let Some(source) = source {
+                request = request.arg("source", source.as_str());
+            }
+            if let Some(language) = language {
+                request = request.arg("language", language);
+            }
+            let action = tokio::runtime::Runtime::new()?.block_on(send_command(request))?;
+            println!("{}", action);
         }
         Commands::Cancel => {
-            tokio::runtime::Runtime::new()?.block_on(send_command("cancel"))?;
+            tokio::runtime::Runtime::new()?.block_on(send_command(Request::new("cancel")))?;
+        }
+        Commands::Status { json } => {
+            let status = tokio::runtime::Runtime::new()?.block_on(send_command(Request::new("status")))?;
+            if json {
+                println!("{}", status);
+            } else {
+                println!("{}", serde_json::from_str::<Status>(&status)?);
+            }
         }
-        Commands::Status => {
-            let status = tokio::runtime::Runtime::new()?.block_on(send_command("status"))?;
-            println!("{}", status);
+        Commands::Stats { json } => {
+            let stats = tokio::runtime::Runtime::new()?.block_on(send_command(Request::new("stats")))?;
+            if json {
+                println!("{}", stats);
+            } else {
+                println!("{}", serde_json::from_str::<daemon::stats::Stats>(&stats)?);
+            }
+        }
+        Commands::Logs { follow, lines } => {
+            let runtime = tokio::runtime::Runtime::new()?;
+            if follow {
+                runtime.block_on(follow_logs(lines))?;
+            } else {
+                print!("{}", runtime.block_on(send_command(Request::new("logs").arg("lines", lines)))?);
+            }
+        }
+        Commands::Watch { json } => {
+            tokio::runtime::Runtime::new()?.block_on(watch(json))?;
+        }
+        Commands::Tui => {
+            let config = Config::load()?;
+            let history = config.history.enabled.then(|| history::History::new(&config.history.db_path));
+            tokio::runtime::Runtime::new()?.block_on(tui::run(history))?;
         }
         Commands::ToggleOutputMode => {
-            tokio::runtime::Runtime::new()?.block_on(send_command("toggle-output-mode"))?;
+            tokio::runtime::Runtime::new()?.block_on(send_command(Request::new("toggle-output-mode")))?;
         }
         Commands::ToggleLanguage => {
-            tokio::runtime::Runtime::new()?.block_on(send_command("toggle-language"))?;
+            tokio::runtime::Runtime::new()?.block_on(send_command(Request::new("toggle-language")))?;
+        }
+        Commands::ToggleTranslate => {
+            tokio::runtime::Runtime::new()?.block_on(send_command(Request::new("toggle-translate")))?;
+        }
+        Commands::RawNext => {
+            tokio::runtime::Runtime::new()?.block_on(send_command(Request::new("raw-next")))?;
+        }
+        Commands::Pause => {
+            tokio::runtime::Runtime::new()?.block_on(send_command(Request::new("pause")))?;
+        }
+        Commands::Resume => {
+            tokio::runtime::Runtime::new()?.block_on(send_command(Request::new("resume")))?;
+        }
+        Commands::Quit => {
+            tokio::runtime::Runtime::new()?.block_on(send_command(Request::new("quit")))?;
+        }
+        Commands::SetLanguage { code } => {
+            let request = Request::new("set-language").arg("language", code);
+            tokio::runtime::Runtime::new()?.block_on(send_command(request))?;
+        }
+        Commands::SetOutputMode { mode } => {
+            let request = Request::new("set-output-mode").arg("mode", mode.as_str());
+            tokio::runtime::Runtime::new()?.block_on(send_command(request))?;
+        }
+        Commands::SetCleanup { state } => {
+            let request = Request::new("set-cleanup").arg("enabled", state == "on");
+            tokio::runtime::Runtime::new()?.block_on(send_command(request))?;
+        }
+        Commands::GetConfig => {
+            let config = tokio::runtime::Runtime::new()?.block_on(send_command(Request::new("get-config")))?;
+            let config: serde_json::Value = serde_json::from_str(&config)?;
+            println!("{}", serde_json::to_string_pretty(&config)?);
+        }
+        Commands::Config { command: ConfigCommand::Validate { profile } } => {
+            validate_config(profile.as_deref())?;
+        }
+        Commands::Config { command: ConfigCommand::Use { name } } => {
+            let request = match name {
+                Some(name) => Request::new("set-config-profile").arg("name", name),
+                None => Request::new("clear-config-profile"),
+            };
+            tokio::runtime::Runtime::new()?.block_on(send_command(request))?;
+        }
+        Commands::Profile { command } => {
+            tokio::runtime::Runtime::new()?.block_on(profile(command))?;
+        }
+        Commands::Last { diff, raw } => {
+            last(diff, raw)?;
+        }
+        Commands::Repeat => {
+            tokio::runtime::Runtime::new()?.block_on(send_command(Request::new("repeat")))?;
+        }
+        Commands::Recleanup { prompt } => {
+            let mut request = Request::new("recleanup");
+            if let Some(path) = prompt {
+                // The daemon has its own working directory, so send an absolute path
+                request = request.arg("prompt", std::fs::canonicalize(path)?.display().to_string());
+            }
+            tokio::runtime::Runtime::new()?.block_on(send_command(request))?;
+        }
+        Commands::Undo => {
+            tokio::runtime::Runtime::new()?.block_on(send_command(Request::new("undo")))?;
+        }
+        Commands::Receive { listen } => {
+            tokio::runtime::Runtime::new()?.block_on(receive(listen))?;
+        }
+        Commands::InstallService { no_socket } => {
+            let exe = std::env::current_exe()?;
+            for path in daemon::service::install(&exe, !no_socket)? {
+                println!("Wrote {}", path.display());
+            }
+            println!("Enabled and started {} ({})", daemon::instance::file_stem(), exe.display());
         }
         Commands::Configure => {
             tokio::runtime::Runtime::new()?.block_on(configure())?;
         }
+        Commands::Devices => {
+            list_devices()?;
+        }
+        Commands::History { command } => {
+            history(command)?;
+        }
     }
 
     Ok(())
 }
 
-fn serve() -> anyhow::Result<()> {
+/// `inherited` is the control socket and socket count systemd passed, if it started us.
+fn serve(replace: bool, mock: bool, inherited: Option<(std::os::unix::net::UnixListener, i32)>) -> anyhow::Result<()> {
     tracing::info!("Starting croaker daemon");
+    let inherited = inherited.map(|(listener, fds)| {
+        if fds > 1 {
+            tracing::warn!("systemd passed {} sockets, using the first", fds);
+        }
+        listener
+    });
+    // Held until the process exits
+    let _instance = daemon::instance::Instance::acquire(replace)?;
 
     // Load config
-    let config = Config::load()?;
+    let mut config = Config::load()?;
+    if let Some(profile) = config::config_profile() {
+        tracing::info!("Config profile: {}", profile);
+    }
+    if mock {
+        tracing::info!("Mock mode: transcribing to {:?} without the API", config.mock.text);
+        config.transcription.provider = "mock".to_string();
+    }
     tracing::info!("Config loaded, push_to_talk_enabled: {}", config.hotkeys.push_to_talk_enabled);
+    let unsupported = config.overlay.unsupported_settings();
+    if !unsupported.is_empty() {
+        tracing::warn!(
+            "overlay.{} set, but the {} backend is placed by the desktop; these only apply to a floating overlay, which croaker doesn't have yet",
+            unsupported.join("/overlay."),
+            config.overlay.backend
+        );
+    }
 
-    let backend = config.overlay.backend.clone();
     let overlay_enabled = config.overlay.enabled;
     
     // Create message channel for overlay/tray
     let (overlay_tx, overlay_rx) = std::sync::mpsc::channel::<crate::overlay::OverlayMessage>();
     
-    // Spawn the daemon logic in a background thread with its own tokio runtime
+    // Spawn the daemon logic in a background thread with its own tokio runtime. Once it's done
+    // the overlay channel closes, which ends the tray/overlay loop below
     let config_clone = config.clone();
-    let overlay_tx_clone = overlay_tx.clone();
-    std::thread::spawn(move || {
+    let daemon = std::thread::spawn(move || -> anyhow::Result<()> {
         let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
-        rt.block_on(async move {
-            if let Err(e) = run_daemon(config_clone, overlay_tx_clone).await {
-                tracing::error!("Daemon error: {}", e);
-            }
-        });
+        let result = rt.block_on(run_daemon(config_clone, overlay_tx, inherited));
+        // NOTE: blocking reads (evdev, recorder output) don't notice the runtime going away
+        rt.shutdown_timeout(Duration::from_secs(1));
+        result
     });
+
+    // Probed once the daemon is running, so waiting for a tray host doesn't hold up hotkeys
+    let backend = if overlay_enabled { overlay::resolve_backend(&config.overlay.backend) } else { String::new() };
     
     // Run tray/overlay on main thread
-    if overlay_enabled && (backend == "tray" || backend == "auto") {
+    if overlay_enabled && backend == "tray" {
         tracing::info!("Starting system tray");
-        match overlay::run_tray(overlay_rx) {
+        let theme = overlay::Theme::new(&config.overlay.colors);
+        match overlay::run_tray(overlay_rx, config.profiles.keys().cloned().collect(), theme) {
             Ok(_) => {
                 tracing::info!("Tray exited normally");
             }
             Err(e) => {
                 tracing::error!("Tray error: {}", e);
                 tracing::warn!("Continuing without tray - daemon will still work, just without visual feedback");
-                // Continue running without tray - daemon functionality still works
-                // Block main thread forever to keep process alive
-                loop {
-                    std::thread::sleep(std::time::Duration::from_secs(3600));
-                }
             }
         }
-    } else if overlay_enabled && backend == "notification" {
-        // For notification backend, process messages in a loop
-        match create_overlay(&backend) {
+    } else if overlay_enabled {
+        // For the notification and OSD backends, process messages in a loop
+        match create_overlay(&backend, &config.overlay) {
             Ok(overlay) => {
                 tracing::info!("Overlay initialized with backend: {}", backend);
                 while let Ok(msg) = overlay_rx.recv() {
                     match msg {
                         crate::overlay::OverlayMessage::State(state) => {
+                            let idle = state == DaemonState::Idle;
                             overlay.update_state(state);
-                            match state {
-                                DaemonState::Idle => overlay.hide(),
-                                _ => overlay.show(),
+                            if idle {
+                                overlay.hide();
+                            } else {
+                                overlay.show();
                             }
                         }
                         crate::overlay::OverlayMessage::OutputMode(mode) => {
@@ -148,43 +494,102 @@ fn serve() -> anyhow::Result<()> {
                         crate::overlay::OverlayMessage::Language(lang) => {
                             overlay.update_language(&lang);
                         }
-                        crate::overlay::OverlayMessage::AudioLevel(level) => {
-                            overlay.update_audio_level(level);
+                        crate::overlay::OverlayMessage::Translate(enabled) => {
+                            overlay.update_translate(enabled);
+                        }
+                        crate::overlay::OverlayMessage::Profile(profile) => {
+                            overlay.update_profile(profile.as_deref());
+                        }
+                        crate::overlay::OverlayMessage::Paused(paused) => {
+                            overlay.update_paused(paused);
+                        }
+                        crate::overlay::OverlayMessage::Notice(message) => {
+                            overlay.show_notice(&message);
+                        }
+                        crate::overlay::OverlayMessage::Warning(message) => {
+                            overlay.show_warning(&message);
+                        }
+                        crate::overlay::OverlayMessage::Countdown(seconds) => {
+                            overlay.show_countdown(seconds);
                         }
-                        crate::overlay::OverlayMessage::Show => {
-                            overlay.show();
+                        crate::overlay::OverlayMessage::Locked(locked) => {
+                            overlay.update_locked(locked);
                         }
-                        crate::overlay::OverlayMessage::Hide => {
-                            overlay.hide();
+                        crate::overlay::OverlayMessage::Transcribed(text) => {
+                            overlay.show_transcription(&text);
+                        }
+                        crate::overlay::OverlayMessage::Progress(step) => {
+                            overlay.update_progress(step);
+                        }
+                        crate::overlay::OverlayMessage::OutputLength(chars) => {
+                            overlay.update_output_length(chars);
+                        }
+                        crate::overlay::OverlayMessage::AudioLevel(level) => {
+                            overlay.update_audio_level(level);
                         }
                     }
                 }
             }
             Err(e) => {
                 tracing::warn!("Failed to initialize overlay: {} (overlay disabled)", e);
-                // Block main thread forever
-                loop {
-                    std::thread::sleep(std::time::Duration::from_secs(3600));
-                }
             }
         }
-    } else {
-        // No overlay/tray, just block main thread
-        loop {
-            std::thread::sleep(std::time::Duration::from_secs(3600));
-        }
     }
 
+    // Until `croaker quit`, the tray's Quit or SIGTERM/SIGINT stops the daemon. A failed one
+    // exits non-zero, so systemd restarts it
+    match daemon.join() {
+        Ok(result) => result?,
+        Err(_) => anyhow::bail!("Daemon thread panicked"),
+    }
+    tracing::info!("Daemon stopped");
     Ok(())
 }
 
-async fn run_daemon(config: Config, overlay_tx: std::sync::mpsc::Sender<crate::overlay::OverlayMessage>) -> anyhow::Result<()> {
+async fn run_daemon(
+    config: Config,
+    overlay_tx: std::sync::mpsc::Sender<crate::overlay::OverlayMessage>,
+    inherited: Option<std::os::unix::net::UnixListener>,
+) -> anyhow::Result<()> {
     // Create state machine
     let mut state_machine = StateMachine::new(config.clone())?;
     let event_tx = state_machine.event_sender();
 
+    // Leftovers of a crashed daemon; the instance lock says there's no live one
+    let recovered = daemon::orphans::recover(&config).await;
+    if recovered > 0 {
+        let message = format!("Transcribing {} recording(s) left by a crash", recovered);
+        let _ = overlay_tx.send(crate::overlay::OverlayMessage::Notice(message));
+    }
+
+    // Hotkeys go through a gate for `croaker pause`, the socket doesn't
+    let hotkey_tx = input::hotkey_sender(event_tx.clone(), state_machine.paused_flag());
+
     // Create socket server and state update channel
     let (mut socket_server, state_tx) = SocketServer::new(event_tx.clone());
+    socket_server.set_recorder(state_machine.recorder_name());
+    if let Some(listener) = inherited {
+        socket_server.set_inherited_listener(listener);
+    }
+    socket_server.set_translate_flag(state_machine.translate_flag());
+    socket_server.set_output_mode(state_machine.output_mode_handle());
+    socket_server.set_language(state_machine.language_handle());
+    socket_server.set_activity(state_machine.activity_handle());
+    socket_server.set_profile(state_machine.profile_handle());
+    socket_server.set_paused_flag(state_machine.paused_flag());
+    socket_server.set_audio_level(state_machine.audio_level_handle());
+    socket_server.set_stats(state_machine.stats_handle());
+    socket_server.set_log_file((!config.general.log_file.is_empty()).then(|| config.general.log_file.clone().into()));
+    if !config.stats.prometheus_listen.is_empty() {
+        let stats = state_machine.stats_handle();
+        let address = config.stats.prometheus_listen.clone();
+        let started = std::time::Instant::now();
+        tokio::spawn(async move {
+            if let Err(e) = daemon::stats::serve_prometheus(&address, stats, started).await {
+                tracing::error!("Prometheus endpoint on {} failed: {}", address, e);
+            }
+        });
+    }
     
     // Connect state updates
     state_machine.set_state_sender(state_tx);
@@ -195,9 +600,34 @@ async fn run_daemon(config: Config, overlay_tx: std::sync::mpsc::Sender<crate::o
         crate::config::OutputMode::Direct => "Direct",
         crate::config::OutputMode::Clipboard => "Clipboard",
         crate::config::OutputMode::Both => "Both",
+        crate::config::OutputMode::WtypeType => "Wtype",
+        crate::config::OutputMode::File => "File",
+        crate::config::OutputMode::Dbus => "D-Bus",
+        crate::config::OutputMode::Command => "Command",
+        crate::config::OutputMode::Remote => "Remote",
     };
     let _ = overlay_tx.send(crate::overlay::OverlayMessage::OutputMode(initial_mode.to_string()));
     let _ = overlay_tx.send(crate::overlay::OverlayMessage::Language(config.general.language.clone()));
+    if config.general.translate {
+        let _ = overlay_tx.send(crate::overlay::OverlayMessage::Translate(true));
+    }
+
+    // SIGTERM (systemd, `croaker serve --replace`) and Ctrl+C stop the daemon like `croaker quit`
+    let event_tx_signal = event_tx.clone();
+    tokio::spawn(async move {
+        let mut terminate = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(terminate) => terminate,
+            Err(e) => {
+                tracing::warn!("Can't handle SIGTERM: {}", e);
+                return;
+            }
+        };
+        tokio::select! {
+            _ = terminate.recv() => tracing::info!("Got SIGTERM"),
+            _ = tokio::signal::ctrl_c() => tracing::info!("Got SIGINT"),
+        }
+        let _ = event_tx_signal.send(StateEvent::Shutdown).await;
+    });
 
     // Spawn state machine task
     let state_machine_task = tokio::spawn(async move {
@@ -215,7 +645,7 @@ async fn run_daemon(config: Config, overlay_tx: std::sync::mpsc::Sender<crate::o
 
     // Spawn evdev push-to-talk monitor (if enabled)
     if config.hotkeys.push_to_talk_enabled {
-        let event_tx_evdev = event_tx.clone();
+        let event_tx_evdev = hotkey_tx.clone();
         let config_evdev = config.clone();
         tokio::spawn(async move {
             loop {
@@ -243,9 +673,44 @@ async fn run_daemon(config: Config, overlay_tx: std::sync::mpsc::Sender<crate::o
         });
     }
 
+    // Spawn gamepad push-to-talk monitor (if a button is set)
+    #[cfg(feature = "gamepad")]
+    if !config.hotkeys.gamepad_button.is_empty() {
+        match input::gamepad::GamepadMonitor::new(&config, hotkey_tx.clone()) {
+            Ok(monitor) => {
+                tokio::spawn(async move {
+                    loop {
+                        match monitor.clone().monitor().await {
+                            Ok(()) => break,
+                            Err(e) => tracing::warn!("Gamepad monitor stopped: {}. Retrying in 5 seconds...", e),
+                        }
+                        sleep(Duration::from_secs(5)).await;
+                    }
+                });
+            }
+            Err(e) => tracing::error!("Gamepad push-to-talk disabled: {}", e),
+        }
+    }
+    #[cfg(not(feature = "gamepad"))]
+    if !config.hotkeys.gamepad_button.is_empty() {
+        tracing::warn!("hotkeys.gamepad_button is set, but this build has no gamepad support (rebuild with `--features gamepad`)");
+    }
+
+    // Bind the toggle/cancel shortcuts in Hyprland or Sway directly, if asked to
+    let mut compositor_bound = false;
+    if config.hotkeys.toggle_enabled && config.hotkeys.compositor_bindings {
+        match input::compositor::bind_shortcuts(&config, &std::env::current_exe()?).await {
+            Ok(compositor) => {
+                tracing::info!("Shortcuts bound through {:?} IPC", compositor);
+                compositor_bound = true;
+            }
+            Err(e) => tracing::warn!("Failed to bind shortcuts in the compositor: {}. Using the portal", e),
+        }
+    }
+
     // Spawn portal shortcuts monitor (if enabled)
-    if config.hotkeys.toggle_enabled {
-        let event_tx_portal = event_tx.clone();
+    if config.hotkeys.toggle_enabled && !compositor_bound {
+        let event_tx_portal = hotkey_tx.clone();
         let config_portal = config.clone();
         tokio::spawn(async move {
             loop {
@@ -284,7 +749,7 @@ async fn run_daemon(config: Config, overlay_tx: std::sync::mpsc::Sender<crate::o
     // Wait for core tasks (state machine and socket server)
     tokio::select! {
         _ = state_machine_task => {
-            tracing::error!("State machine task ended");
+            tracing::info!("State machine stopped");
         }
         _ = socket_task => {
             tracing::error!("Socket server task ended");
@@ -294,7 +759,8 @@ async fn run_daemon(config: Config, overlay_tx: std::sync::mpsc::Sender<crate::o
     Ok(())
 }
 
-async fn send_command(cmd: &str) -> anyhow::Result<String> {
+/// Send one JSON request to the daemon and return its result, or its error as `Err`.
+async fn send_command(request: Request) -> anyhow::Result<String> {
     let socket_path = SocketServer::socket_path()?;
 
     if !socket_path.exists() {
@@ -302,14 +768,250 @@ async fn send_command(cmd: &str) -> anyhow::Result<String> {
     }
 
     let mut stream = UnixStream::connect(&socket_path).await?;
-    stream.write_all(cmd.as_bytes()).await?;
-    stream.write_all(b"\n").await?;
+    let mut line = serde_json::to_string(&request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes()).await?;
 
     let mut response = String::new();
     let mut reader = tokio::io::BufReader::new(stream);
     reader.read_line(&mut response).await?;
+    let response: Response = serde_json::from_str(&response)
+        .map_err(|e| anyhow::anyhow!("Unexpected reply from the daemon ({}), is it an older version?", e))?;
+
+    match (response.ok, response.result) {
+        (true, Some(serde_json::Value::String(result))) => Ok(result),
+        (true, Some(result)) => Ok(result.to_string()),
+        (true, None) => Ok(String::new()),
+        (false, _) => Err(anyhow::anyhow!(response.error.unwrap_or_else(|| "request failed".to_string()))),
+    }
+}
+
+async fn profile(command: ProfileCommand) -> anyhow::Result<()> {
+    match command {
+        ProfileCommand::Set { name } => {
+            send_command(Request::new("set-profile").arg("name", name)).await?;
+        }
+        ProfileCommand::Clear => {
+            send_command(Request::new("clear-profile")).await?;
+        }
+        ProfileCommand::List => {
+            let config: Config = serde_json::from_str(&send_command(Request::new("get-config")).await?)?;
+            let status: Status = serde_json::from_str(&send_command(Request::new("status")).await?)?;
+            if config.profiles.is_empty() {
+                println!("No profiles configured, add them as [profiles.<name>] in the config");
+            }
+            for name in config.profiles.keys() {
+                let marker = if status.profile.as_ref() == Some(name) { "*" } else { " " };
+                println!("{} {}", marker, name);
+            }
+        }
+    }
+    Ok(())
+}
 
-    Ok(response.trim().to_string())
+/// Subscribe to status changes and print each one.
+async fn watch(json: bool) -> anyhow::Result<()> {
+    let mut lines = subscribe().await?;
+    while let Some(line) = lines.next_line().await? {
+        let event: serde_json::Value = serde_json::from_str(&line)?;
+        // Level events come several times a second, more than a status bar wants
+        if event["event"] == "level" {
+            continue;
+        }
+        if json {
+            println!("{}", line);
+        } else {
+            println!("{}", serde_json::from_value::<Event>(event)?.status);
+        }
+    }
+    Ok(())
+}
+
+/// Send `subscribe` and return the stream of status events that follows its answer.
+async fn subscribe() -> anyhow::Result<tokio::io::Lines<tokio::io::BufReader<UnixStream>>> {
+    let socket_path = SocketServer::socket_path()?;
+    if !socket_path.exists() {
+        anyhow::bail!("Daemon is not running (socket not found)");
+    }
+
+    let mut stream = UnixStream::connect(&socket_path).await?;
+    let mut request = serde_json::to_string(&Request::new("subscribe"))?;
+    request.push('\n');
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut lines = tokio::io::BufReader::new(stream).lines();
+    let first = lines.next_line().await?.unwrap_or_default();
+    let response: Response = serde_json::from_str(&first)
+        .map_err(|e| anyhow::anyhow!("Unexpected reply from the daemon ({}), is it an older version?", e))?;
+    if !response.ok {
+        anyhow::bail!(response.error.unwrap_or_else(|| "subscribe failed".to_string()));
+    }
+    Ok(lines)
+}
+
+/// Print the end of the log, then every line the daemon logs.
+async fn follow_logs(lines: usize) -> anyhow::Result<()> {
+    let socket_path = SocketServer::socket_path()?;
+    if !socket_path.exists() {
+        anyhow::bail!("Daemon is not running (socket not found)");
+    }
+
+    let mut stream = UnixStream::connect(&socket_path).await?;
+    let mut request = serde_json::to_string(&Request::new("logs").arg("lines", lines).arg("follow", true))?;
+    request.push('\n');
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut reply = tokio::io::BufReader::new(stream).lines();
+    let first = reply.next_line().await?.unwrap_or_default();
+    let response: Response = serde_json::from_str(&first)
+        .map_err(|e| anyhow::anyhow!("Unexpected reply from the daemon ({}), is it an older version?", e))?;
+    if !response.ok {
+        anyhow::bail!(response.error.unwrap_or_else(|| "logs failed".to_string()));
+    }
+
+    while let Some(line) = reply.next_line().await? {
+        let event: LogEvent = serde_json::from_str(&line)?;
+        println!("{}", event.line);
+    }
+    Ok(())
+}
+
+/// Relay texts from `croaker receive` to the local daemon, which outputs them like its own.
+async fn receive(listen: Option<String>) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let listen = listen.unwrap_or(config.remote.listen);
+    output::remote::receive(&listen, &config.remote.token, |text| async move {
+        send_command(Request::new("output").arg("text", text))
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    })
+    .await?;
+    Ok(())
+}
+
+fn list_devices() -> anyhow::Result<()> {
+    let devices = audio::devices::list_capture_devices()?;
+    if devices.is_empty() {
+        println!("No PipeWire capture devices found");
+        return Ok(());
+    }
+
+    println!("{:<2} {:<6} {:<50} DESCRIPTION", "", "ID", "NAME");
+    for device in devices {
+        println!(
+            "{:<2} {:<6} {:<50} {}",
+            if device.is_default { "*" } else { "" },
+            device.id,
+            device.name,
+            device.description
+        );
+    }
+    println!();
+    println!("* = current default source. Use the NAME as `device` under [audio] in config.toml");
+
+    Ok(())
+}
+
+fn last(diff: bool, raw: bool) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    if !config.history.enabled {
+        anyhow::bail!("History is disabled (history.enabled = false), so there is no last transcription to show");
+    }
+    let entry = history::History::new(&config.history.db_path)
+        .list(1)?
+        .pop()
+        .ok_or_else(|| anyhow::anyhow!("No transcriptions yet"))?;
+
+    if diff {
+        println!("{}", history::diff::word_diff(&entry.raw_text, &entry.cleaned_text));
+    } else if raw {
+        println!("{}", entry.raw_text);
+    } else {
+        println!("{}", entry.cleaned_text);
+    }
+    Ok(())
+}
+
+fn history(command: HistoryCommand) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let history = history::History::new(&config.history.db_path);
+
+    let entries = match command {
+        HistoryCommand::List { limit } => history.list(limit)?,
+        HistoryCommand::Search { query, limit } => history.search(&query, limit)?,
+        HistoryCommand::Copy { id, raw } => {
+            let entry = history.get(id)?
+                .ok_or_else(|| anyhow::anyhow!("No transcription with id {}", id))?;
+            let text = if raw { entry.raw_text } else { entry.cleaned_text };
+            let status = std::process::Command::new("wl-copy").arg(&text).status()?;
+            if !status.success() {
+                anyhow::bail!("wl-copy failed");
+            }
+            println!("Copied {} chars to the clipboard", text.len());
+            return Ok(());
+        }
+    };
+
+    if entries.is_empty() {
+        println!("No transcriptions found");
+        return Ok(());
+    }
+
+    for entry in entries {
+        let duration = entry.duration_ms
+            .map(|ms| format!("{:.1}s", ms as f64 / 1000.0))
+            .unwrap_or_else(|| "-".to_string());
+        // Seconds precision is plenty for a listing
+        let created_at = entry.created_at.get(..19).unwrap_or(&entry.created_at).replace('T', " ");
+        println!(
+            "{:>5}  {}  {:>6}  {:<3} {}",
+            entry.id, created_at, duration, entry.language, entry.model
+        );
+        println!("       {}", entry.cleaned_text);
+    }
+
+    Ok(())
+}
+
+fn validate_config(profile: Option<&str>) -> anyhow::Result<()> {
+    let report = config::validate::validate(profile);
+    for error in &report.errors {
+        println!("✗ {}", error);
+    }
+    for warning in &report.warnings {
+        println!("⚠ {}", warning);
+    }
+    if !report.errors.is_empty() {
+        anyhow::bail!("{} problem(s) in {}", report.errors.len(), Config::config_path()?.display());
+    }
+    if report.warnings.is_empty() {
+        println!("✓ {} is valid", Config::config_path()?.display());
+    }
+    Ok(())
+}
+
+/// Read a line from the terminal without echoing it.
+fn read_secret(prompt: &str) -> anyhow::Result<String> {
+    use std::io::Write;
+    print!("{}", prompt);
+    std::io::stdout().flush()?;
+    // SAFETY: termios is plain data, filled in by tcgetattr before it's used
+    let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+    let is_terminal = unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut saved) } == 0;
+    if is_terminal {
+        let mut quiet = saved;
+        quiet.c_lflag &= !libc::ECHO;
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &quiet) };
+    }
+    let mut line = String::new();
+    let result = std::io::stdin().read_line(&mut line);
+    if is_terminal {
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &saved) };
+        println!();
+    }
+    result?;
+    Ok(line.trim().to_string())
 }
 
 async fn configure() -> anyhow::Result<()> {
@@ -321,10 +1023,22 @@ async fn configure() -> anyhow::Result<()> {
     let config = Config::load()?;
     match config.load_api_key() {
         Ok(_) => println!("✓ API key found"),
+        Err(e) if config.groq.key_source == KeySource::Keyring => {
+            println!("✗ API key not found: {}", e);
+            let key = read_secret("Paste your Groq API key to store it in the keyring (empty to skip): ")?;
+            if !key.is_empty() {
+                let account = config.groq.keyring_account.clone();
+                match tokio::task::spawn_blocking(move || keyring::store(&account, &key)).await? {
+                    Ok(()) => println!("✓ API key stored in the keyring"),
+                    Err(e) => println!("✗ Can't store the API key: {}", e),
+                }
+            }
+        }
         Err(_) => {
             println!("✗ API key not found");
             println!("Please create ~/.config/croaker/groq.key and add your Groq API key");
             println!("Make sure to set permissions: chmod 600 ~/.config/croaker/groq.key");
+            println!("Or keep it in the keyring: set groq.key_source = \"keyring\" and run croaker configure again");
         }
     }
 
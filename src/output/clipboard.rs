@@ -1,184 +1,202 @@
+// Clipboard access through the wlr/ext data-control protocols (wl-clipboard-rs), so no
+// wl-copy/wl-paste processes are needed. GNOME doesn't offer data-control; there we still
+// shell out to wl-copy.
+
+use crate::output::keys::PasteKeys;
 use crate::output::uinput::UinputKeyboard;
+use std::collections::HashSet;
+use std::io::Read;
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 use tokio::process::Command as TokioCommand;
+use wl_clipboard_rs::{copy, paste};
+
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
 
 #[derive(Debug, Error)]
 pub enum ClipboardError {
-    #[error("Failed to execute wl-copy: {0}")]
-    CopyError(String),
-    #[error("Failed to execute wl-paste: {0}")]
-    PasteError(String),
+    #[error("Failed to copy to clipboard: {0}")]
+    CopyError(#[from] copy::Error),
+    #[error("Failed to read clipboard: {0}")]
+    PasteError(#[from] paste::Error),
+    #[error("Failed to read clipboard contents: {0}")]
+    ReadError(#[from] std::io::Error),
+    #[error("wl-copy failed: {0}")]
+    WlCopyError(String),
     #[error("Uinput error: {0}")]
     UinputError(#[from] crate::output::uinput::UinputError),
 }
 
 pub struct ClipboardOutput {
     keyboard: Arc<UinputKeyboard>,
-    restore_enabled: bool,
-    saved_content: Option<String>,
+    /// Delay between pasting and putting the previous contents back; `None` disables restoring
+    restore_delay: Option<Duration>,
+    /// Clipboard contents before the last paste, one entry per MIME type.
+    /// `Some(vec![])` means the clipboard was empty.
+    saved: Option<Vec<(String, Vec<u8>)>>,
+    /// Text we put in the clipboard last, so a restore doesn't clobber a newer copy
+    copied: Option<String>,
 }
 
 impl ClipboardOutput {
-    pub fn new(keyboard: Arc<UinputKeyboard>, restore_enabled: bool) -> Self {
+    pub fn new(keyboard: Arc<UinputKeyboard>, restore_enabled: bool, restore_delay_ms: u64) -> Self {
         Self {
             keyboard,
-            restore_enabled,
-            saved_content: None,
+            restore_delay: restore_enabled.then(|| Duration::from_millis(restore_delay_ms)),
+            saved: None,
+            copied: None,
+        }
+    }
+
+    /// MIME types currently offered by the clipboard; empty when nothing is copied.
+    pub async fn mime_types() -> Result<HashSet<String>, ClipboardError> {
+        let result = tokio::task::spawn_blocking(|| {
+            paste::get_mime_types(paste::ClipboardType::Regular, paste::Seat::Unspecified)
+        })
+        .await
+        .map_err(|e| ClipboardError::ReadError(std::io::Error::other(e)))?;
+        match result {
+            Ok(types) => Ok(types),
+            Err(paste::Error::NoSeats | paste::Error::ClipboardEmpty) => Ok(HashSet::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Read the clipboard in `mime_type`. Bounded by a timeout, since the owning app has to
+    /// answer and a hung one would otherwise stall output.
+    async fn read(mime_type: String) -> Result<Vec<u8>, ClipboardError> {
+        let task = tokio::task::spawn_blocking(move || {
+            let (mut pipe, _) = paste::get_contents(
+                paste::ClipboardType::Regular,
+                paste::Seat::Unspecified,
+                paste::MimeType::Specific(&mime_type),
+            )?;
+            let mut contents = Vec::new();
+            pipe.read_to_end(&mut contents)?;
+            Ok::<_, ClipboardError>(contents)
+        });
+        match tokio::time::timeout(READ_TIMEOUT, task).await {
+            Ok(result) => result.map_err(|e| ClipboardError::ReadError(std::io::Error::other(e)))?,
+            Err(_) => Err(ClipboardError::ReadError(std::io::ErrorKind::TimedOut.into())),
         }
     }
 
+    /// Remember the clipboard contents in every MIME type it offers (images, rich text, file
+    /// lists, ...), to be put back after the next paste. Does nothing unless restoring is on.
     pub async fn save_current(&mut self) -> Result<(), ClipboardError> {
-        if !self.restore_enabled {
+        self.saved = None;
+        if self.restore_delay.is_none() {
             return Ok(());
         }
 
-        let output = TokioCommand::new("wl-paste")
-            .output()
-            .await
-            .map_err(|e| ClipboardError::PasteError(e.to_string()))?;
+        // X11 targets such as TARGETS or TIMESTAMP that XWayland offers aren't data
+        let types: Vec<String> = Self::mime_types().await?.into_iter().filter(|t| t.contains('/')).collect();
+        tracing::debug!("Saving clipboard offered as {:?}", types);
+        let mut saved = Vec::new();
+        for mime_type in types {
+            match Self::read(mime_type.clone()).await {
+                Ok(contents) => saved.push((mime_type, contents)),
+                Err(e) => tracing::debug!("Couldn't save clipboard as {}: {}", mime_type, e),
+            }
+        }
+        self.saved = Some(saved);
+        Ok(())
+    }
+
+    /// Put the saved contents back, unless something else has been copied since our text.
+    pub async fn restore(&mut self) -> Result<(), ClipboardError> {
+        let (Some(delay), Some(saved)) = (self.restore_delay, self.saved.take()) else {
+            return Ok(());
+        };
+        // The target app reads the clipboard asynchronously after the paste keys
+        tokio::time::sleep(delay).await;
 
-        if output.status.success() {
-            self.saved_content = Some(
-                String::from_utf8_lossy(&output.stdout)
-                    .trim()
-                    .to_string(),
-            );
-            tracing::debug!("Saved clipboard content: {} chars", self.saved_content.as_ref().unwrap().len());
+        let current = Self::read("text/plain;charset=utf-8".to_string()).await.ok();
+        if current.is_some() && current.as_deref() != self.copied.as_deref().map(str::as_bytes) {
+            tracing::debug!("Clipboard changed since the paste, not restoring it");
+            return Ok(());
         }
 
+        let result = tokio::task::spawn_blocking(move || {
+            if saved.is_empty() {
+                return copy::clear(copy::ClipboardType::Regular, copy::Seat::All);
+            }
+            let sources = saved
+                .into_iter()
+                .map(|(mime_type, contents)| copy::MimeSource {
+                    source: copy::Source::Bytes(contents.into()),
+                    mime_type: copy::MimeType::Specific(mime_type),
+                })
+                .collect();
+            let mut options = copy::Options::new();
+            // Offer exactly the saved types, nothing synthesized
+            options.omit_additional_text_mime_types(true);
+            options.copy_multi(sources)
+        })
+        .await
+        .map_err(|e| ClipboardError::ReadError(std::io::Error::other(e)))?;
+        result?;
+        self.copied = None;
+        tracing::debug!("Clipboard restored");
         Ok(())
     }
 
-    pub async fn copy_and_paste(&mut self, text: &str) -> Result<(), ClipboardError> {
-        tracing::info!("Copying {} chars to clipboard and pasting", text.len());
-        
-        // Save current clipboard
-        self.save_current().await?;
+    pub async fn copy_to_clipboard(&mut self, text: &str) -> Result<(), ClipboardError> {
+        tracing::info!("Copying {} chars to clipboard", text.len());
 
-        // Copy text to clipboard
-        let mut child = TokioCommand::new("wl-copy")
-            .arg(text)
-            .spawn()
-            .map_err(|e| ClipboardError::CopyError(e.to_string()))?;
+        // The copy is served from a background thread until something else takes the clipboard
+        let bytes: Box<[u8]> = text.as_bytes().into();
+        let result = tokio::task::spawn_blocking(move || {
+            copy::Options::new().copy(copy::Source::Bytes(bytes), copy::MimeType::Text)
+        })
+        .await
+        .map_err(|e| ClipboardError::ReadError(std::io::Error::other(e)))?;
 
-        let status = child.wait().await.map_err(|e| ClipboardError::CopyError(e.to_string()))?;
-        
-        if !status.success() {
-            return Err(ClipboardError::CopyError("wl-copy failed".to_string()));
+        match result {
+            Ok(()) => {}
+            Err(copy::Error::MissingProtocol { name, .. }) => {
+                tracing::debug!("Compositor lacks {}, copying with wl-copy", name);
+                Self::wl_copy(text).await?;
+            }
+            Err(e) => return Err(e.into()),
         }
+        self.copied = Some(text.to_string());
 
-        tracing::debug!("Text copied to clipboard, waiting before paste");
-        
-        // Wait a bit for clipboard to be ready
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        tracing::debug!("Text copied to clipboard");
+        Ok(())
+    }
 
-        // On Wayland, use wtype instead of uinput (uinput doesn't work reliably on Wayland)
-        let is_wayland = std::env::var("XDG_SESSION_TYPE")
-            .map(|s| s == "wayland")
-            .unwrap_or(false);
-        
-        if is_wayland {
-            // Try wtype first (Wayland-native)
-            tracing::debug!("Sending Ctrl+V via wtype (Wayland)");
-            let wtype_result = TokioCommand::new("wtype")
-                .args(&["-M", "ctrl", "-k", "v"])
-                .output()
-                .await;
-            
-            match wtype_result {
-                Ok(output) if output.status.success() => {
-                    tracing::info!("Paste command sent via wtype");
-                }
-                Ok(output) => {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
-                    tracing::warn!("wtype failed: {}", stderr);
-                    if stderr.contains("virtual keyboard protocol") || stderr.contains("not support") {
-                        tracing::warn!("Compositor doesn't support virtual keyboard protocol (KDE/GNOME limitation). Trying uinput Ctrl+V as fallback...");
-                        // Try uinput anyway - sometimes Ctrl+V works even if typing doesn't
-                        if let Err(e) = self.keyboard.send_paste().await {
-                            tracing::warn!("uinput Ctrl+V also failed: {}. Text is in clipboard - paste manually with Ctrl+V", e);
-                            // Send a notification to remind user to paste
-                            let _ = TokioCommand::new("notify-send")
-                                .args(&["--app-name=croaker", "--urgency=normal", "--expire-time=3000", "croaker", "Text ready! Press Ctrl+V to paste."])
-                                .output()
-                                .await;
-                            // Don't error - text is in clipboard, user can paste manually
-                            return Ok(());
-                        } else {
-                            tracing::info!("uinput Ctrl+V sent successfully");
-                        }
-                    } else {
-                        tracing::warn!("wtype failed, trying uinput fallback");
-                        if let Err(e) = self.keyboard.send_paste().await {
-                            tracing::warn!("uinput fallback also failed: {}. Text is in clipboard.", e);
-                            let _ = TokioCommand::new("notify-send")
-                                .args(&["--app-name=croaker", "--urgency=normal", "--expire-time=3000", "croaker", "Text ready! Press Ctrl+V to paste."])
-                                .output()
-                                .await;
-                            return Ok(());
-                        }
-                    }
-                }
-                Err(_) => {
-                    tracing::warn!("wtype not found, trying uinput fallback");
-                    if let Err(e) = self.keyboard.send_paste().await {
-                        tracing::warn!("uinput fallback also failed: {}. Text is in clipboard.", e);
-                        let _ = TokioCommand::new("notify-send")
-                            .args(&["--app-name=croaker", "--urgency=normal", "--expire-time=3000", "croaker", "Text ready! Press Ctrl+V to paste."])
-                            .output()
-                            .await;
-                        return Ok(());
-                    }
-                }
+    /// Paste the clipboard into the focused app, then restore what was in the clipboard
+    /// before `save_current` if restoring is on.
+    pub async fn paste(&mut self, keys: &PasteKeys) -> Result<(), ClipboardError> {
+        if self.press_paste(keys).await? {
+            if let Err(e) = self.restore().await {
+                tracing::warn!("Failed to restore clipboard: {}", e);
             }
         } else {
-            // Use uinput on X11
-            tracing::debug!("Sending Ctrl+V via uinput (X11)");
-            if let Err(e) = self.keyboard.send_paste().await {
-                tracing::warn!("uinput failed: {}. Text is in clipboard.", e);
-                let _ = TokioCommand::new("notify-send")
-                    .args(&["--app-name=croaker", "--urgency=normal", "--expire-time=3000", "croaker", "Text ready! Press Ctrl+V to paste."])
-                    .output()
-                    .await;
-                return Ok(());
-            }
+            // The user still has to paste our text by hand
+            self.saved = None;
         }
-        
-        // Give the paste time to complete
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-        
-        tracing::info!("Paste command sent");
-
         Ok(())
     }
 
-    pub async fn copy_to_clipboard(&mut self, text: &str) -> Result<(), ClipboardError> {
-        tracing::info!("Copying {} chars to clipboard", text.len());
-        
-        // Save current clipboard if restore is enabled
-        if self.restore_enabled {
-            self.save_current().await?;
-        }
-
-        // Copy text to clipboard
-        let mut child = TokioCommand::new("wl-copy")
+    /// Fallback for compositors without a data-control protocol (GNOME).
+    async fn wl_copy(text: &str) -> Result<(), ClipboardError> {
+        let status = TokioCommand::new("wl-copy")
             .arg(text)
-            .spawn()
-            .map_err(|e| ClipboardError::CopyError(e.to_string()))?;
-
-        let status = child.wait().await.map_err(|e| ClipboardError::CopyError(e.to_string()))?;
-        
+            .status()
+            .await
+            .map_err(|e| ClipboardError::WlCopyError(e.to_string()))?;
         if !status.success() {
-            return Err(ClipboardError::CopyError("wl-copy failed".to_string()));
+            return Err(ClipboardError::WlCopyError(status.to_string()));
         }
-
-        tracing::debug!("Text copied to clipboard");
         Ok(())
     }
 
-    pub async fn paste(&mut self) -> Result<(), ClipboardError> {
-        tracing::info!("Pasting from clipboard");
+    /// Press the paste keys. `false` when no key could be sent and the user was asked to paste.
+    async fn press_paste(&self, keys: &PasteKeys) -> Result<bool, ClipboardError> {
+        tracing::info!("Pasting from clipboard with {}", keys);
         
         // Wait a bit for clipboard to be ready
         tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
@@ -190,9 +208,9 @@ impl ClipboardOutput {
         
         if is_wayland {
             // Try wtype first (Wayland-native)
-            tracing::debug!("Sending Ctrl+V via wtype (Wayland)");
+            tracing::debug!("Sending {} via wtype (Wayland)", keys);
             let wtype_result = TokioCommand::new("wtype")
-                .args(&["-M", "ctrl", "-k", "v"])
+                .args(keys.wtype_args())
                 .output()
                 .await;
             
@@ -204,52 +222,52 @@ impl ClipboardOutput {
                     let stderr = String::from_utf8_lossy(&output.stderr);
                     tracing::warn!("wtype failed: {}", stderr);
                     if stderr.contains("virtual keyboard protocol") || stderr.contains("not support") {
-                        tracing::warn!("Compositor doesn't support virtual keyboard protocol (KDE/GNOME limitation). Trying uinput Ctrl+V as fallback...");
-                        if let Err(e) = self.keyboard.send_paste().await {
-                            tracing::warn!("uinput Ctrl+V also failed: {}. Text is in clipboard - paste manually with Ctrl+V", e);
+                        tracing::warn!("Compositor doesn't support virtual keyboard protocol (KDE/GNOME limitation). Trying uinput {} as fallback...", keys);
+                        if let Err(e) = self.keyboard.send_combo(keys).await {
+                            tracing::warn!("uinput {} also failed: {}. Text is in clipboard - paste manually with {}", keys, e, keys);
                             // Send a notification to remind user to paste
                             let _ = TokioCommand::new("notify-send")
-                                .args(&["--app-name=croaker", "--urgency=normal", "--expire-time=3000", "croaker", "Text ready! Press Ctrl+V to paste."])
+                                .args(["--app-name=croaker", "--urgency=normal", "--expire-time=3000", "croaker", &format!("Text ready! Press {} to paste.", keys)])
                                 .output()
                                 .await;
-                            return Ok(());
+                            return Ok(false);
                         } else {
-                            tracing::info!("uinput Ctrl+V sent successfully");
+                            tracing::info!("uinput {} sent successfully", keys);
                         }
                     } else {
                         tracing::warn!("wtype failed, trying uinput fallback");
-                        if let Err(e) = self.keyboard.send_paste().await {
+                        if let Err(e) = self.keyboard.send_combo(keys).await {
                             tracing::warn!("uinput fallback also failed: {}. Text is in clipboard.", e);
                             let _ = TokioCommand::new("notify-send")
-                                .args(&["--app-name=croaker", "--urgency=normal", "--expire-time=3000", "croaker", "Text ready! Press Ctrl+V to paste."])
+                                .args(["--app-name=croaker", "--urgency=normal", "--expire-time=3000", "croaker", &format!("Text ready! Press {} to paste.", keys)])
                                 .output()
                                 .await;
-                            return Ok(());
+                            return Ok(false);
                         }
                     }
                 }
                 Err(_) => {
                     tracing::warn!("wtype not found, trying uinput fallback");
-                    if let Err(e) = self.keyboard.send_paste().await {
+                    if let Err(e) = self.keyboard.send_combo(keys).await {
                         tracing::warn!("uinput fallback also failed: {}. Text is in clipboard.", e);
                         let _ = TokioCommand::new("notify-send")
-                            .args(&["--app-name=croaker", "--urgency=normal", "--expire-time=3000", "croaker", "Text ready! Press Ctrl+V to paste."])
+                            .args(["--app-name=croaker", "--urgency=normal", "--expire-time=3000", "croaker", &format!("Text ready! Press {} to paste.", keys)])
                             .output()
                             .await;
-                        return Ok(());
+                        return Ok(false);
                     }
                 }
             }
         } else {
             // Use uinput on X11
-            tracing::debug!("Sending Ctrl+V via uinput (X11)");
-            if let Err(e) = self.keyboard.send_paste().await {
+            tracing::debug!("Sending {} via uinput (X11)", keys);
+            if let Err(e) = self.keyboard.send_combo(keys).await {
                 tracing::warn!("uinput failed: {}. Text is in clipboard.", e);
                 let _ = TokioCommand::new("notify-send")
-                    .args(&["--app-name=croaker", "--urgency=normal", "--expire-time=3000", "croaker", "Text ready! Press Ctrl+V to paste."])
+                    .args(["--app-name=croaker", "--urgency=normal", "--expire-time=3000", "croaker", &format!("Text ready! Press {} to paste.", keys)])
                     .output()
                     .await;
-                return Ok(());
+                return Ok(false);
             }
         }
         
@@ -257,7 +275,7 @@ impl ClipboardOutput {
         tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
         
         tracing::info!("Paste command sent");
-        Ok(())
+        Ok(true)
     }
 }
 
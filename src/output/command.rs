@@ -0,0 +1,65 @@
+// Handing transcriptions to a user command (`output.command`), e.g. a script that posts
+// to a todo app. The text arrives on stdin; details about it in CROAKER_* variables.
+
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command as TokioCommand;
+
+#[derive(Debug, Error)]
+pub enum CommandOutputError {
+    #[error("No output command configured (output.command)")]
+    NoCommandError,
+    #[error("Failed to run {0:?}: {1}")]
+    SpawnError(String, std::io::Error),
+    #[error("{0:?} failed ({1}): {2}")]
+    ExitError(String, std::process::ExitStatus, String),
+}
+
+/// What the command gets to know besides the text.
+pub struct CommandEnv<'a> {
+    /// Language code of the text (`en` in translation mode)
+    pub language: &'a str,
+    /// "dictation" or "translate"
+    pub mode: &'a str,
+    /// Focused app id, when known
+    pub app: Option<&'a str>,
+    /// Transcript before cleanup
+    pub raw_text: &'a str,
+}
+
+/// Run `command` through `sh -c` with `text` on stdin and wait for it.
+pub async fn run(command: &str, text: &str, env: &CommandEnv<'_>) -> Result<(), CommandOutputError> {
+    if command.is_empty() {
+        return Err(CommandOutputError::NoCommandError);
+    }
+    let mut child = TokioCommand::new("sh")
+        .args(["-c", command])
+        .env("CROAKER_LANGUAGE", env.language)
+        .env("CROAKER_MODE", env.mode)
+        .env("CROAKER_APP", env.app.unwrap_or(""))
+        .env("CROAKER_RAW_TEXT", env.raw_text)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| CommandOutputError::SpawnError(command.to_string(), e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command that doesn't read stdin closes it early, that's fine
+        let _ = stdin.write_all(text.as_bytes()).await;
+    }
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|e| CommandOutputError::SpawnError(command.to_string(), e))?;
+    if !output.status.success() {
+        return Err(CommandOutputError::ExitError(
+            command.to_string(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    tracing::info!("Passed {} chars to {:?}", text.len(), command);
+    Ok(())
+}
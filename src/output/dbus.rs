@@ -0,0 +1,55 @@
+// Every finished transcription is broadcast as a D-Bus signal on the session bus, so
+// editors, launchers and scripts can consume it. With `output_mode = "dbus"` that's all
+// croaker does with the text.
+//
+//   dbus-monitor "type='signal',interface='io.github.kumralcem.Croaker'"
+
+use thiserror::Error;
+use zbus::{Connection, SignalContext};
+
+pub const SERVICE: &str = "io.github.kumralcem.Croaker";
+pub const PATH: &str = "/io/github/kumralcem/Croaker";
+
+#[derive(Debug, Error)]
+pub enum DbusError {
+    #[error("D-Bus error: {0}")]
+    BusError(#[from] zbus::Error),
+}
+
+struct Transcriptions;
+
+#[zbus::interface(name = "io.github.kumralcem.Croaker")]
+impl Transcriptions {
+    /// Cleaned text, the transcript before cleanup, and the language code of `text`
+    #[zbus(signal)]
+    async fn transcription_ready(
+        ctxt: &SignalContext<'_>,
+        text: &str,
+        raw_text: &str,
+        language: &str,
+    ) -> zbus::Result<()>;
+}
+
+#[derive(Clone)]
+pub struct DbusOutput {
+    connection: Connection,
+}
+
+impl DbusOutput {
+    /// Claim the service name on the session bus and export the signal's object.
+    pub async fn connect() -> Result<Self, DbusError> {
+        let connection = zbus::connection::Builder::session()?
+            .name(SERVICE)?
+            .serve_at(PATH, Transcriptions)?
+            .build()
+            .await?;
+        tracing::info!("Emitting TranscriptionReady on the session bus as {}", SERVICE);
+        Ok(Self { connection })
+    }
+
+    pub async fn emit(&self, text: &str, raw_text: &str, language: &str) -> Result<(), DbusError> {
+        let ctxt = SignalContext::new(&self.connection, PATH)?;
+        Transcriptions::transcription_ready(&ctxt, text, raw_text, language).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+// Appending transcriptions to a notes file, e.g. an Obsidian daily note.
+
+use std::path::PathBuf;
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+
+#[derive(Debug, Error)]
+pub enum FileOutputError {
+    #[error("No output file configured (output.file_path)")]
+    NoPathError,
+    #[error("Failed to append to {0}: {1}")]
+    WriteError(PathBuf, std::io::Error),
+}
+
+/// Append `text` to the file at `path_pattern` (strftime placeholders allowed, e.g.
+/// `~/Notes/%Y-%m-%d.md`), formatted with `template`. Returns the file written to.
+pub async fn append(path_pattern: &str, template: &str, text: &str) -> Result<PathBuf, FileOutputError> {
+    if path_pattern.is_empty() {
+        return Err(FileOutputError::NoPathError);
+    }
+    let now = chrono::Local::now();
+    let path = PathBuf::from(now.format(path_pattern).to_string());
+    let entry = render_entry(template, text, &now);
+
+    let write = async {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        file.write_all(entry.as_bytes()).await
+    };
+    write.await.map_err(|e| FileOutputError::WriteError(path.clone(), e))?;
+
+    tracing::info!("Appended {} chars to {:?}", entry.len(), path);
+    Ok(path)
+}
+
+/// Fill in `{text}`, `{date}` (YYYY-MM-DD) and `{time}` (HH:MM); a newline is added
+/// when the template doesn't end with one.
+fn render_entry(template: &str, text: &str, now: &chrono::DateTime<chrono::Local>) -> String {
+    let mut entry = template
+        .replace("{date}", &now.format("%Y-%m-%d").to_string())
+        .replace("{time}", &now.format("%H:%M").to_string())
+        .replace("{text}", text);
+    if !entry.ends_with('\n') {
+        entry.push('\n');
+    }
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render_entry;
+    use chrono::TimeZone;
+
+    #[test]
+    fn renders_template_placeholders() {
+        let now = chrono::Local.with_ymd_and_hms(2024, 3, 5, 9, 7, 0).unwrap();
+        assert_eq!(
+            render_entry("- {date} {time} {text}", "Call the bank.", &now),
+            "- 2024-03-05 09:07 Call the bank.\n"
+        );
+    }
+}
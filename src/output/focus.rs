@@ -0,0 +1,61 @@
+// Which application has keyboard focus, asked from the compositor's IPC.
+// Only Hyprland and Sway expose this; elsewhere the answer is None.
+
+use crate::input::compositor::Compositor;
+use tokio::process::Command as TokioCommand;
+
+/// App id (Wayland) or window class (XWayland) of the focused window.
+pub async fn focused_app() -> Option<String> {
+    match Compositor::detect()? {
+        Compositor::Hyprland => {
+            let json = run_json("hyprctl", &["activewindow", "-j"]).await?;
+            json.get("class")?.as_str().filter(|c| !c.is_empty()).map(str::to_string)
+        }
+        Compositor::Sway => {
+            let tree = run_json("swaymsg", &["-t", "get_tree"]).await?;
+            sway_focused_app(&tree)
+        }
+    }
+}
+
+async fn run_json(program: &str, args: &[&str]) -> Option<serde_json::Value> {
+    let output = TokioCommand::new(program).args(args).output().await.ok()?;
+    if !output.status.success() {
+        tracing::debug!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim());
+        return None;
+    }
+    serde_json::from_slice(&output.stdout).ok()
+}
+
+/// Walk a `swaymsg -t get_tree` dump down to the focused window.
+fn sway_focused_app(node: &serde_json::Value) -> Option<String> {
+    if node.get("focused").and_then(|f| f.as_bool()) == Some(true) {
+        let app_id = node.get("app_id").and_then(|a| a.as_str());
+        let class = node.pointer("/window_properties/class").and_then(|c| c.as_str());
+        return app_id.or(class).map(str::to_string);
+    }
+    ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node.get(key)?.as_array())
+        .flatten()
+        .find_map(sway_focused_app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sway_focused_app;
+
+    #[test]
+    fn finds_focused_sway_window() {
+        let tree = serde_json::json!({
+            "focused": false,
+            "nodes": [
+                {"focused": false, "app_id": "firefox", "nodes": []},
+                {"focused": false, "nodes": [], "floating_nodes": [
+                    {"focused": true, "app_id": null, "window_properties": {"class": "Alacritty"}}
+                ]}
+            ]
+        });
+        assert_eq!(sway_focused_app(&tree).as_deref(), Some("Alacritty"));
+    }
+}
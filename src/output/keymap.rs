@@ -0,0 +1,200 @@
+// Character -> key lookup for typing through uinput. Built from the active XKB keymap, so
+// accented letters, Turkish ı/İ and symbols on non-QWERTY layouts end up on the right keys.
+//
+// libxkbcommon is loaded at runtime; without it uinput falls back to its built-in US table.
+
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::os::unix::fs::FileExt;
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::{wl_keyboard, wl_registry, wl_seat};
+use wayland_client::{delegate_noop, Connection, Dispatch, QueueHandle, WEnum};
+use xkbcommon_dl::{
+    xkb_context_flags, xkb_keymap_compile_flags, xkb_keymap_format, xkb_rule_names, XkbCommon,
+    XKB_MOD_INVALID, XKB_MOD_NAME_SHIFT,
+};
+
+/// AltGr is ISO_Level3_Shift, which every stock layout puts on Mod5
+const XKB_MOD_NAME_LEVEL3: &[u8] = b"Mod5\0";
+
+/// The key (evdev code) and modifiers that produce a character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyStroke {
+    pub code: u16,
+    pub shift: bool,
+    pub altgr: bool,
+}
+
+pub struct Keymap {
+    keys: HashMap<char, KeyStroke>,
+}
+
+impl Keymap {
+    /// The compositor's keymap when `layout` is empty and we're on Wayland, else one compiled
+    /// from `layout`/`variant` (empty: `XKB_DEFAULT_LAYOUT`, then "us").
+    /// `None` if libxkbcommon isn't installed or the keymap doesn't compile.
+    pub fn load(layout: &str, variant: &str) -> Option<Self> {
+        let xkb = xkbcommon_dl::xkbcommon_option()?;
+        let source = if layout.is_empty() { compositor_keymap() } else { None };
+        let keys = unsafe { build(xkb, source.as_deref(), layout, variant) }?;
+        tracing::info!(
+            "Loaded XKB keymap ({}) with {} typeable characters",
+            if source.is_some() { "from compositor" } else { "from layout names" },
+            keys.len()
+        );
+        Some(Self { keys })
+    }
+
+    pub fn get(&self, c: char) -> Option<KeyStroke> {
+        self.keys.get(&c).copied()
+    }
+
+    /// Evdev codes of every key that produces a character.
+    pub fn codes(&self) -> impl Iterator<Item = u16> + '_ {
+        self.keys.values().map(|stroke| stroke.code)
+    }
+}
+
+/// Walk every key under no modifiers, Shift, AltGr and Shift+AltGr and record the first
+/// (lowest) key producing each character.
+unsafe fn build(xkb: &XkbCommon, source: Option<&str>, layout: &str, variant: &str) -> Option<HashMap<char, KeyStroke>> {
+    let source = match source {
+        Some(source) => Some(CString::new(source.trim_end_matches('\0')).ok()?),
+        None => None,
+    };
+    let layout = CString::new(layout).ok()?;
+    let variant = CString::new(variant).ok()?;
+    let optional = |s: &CString| if s.as_bytes().is_empty() { std::ptr::null() } else { s.as_ptr() };
+
+    let context = (xkb.xkb_context_new)(xkb_context_flags::XKB_CONTEXT_NO_FLAGS);
+    if context.is_null() {
+        return None;
+    }
+    let keymap = match source {
+        Some(ref source) => (xkb.xkb_keymap_new_from_string)(
+            context,
+            source.as_ptr(),
+            xkb_keymap_format::XKB_KEYMAP_FORMAT_TEXT_V1,
+            xkb_keymap_compile_flags::XKB_KEYMAP_COMPILE_NO_FLAGS,
+        ),
+        None => {
+            let names = xkb_rule_names {
+                rules: std::ptr::null(),
+                model: std::ptr::null(),
+                layout: optional(&layout),
+                variant: optional(&variant),
+                options: std::ptr::null(),
+            };
+            (xkb.xkb_keymap_new_from_names)(context, &names, xkb_keymap_compile_flags::XKB_KEYMAP_COMPILE_NO_FLAGS)
+        }
+    };
+    if keymap.is_null() {
+        tracing::warn!("Failed to compile XKB keymap");
+        (xkb.xkb_context_unref)(context);
+        return None;
+    }
+
+    let state = (xkb.xkb_state_new)(keymap);
+    let shift = (xkb.xkb_keymap_mod_get_index)(keymap, XKB_MOD_NAME_SHIFT.as_ptr() as *const c_char);
+    let level3 = (xkb.xkb_keymap_mod_get_index)(keymap, XKB_MOD_NAME_LEVEL3.as_ptr() as *const c_char);
+    // uinput only exposes evdev codes 1-255 (xkb keycode = evdev code + 8)
+    let min = (xkb.xkb_keymap_min_keycode)(keymap).max(9);
+    let max = (xkb.xkb_keymap_max_keycode)(keymap).min(255 + 8);
+
+    let mut keys = HashMap::new();
+    for (with_shift, with_altgr) in [(false, false), (true, false), (false, true), (true, true)] {
+        if (with_shift && shift == XKB_MOD_INVALID) || (with_altgr && level3 == XKB_MOD_INVALID) {
+            continue;
+        }
+        let mask = (if with_shift { 1 << shift } else { 0 }) | (if with_altgr { 1 << level3 } else { 0 });
+        (xkb.xkb_state_update_mask)(state, mask, 0, 0, 0, 0, 0);
+        for keycode in min..=max {
+            let Some(c) = char::from_u32((xkb.xkb_state_key_get_utf32)(state, keycode)) else {
+                continue;
+            };
+            if c.is_control() {
+                continue;
+            }
+            keys.entry(c).or_insert(KeyStroke {
+                code: (keycode - 8) as u16,
+                shift: with_shift,
+                altgr: with_altgr,
+            });
+        }
+    }
+
+    (xkb.xkb_state_unref)(state);
+    (xkb.xkb_keymap_unref)(keymap);
+    (xkb.xkb_context_unref)(context);
+    Some(keys)
+}
+
+#[derive(Default)]
+struct KeymapState {
+    keymap: Option<String>,
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for KeymapState {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<wl_keyboard::WlKeyboard, ()> for KeymapState {
+    fn event(
+        state: &mut Self,
+        _: &wl_keyboard::WlKeyboard,
+        event: wl_keyboard::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_keyboard::Event::Keymap { format: WEnum::Value(wl_keyboard::KeymapFormat::XkbV1), fd, size } = event {
+            let mut buf = vec![0; size as usize];
+            if std::fs::File::from(fd).read_exact_at(&mut buf, 0).is_ok() {
+                state.keymap = String::from_utf8(buf).ok();
+            }
+        }
+    }
+}
+
+delegate_noop!(KeymapState: ignore wl_seat::WlSeat);
+
+/// The keymap the compositor hands to its clients, i.e. the layout the user is typing with.
+fn compositor_keymap() -> Option<String> {
+    std::env::var_os("WAYLAND_DISPLAY")?;
+    let conn = Connection::connect_to_env().ok()?;
+    let (globals, mut queue) = registry_queue_init::<KeymapState>(&conn).ok()?;
+    let qh = queue.handle();
+    let seat: wl_seat::WlSeat = globals.bind(&qh, 1..=7, ()).ok()?;
+    let _keyboard = seat.get_keyboard(&qh, ());
+
+    let mut state = KeymapState::default();
+    queue.roundtrip(&mut state).ok()?;
+    state.keymap
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Keymap;
+
+    #[test]
+    fn maps_turkish_layout() {
+        // Needs libxkbcommon and xkeyboard-config at runtime
+        let Some(keymap) = Keymap::load("tr", "") else {
+            return;
+        };
+        let a = keymap.get('a').unwrap();
+        assert_eq!((a.code, a.shift, a.altgr), (30, false, false));
+        // Turkish Q: dotless ı where US has i, dotted i/İ next to ş
+        assert_eq!(keymap.get('ı').unwrap().code, 23);
+        assert!(keymap.get('İ').unwrap().shift);
+    }
+}
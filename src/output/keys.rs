@@ -0,0 +1,105 @@
+// Key combinations used to paste, e.g. "ctrl+v" or "ctrl+shift+v" for terminals.
+
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modifier {
+    Ctrl,
+    Shift,
+    Alt,
+    Super,
+}
+
+impl Modifier {
+    /// Modifier name as `wtype -M` expects it
+    pub fn wtype_name(self) -> &'static str {
+        match self {
+            Modifier::Ctrl => "ctrl",
+            Modifier::Shift => "shift",
+            Modifier::Alt => "alt",
+            Modifier::Super => "logo",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasteKeys {
+    pub modifiers: Vec<Modifier>,
+    /// Lowercase key name: a single character, or "insert"
+    pub key: String,
+}
+
+impl Default for PasteKeys {
+    fn default() -> Self {
+        Self {
+            modifiers: vec![Modifier::Ctrl],
+            key: "v".to_string(),
+        }
+    }
+}
+
+impl FromStr for PasteKeys {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = Vec::new();
+        let mut key = None;
+        for part in s.split('+').map(|p| p.trim().to_lowercase()) {
+            let modifier = match part.as_str() {
+                "ctrl" | "control" => Some(Modifier::Ctrl),
+                "shift" => Some(Modifier::Shift),
+                "alt" => Some(Modifier::Alt),
+                "super" | "meta" | "logo" => Some(Modifier::Super),
+                _ => None,
+            };
+            match modifier {
+                Some(modifier) if key.is_none() => modifiers.push(modifier),
+                None if key.is_none() && (part.chars().count() == 1 || part == "insert") => key = Some(part),
+                _ => return Err(format!("Invalid paste key combination: {:?}", s)),
+            }
+        }
+        let key = key.ok_or_else(|| format!("Paste key combination has no key: {:?}", s))?;
+        Ok(Self { modifiers, key })
+    }
+}
+
+impl PasteKeys {
+    /// Arguments for `wtype` that press the modifiers, tap the key and release them again.
+    pub fn wtype_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for modifier in &self.modifiers {
+            args.extend(["-M".to_string(), modifier.wtype_name().to_string()]);
+        }
+        let keysym = if self.key == "insert" { "Insert".to_string() } else { self.key.clone() };
+        args.extend(["-k".to_string(), keysym]);
+        for modifier in self.modifiers.iter().rev() {
+            args.extend(["-m".to_string(), modifier.wtype_name().to_string()]);
+        }
+        args
+    }
+}
+
+impl std::fmt::Display for PasteKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for modifier in &self.modifiers {
+            write!(f, "{}+", modifier.wtype_name())?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_combinations() {
+        let keys: PasteKeys = "Ctrl+Shift+V".parse().unwrap();
+        assert_eq!(keys.modifiers, [Modifier::Ctrl, Modifier::Shift]);
+        assert_eq!(keys.key, "v");
+        assert_eq!(keys.wtype_args().join(" "), "-M ctrl -M shift -k v -m shift -m ctrl");
+        assert_eq!("shift+insert".parse::<PasteKeys>().unwrap().wtype_args()[3], "Insert");
+        assert!("ctrl+shift".parse::<PasteKeys>().is_err());
+        assert!("ctrl+v+x".parse::<PasteKeys>().is_err());
+    }
+}
@@ -0,0 +1,73 @@
+// Recording indicator on a keyboard LED (`overlay.keyboard_led`): the LED is switched on through
+// evdev while recording, on every keyboard that has it. Needs write access to /dev/input, which
+// the `input` group used for hotkeys gives.
+
+use evdev::{Device, EventType, InputEvent, LedType};
+use std::sync::Mutex;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum LedError {
+    #[error("Unknown keyboard LED {0:?} (expected \"scrolllock\", \"capslock\", \"numlock\", \"compose\" or \"kana\")")]
+    UnknownLedError(String),
+    #[error("No keyboard with a {0} LED can be opened for writing")]
+    NoDeviceError(String),
+}
+
+pub struct KeyboardLed {
+    led: LedType,
+    devices: Mutex<Vec<Device>>,
+}
+
+pub fn parse_led(name: &str) -> Result<LedType, LedError> {
+    match name.to_lowercase().as_str() {
+        "scrolllock" | "scroll_lock" | "scroll" => Ok(LedType::LED_SCROLLL),
+        "capslock" | "caps_lock" | "caps" => Ok(LedType::LED_CAPSL),
+        "numlock" | "num_lock" | "num" => Ok(LedType::LED_NUML),
+        "compose" => Ok(LedType::LED_COMPOSE),
+        "kana" => Ok(LedType::LED_KANA),
+        _ => Err(LedError::UnknownLedError(name.to_string())),
+    }
+}
+
+impl KeyboardLed {
+    /// Open every keyboard with the LED called `name`.
+    pub fn open(name: &str) -> Result<Self, LedError> {
+        let led = parse_led(name)?;
+        let devices: Vec<Device> = evdev::enumerate()
+            .map(|(_, device)| device)
+            .filter(|device| device.supported_leds().is_some_and(|leds| leds.contains(led)))
+            .collect();
+        if devices.is_empty() {
+            return Err(LedError::NoDeviceError(name.to_string()));
+        }
+        tracing::info!("Showing recording on the {} LED of {} keyboard(s)", name, devices.len());
+        Ok(Self { led, devices: Mutex::new(devices) })
+    }
+
+    /// Switch the LED on or off. A keyboard that can't be written to (opened read-only, or
+    /// unplugged) is dropped.
+    pub fn set(&self, on: bool) {
+        let event = InputEvent::new(EventType::LED, self.led.0, on as i32);
+        let mut devices = self.devices.lock().unwrap();
+        devices.retain_mut(|device| match device.send_events(&[event]) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!("Can't set the LED of {}: {}", device.name().unwrap_or("keyboard"), e);
+                false
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn led_names() {
+        assert_eq!(parse_led("ScrollLock").unwrap(), LedType::LED_SCROLLL);
+        assert_eq!(parse_led("caps").unwrap(), LedType::LED_CAPSL);
+        assert!(parse_led("hazard").is_err());
+    }
+}
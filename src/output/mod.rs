@@ -1,6 +1,14 @@
 pub mod uinput;
 pub mod clipboard;
-
-pub use uinput::UinputKeyboard;
-pub use clipboard::ClipboardOutput;
-
+pub mod wtype;
+pub mod virtual_keyboard;
+pub mod keys;
+pub mod keymap;
+pub mod focus;
+pub mod file;
+pub mod preview;
+pub mod dbus;
+pub mod command;
+pub mod pacing;
+pub mod remote;
+pub mod led;
@@ -0,0 +1,93 @@
+// Typing in bursts for apps that drop keys when text arrives too fast (some Electron
+// editors): `output.typing_burst_chars` characters at a time, `output.chunk_delay_ms`
+// apart. The same splitting is used to paste long text in several pieces.
+
+use std::future::Future;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Pacing {
+    /// Characters per burst, 0 types everything at once
+    pub burst_chars: usize,
+    /// Pause between bursts
+    pub chunk_delay_ms: u64,
+}
+
+/// Split `text` into pieces of at most `size` characters, breaking after whitespace where
+/// possible so words aren't cut in half. `size` 0 yields the whole text.
+pub fn chunks(text: &str, size: usize) -> Vec<&str> {
+    if size == 0 || text.chars().count() <= size {
+        return vec![text];
+    }
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let end = rest.char_indices().nth(size).map_or(rest.len(), |(i, _)| i);
+        if end == rest.len() {
+            pieces.push(rest);
+            break;
+        }
+        let cut = rest[..end]
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(end, |(i, c)| i + c.len_utf8());
+        pieces.push(&rest[..cut]);
+        rest = &rest[cut..];
+    }
+    pieces
+}
+
+/// A burst failed after the ones before it went through.
+#[derive(Debug)]
+pub struct Interrupted<E> {
+    /// Bytes of the text in the bursts that were typed, so a fallback only writes the rest
+    pub typed: usize,
+    pub error: E,
+}
+
+/// Run `type_burst` on each burst of `text`, pausing between them.
+pub async fn in_bursts<'t, E, F, Fut>(text: &'t str, pacing: Pacing, mut type_burst: F) -> Result<(), Interrupted<E>>
+where
+    F: FnMut(&'t str) -> Fut,
+    Fut: Future<Output = Result<(), E>>,
+{
+    let mut typed = 0;
+    for (i, burst) in chunks(text, pacing.burst_chars).into_iter().enumerate() {
+        if i > 0 {
+            tokio::time::sleep(Duration::from_millis(pacing.chunk_delay_ms)).await;
+        }
+        type_burst(burst).await.map_err(|error| Interrupted { typed, error })?;
+        typed += burst.len();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_at_whitespace() {
+        assert_eq!(chunks("hello world again", 0), ["hello world again"]);
+        assert_eq!(chunks("hello world again", 8), ["hello ", "world ", "again"]);
+        assert_eq!(chunks("abcdefghij", 4), ["abcd", "efgh", "ij"]);
+        assert_eq!(chunks("çok güzel", 5), ["çok ", "güzel"]);
+    }
+
+    #[tokio::test]
+    async fn interrupted_bursts_report_what_was_typed() {
+        let pacing = Pacing { burst_chars: 6, chunk_delay_ms: 0 };
+        let result = in_bursts("hello wörld again", pacing, |burst| async move {
+            if burst.starts_with('a') {
+                Err("dropped")
+            } else {
+                Ok(())
+            }
+        })
+        .await;
+        let interrupted = result.unwrap_err();
+        assert_eq!(&"hello wörld again"[..interrupted.typed], "hello wörld ");
+        assert_eq!(interrupted.error, "dropped");
+    }
+}
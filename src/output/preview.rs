@@ -0,0 +1,132 @@
+// Confirmation before output (`output.preview`): the text is shown in a notification with
+// Accept / Edit / Discard actions, and Edit opens it in an editable zenity window. With
+// `output.preview_window` the editable window opens right away, and once it's done a second
+// window asks whether to insert the text or only copy it.
+
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command as TokioCommand;
+
+#[derive(Debug, Error)]
+pub enum PreviewError {
+    #[error("Failed to run {0}: {1}")]
+    SpawnError(&'static str, std::io::Error),
+    #[error("notify-send can't show actions: {0}")]
+    ActionsUnsupported(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    /// Output this text (edited, if the user chose Edit)
+    Accept(String),
+    /// Only put the text in the clipboard, as chosen in the preview window
+    Copy(String),
+    /// Only put the text in the clipboard; used when no preview could be shown
+    CopyOnly(String),
+    Discard,
+}
+
+/// Ask whether to output `text`. Dismissing the notification counts as Discard.
+pub async fn confirm(text: &str) -> Result<Decision, PreviewError> {
+    let output = TokioCommand::new("notify-send")
+        .args([
+            "--app-name=croaker",
+            "--urgency=critical",
+            "--action=accept=Accept",
+            "--action=edit=Edit",
+            "--action=discard=Discard",
+            "croaker: output this?",
+            text,
+        ])
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| PreviewError::SpawnError("notify-send", e))?;
+    if !output.status.success() {
+        // libnotify before 0.7.10 has no --action
+        return Err(PreviewError::ActionsUnsupported(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "accept" => Ok(Decision::Accept(text.to_string())),
+        "edit" => edit(text).await,
+        _ => Ok(Decision::Discard),
+    }
+}
+
+/// Labels of the buttons asking what to do with the reviewed text, which zenity prints when clicked
+const INSERT: &str = "Insert";
+const COPY_ONLY: &str = "Copy only";
+
+/// Let the user edit `text` in a window, then ask whether to output it, only copy it, or drop it.
+pub async fn review(text: &str) -> Result<Decision, PreviewError> {
+    let output = editor(text, &["--title=croaker: review before output", "--ok-label=Done", "--cancel-label=Discard"]).await?;
+    if !output.status.success() {
+        return Ok(Decision::Discard);
+    }
+    let Decision::Accept(text) = edited(&output.stdout) else {
+        return Ok(Decision::Discard);
+    };
+
+    // NOTE: zenity only prints the edited text for OK, not for an extra button, so the choice
+    // between inserting and copying comes after the edit
+    let output = TokioCommand::new("zenity")
+        .args([
+            "--question",
+            "--switch",
+            "--title=croaker: review before output",
+            "--text=Insert the text, or only copy it to the clipboard?",
+            &format!("--extra-button={}", INSERT),
+            &format!("--extra-button={}", COPY_ONLY),
+        ])
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| PreviewError::SpawnError("zenity", e))?;
+    // Closing the window discards
+    Ok(match String::from_utf8_lossy(&output.stdout).trim() {
+        INSERT => Decision::Accept(text),
+        COPY_ONLY => Decision::Copy(text),
+        _ => Decision::Discard,
+    })
+}
+
+async fn edit(text: &str) -> Result<Decision, PreviewError> {
+    let output = editor(text, &["--title=croaker: edit before output"]).await?;
+    // Cancel or closing the window discards
+    if !output.status.success() {
+        return Ok(Decision::Discard);
+    }
+    Ok(edited(&output.stdout))
+}
+
+/// Open `text` in an editable zenity window and wait for it to close.
+async fn editor(text: &str, args: &[&str]) -> Result<std::process::Output, PreviewError> {
+    let mut child = TokioCommand::new("zenity")
+        .args(["--text-info", "--editable", "--width=600", "--height=300"])
+        .args(args)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| PreviewError::SpawnError("zenity", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(text.as_bytes()).await;
+    }
+    child
+        .wait_with_output()
+        .await
+        .map_err(|e| PreviewError::SpawnError("zenity", e))
+}
+
+/// The text zenity printed on OK; emptied out counts as Discard.
+fn edited(stdout: &[u8]) -> Decision {
+    let edited = String::from_utf8_lossy(stdout);
+    let edited = edited.strip_suffix('\n').unwrap_or(&edited);
+    if edited.trim().is_empty() {
+        return Decision::Discard;
+    }
+    Decision::Accept(edited.to_string())
+}
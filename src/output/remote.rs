@@ -0,0 +1,143 @@
+// Remote output (`output_mode = "remote"`): the text is sent to `croaker receive` on another
+// machine, which hands it to the croaker daemon running there to be typed or pasted.
+//
+// One request per connection: a JSON line `{"token": "...", "text": "..."}`, answered with
+// `ok` or `error <reason>`. Addresses starting with `/` are Unix sockets (e.g. forwarded with
+// `ssh -R`), anything else is `host:port` over TCP. The token is the only protection, so
+// over an untrusted network go through SSH rather than exposing the port.
+
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream, UnixListener, UnixStream};
+use tokio::time::{timeout, Duration};
+
+/// Longer requests are rejected, a dictation is nowhere near this
+const MAX_REQUEST_BYTES: u64 = 1024 * 1024;
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum RemoteError {
+    #[error("remote.address is not set")]
+    NoAddressError,
+    #[error("remote.token is not set")]
+    NoTokenError,
+    #[error("Remote connection failed: {0}")]
+    ConnectionError(#[from] std::io::Error),
+    #[error("Remote didn't answer within {0:?}")]
+    TimeoutError(Duration),
+    #[error("Remote refused the text: {0}")]
+    RefusedError(String),
+}
+
+#[derive(Serialize, Deserialize)]
+struct Request {
+    token: String,
+    text: String,
+}
+
+/// Send `text` to the `croaker receive` listening at `address`.
+pub async fn send(address: &str, token: &str, text: &str) -> Result<(), RemoteError> {
+    if address.is_empty() {
+        return Err(RemoteError::NoAddressError);
+    }
+    if token.is_empty() {
+        return Err(RemoteError::NoTokenError);
+    }
+    let mut line = serde_json::to_string(&Request { token: token.to_string(), text: text.to_string() })
+        .expect("request serializes");
+    line.push('\n');
+
+    let exchange = async {
+        if address.starts_with('/') {
+            exchange(UnixStream::connect(address).await?, &line).await
+        } else {
+            exchange(TcpStream::connect(address).await?, &line).await
+        }
+    };
+    let reply = timeout(SEND_TIMEOUT, exchange)
+        .await
+        .map_err(|_| RemoteError::TimeoutError(SEND_TIMEOUT))??;
+    match reply.trim() {
+        "ok" => Ok(()),
+        reply => Err(RemoteError::RefusedError(reply.strip_prefix("error ").unwrap_or(reply).to_string())),
+    }
+}
+
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(stream: S, line: &str) -> Result<String, RemoteError> {
+    let mut stream = BufReader::new(stream);
+    stream.get_mut().write_all(line.as_bytes()).await?;
+    let mut reply = String::new();
+    stream.read_line(&mut reply).await?;
+    Ok(reply)
+}
+
+/// Accept texts on `listen` until an error, passing each one with the right token to `deliver`.
+/// Its error is sent back to the sender.
+pub async fn receive<F, Fut>(listen: &str, token: &str, deliver: F) -> Result<(), RemoteError>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<(), String>>,
+{
+    if token.is_empty() {
+        return Err(RemoteError::NoTokenError);
+    }
+    if listen.starts_with('/') {
+        let _ = std::fs::remove_file(listen);
+        let listener = UnixListener::bind(listen)?;
+        tracing::info!("Receiving text on {}", listen);
+        loop {
+            let (stream, _) = listener.accept().await?;
+            handle(stream, "unix socket", token, &deliver).await;
+        }
+    } else {
+        let listener = TcpListener::bind(listen).await?;
+        tracing::info!("Receiving text on {}", listen);
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            handle(stream, &peer.to_string(), token, &deliver).await;
+        }
+    }
+}
+
+/// Requests are handled one at a time, so texts are output in the order they arrive.
+async fn handle<S, F, Fut>(stream: S, peer: &str, token: &str, deliver: &F)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<(), String>>,
+{
+    let (read_half, mut write_half) = tokio::io::split(stream);
+    let mut reader = BufReader::new(read_half.take(MAX_REQUEST_BYTES));
+    let mut line = String::new();
+    let reply = match timeout(SEND_TIMEOUT, reader.read_line(&mut line)).await {
+        Err(_) => "error timed out".to_string(),
+        Ok(Err(e)) => format!("error {}", e),
+        Ok(Ok(_)) => match serde_json::from_str::<Request>(&line) {
+            Err(_) => "error malformed request".to_string(),
+            Ok(request) if !tokens_match(&request.token, token) => {
+                tracing::warn!("Rejected text from {}: wrong token", peer);
+                "error wrong token".to_string()
+            }
+            Ok(request) => {
+                tracing::info!("Received {} chars from {}", request.text.len(), peer);
+                match deliver(request.text).await {
+                    Ok(()) => "ok".to_string(),
+                    Err(e) => format!("error {}", e),
+                }
+            }
+        },
+    };
+    let _ = write_half.write_all(format!("{}\n", reply).as_bytes()).await;
+}
+
+/// Compare without stopping at the first difference, so timing doesn't reveal the token.
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
@@ -1,7 +1,9 @@
-use crate::config::Config;
+use crate::output::keymap::{KeyStroke, Keymap};
+use crate::output::keys::{Modifier, PasteKeys};
+use std::collections::BTreeSet;
 use std::fs::OpenOptions;
 use std::os::unix::fs::OpenOptionsExt;
-use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::io::AsRawFd;
 use std::path::Path;
 use std::sync::Mutex;
 use thiserror::Error;
@@ -26,26 +28,32 @@ impl From<std::io::Error> for UinputError {
 // Linux input event codes
 const KEY_LEFTCTRL: u16 = 29;
 const KEY_LEFTSHIFT: u16 = 42;
+const KEY_LEFTALT: u16 = 56;
+const KEY_RIGHTALT: u16 = 100;
+const KEY_LEFTMETA: u16 = 125;
+const KEY_INSERT: u16 = 110;
+const KEY_BACKSPACE: u16 = 14;
 const KEY_V: u16 = 47;
 
 // Key codes for ASCII printable characters
 const KEY_A: u16 = 30;
 const KEY_B: u16 = 48;
+const KEY_C: u16 = 46;
 const KEY_D: u16 = 32;
-const KEY_E: u16 = 33;
-const KEY_F: u16 = 34;
-const KEY_G: u16 = 35;
-const KEY_H: u16 = 36;
+const KEY_E: u16 = 18;
+const KEY_F: u16 = 33;
+const KEY_G: u16 = 34;
+const KEY_H: u16 = 35;
 const KEY_I: u16 = 23;
-const KEY_J: u16 = 37;
-const KEY_K: u16 = 38;
-const KEY_L: u16 = 39;
+const KEY_J: u16 = 36;
+const KEY_K: u16 = 37;
+const KEY_L: u16 = 38;
 const KEY_M: u16 = 50;
 const KEY_N: u16 = 49;
 const KEY_O: u16 = 24;
 const KEY_P: u16 = 25;
 const KEY_Q: u16 = 16;
-const KEY_R: u16 = 17;
+const KEY_R: u16 = 19;
 const KEY_S: u16 = 31;
 const KEY_T: u16 = 20;
 const KEY_U: u16 = 22;
@@ -78,6 +86,16 @@ const KEY_SPACE: u16 = 57;
 const KEY_ENTER: u16 = 28;
 const KEY_TAB: u16 = 15;
 
+/// Every key the built-in table, paste combinations and undo can press
+const USED_KEYS: &[u16] = &[
+    KEY_LEFTCTRL, KEY_LEFTSHIFT, KEY_LEFTALT, KEY_RIGHTALT, KEY_LEFTMETA, KEY_INSERT, KEY_BACKSPACE,
+    KEY_V, KEY_A, KEY_B, KEY_C, KEY_D, KEY_E, KEY_F, KEY_G, KEY_H, KEY_I, KEY_J, KEY_K, KEY_L,
+    KEY_M, KEY_N, KEY_O, KEY_P, KEY_Q, KEY_R, KEY_S, KEY_T, KEY_U, KEY_W, KEY_X, KEY_Y, KEY_Z,
+    KEY_1, KEY_2, KEY_3, KEY_4, KEY_5, KEY_6, KEY_7, KEY_8, KEY_9, KEY_0, KEY_MINUS, KEY_EQUAL,
+    KEY_LEFTBRACE, KEY_RIGHTBRACE, KEY_BACKSLASH, KEY_SEMICOLON, KEY_APOSTROPHE, KEY_GRAVE,
+    KEY_COMMA, KEY_DOT, KEY_SLASH, KEY_SPACE, KEY_ENTER, KEY_TAB,
+];
+
 // Input event structures
 #[repr(C, packed)]
 struct InputEvent {
@@ -101,14 +119,16 @@ const KEY_RELEASE: i32 = 0;
 
 pub struct UinputKeyboard {
     file: Mutex<std::fs::File>,
-    delay_ms: u64,
+    keymap: Option<Keymap>,
 }
 
 impl UinputKeyboard {
-    pub fn new(config: &Config) -> Result<Self, UinputError> {
+    /// `keymap` maps characters to keys for the user's layout; without one only ASCII
+    /// can be typed, assuming US QWERTY. `settle_ms` is waited after creating the device.
+    pub fn new(keymap: Option<Keymap>, settle_ms: u64) -> Result<Self, UinputError> {
         let uinput_path = Path::new("/dev/uinput");
         
-        let mut file = OpenOptions::new()
+        let file = OpenOptions::new()
             .read(true)
             .write(true)
             .custom_flags(libc::O_NONBLOCK)
@@ -126,51 +146,37 @@ impl UinputKeyboard {
             }
         }
 
-        // Enable all key codes we might need (1-255)
-        for key_code in 1..=255u16 {
+        // Only the keys we press. Advertising every code makes some compositors and
+        // libinput classify the device as something other than a keyboard.
+        let keys = Self::used_keys(keymap.as_ref());
+        for &key_code in &keys {
             unsafe {
-                let _ = libc::ioctl(fd, uinput_ioctl::UI_SET_KEYBIT as libc::c_ulong, key_code as libc::c_ulong);
+                if libc::ioctl(fd, uinput_ioctl::UI_SET_KEYBIT as libc::c_ulong, key_code as libc::c_ulong) < 0 {
+                    tracing::warn!("ioctl UI_SET_KEYBIT {} failed: {}", key_code, std::io::Error::last_os_error());
+                }
             }
         }
+        tracing::debug!("uinput keyboard advertises {} keys", keys.len());
 
-        // Create device using the old uinput_user_dev method
-        // First write the structure, then call UI_DEV_CREATE
+        // NOTE: no UI_ABS_SETUP, absolute axes would make this look like a joystick or tablet
         unsafe {
-            let mut uidev = std::mem::zeroed::<libc::uinput_user_dev>();
-            
-            // Set device name (must be null-terminated, max 80 chars)
-            let name_bytes = b"croaker virtual keyboard\0";
-            let name_len = (name_bytes.len() - 1).min(79); // -1 to exclude null terminator, max 79 chars
-            std::ptr::copy_nonoverlapping(
-                name_bytes.as_ptr() as *const libc::c_char,
-                uidev.name.as_mut_ptr() as *mut libc::c_char,
-                name_len,
-            );
-            // Ensure null termination
-            *uidev.name.as_mut_ptr().add(name_len) = 0;
-            
-            // Set input device ID
-            // Use BUS_VIRTUAL (0x06) for uinput devices
-            uidev.id.bustype = 0x06; // BUS_VIRTUAL
-            uidev.id.vendor = 0x1;
-            uidev.id.product = 0x1;
-            uidev.id.version = 1;
-            
-            // Set ff_effects_max (required field, 0 means no force feedback)
-            uidev.ff_effects_max = 0;
-            
-            // Write the structure to the file descriptor (old method)
-            use std::io::Write;
-            let uidev_bytes = std::slice::from_raw_parts(
-                &uidev as *const _ as *const u8,
-                std::mem::size_of::<libc::uinput_user_dev>(),
-            );
-            if file.write_all(uidev_bytes).is_err() {
-                return Err(UinputError::OpenError("Failed to write uinput_user_dev structure".to_string()));
+            let mut setup = std::mem::zeroed::<libc::uinput_setup>();
+            let name = b"croaker virtual keyboard";
+            for (dst, &src) in setup.name.iter_mut().zip(name.iter()) {
+                *dst = src as libc::c_char;
+            }
+            setup.id.bustype = 0x06; // BUS_VIRTUAL
+            setup.id.vendor = 0x1;
+            setup.id.product = 0x1;
+            setup.id.version = 1;
+
+            if libc::ioctl(fd, uinput_ioctl::UI_DEV_SETUP as libc::c_ulong, &setup as *const libc::uinput_setup) < 0 {
+                return Err(UinputError::OpenError(format!(
+                    "ioctl UI_DEV_SETUP failed: {}",
+                    std::io::Error::last_os_error()
+                )));
             }
-            file.flush().ok();
 
-            // Now create the device
             let result = libc::ioctl(fd, uinput_ioctl::UI_DEV_CREATE as libc::c_ulong);
             if result < 0 {
                 let err = std::io::Error::last_os_error();
@@ -182,18 +188,36 @@ impl UinputKeyboard {
             tracing::info!("uinput virtual keyboard device created successfully");
         }
 
-        // Note: Device creation is synchronous, no need to wait
+        // The compositor has to notice the new device before it takes our key events
+        std::thread::sleep(Duration::from_millis(settle_ms));
 
         Ok(Self {
             file: Mutex::new(file),
-            delay_ms: config.output.keystroke_delay_ms,
+            keymap,
         })
     }
 
-    pub async fn type_text(&self, text: &str) -> Result<(), UinputError> {
+    /// Keys croaker can press: modifiers, the built-in US table, and whatever the keymap uses.
+    fn used_keys(keymap: Option<&Keymap>) -> BTreeSet<u16> {
+        let mut keys: BTreeSet<u16> = USED_KEYS.iter().copied().collect();
+        if let Some(keymap) = keymap {
+            keys.extend(keymap.codes());
+        }
+        keys
+    }
+
+    /// Type `text`, waiting `delay_ms` after each character (`output.keystroke_delay_ms`,
+    /// or the focused app's profile).
+    pub async fn type_text(&self, text: &str, delay_ms: u64) -> Result<(), UinputError> {
         tracing::info!("Typing text via uinput: {} chars", text.len());
         tracing::debug!("Text content: {:?}", text);
 
+        // Check every character first, so text the layout can't produce falls back to the
+        // clipboard before any of it is typed
+        for ch in text.chars().filter(|ch| !matches!(ch, '\n' | '\t')) {
+            self.char_to_keycode(ch)?;
+        }
+
         for ch in text.chars() {
             if ch == '\n' {
                 self.send_key(KEY_ENTER, true).await?;
@@ -201,41 +225,81 @@ impl UinputKeyboard {
             } else if ch == '\t' {
                 self.send_key(KEY_TAB, true).await?;
                 self.send_key(KEY_TAB, false).await?;
-            } else if ch.is_ascii() {
-                let (key_code, needs_shift) = self.char_to_keycode(ch)?;
-                
-                if needs_shift {
+            } else {
+                // Characters the layout can't produce - caller should use clipboard fallback
+                let stroke = self.char_to_keycode(ch)?;
+
+                if stroke.shift {
                     self.send_key(KEY_LEFTSHIFT, true).await?;
                 }
-                
-                self.send_key(key_code, true).await?;
-                self.send_key(key_code, false).await?;
-                
-                if needs_shift {
+                if stroke.altgr {
+                    self.send_key(KEY_RIGHTALT, true).await?;
+                }
+
+                self.send_key(stroke.code, true).await?;
+                self.send_key(stroke.code, false).await?;
+
+                if stroke.altgr {
+                    self.send_key(KEY_RIGHTALT, false).await?;
+                }
+                if stroke.shift {
                     self.send_key(KEY_LEFTSHIFT, false).await?;
                 }
-            } else {
-                // Non-ASCII character - caller should use clipboard fallback
-                return Err(UinputError::UnsupportedChar(ch));
             }
 
-            tokio::time::sleep(Duration::from_millis(self.delay_ms)).await;
+            tokio::time::sleep(Duration::from_millis(delay_ms)).await;
         }
 
         Ok(())
     }
 
-    pub async fn send_paste(&self) -> Result<(), UinputError> {
-        tracing::debug!("Sending Ctrl+V keystroke");
-        // Send Ctrl+V
-        self.send_key(KEY_LEFTCTRL, true).await?;
-        tokio::time::sleep(Duration::from_millis(10)).await;
-        self.send_key(KEY_V, true).await?;
-        tokio::time::sleep(Duration::from_millis(10)).await;
-        self.send_key(KEY_V, false).await?;
+    /// Press Backspace `count` times, waiting `delay_ms` after each press.
+    pub async fn backspace(&self, count: usize, delay_ms: u64) -> Result<(), UinputError> {
+        tracing::debug!("Sending {} backspaces", count);
+        for _ in 0..count {
+            self.send_key(KEY_BACKSPACE, true).await?;
+            self.send_key(KEY_BACKSPACE, false).await?;
+            tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+        }
+        Ok(())
+    }
+
+    /// Press a key combination such as the paste keys or Ctrl+Z.
+    pub async fn send_combo(&self, keys: &PasteKeys) -> Result<(), UinputError> {
+        tracing::debug!("Sending {} keystroke", keys);
+        let mut modifiers: Vec<u16> = keys
+            .modifiers
+            .iter()
+            .map(|modifier| match modifier {
+                Modifier::Ctrl => KEY_LEFTCTRL,
+                Modifier::Shift => KEY_LEFTSHIFT,
+                Modifier::Alt => KEY_LEFTALT,
+                Modifier::Super => KEY_LEFTMETA,
+            })
+            .collect();
+        let key = if keys.key == "insert" {
+            KEY_INSERT
+        } else {
+            let ch = keys.key.chars().next().unwrap_or('v');
+            let stroke = self.char_to_keycode(ch)?;
+            if stroke.shift && !modifiers.contains(&KEY_LEFTSHIFT) {
+                modifiers.push(KEY_LEFTSHIFT);
+            }
+            stroke.code
+        };
+
+        for &modifier in &modifiers {
+            self.send_key(modifier, true).await?;
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        self.send_key(key, true).await?;
         tokio::time::sleep(Duration::from_millis(10)).await;
-        self.send_key(KEY_LEFTCTRL, false).await?;
-        tracing::debug!("Ctrl+V keystroke sent");
+        self.send_key(key, false).await?;
+        for &modifier in modifiers.iter().rev() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            self.send_key(modifier, false).await?;
+        }
+        tracing::debug!("{} keystroke sent", keys);
         Ok(())
     }
 
@@ -300,48 +364,91 @@ impl UinputKeyboard {
         Ok(())
     }
 
-    fn char_to_keycode(&self, ch: char) -> Result<(u16, bool), UinputError> {
+    /// Key and modifiers for `ch`: from the XKB keymap when one is loaded, else from a
+    /// built-in US QWERTY table (ASCII only).
+    fn char_to_keycode(&self, ch: char) -> Result<KeyStroke, UinputError> {
+        if let Some(ref keymap) = self.keymap {
+            return keymap.get(ch).ok_or(UinputError::UnsupportedChar(ch));
+        }
         match ch {
-            'a'..='z' => Ok((KEY_A + (ch as u16 - b'a' as u16), false)),
-            'A'..='Z' => Ok((KEY_A + (ch.to_ascii_lowercase() as u16 - b'a' as u16), true)),
-            '0' => Ok((KEY_0, false)),
-            '1'..='9' => Ok((KEY_1 + (ch as u16 - b'1' as u16), false)),
-            ' ' => Ok((KEY_SPACE, false)),
-            '-' => Ok((KEY_MINUS, false)),
-            '=' => Ok((KEY_EQUAL, false)),
-            '[' => Ok((KEY_LEFTBRACE, false)),
-            ']' => Ok((KEY_RIGHTBRACE, false)),
-            '\\' => Ok((KEY_BACKSLASH, false)),
-            ';' => Ok((KEY_SEMICOLON, false)),
-            '\'' => Ok((KEY_APOSTROPHE, false)),
-            '`' => Ok((KEY_GRAVE, false)),
-            ',' => Ok((KEY_COMMA, false)),
-            '.' => Ok((KEY_DOT, false)),
-            '/' => Ok((KEY_SLASH, false)),
-            '!' => Ok((KEY_1, true)),
-            '@' => Ok((KEY_2, true)),
-            '#' => Ok((KEY_3, true)),
-            '$' => Ok((KEY_4, true)),
-            '%' => Ok((KEY_5, true)),
-            '^' => Ok((KEY_6, true)),
-            '&' => Ok((KEY_7, true)),
-            '*' => Ok((KEY_8, true)),
-            '(' => Ok((KEY_9, true)),
-            ')' => Ok((KEY_0, true)),
-            '_' => Ok((KEY_MINUS, true)),
-            '+' => Ok((KEY_EQUAL, true)),
-            '{' => Ok((KEY_LEFTBRACE, true)),
-            '}' => Ok((KEY_RIGHTBRACE, true)),
-            '|' => Ok((KEY_BACKSLASH, true)),
-            ':' => Ok((KEY_SEMICOLON, true)),
-            '"' => Ok((KEY_APOSTROPHE, true)),
-            '~' => Ok((KEY_GRAVE, true)),
-            '<' => Ok((KEY_COMMA, true)),
-            '>' => Ok((KEY_DOT, true)),
-            '?' => Ok((KEY_SLASH, true)),
+            'a'..='z' => Ok(Self::qwerty(Self::letter_keycode(ch), false)),
+            'A'..='Z' => Ok(Self::qwerty(Self::letter_keycode(ch.to_ascii_lowercase()), true)),
+            '0' => Ok(Self::qwerty(KEY_0, false)),
+            '1'..='9' => Ok(Self::qwerty(KEY_1 + (ch as u16 - b'1' as u16), false)),
+            ' ' => Ok(Self::qwerty(KEY_SPACE, false)),
+            '-' => Ok(Self::qwerty(KEY_MINUS, false)),
+            '=' => Ok(Self::qwerty(KEY_EQUAL, false)),
+            '[' => Ok(Self::qwerty(KEY_LEFTBRACE, false)),
+            ']' => Ok(Self::qwerty(KEY_RIGHTBRACE, false)),
+            '\\' => Ok(Self::qwerty(KEY_BACKSLASH, false)),
+            ';' => Ok(Self::qwerty(KEY_SEMICOLON, false)),
+            '\'' => Ok(Self::qwerty(KEY_APOSTROPHE, false)),
+            '`' => Ok(Self::qwerty(KEY_GRAVE, false)),
+            ',' => Ok(Self::qwerty(KEY_COMMA, false)),
+            '.' => Ok(Self::qwerty(KEY_DOT, false)),
+            '/' => Ok(Self::qwerty(KEY_SLASH, false)),
+            '!' => Ok(Self::qwerty(KEY_1, true)),
+            '@' => Ok(Self::qwerty(KEY_2, true)),
+            '#' => Ok(Self::qwerty(KEY_3, true)),
+            '$' => Ok(Self::qwerty(KEY_4, true)),
+            '%' => Ok(Self::qwerty(KEY_5, true)),
+            '^' => Ok(Self::qwerty(KEY_6, true)),
+            '&' => Ok(Self::qwerty(KEY_7, true)),
+            '*' => Ok(Self::qwerty(KEY_8, true)),
+            '(' => Ok(Self::qwerty(KEY_9, true)),
+            ')' => Ok(Self::qwerty(KEY_0, true)),
+            '_' => Ok(Self::qwerty(KEY_MINUS, true)),
+            '+' => Ok(Self::qwerty(KEY_EQUAL, true)),
+            '{' => Ok(Self::qwerty(KEY_LEFTBRACE, true)),
+            '}' => Ok(Self::qwerty(KEY_RIGHTBRACE, true)),
+            '|' => Ok(Self::qwerty(KEY_BACKSLASH, true)),
+            ':' => Ok(Self::qwerty(KEY_SEMICOLON, true)),
+            '"' => Ok(Self::qwerty(KEY_APOSTROPHE, true)),
+            '~' => Ok(Self::qwerty(KEY_GRAVE, true)),
+            '<' => Ok(Self::qwerty(KEY_COMMA, true)),
+            '>' => Ok(Self::qwerty(KEY_DOT, true)),
+            '?' => Ok(Self::qwerty(KEY_SLASH, true)),
             _ => Err(UinputError::UnsupportedChar(ch)),
         }
     }
+
+    fn qwerty(code: u16, shift: bool) -> KeyStroke {
+        KeyStroke { code, shift, altgr: false }
+    }
+
+    // NOTE: Letter key codes follow the physical QWERTY layout, not the alphabet,
+    // so they can't be derived by offsetting from KEY_A.
+    fn letter_keycode(ch: char) -> u16 {
+        match ch {
+            'a' => KEY_A,
+            'b' => KEY_B,
+            'c' => KEY_C,
+            'd' => KEY_D,
+            'e' => KEY_E,
+            'f' => KEY_F,
+            'g' => KEY_G,
+            'h' => KEY_H,
+            'i' => KEY_I,
+            'j' => KEY_J,
+            'k' => KEY_K,
+            'l' => KEY_L,
+            'm' => KEY_M,
+            'n' => KEY_N,
+            'o' => KEY_O,
+            'p' => KEY_P,
+            'q' => KEY_Q,
+            'r' => KEY_R,
+            's' => KEY_S,
+            't' => KEY_T,
+            'u' => KEY_U,
+            'v' => KEY_V,
+            'w' => KEY_W,
+            'x' => KEY_X,
+            'y' => KEY_Y,
+            'z' => KEY_Z,
+            _ => unreachable!("not an ASCII letter: {:?}", ch),
+        }
+    }
 }
 
 impl Drop for UinputKeyboard {
@@ -359,6 +466,8 @@ impl Drop for UinputKeyboard {
 mod uinput_ioctl {
     pub const UI_SET_EVBIT: u32 = 0x40045564;
     pub const UI_SET_KEYBIT: u32 = 0x40045565;
+    /// _IOW('U', 3, struct uinput_setup)
+    pub const UI_DEV_SETUP: u32 = 0x405c5503;
     pub const UI_DEV_CREATE: u32 = 0x5501;
     pub const UI_DEV_DESTROY: u32 = 0x5502;
 }
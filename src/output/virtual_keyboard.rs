@@ -0,0 +1,234 @@
+// Typing through the zwp_virtual_keyboard_v1 Wayland protocol (wlroots compositors:
+// Sway, Hyprland, river, ...). Works without uinput permissions, wtype or the clipboard.
+//
+// The protocol sends raw keycodes interpreted through a keymap we supply, so like wtype
+// every text gets its own keymap with one keycode per distinct character. xkb keycodes end
+// at 255, so text with more distinct characters than that is typed in batches, each with
+// its own keymap.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{Seek, SeekFrom, Write};
+use std::os::fd::AsFd;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::{wl_registry, wl_seat};
+use wayland_client::{delegate_noop, Connection, Dispatch, QueueHandle};
+use wayland_protocols_misc::zwp_virtual_keyboard_v1::client::{
+    zwp_virtual_keyboard_manager_v1::ZwpVirtualKeyboardManagerV1,
+    zwp_virtual_keyboard_v1::ZwpVirtualKeyboardV1,
+};
+
+#[derive(Debug, Error)]
+pub enum VirtualKeyboardError {
+    #[error("Failed to connect to the Wayland display: {0}")]
+    ConnectError(#[from] wayland_client::ConnectError),
+    #[error("Compositor doesn't support {0}")]
+    Unsupported(&'static str),
+    #[error("Wayland protocol error: {0}")]
+    ProtocolError(String),
+    #[error("Failed to write keymap: {0}")]
+    KeymapError(#[from] std::io::Error),
+}
+
+const WL_KEYBOARD_KEYMAP_FORMAT_XKB_V1: u32 = 1;
+const KEY_PRESSED: u32 = 1;
+const KEY_RELEASED: u32 = 0;
+/// Distinct characters per keymap, with room to spare below the highest xkb keycode (255)
+const MAX_KEYS: usize = 240;
+
+struct State;
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for State {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+delegate_noop!(State: ignore wl_seat::WlSeat);
+delegate_noop!(State: ZwpVirtualKeyboardManagerV1);
+delegate_noop!(State: ZwpVirtualKeyboardV1);
+
+pub struct VirtualKeyboardOutput;
+
+impl VirtualKeyboardOutput {
+    /// Returns an output if the running compositor offers the virtual keyboard protocol.
+    pub fn detect() -> Option<Self> {
+        std::env::var_os("WAYLAND_DISPLAY")?;
+        match connect() {
+            Ok(_) => {
+                tracing::info!("Compositor supports zwp_virtual_keyboard_v1, using it for typing");
+                Some(Self)
+            }
+            Err(e) => {
+                tracing::debug!("Virtual keyboard not available: {}", e);
+                None
+            }
+        }
+    }
+
+    pub async fn type_text(&self, text: &str, keystroke_delay_ms: u64) -> Result<(), VirtualKeyboardError> {
+        tracing::info!("Typing {} chars via virtual keyboard", text.len());
+        let text = text.to_string();
+        let delay = Duration::from_millis(keystroke_delay_ms);
+        tokio::task::spawn_blocking(move || type_blocking(&text, delay))
+            .await
+            .map_err(|e| VirtualKeyboardError::ProtocolError(e.to_string()))?
+    }
+}
+
+/// Open a fresh connection and bind the seat and virtual keyboard manager.
+/// A connection per text keeps the daemon from holding compositor resources while idle.
+fn connect() -> Result<(Connection, wayland_client::EventQueue<State>, ZwpVirtualKeyboardV1), VirtualKeyboardError> {
+    let conn = Connection::connect_to_env()?;
+    let (globals, queue) = registry_queue_init::<State>(&conn)
+        .map_err(|e| VirtualKeyboardError::ProtocolError(e.to_string()))?;
+    let qh = queue.handle();
+
+    let seat: wl_seat::WlSeat = globals
+        .bind(&qh, 1..=7, ())
+        .map_err(|_| VirtualKeyboardError::Unsupported("wl_seat"))?;
+    let manager: ZwpVirtualKeyboardManagerV1 = globals
+        .bind(&qh, 1..=1, ())
+        .map_err(|_| VirtualKeyboardError::Unsupported("zwp_virtual_keyboard_manager_v1"))?;
+    let keyboard = manager.create_virtual_keyboard(&seat, &qh, ());
+
+    Ok((conn, queue, keyboard))
+}
+
+fn type_blocking(text: &str, delay: Duration) -> Result<(), VirtualKeyboardError> {
+    let (conn, mut queue, keyboard) = connect()?;
+    let protocol_error = |e: wayland_client::DispatchError| VirtualKeyboardError::ProtocolError(e.to_string());
+
+    let start = Instant::now();
+    let time = || start.elapsed().as_millis() as u32;
+    for batch in batches(text) {
+        // Each batch has its own keymap, uploaded once the previous batch's keys are through
+        let (keymap, keycodes) = build_keymap(batch);
+        let mut file = tempfile::tempfile()?;
+        file.write_all(keymap.as_bytes())?;
+        // The compositor reads a NUL-terminated string of `size` bytes
+        file.write_all(&[0])?;
+        file.seek(SeekFrom::Start(0))?;
+        keyboard.keymap(WL_KEYBOARD_KEYMAP_FORMAT_XKB_V1, file.as_fd(), keymap.len() as u32 + 1);
+        keyboard.modifiers(0, 0, 0, 0);
+        queue.roundtrip(&mut State).map_err(protocol_error)?;
+
+        for c in batch.chars() {
+            let Some(&key) = keycodes.get(&c) else {
+                continue;
+            };
+            keyboard.key(time(), key, KEY_PRESSED);
+            keyboard.key(time(), key, KEY_RELEASED);
+            conn.flush().map_err(|e| VirtualKeyboardError::ProtocolError(e.to_string()))?;
+            if !delay.is_zero() {
+                std::thread::sleep(delay);
+            }
+        }
+        queue.roundtrip(&mut State).map_err(protocol_error)?;
+    }
+
+    keyboard.destroy();
+    queue.roundtrip(&mut State).map_err(protocol_error)?;
+    Ok(())
+}
+
+/// X keysym for a character: Latin-1 maps directly, everything else uses the Unicode range.
+fn keysym(c: char) -> Option<u32> {
+    match c {
+        '\n' => Some(0xff0d), // Return
+        '\t' => Some(0xff09), // Tab
+        c if c.is_control() => None,
+        c if matches!(c as u32, 0x20..=0x7e | 0xa0..=0xff) => Some(c as u32),
+        c => Some(0x0100_0000 + c as u32),
+    }
+}
+
+/// Split `text` into runs with at most `MAX_KEYS` distinct characters each, so every run fits
+/// in one keymap.
+fn batches(text: &str) -> Vec<&str> {
+    let mut batches = Vec::new();
+    let mut seen = HashSet::new();
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        if keysym(c).is_none() || seen.contains(&c) {
+            continue;
+        }
+        if seen.len() == MAX_KEYS {
+            batches.push(&text[start..i]);
+            seen.clear();
+            start = i;
+        }
+        seen.insert(c);
+    }
+    batches.push(&text[start..]);
+    batches
+}
+
+/// An xkb keymap with one key per distinct character of `text`, and the evdev keycode
+/// (xkb keycode - 8) to send for each character.
+fn build_keymap(text: &str) -> (String, HashMap<char, u32>) {
+    let mut keycodes = HashMap::new();
+    let mut symbols = Vec::new();
+    for c in text.chars() {
+        if keycodes.contains_key(&c) {
+            continue;
+        }
+        if let Some(sym) = keysym(c) {
+            keycodes.insert(c, symbols.len() as u32 + 1);
+            symbols.push(sym);
+        }
+    }
+
+    let mut keymap = String::from("xkb_keymap {\nxkb_keycodes \"(unnamed)\" {\nminimum = 8;\n");
+    keymap.push_str(&format!("maximum = {};\n", symbols.len() + 9));
+    for i in 0..symbols.len() {
+        keymap.push_str(&format!("<K{}> = {};\n", i + 1, i + 9));
+    }
+    keymap.push_str("};\nxkb_types \"(unnamed)\" { include \"complete\" };\n");
+    keymap.push_str("xkb_compatibility \"(unnamed)\" { include \"complete\" };\n");
+    keymap.push_str("xkb_symbols \"(unnamed)\" {\n");
+    for (i, sym) in symbols.iter().enumerate() {
+        keymap.push_str(&format!("key <K{}> {{[ {:#x} ]}};\n", i + 1, sym));
+    }
+    keymap.push_str("};\n};\n");
+
+    (keymap, keycodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keymap_has_one_key_per_distinct_char() {
+        let (keymap, keycodes) = build_keymap("aba\nş");
+        assert_eq!(keycodes.len(), 4);
+        assert_eq!(keycodes[&'a'], 1);
+        assert_eq!(keycodes[&'b'], 2);
+        assert!(keymap.contains("<K1> = 9;"));
+        assert!(keymap.contains("key <K3> {[ 0xff0d ]};"));
+        assert!(keymap.contains("key <K4> {[ 0x100015f ]};"));
+    }
+
+    #[test]
+    fn text_with_many_distinct_chars_is_typed_in_batches() {
+        // 300 distinct CJK characters, more than a keymap's 255 keycodes hold
+        let text: String = ('\u{4e00}'..).take(300).collect();
+        let batches = batches(&text);
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches.concat(), text);
+        for batch in batches {
+            let (keymap, keycodes) = build_keymap(batch);
+            assert!(keycodes.len() <= MAX_KEYS);
+            assert!(keycodes.values().all(|&key| key + 8 <= 255));
+            assert!(keymap.contains(&format!("maximum = {};", keycodes.len() + 9)));
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use thiserror::Error;
+use tokio::process::Command as TokioCommand;
+
+#[derive(Debug, Error)]
+pub enum WtypeError {
+    #[error("Failed to execute wtype: {0}")]
+    SpawnError(#[from] std::io::Error),
+    #[error("wtype failed: {0}")]
+    TypeError(String),
+}
+
+/// Types text through the Wayland virtual keyboard protocol, leaving the clipboard alone.
+/// Needs a compositor that implements `zwp_virtual_keyboard_v1` (wlroots-based ones do,
+/// KDE and GNOME don't).
+pub async fn type_text(text: &str, keystroke_delay_ms: u64) -> Result<(), WtypeError> {
+    tracing::info!("Typing {} chars via wtype", text.len());
+
+    // `--` so text starting with a dash isn't read as an option
+    let output = TokioCommand::new("wtype")
+        .args(["-d", &keystroke_delay_ms.to_string(), "--", text])
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        return Err(WtypeError::TypeError(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}
@@ -1,25 +1,54 @@
 pub mod notification;
+pub mod osd;
 pub mod tray;
 
-use crate::daemon::state::DaemonState;
+use crate::config::{self, OverlayColors, OverlayConfig};
+use crate::daemon::state::{DaemonState, Step};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
+/// Longest first line shown by `transcription_preview`
+const PREVIEW_CHARS: usize = 60;
+
+/// How long `auto` waits for a tray host to appear, as one may start after us at login
+const TRAY_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+const TRAY_PROBE_INTERVAL: Duration = Duration::from_millis(250);
+
 #[derive(Debug, Clone)]
 pub enum OverlayMessage {
     State(DaemonState),
     OutputMode(String),
     Language(String),
+    /// Translation mode switched on/off
+    Translate(bool),
+    /// Session profile switched, or back to none
+    Profile(Option<String>),
+    /// Hotkeys paused (`croaker pause`) or resumed
+    Paused(bool),
+    /// Short informational message
+    Notice(String),
+    /// Something the user should act on, e.g. a muted microphone
+    Warning(String),
+    /// Seconds left before the text is typed (`output.countdown_secs`)
+    Countdown(u32),
+    /// Recording latched on by a push-to-talk double tap, or released again
+    Locked(bool),
+    /// Text of a dictation that was just written (`overlay.show_transcription`)
+    Transcribed(String),
+    /// The dictation being processed moved on to the next step
+    Progress(Step),
+    /// Characters about to be written, for `{chars}` in notification templates
+    OutputLength(usize),
+    /// Input level of the recording in dBFS, a few times a second
     AudioLevel(f32),
-    Show,
-    Hide,
 }
 
 #[derive(Debug, Error)]
 pub enum OverlayError {
-    #[error("Overlay error: {0}")]
-    Error(String),
-    #[error("Failed to initialize overlay")]
-    InitError,
+    #[error("Unknown overlay backend {0:?} (expected \"tray\", \"notification\", \"wob\", \"swayosd\" or \"auto\")")]
+    UnknownBackendError(String),
+    #[error("Can't reach the notification server: {0}")]
+    BusError(#[from] zbus::Error),
 }
 
 pub trait Overlay: Send {
@@ -27,20 +56,182 @@ pub trait Overlay: Send {
     fn update_audio_level(&self, level: f32);
     fn update_output_mode(&self, mode: &str);
     fn update_language(&self, language: &str);
+    fn update_translate(&self, enabled: bool);
+    fn update_profile(&self, profile: Option<&str>);
+    fn update_paused(&self, paused: bool);
+    fn show_notice(&self, message: &str);
+    fn show_warning(&self, message: &str);
+    fn show_countdown(&self, seconds: u32);
+    fn update_locked(&self, locked: bool);
+    fn show_transcription(&self, text: &str);
+    fn update_progress(&self, step: Step);
+    fn update_output_length(&self, chars: usize);
     fn show(&self);
     fn hide(&self);
 }
 
-pub fn create_overlay(backend: &str) -> Result<Box<dyn Overlay>, OverlayError> {
+pub fn create_overlay(backend: &str, config: &OverlayConfig) -> Result<Box<dyn Overlay>, OverlayError> {
     match backend {
         "notification" => {
-            notification::NotificationOverlay::new().map(|o| Box::new(o) as Box<dyn Overlay>)
+            notification::NotificationOverlay::new(config.notifications.clone()).map(|o| Box::new(o) as Box<dyn Overlay>)
         }
-        _ => Err(OverlayError::InitError),
+        "wob" => Ok(Box::new(osd::WobOverlay::new(&config.wob_pipe))),
+        "swayosd" => Ok(Box::new(osd::SwayOsdOverlay)),
+        _ => Err(OverlayError::UnknownBackendError(backend.to_string())),
     }
 }
 
-/// Run the system tray - this blocks and processes messages
-pub fn run_tray(message_rx: std::sync::mpsc::Receiver<OverlayMessage>) -> anyhow::Result<()> {
-    tray::run_tray(message_rx)
+pub type Rgb = (u8, u8, u8);
+
+/// State colors of the tray icon, from `[overlay.colors]`.
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    pub idle: Rgb,
+    pub paused: Rgb,
+    pub recording: Rgb,
+    pub processing: Rgb,
+    pub outputting: Rgb,
+    pub error: Rgb,
+}
+
+impl Theme {
+    /// A color that can't be read keeps its default, with a warning.
+    pub fn new(colors: &OverlayColors) -> Self {
+        let color = |name: &str, value: &str, default: fn() -> String| {
+            parse_color(value).unwrap_or_else(|| {
+                tracing::warn!("overlay.colors.{} = {:?} isn't a \"#rrggbb\" color, using the default", name, value);
+                parse_color(&default()).expect("default colors are valid")
+            })
+        };
+        Self {
+            idle: color("idle", &colors.idle, config::default_idle_color),
+            paused: color("paused", &colors.paused, config::default_paused_color),
+            recording: color("recording", &colors.recording, config::default_recording_color),
+            processing: color("processing", &colors.processing, config::default_processing_color),
+            outputting: color("outputting", &colors.outputting, config::default_outputting_color),
+            error: color("error", &colors.error, config::default_error_color),
+        }
+    }
+}
+
+/// `#rrggbb` (the `#` may be left out) as red, green and blue.
+pub fn parse_color(value: &str) -> Option<Rgb> {
+    let hex = value.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Processing status with elapsed time and, once known, the step: `Processing... 3s (cleaning up)`.
+pub fn progress_label(elapsed: Duration, step: Option<Step>) -> String {
+    match step {
+        Some(step) => format!("Processing... {}s ({})", elapsed.as_secs(), step.label()),
+        None => format!("Processing... {}s", elapsed.as_secs()),
+    }
+}
+
+/// First line of `text`, cut short if long, and its word count: `Hello there… (12 words)`.
+pub fn transcription_preview(text: &str) -> String {
+    let first = text.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("");
+    let mut line: String = first.chars().take(PREVIEW_CHARS).collect();
+    if line.len() < first.len() || text.trim() != first {
+        line.truncate(line.trim_end().len());
+        line.push('…');
+    }
+    let words = text.split_whitespace().count();
+    format!("{} ({} word{})", line, words, if words == 1 { "" } else { "s" })
+}
+
+/// Whether `overlay.backend` names a backend croaker knows, for `croaker config validate`.
+pub fn check_backend(backend: &str) -> Result<(), OverlayError> {
+    match backend {
+        "tray" | "notification" | "wob" | "swayosd" | "auto" => Ok(()),
+        _ if is_floating(backend) => Ok(()),
+        _ => Err(OverlayError::UnknownBackendError(backend.to_string())),
+    }
+}
+
+/// "gtk" and "layer-shell" would draw a floating overlay, which croaker doesn't have yet; they're
+/// treated as "auto".
+pub fn is_floating(backend: &str) -> bool {
+    matches!(backend, "gtk" | "layer-shell")
+}
+
+/// The backend to use for `overlay.backend`: `auto` is the tray when something on the session
+/// bus can show tray icons (a StatusNotifierWatcher), desktop notifications otherwise.
+/// Waits up to `TRAY_PROBE_TIMEOUT` for a tray host, so call it off the tokio runtime.
+pub fn resolve_backend(backend: &str) -> String {
+    if is_floating(backend) {
+        tracing::warn!("The {} overlay isn't implemented yet, picking a backend as for \"auto\"", backend);
+    } else if backend != "auto" {
+        return backend.to_string();
+    }
+    let deadline = Instant::now() + TRAY_PROBE_TIMEOUT;
+    loop {
+        match tray_host_available() {
+            Ok(true) => return "tray".to_string(),
+            Ok(false) if Instant::now() < deadline => std::thread::sleep(TRAY_PROBE_INTERVAL),
+            Ok(false) => {
+                tracing::info!("No tray host on the session bus, using notifications");
+                return "notification".to_string();
+            }
+            Err(e) => {
+                tracing::info!("Can't look for a tray host ({}), using notifications", e);
+                return "notification".to_string();
+            }
+        }
+    }
+}
+
+fn tray_host_available() -> zbus::Result<bool> {
+    let connection = zbus::blocking::Connection::session()?;
+    let dbus = zbus::blocking::fdo::DBusProxy::new(&connection)?;
+    Ok(dbus.name_has_owner("org.kde.StatusNotifierWatcher".try_into()?)?)
+}
+
+/// Run the system tray - this blocks and processes messages. `profiles` are offered in its menu.
+pub fn run_tray(
+    message_rx: std::sync::mpsc::Receiver<OverlayMessage>,
+    profiles: Vec<String>,
+    theme: Theme,
+) -> anyhow::Result<()> {
+    tray::run_tray(message_rx, profiles, theme)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn floating_backends_are_accepted_and_typos_are_not() {
+        assert!(check_backend("auto").is_ok());
+        assert!(check_backend("gtk").is_ok());
+        assert!(check_backend("layer-shell").is_ok());
+        assert!(matches!(check_backend("tary"), Err(OverlayError::UnknownBackendError(_))));
+    }
+
+    #[test]
+    fn preview_shows_the_first_line_and_word_count() {
+        assert_eq!(transcription_preview("Hello there."), "Hello there. (2 words)");
+        assert_eq!(transcription_preview("\nFirst line\nsecond line"), "First line… (4 words)");
+        let long = "word ".repeat(20);
+        assert_eq!(transcription_preview(&long), format!("{}… (20 words)", &long[..59]));
+        assert_eq!(transcription_preview("Yes"), "Yes (1 word)");
+    }
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(parse_color("#ff3c3c"), Some((255, 60, 60)));
+        assert_eq!(parse_color("0072B2"), Some((0, 114, 178)));
+        assert_eq!(parse_color("#fff"), None);
+        assert_eq!(parse_color("#gg0000"), None);
+    }
+
+    #[test]
+    fn progress_label_shows_elapsed_seconds_and_step() {
+        assert_eq!(progress_label(Duration::from_millis(900), None), "Processing... 0s");
+        assert_eq!(progress_label(Duration::from_secs(3), Some(Step::CleaningUp)), "Processing... 3s (cleaning up)");
+    }
 }
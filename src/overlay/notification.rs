@@ -1,54 +1,165 @@
-use crate::daemon::state::DaemonState;
+use crate::config::{NotificationTemplate, NotificationTemplates};
+use crate::daemon::state::{DaemonState, Step};
 use crate::overlay::{Overlay, OverlayError};
-use std::process::Command;
+use futures_util::StreamExt;
+use std::collections::HashMap;
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use zbus::proxy;
+use zbus::zvariant::Value;
+
+const DEFAULT_TITLE: &str = "croaker";
+const APP_NAME: &str = "croaker";
+/// Let the notification server decide how long a notification stays
+const DEFAULT_EXPIRY: i32 = -1;
+
+/// The desktop notification server (Desktop Notifications Specification)
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    fn close_notification(&self, id: u32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn action_invoked(&self, id: u32, action_key: String) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn notification_closed(&self, id: u32, reason: u32) -> zbus::Result<()>;
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Urgency {
+    Low,
+    Normal,
+    Critical,
+}
+
+impl Urgency {
+    fn hints(self) -> HashMap<&'static str, Value<'static>> {
+        let level = match self {
+            Urgency::Low => 0u8,
+            Urgency::Normal => 1,
+            Urgency::Critical => 2,
+        };
+        HashMap::from([("urgency", Value::U8(level))])
+    }
+}
 
 pub struct NotificationOverlay {
+    notifications: NotificationsProxyBlocking<'static>,
     current_notification_id: Mutex<Option<u32>>,
+    /// When the current Processing state began, for the elapsed time in progress updates
+    processing_since: Mutex<Option<Instant>>,
+    templates: NotificationTemplates,
+    /// What the template placeholders stand for
+    context: Mutex<Context>,
+}
+
+#[derive(Default)]
+struct Context {
+    language: String,
+    mode: String,
+    recording_since: Option<Instant>,
+    /// Length of the last recording
+    recorded: Duration,
+    /// Characters of the text being written
+    chars: usize,
 }
 
 impl NotificationOverlay {
-    pub fn new() -> Result<Self, OverlayError> {
+    pub fn new(templates: NotificationTemplates) -> Result<Self, OverlayError> {
+        let connection = zbus::blocking::Connection::session()?;
         Ok(Self {
+            notifications: NotificationsProxyBlocking::new(&connection)?,
             current_notification_id: Mutex::new(None),
+            processing_since: Mutex::new(None),
+            templates,
+            context: Mutex::new(Context::default()),
         })
     }
 
-    fn send_notification(&self, message: &str, urgency: &str) {
-        let mut cmd = Command::new("notify-send");
-        cmd.arg("--app-name=croaker")
-            .arg(format!("--urgency={}", urgency))
-            .arg("croaker")
-            .arg(message);
+    fn send_notification(&self, message: &str, urgency: Urgency) {
+        self.notify(DEFAULT_TITLE, message, urgency, None);
+    }
 
-        if let Ok(mut id_guard) = self.current_notification_id.lock() {
-            if let Some(id) = *id_guard {
-                cmd.arg(format!("--replace-id={}", id));
-            }
-        }
+    /// Show the notification for a state, from its template or the built-in text.
+    fn send_templated(&self, template: &NotificationTemplate, state: &str, body: &str, reason: &str, urgency: Urgency) {
+        let values = self.values(state, reason);
+        let title = render(template.title.as_deref().unwrap_or(DEFAULT_TITLE), &values);
+        let body = render(template.body.as_deref().unwrap_or(body), &values);
+        self.notify(&title, &body, urgency, template.icon.as_deref());
+    }
 
-        if let Ok(output) = cmd.output() {
-            if let Ok(id_str) = String::from_utf8(output.stdout) {
-                if let Ok(id) = id_str.trim().parse::<u32>() {
-                    if let Ok(mut id_guard) = self.current_notification_id.lock() {
-                        *id_guard = Some(id);
-                    }
-                }
-            }
+    fn values(&self, state: &str, reason: &str) -> [(&'static str, String); 6] {
+        let context = self.context.lock().unwrap();
+        [
+            ("state", state.to_string()),
+            ("language", context.language.to_uppercase()),
+            ("mode", context.mode.clone()),
+            ("duration", context.recorded.as_secs().to_string()),
+            ("chars", context.chars.to_string()),
+            ("reason", reason.to_string()),
+        ]
+    }
+
+    /// Show a notification in place of the current one, if there is one.
+    fn notify(&self, title: &str, message: &str, urgency: Urgency, icon: Option<&str>) {
+        let Ok(mut id_guard) = self.current_notification_id.lock() else {
+            return;
+        };
+        let replaces = id_guard.unwrap_or(0);
+        match self.notifications.notify(
+            APP_NAME,
+            replaces,
+            icon.unwrap_or(""),
+            title,
+            message,
+            &[],
+            urgency.hints(),
+            DEFAULT_EXPIRY,
+        ) {
+            Ok(id) => *id_guard = Some(id),
+            Err(e) => tracing::warn!("Failed to show notification: {}", e),
         }
     }
 }
 
 impl Overlay for NotificationOverlay {
     fn update_state(&self, state: DaemonState) {
-        let (message, urgency) = match state {
-            DaemonState::Recording => ("Recording...", "normal"),
-            DaemonState::Processing => ("Processing...", "normal"),
-            DaemonState::Outputting => ("Outputting...", "normal"),
-            DaemonState::Idle => return,
-        };
-        
-        self.send_notification(message, urgency);
+        if let Ok(mut since) = self.processing_since.lock() {
+            *since = (state == DaemonState::Processing).then(Instant::now);
+        }
+        if let Ok(mut context) = self.context.lock() {
+            if state == DaemonState::Recording {
+                context.recording_since = Some(Instant::now());
+                context.chars = 0;
+            } else if let Some(since) = context.recording_since.take() {
+                context.recorded = since.elapsed();
+            }
+        }
+        let templates = &self.templates;
+        match state {
+            DaemonState::Recording => self.send_templated(&templates.recording, "Recording", "Recording...", "", Urgency::Normal),
+            DaemonState::Processing => self.send_templated(&templates.processing, "Processing", "Processing...", "", Urgency::Normal),
+            DaemonState::Outputting => self.send_templated(&templates.outputting, "Outputting", "Outputting...", "", Urgency::Normal),
+            DaemonState::Error(reason) => self.send_templated(&templates.error, "Error", "Error: {reason}", &reason, Urgency::Critical),
+            DaemonState::Idle => {}
+        }
     }
 
     fn update_audio_level(&self, _level: f32) {
@@ -56,11 +167,72 @@ impl Overlay for NotificationOverlay {
     }
 
     fn update_output_mode(&self, mode: &str) {
-        self.send_notification(&format!("Output mode: {}", mode), "normal");
+        if let Ok(mut context) = self.context.lock() {
+            context.mode = mode.to_string();
+        }
+        self.send_notification(&format!("Output mode: {}", mode), Urgency::Normal);
     }
 
     fn update_language(&self, language: &str) {
-        self.send_notification(&format!("Language: {}", language.to_uppercase()), "normal");
+        if let Ok(mut context) = self.context.lock() {
+            context.language = language.to_string();
+        }
+        self.send_notification(&format!("Language: {}", language.to_uppercase()), Urgency::Normal);
+    }
+
+    fn update_translate(&self, enabled: bool) {
+        let message = if enabled { "Translation: on (output English)" } else { "Translation: off" };
+        self.send_notification(message, Urgency::Normal);
+    }
+
+    fn update_profile(&self, profile: Option<&str>) {
+        self.send_notification(&format!("Profile: {}", profile.unwrap_or("none")), Urgency::Normal);
+    }
+
+    fn update_paused(&self, paused: bool) {
+        let message = if paused { "Hotkeys paused (croaker resume to listen again)" } else { "Hotkeys resumed" };
+        self.send_notification(message, Urgency::Normal);
+    }
+
+    fn show_notice(&self, message: &str) {
+        self.send_notification(message, Urgency::Low);
+    }
+
+    fn show_warning(&self, message: &str) {
+        self.send_notification(message, Urgency::Critical);
+    }
+
+    fn show_countdown(&self, seconds: u32) {
+        // Replaces the previous second's notification like the state messages do
+        self.send_notification(&format!("Typing in {}...", seconds), Urgency::Normal);
+    }
+
+    fn update_locked(&self, locked: bool) {
+        // Unlocking stops the recording, and the state change says so
+        if locked {
+            self.send_notification("Recording locked, press push-to-talk to stop", Urgency::Normal);
+        }
+    }
+
+    fn show_transcription(&self, text: &str) {
+        self.send_notification(&super::transcription_preview(text), Urgency::Low);
+    }
+
+    fn update_output_length(&self, chars: usize) {
+        if let Ok(mut context) = self.context.lock() {
+            context.chars = chars;
+        }
+    }
+
+    fn update_progress(&self, step: Step) {
+        // Replaces the "Processing..." notification; no timer, a notification per second would be noise
+        let since = self.processing_since.lock().ok().and_then(|since| *since);
+        if let Some(since) = since {
+            let template = &self.templates.processing;
+            // The body says which step it's at; title and icon stay the processing template's
+            let title = render(template.title.as_deref().unwrap_or(DEFAULT_TITLE), &self.values("Processing", ""));
+            self.notify(&title, &super::progress_label(since.elapsed(), Some(step)), Urgency::Normal, template.icon.as_deref());
+        }
     }
 
     fn show(&self) {
@@ -68,14 +240,70 @@ impl Overlay for NotificationOverlay {
     }
 
     fn hide(&self) {
-        // Close current notification
-        if let Ok(id_guard) = self.current_notification_id.lock() {
-            if let Some(id) = *id_guard {
-                let _ = Command::new("notify-send")
-                    .arg(format!("--close={}", id))
-                    .output();
+        // Close current notification; the next one starts fresh rather than replacing it
+        if let Ok(mut id_guard) = self.current_notification_id.lock() {
+            if let Some(id) = id_guard.take() {
+                if let Err(e) = self.notifications.close_notification(id) {
+                    tracing::debug!("Failed to close notification {}: {}", id, e);
+                }
             }
         }
     }
 }
 
+/// Replace each `{name}` in `template` with its value; unknown placeholders are left as they are.
+fn render(template: &str, values: &[(&str, String)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |text, (name, value)| text.replace(&format!("{{{}}}", name), value))
+}
+
+/// Show a failure with a Retry action and wait for the answer: whether Retry was clicked
+/// before the notification was closed.
+pub async fn ask_retry(reason: &str) -> Result<bool, zbus::Error> {
+    let connection = zbus::Connection::session().await?;
+    let notifications = NotificationsProxy::new(&connection).await?;
+    // Subscribe first so a quick click isn't missed
+    let mut invoked = notifications.receive_action_invoked().await?;
+    let mut closed = notifications.receive_notification_closed().await?;
+    let id = notifications
+        .notify(
+            APP_NAME,
+            0,
+            "",
+            "croaker: dictation failed",
+            reason,
+            &["retry", "Retry"],
+            Urgency::Critical.hints(),
+            DEFAULT_EXPIRY,
+        )
+        .await?;
+    loop {
+        tokio::select! {
+            Some(signal) = invoked.next() => {
+                let args = signal.args()?;
+                if args.id == id {
+                    return Ok(args.action_key == "retry");
+                }
+            }
+            Some(signal) = closed.next() => {
+                if signal.args()?.id == id {
+                    return Ok(false);
+                }
+            }
+            else => return Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_fills_known_placeholders() {
+        let values = [("language", "TR".to_string()), ("duration", "12".to_string())];
+        assert_eq!(render("Recorded {duration}s ({language})", &values), "Recorded 12s (TR)");
+        assert_eq!(render("{unknown} stays", &values), "{unknown} stays");
+    }
+}
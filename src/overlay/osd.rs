@@ -0,0 +1,185 @@
+// On-screen display backends for setups that already run one: `wob` gets a bar value written to
+// its pipe, `swayosd` gets a message and icon through swayosd-client. Neither draws a window of
+// croaker's own.
+
+use crate::daemon::state::{DaemonState, Step};
+use crate::overlay::Overlay;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Mutex;
+
+/// Writes how far the dictation has got to wob's pipe: a full bar while recording, filling up
+/// again through processing, and the seconds left during a countdown.
+pub struct WobOverlay {
+    pipe: PathBuf,
+    /// First value of the running countdown, so the bar can shrink towards 0
+    countdown_from: Mutex<u32>,
+}
+
+impl WobOverlay {
+    /// `pipe` empty means `$XDG_RUNTIME_DIR/wob.sock`, where the sway and Hyprland examples put it.
+    pub fn new(pipe: &str) -> Self {
+        let pipe = if pipe.is_empty() {
+            dirs::runtime_dir().unwrap_or_else(std::env::temp_dir).join("wob.sock")
+        } else {
+            PathBuf::from(pipe)
+        };
+        Self {
+            pipe,
+            countdown_from: Mutex::new(0),
+        }
+    }
+
+    fn send(&self, value: u32) {
+        // Non-blocking, so a pipe nobody reads fails right away instead of hanging the overlay
+        let result = OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(&self.pipe)
+            .and_then(|mut pipe| writeln!(pipe, "{}", value.min(100)));
+        if let Err(e) = result {
+            tracing::debug!("Can't write to wob pipe {}: {}", self.pipe.display(), e);
+        }
+    }
+}
+
+impl Overlay for WobOverlay {
+    fn update_state(&self, state: DaemonState) {
+        match state {
+            DaemonState::Recording | DaemonState::Outputting => self.send(100),
+            DaemonState::Processing => self.send(25),
+            // wob hides the bar by itself after its timeout
+            DaemonState::Idle | DaemonState::Error(_) => {}
+        }
+    }
+
+    fn update_progress(&self, step: Step) {
+        match step {
+            Step::Transcribing => self.send(50),
+            Step::CleaningUp => self.send(75),
+        }
+    }
+
+    fn show_countdown(&self, seconds: u32) {
+        let from = {
+            let mut from = self.countdown_from.lock().unwrap();
+            if seconds > *from {
+                *from = seconds;
+            }
+            *from
+        };
+        self.send(seconds * 100 / from);
+        if seconds == 1 {
+            *self.countdown_from.lock().unwrap() = 0;
+        }
+    }
+
+    // A bar has no room for text
+    fn update_audio_level(&self, _level: f32) {}
+    fn update_output_mode(&self, _mode: &str) {}
+    fn update_language(&self, _language: &str) {}
+    fn update_translate(&self, _enabled: bool) {}
+    fn update_profile(&self, _profile: Option<&str>) {}
+    fn update_paused(&self, _paused: bool) {}
+    fn show_notice(&self, _message: &str) {}
+    fn show_warning(&self, _message: &str) {}
+    fn update_locked(&self, _locked: bool) {}
+    fn show_transcription(&self, _text: &str) {}
+    fn update_output_length(&self, _chars: usize) {}
+    fn show(&self) {}
+    fn hide(&self) {}
+}
+
+/// Shows states and messages through `swayosd-client --custom-message`.
+pub struct SwayOsdOverlay;
+
+impl SwayOsdOverlay {
+    fn send(&self, message: &str, icon: &str) {
+        let output = Command::new("swayosd-client")
+            .arg(format!("--custom-message={}", message))
+            .arg(format!("--custom-icon={}", icon))
+            .output();
+        match output {
+            Ok(output) if !output.status.success() => {
+                tracing::debug!("swayosd-client failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+            }
+            Ok(_) => {}
+            Err(e) => tracing::debug!("Can't run swayosd-client: {}", e),
+        }
+    }
+}
+
+impl Overlay for SwayOsdOverlay {
+    fn update_state(&self, state: DaemonState) {
+        match state {
+            DaemonState::Recording => self.send("Recording", "media-record"),
+            DaemonState::Processing => self.send("Processing", "view-refresh"),
+            DaemonState::Outputting => self.send("Typing", "dialog-ok"),
+            DaemonState::Error(reason) => self.send(&format!("Error: {}", reason), "dialog-error"),
+            DaemonState::Idle => {}
+        }
+    }
+
+    fn update_progress(&self, step: Step) {
+        let label = step.label();
+        let mut message = label[..1].to_uppercase();
+        message.push_str(&label[1..]);
+        self.send(&message, "view-refresh");
+    }
+
+    fn show_countdown(&self, seconds: u32) {
+        self.send(&format!("Typing in {}...", seconds), "dialog-ok");
+    }
+
+    fn update_output_mode(&self, mode: &str) {
+        self.send(&format!("Output mode: {}", mode), "audio-input-microphone");
+    }
+
+    fn update_language(&self, language: &str) {
+        self.send(&format!("Language: {}", language.to_uppercase()), "audio-input-microphone");
+    }
+
+    fn update_translate(&self, enabled: bool) {
+        let message = if enabled { "Translation: on" } else { "Translation: off" };
+        self.send(message, "audio-input-microphone");
+    }
+
+    fn update_profile(&self, profile: Option<&str>) {
+        self.send(&format!("Profile: {}", profile.unwrap_or("none")), "audio-input-microphone");
+    }
+
+    fn update_paused(&self, paused: bool) {
+        if paused {
+            self.send("Hotkeys paused", "microphone-sensitivity-muted");
+        } else {
+            self.send("Hotkeys resumed", "audio-input-microphone");
+        }
+    }
+
+    fn show_notice(&self, message: &str) {
+        self.send(message, "dialog-information");
+    }
+
+    fn show_warning(&self, message: &str) {
+        self.send(message, "dialog-warning");
+    }
+
+    fn update_locked(&self, locked: bool) {
+        if locked {
+            self.send("Recording locked", "media-record");
+        }
+    }
+
+    fn show_transcription(&self, text: &str) {
+        self.send(&super::transcription_preview(text), "dialog-ok");
+    }
+
+    fn update_audio_level(&self, _level: f32) {}
+    fn update_output_length(&self, _chars: usize) {}
+    // The OSD fades out by itself
+    fn show(&self) {}
+    fn hide(&self) {}
+}
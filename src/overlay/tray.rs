@@ -1,5 +1,5 @@
-use crate::daemon::state::DaemonState;
-use crate::overlay::OverlayMessage;
+use crate::daemon::state::{DaemonState, Step};
+use crate::overlay::{OverlayMessage, Theme};
 use ksni::{self, Icon, ToolTip};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
@@ -7,38 +7,68 @@ use std::time::{Duration, Instant};
 /// System tray icon for croaker
 pub struct CroakerTray {
     state: Arc<Mutex<TrayState>>,
+    /// Session profiles offered in the menu, in name order
+    profiles: Vec<String>,
+    theme: Theme,
 }
 
 struct TrayState {
     daemon_state: DaemonState,
     output_mode: String,
     language: String,
+    translate: bool,
+    /// Active session profile
+    profile: Option<String>,
+    /// Hotkeys paused (`croaker pause`)
+    paused: bool,
+    /// Recording latched on by a push-to-talk double tap
+    locked: bool,
+    /// When Processing began, and the step it has reached
+    processing: Option<(Instant, Option<Step>)>,
+    /// Elapsed seconds last shown for Processing, to refresh once a second
+    progress_shown: u64,
     temporary_message: Option<(String, Instant)>,
     flash_until: Option<Instant>,
 }
 
-impl CroakerTray {
-    pub fn new() -> Self {
-        Self::with_state(Arc::new(Mutex::new(TrayState {
-            daemon_state: DaemonState::Idle,
-            output_mode: "Both".to_string(),
-            language: "en".to_string(),
-            temporary_message: None,
-            flash_until: None,
-        })))
+impl TrayState {
+    fn language_label(&self) -> String {
+        if self.translate {
+            format!("{} → EN", self.language.to_uppercase())
+        } else {
+            self.language.to_uppercase()
+        }
+    }
+
+    fn progress_label(&self) -> String {
+        match self.processing {
+            Some((since, step)) => crate::overlay::progress_label(since.elapsed(), step),
+            None => "Processing...".to_string(),
+        }
+    }
+
+    fn mode_label(&self) -> String {
+        match self.profile {
+            Some(ref profile) => format!("{} | Profile: {}", self.output_mode, profile),
+            None => self.output_mode.clone(),
+        }
     }
+}
 
-    fn with_state(state: Arc<Mutex<TrayState>>) -> Self {
-        Self { state }
+impl CroakerTray {
+    fn with_state(state: Arc<Mutex<TrayState>>, profiles: Vec<String>, theme: Theme) -> Self {
+        Self { state, profiles, theme }
     }
     
     fn get_icon_name(&self) -> String {
         let state = self.state.lock().unwrap();
         match state.daemon_state {
+            DaemonState::Idle if state.paused => "microphone-sensitivity-muted".to_string(),
             DaemonState::Idle => "audio-input-microphone".to_string(),
             DaemonState::Recording => "media-record".to_string(),
             DaemonState::Processing => "view-refresh".to_string(),
             DaemonState::Outputting => "dialog-ok".to_string(),
+            DaemonState::Error(_) => "dialog-error".to_string(),
         }
     }
     
@@ -53,27 +83,25 @@ impl CroakerTray {
         }
         
         let status = match state.daemon_state {
-            DaemonState::Idle => "Ready",
-            DaemonState::Recording => "● Recording...",
-            DaemonState::Processing => "Processing...",
-            DaemonState::Outputting => "Outputting...",
+            DaemonState::Idle if state.paused => "Paused (hotkeys off)".to_string(),
+            DaemonState::Idle => "Ready".to_string(),
+            DaemonState::Recording if state.locked => "● Recording (locked)...".to_string(),
+            DaemonState::Recording => "● Recording...".to_string(),
+            DaemonState::Processing => state.progress_label(),
+            DaemonState::Outputting => "Outputting...".to_string(),
+            DaemonState::Error(ref reason) => format!("Error: {}", reason),
         };
         
         // Show temporary message if present, otherwise show normal tooltip
         if let Some((ref msg, _)) = state.temporary_message {
             format!("{}\n\nCroaker: {}\nMode: {} | Lang: {}", 
-                msg, status, state.output_mode, state.language.to_uppercase())
+                msg, status, state.mode_label(), state.language_label())
         } else {
             format!("Croaker: {}\nMode: {} | Lang: {}", 
-                status, state.output_mode, state.language.to_uppercase())
+                status, state.mode_label(), state.language_label())
         }
     }
     
-    fn show_temporary_message(state: &Arc<Mutex<TrayState>>, message: String) {
-        let mut tray_state = state.lock().unwrap();
-        tray_state.temporary_message = Some((message, Instant::now()));
-    }
-    
     fn get_color(&self) -> (u8, u8, u8) {
         let state = self.state.lock().unwrap();
         
@@ -84,18 +112,29 @@ impl CroakerTray {
             }
         }
         
+        let theme = &self.theme;
         match state.daemon_state {
-            DaemonState::Idle => (128, 128, 128),      // Grey
-            DaemonState::Recording => (255, 60, 60),   // Red
-            DaemonState::Processing => (255, 180, 60), // Orange
-            DaemonState::Outputting => (60, 200, 60),  // Green
+            DaemonState::Idle if state.paused => theme.paused,
+            DaemonState::Idle => theme.idle,
+            DaemonState::Recording => theme.recording,
+            DaemonState::Processing => theme.processing,
+            DaemonState::Outputting => theme.outputting,
+            DaemonState::Error(_) => theme.error,
         }
     }
 }
 
+/// Tells two instances' tray icons apart
+fn instance_title() -> String {
+    match crate::daemon::instance::name() {
+        Some(name) => format!("Croaker ({})", name),
+        None => "Croaker".to_string(),
+    }
+}
+
 impl ksni::Tray for CroakerTray {
     fn id(&self) -> String {
-        "croaker".to_string()
+        crate::daemon::instance::file_stem()
     }
     
     fn icon_name(&self) -> String {
@@ -103,12 +142,12 @@ impl ksni::Tray for CroakerTray {
     }
     
     fn title(&self) -> String {
-        "Croaker".to_string()
+        instance_title()
     }
     
     fn tool_tip(&self) -> ToolTip {
         ToolTip {
-            title: "Croaker".to_string(),
+            title: instance_title(),
             description: self.get_tooltip(),
             icon_name: self.get_icon_name(),
             icon_pixmap: vec![],
@@ -165,31 +204,96 @@ impl ksni::Tray for CroakerTray {
         
         let state = self.state.lock().unwrap();
         let status_text = match state.daemon_state {
-            DaemonState::Idle => format!("Ready | {} | [{}]", state.output_mode, state.language.to_uppercase()),
+            DaemonState::Idle if state.paused => "Paused (hotkeys off)".to_string(),
+            DaemonState::Idle => format!("Ready | {} | [{}]", state.mode_label(), state.language_label()),
             DaemonState::Recording => "● Recording...".to_string(),
-            DaemonState::Processing => "◐ Processing...".to_string(),
+            DaemonState::Processing => format!("◐ {}", state.progress_label()),
             DaemonState::Outputting => "✓ Outputting...".to_string(),
+            DaemonState::Error(ref reason) => format!("⚠ {}", reason),
         };
+        // "No profile" first, then the configured ones
+        let selected = state
+            .profile
+            .as_ref()
+            .and_then(|active| self.profiles.iter().position(|p| p == active))
+            .map_or(0, |index| index + 1);
+        let paused = state.paused;
         drop(state);
         
-        vec![
+        let mut items = vec![
             StandardItem {
                 label: status_text,
                 enabled: false,
                 ..Default::default()
             }.into(),
             MenuItem::Separator,
+            StandardItem {
+                label: if paused { "Resume hotkeys" } else { "Pause hotkeys" }.to_string(),
+                activate: Box::new(move |_| send_command(if paused { "resume" } else { "pause" }.to_string())),
+                ..Default::default()
+            }.into(),
+        ];
+        if !self.profiles.is_empty() {
+            let options = std::iter::once("No profile".to_string())
+                // Single underscores would be taken as access keys
+                .chain(self.profiles.iter().map(|p| p.replace('_', "__")))
+                .map(|label| RadioItem { label, ..Default::default() })
+                .collect();
+            items.push(
+                SubMenu {
+                    label: "Profile".to_string(),
+                    submenu: vec![RadioGroup {
+                        selected,
+                        select: Box::new(|tray: &mut Self, index| {
+                            let command = match index.checked_sub(1).and_then(|i| tray.profiles.get(i)) {
+                                Some(profile) => format!("set-profile {}", profile),
+                                None => "clear-profile".to_string(),
+                            };
+                            send_command(command);
+                        }),
+                        options,
+                    }
+                    .into()],
+                    ..Default::default()
+                }
+                .into(),
+            );
+        }
+        items.push(MenuItem::Separator);
+        items.push(
             StandardItem {
                 label: "Quit".to_string(),
-                activate: Box::new(|_| std::process::exit(0)),
+                activate: Box::new(|_| send_command("quit".to_string())),
                 ..Default::default()
             }.into(),
-        ]
+        );
+        items
     }
 }
 
+/// Send a plain command to the daemon's socket off the menu thread. The daemon answers
+/// through the overlay channel, so only failures matter here.
+fn send_command(command: String) {
+    std::thread::spawn(move || {
+        use std::io::{BufRead, Write};
+        let result = crate::input::socket::SocketServer::socket_path()
+            .and_then(std::os::unix::net::UnixStream::connect)
+            .and_then(|mut stream| {
+                writeln!(stream, "{}", command)?;
+                let mut reply = String::new();
+                std::io::BufReader::new(stream).read_line(&mut reply)?;
+                Ok(reply)
+            });
+        match result {
+            Ok(reply) if reply.trim() == "ok" => {}
+            Ok(_) => tracing::warn!("Tray command {:?} failed, see the daemon log", command),
+            Err(e) => tracing::warn!("Tray command {:?} failed: {}", command, e),
+        }
+    });
+}
+
 /// Run the system tray. This blocks and processes messages.
-pub fn run_tray(message_rx: std::sync::mpsc::Receiver<OverlayMessage>) -> anyhow::Result<()> {
+pub fn run_tray(message_rx: std::sync::mpsc::Receiver<OverlayMessage>, profiles: Vec<String>, theme: Theme) -> anyhow::Result<()> {
     use ksni::blocking::TrayMethods;
     
     // NOTE: When croaker is auto-started very early in a login session, the StatusNotifierWatcher
@@ -200,6 +304,12 @@ pub fn run_tray(message_rx: std::sync::mpsc::Receiver<OverlayMessage>) -> anyhow
         daemon_state: DaemonState::Idle,
         output_mode: "Both".to_string(),
         language: "en".to_string(),
+        translate: false,
+        profile: None,
+        paused: false,
+        locked: false,
+        processing: None,
+        progress_shown: 0,
         temporary_message: None,
         flash_until: None,
     }));
@@ -222,7 +332,7 @@ pub fn run_tray(message_rx: std::sync::mpsc::Receiver<OverlayMessage>) -> anyhow
                 );
             }
 
-            let tray = CroakerTray::with_state(Arc::clone(&state));
+            let tray = CroakerTray::with_state(Arc::clone(&state), profiles.clone(), theme);
             match tray.spawn() {
                 Ok(handle) => {
                     tracing::info!("System tray started");
@@ -245,8 +355,22 @@ pub fn run_tray(message_rx: std::sync::mpsc::Receiver<OverlayMessage>) -> anyhow
                     let mut tray_state = state.lock().unwrap();
                     match msg {
                         OverlayMessage::State(daemon_state) => {
+                            if daemon_state != DaemonState::Recording {
+                                tray_state.locked = false;
+                            }
+                            tray_state.processing = (daemon_state == DaemonState::Processing).then(|| (Instant::now(), None));
+                            tray_state.progress_shown = 0;
                             tray_state.daemon_state = daemon_state;
                         }
+                        OverlayMessage::Progress(step) => {
+                            if let Some((_, ref mut current)) = tray_state.processing {
+                                *current = Some(step);
+                            }
+                        }
+                        OverlayMessage::Locked(locked) => {
+                            tray_state.locked = locked;
+                            tray_state.flash_until = Some(Instant::now() + Duration::from_millis(500));
+                        }
                         OverlayMessage::OutputMode(mode) => {
                             tray_state.output_mode = mode.clone();
                             // Show temporary message for mode change (in tooltip)
@@ -256,7 +380,7 @@ pub fn run_tray(message_rx: std::sync::mpsc::Receiver<OverlayMessage>) -> anyhow
                             // Show a brief notification that appears near the tray icon
                             drop(tray_state);
                             let _ = std::process::Command::new("notify-send")
-                                .args(&[
+                                .args([
                                     "--app-name=croaker",
                                     "--urgency=low",
                                     "--expire-time=2000",
@@ -276,7 +400,7 @@ pub fn run_tray(message_rx: std::sync::mpsc::Receiver<OverlayMessage>) -> anyhow
                             // Show a brief notification that appears near the tray icon
                             drop(tray_state);
                             let _ = std::process::Command::new("notify-send")
-                                .args(&[
+                                .args([
                                     "--app-name=croaker",
                                     "--urgency=low",
                                     "--expire-time=2000",
@@ -287,6 +411,125 @@ pub fn run_tray(message_rx: std::sync::mpsc::Receiver<OverlayMessage>) -> anyhow
                                 ])
                                 .spawn();
                         }
+                        OverlayMessage::Translate(enabled) => {
+                            tray_state.translate = enabled;
+                            let message = if enabled { "Translation: on (output English)" } else { "Translation: off" };
+                            tray_state.temporary_message = Some((message.to_string(), Instant::now()));
+                            tray_state.flash_until = Some(Instant::now() + Duration::from_millis(500));
+                            drop(tray_state);
+                            let _ = std::process::Command::new("notify-send")
+                                .args([
+                                    "--app-name=croaker",
+                                    "--urgency=low",
+                                    "--expire-time=2000",
+                                    "--hint=int:transient:1",
+                                    "--hint=string:x-croaker-tray:true",
+                                    "croaker",
+                                    message
+                                ])
+                                .spawn();
+                        }
+                        OverlayMessage::Profile(profile) => {
+                            let message = format!("Profile: {}", profile.as_deref().unwrap_or("none"));
+                            tray_state.profile = profile;
+                            tray_state.temporary_message = Some((message.clone(), Instant::now()));
+                            tray_state.flash_until = Some(Instant::now() + Duration::from_millis(500));
+                            drop(tray_state);
+                            let _ = std::process::Command::new("notify-send")
+                                .args([
+                                    "--app-name=croaker",
+                                    "--urgency=low",
+                                    "--expire-time=2000",
+                                    "--hint=int:transient:1",
+                                    "--hint=string:x-croaker-tray:true",
+                                    "croaker",
+                                    &message
+                                ])
+                                .spawn();
+                        }
+                        OverlayMessage::Paused(paused) => {
+                            tray_state.paused = paused;
+                            let message = if paused { "Hotkeys paused" } else { "Hotkeys resumed" };
+                            tray_state.temporary_message = Some((message.to_string(), Instant::now()));
+                            tray_state.flash_until = Some(Instant::now() + Duration::from_millis(500));
+                            drop(tray_state);
+                            let _ = std::process::Command::new("notify-send")
+                                .args([
+                                    "--app-name=croaker",
+                                    "--urgency=low",
+                                    "--expire-time=2000",
+                                    "--hint=int:transient:1",
+                                    "--hint=string:x-croaker-tray:true",
+                                    "croaker",
+                                    message
+                                ])
+                                .spawn();
+                        }
+                        OverlayMessage::Notice(message) => {
+                            tray_state.temporary_message = Some((message.clone(), Instant::now()));
+                            drop(tray_state);
+                            let _ = std::process::Command::new("notify-send")
+                                .args([
+                                    "--app-name=croaker",
+                                    "--urgency=low",
+                                    "--expire-time=2000",
+                                    "--hint=int:transient:1",
+                                    "--hint=string:x-croaker-tray:true",
+                                    "croaker",
+                                    &message
+                                ])
+                                .spawn();
+                        }
+                        OverlayMessage::Transcribed(text) => {
+                            let message = crate::overlay::transcription_preview(&text);
+                            tray_state.temporary_message = Some((message.clone(), Instant::now()));
+                            drop(tray_state);
+                            let _ = std::process::Command::new("notify-send")
+                                .args([
+                                    "--app-name=croaker",
+                                    "--urgency=low",
+                                    "--expire-time=3000",
+                                    "--hint=int:transient:1",
+                                    "--hint=string:x-croaker-tray:true",
+                                    "croaker",
+                                    &message
+                                ])
+                                .spawn();
+                        }
+                        OverlayMessage::Warning(message) => {
+                            tray_state.temporary_message = Some((message.clone(), Instant::now()));
+                            tray_state.flash_until = Some(Instant::now() + Duration::from_millis(500));
+                            drop(tray_state);
+                            let _ = std::process::Command::new("notify-send")
+                                .args([
+                                    "--app-name=croaker",
+                                    "--urgency=critical",
+                                    "--expire-time=5000",
+                                    "--hint=string:x-croaker-tray:true",
+                                    "croaker",
+                                    &message
+                                ])
+                                .spawn();
+                        }
+                        OverlayMessage::Countdown(seconds) => {
+                            let message = format!("Typing in {}...", seconds);
+                            tray_state.temporary_message = Some((message.clone(), Instant::now()));
+                            tray_state.flash_until = Some(Instant::now() + Duration::from_millis(500));
+                            drop(tray_state);
+                            // The synchronous hint makes each second replace the previous bubble
+                            let _ = std::process::Command::new("notify-send")
+                                .args([
+                                    "--app-name=croaker",
+                                    "--urgency=normal",
+                                    "--expire-time=1200",
+                                    "--hint=int:transient:1",
+                                    "--hint=string:x-canonical-private-synchronous:croaker-countdown",
+                                    "--hint=string:x-croaker-tray:true",
+                                    "croaker",
+                                    &message
+                                ])
+                                .spawn();
+                        }
                         _ => {}
                     }
                 }
@@ -309,6 +552,15 @@ pub fn run_tray(message_rx: std::sync::mpsc::Receiver<OverlayMessage>) -> anyhow
                         }
                     }
                     
+                    // Tick the elapsed time while processing
+                    if let Some((since, _)) = tray_state.processing {
+                        let elapsed = since.elapsed().as_secs();
+                        if elapsed != tray_state.progress_shown {
+                            tray_state.progress_shown = elapsed;
+                            updated = true;
+                        }
+                    }
+                    
                     // Clear expired flash
                     if let Some(flash_time) = tray_state.flash_until {
                         if flash_time.elapsed() >= Duration::from_millis(500) {
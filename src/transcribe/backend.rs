@@ -0,0 +1,70 @@
+use crate::audio::Recording;
+use crate::config::Config;
+use async_trait::async_trait;
+use std::sync::Arc;
+use thiserror::Error;
+
+use super::whisper::{WhisperClient, WhisperError};
+
+#[derive(Debug, Error)]
+pub enum TranscriptionError {
+    #[error(transparent)]
+    WhisperError(#[from] WhisperError),
+    #[error("Unknown transcription provider: {0}")]
+    UnknownProvider(String),
+    #[error("Provider {0} doesn't support translation")]
+    TranslationUnsupported(&'static str),
+    #[cfg_attr(feature = "vosk", allow(dead_code))]
+    #[error("Provider {0} isn't available in this build (rebuild with `--features {0}`)")]
+    ProviderUnavailable(&'static str),
+    #[cfg_attr(not(feature = "vosk"), allow(dead_code))]
+    #[error("Vosk error: {0}")]
+    VoskError(String),
+}
+
+impl TranscriptionError {
+    /// Whether the provider couldn't be reached at all, so retrying later may succeed.
+    pub fn is_network(&self) -> bool {
+        match self {
+            TranscriptionError::WhisperError(WhisperError::RequestError(e)) => {
+                e.is_connect() || e.is_timeout() || e.is_request()
+            }
+            TranscriptionError::WhisperError(WhisperError::Timeout(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Something that turns a recording into text.
+///
+/// The state machine only talks to this trait, so adding a provider means
+/// implementing it and registering it in `create_backend`.
+#[async_trait]
+pub trait TranscriptionBackend: Send + Sync {
+    /// Provider name as used in `[transcription] provider`.
+    fn name(&self) -> &'static str;
+
+    /// Model used for `transcribe`, or for `translate` when `translate` is set.
+    fn model(&self, translate: bool) -> &str;
+
+    /// Transcribe the recording. An empty `language` lets the provider detect it.
+    async fn transcribe(&self, recording: &Recording, language: &str) -> Result<String, TranscriptionError>;
+
+    /// Transcribe speech in any language straight to English text.
+    async fn translate(&self, _recording: &Recording) -> Result<String, TranscriptionError> {
+        Err(TranscriptionError::TranslationUnsupported(self.name()))
+    }
+}
+
+/// Build the backend selected by `[transcription] provider`.
+pub fn create_backend(config: &Config, api_key: &str) -> Result<Arc<dyn TranscriptionBackend>, TranscriptionError> {
+    match config.transcription.provider.as_str() {
+        "groq" => Ok(Arc::new(WhisperClient::new(config.clone(), api_key.to_string()))),
+        "mock" => Ok(Arc::new(super::mock::MockBackend::new(config))),
+        #[cfg(feature = "vosk")]
+        "vosk" => Ok(Arc::new(super::vosk::VoskBackend::new(config)?)),
+        #[cfg(not(feature = "vosk"))]
+        "vosk" => Err(TranscriptionError::ProviderUnavailable("vosk")),
+        other => Err(TranscriptionError::UnknownProvider(other.to_string())),
+    }
+}
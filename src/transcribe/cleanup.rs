@@ -1,7 +1,10 @@
 use crate::config::Config;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use thiserror::Error;
+use tokio::sync::mpsc;
 use tokio::time::{timeout, Duration};
 
 #[derive(Debug, Error)]
@@ -22,6 +25,8 @@ struct ChatRequest {
     messages: Vec<ChatMessage>,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f64>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -45,12 +50,33 @@ struct Message {
     content: String,
 }
 
+/// One `data:` event of a streamed chat completion
+#[derive(Debug, Deserialize)]
+struct StreamChunk {
+    choices: Vec<StreamChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamChoice {
+    #[serde(default)]
+    delta: Delta,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Delta {
+    content: Option<String>,
+}
+
 #[derive(Clone)]
 pub struct CleanupClient {
     client: Client,
     config: Config,
     api_key: String,
     prompt: String,
+    /// Starts as `groq.cleanup_enabled`, switched at runtime by `croaker set-cleanup`
+    enabled: Arc<AtomicBool>,
+    /// With the mock provider, cleanup returns the text unchanged after `mock.delay_ms`
+    mock: bool,
 }
 
 fn strip_output_tags(s: &str) -> String {
@@ -85,71 +111,176 @@ impl CleanupClient {
 
         Ok(Self {
             client,
+            enabled: Arc::new(AtomicBool::new(config.groq.cleanup_enabled)),
+            mock: config.transcription.provider == "mock",
             config,
             api_key,
             prompt,
         })
     }
 
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Whether an API key was loaded (offline providers run without one when cleanup is off)
+    pub fn has_api_key(&self) -> bool {
+        self.mock || !self.api_key.is_empty()
+    }
+
+    /// Turn cleanup on or off for this client and its clones.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
     pub async fn cleanup(&self, text: &str) -> Result<String, CleanupError> {
-        if !self.config.groq.cleanup_enabled {
+        if !self.is_enabled() {
             tracing::debug!("Cleanup disabled, returning original text");
             return Ok(text.to_string());
         }
 
+        self.cleanup_with_prompt(text, None).await
+    }
+
+    /// Run cleanup even if `cleanup_enabled` is off, using `prompt` instead of the
+    /// configured prompt when given.
+    pub async fn cleanup_with_prompt(&self, text: &str, prompt: Option<&str>) -> Result<String, CleanupError> {
         tracing::info!("Cleaning up transcription: {} chars", text.len());
+        if self.mock {
+            return Ok(self.mock_cleanup(text).await);
+        }
 
-        let request = ChatRequest {
-            model: self.config.groq.cleanup_model.clone(),
-            messages: vec![
-                ChatMessage {
-                    role: "system".to_string(),
-                    content: self.prompt.clone(),
-                },
-                ChatMessage {
-                    role: "user".to_string(),
-                    content: text.to_string(),
-                },
-            ],
-            temperature: Some(self.config.groq.cleanup_temperature),
-        };
+        let request = self.request(text, prompt, false);
 
         // Wrap the API call in a timeout to prevent hanging
         let cleanup_timeout = Duration::from_secs(90); // 90 seconds total timeout
         
         let result = timeout(cleanup_timeout, async {
-            let response = self
-                .client
-                .post("https://api.groq.com/openai/v1/chat/completions")
-                .header("Authorization", format!("Bearer {}", self.api_key))
-                .header("Content-Type", "application/json")
-                .json(&request)
-                .send()
-                .await?;
-
-            // Check status
-            let status = response.status();
-            if !status.is_success() {
-                let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-                return Err(CleanupError::ApiError(format!(
-                    "HTTP {}: {}",
-                    status,
-                    error_text
-                )));
-            }
+            let response = self.send(&request).await?;
 
             // Parse response
             let chat_response: ChatResponse = response.json().await?;
             
             let cleaned_text = chat_response
                 .choices
-                .first()
-                .and_then(|c| Some(c.message.content.clone()))
+                .first().map(|c| c.message.content.clone())
                 .ok_or(CleanupError::InvalidResponse)?;
 
             Ok(strip_output_tags(&cleaned_text))
         }).await;
 
+        Self::finish(result, cleanup_timeout)
+    }
+
+    /// Like `cleanup` (or `cleanup_with_prompt` when `prompt` is set), but streams the response and sends each complete sentence to
+    /// `sentences` as soon as it arrives. Returns the whole cleaned text.
+    pub async fn cleanup_streaming(
+        &self,
+        text: &str,
+        prompt: Option<&str>,
+        sentences: mpsc::Sender<String>,
+    ) -> Result<String, CleanupError> {
+        if prompt.is_none() && !self.is_enabled() {
+            let _ = sentences.send(text.to_string()).await;
+            return Ok(text.to_string());
+        }
+
+        tracing::info!("Cleaning up transcription (streaming): {} chars", text.len());
+        if self.mock {
+            let cleaned = self.mock_cleanup(text).await;
+            let _ = sentences.send(cleaned.clone()).await;
+            return Ok(cleaned);
+        }
+
+        let request = self.request(text, prompt, true);
+        let cleanup_timeout = Duration::from_secs(90);
+
+        let result = timeout(cleanup_timeout, async {
+            let mut response = self.send(&request).await?;
+            let mut buffer = SentenceBuffer::default();
+            let mut cleaned = String::new();
+            let mut lines = SseLines::default();
+
+            while let Some(chunk) = response.chunk().await? {
+                for line in lines.push(&chunk) {
+                    let Some(data) = line.trim().strip_prefix("data:") else {
+                        continue;
+                    };
+                    let data = data.trim();
+                    if data == "[DONE]" {
+                        break;
+                    }
+                    let event: StreamChunk = serde_json::from_str(data)
+                        .map_err(|_| CleanupError::InvalidResponse)?;
+                    let delta = event.choices.into_iter().next().and_then(|c| c.delta.content).unwrap_or_default();
+                    for sentence in buffer.push(&delta) {
+                        cleaned.push_str(&sentence);
+                        let _ = sentences.send(sentence).await;
+                    }
+                }
+            }
+
+            let rest = buffer.finish();
+            if !rest.is_empty() {
+                cleaned.push_str(&rest);
+                let _ = sentences.send(rest).await;
+            }
+            Ok(cleaned)
+        }).await;
+
+        Self::finish(result, cleanup_timeout)
+    }
+
+    async fn mock_cleanup(&self, text: &str) -> String {
+        tokio::time::sleep(Duration::from_millis(self.config.mock.delay_ms)).await;
+        text.to_string()
+    }
+
+    fn request(&self, text: &str, prompt: Option<&str>, stream: bool) -> ChatRequest {
+        ChatRequest {
+            model: self.config.groq.cleanup_model.clone(),
+            messages: vec![
+                ChatMessage {
+                    role: "system".to_string(),
+                    content: prompt.unwrap_or(&self.prompt).to_string(),
+                },
+                ChatMessage {
+                    role: "user".to_string(),
+                    content: text.to_string(),
+                },
+            ],
+            temperature: Some(self.config.groq.cleanup_temperature),
+            stream,
+        }
+    }
+
+    async fn send(&self, request: &ChatRequest) -> Result<reqwest::Response, CleanupError> {
+        let response = self
+            .client
+            .post(self.config.groq.chat_completions_url())
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/json")
+            .json(request)
+            .send()
+            .await?;
+
+        // Check status
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(CleanupError::ApiError(format!(
+                "HTTP {}: {}",
+                status,
+                error_text
+            )));
+        }
+        Ok(response)
+    }
+
+    fn finish(
+        result: Result<Result<String, CleanupError>, tokio::time::error::Elapsed>,
+        cleanup_timeout: Duration,
+    ) -> Result<String, CleanupError> {
         match result {
             Ok(Ok(text)) => {
                 tracing::info!("Cleanup completed: {} chars", text.len());
@@ -170,9 +301,104 @@ impl CleanupClient {
     }
 }
 
+/// Cuts a byte stream into lines. SSE events, and the UTF-8 characters in them, can be split
+/// across network chunks, so a line is only decoded once its newline has arrived.
+#[derive(Default)]
+struct SseLines {
+    pending: Vec<u8>,
+}
+
+impl SseLines {
+    /// Add a chunk and return the lines it completed, without their line ends.
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(chunk);
+        let mut lines = Vec::new();
+        while let Some(newline) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=newline).collect();
+            lines.push(String::from_utf8_lossy(&line).trim_end().to_string());
+        }
+        lines
+    }
+}
+
+/// Splits streamed cleanup output into sentences, dropping the `<output>` tags
+/// the default prompt asks for.
+#[derive(Default)]
+struct SentenceBuffer {
+    buffer: String,
+    started: bool,
+    done: bool,
+    emitted: bool,
+}
+
+impl SentenceBuffer {
+    const OPEN: &'static str = "<output>";
+    const CLOSE: &'static str = "</output>";
+
+    /// Add a delta and return the sentences it completed, trailing whitespace included.
+    fn push(&mut self, delta: &str) -> Vec<String> {
+        if self.done {
+            return Vec::new();
+        }
+        self.buffer.push_str(delta);
+
+        if !self.started {
+            let head = self.buffer.trim_start();
+            if let Some(rest) = head.strip_prefix(Self::OPEN) {
+                self.buffer = rest.to_string();
+            } else if Self::OPEN.starts_with(head) {
+                // Might still turn into the opening tag
+                return Vec::new();
+            }
+            self.started = true;
+        }
+
+        if let Some(close) = self.buffer.find(Self::CLOSE) {
+            self.buffer.truncate(close);
+            self.done = true;
+        }
+
+        // Cut after the whitespace that follows the last sentence terminator
+        let bytes = self.buffer.as_bytes();
+        let cut = (1..bytes.len())
+            .rev()
+            .find(|&i| bytes[i].is_ascii_whitespace() && matches!(bytes[i - 1], b'.' | b'!' | b'?' | b'\n'))
+            .map(|i| i + 1);
+
+        match cut {
+            Some(cut) => {
+                let rest = self.buffer.split_off(cut);
+                let sentences = std::mem::replace(&mut self.buffer, rest);
+                self.take(sentences).into_iter().collect()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Whatever is left once the stream ends.
+    fn finish(&mut self) -> String {
+        let mut rest = std::mem::take(&mut self.buffer);
+        if let Some(close) = rest.find(Self::CLOSE) {
+            rest.truncate(close);
+        }
+        let rest = rest.trim_end().to_string();
+        self.take(rest).unwrap_or_default()
+    }
+
+    fn take(&mut self, text: String) -> Option<String> {
+        // Nothing typed yet, so leading whitespace would end up in the output
+        let text = if self.emitted { text } else { text.trim_start().to_string() };
+        if text.is_empty() {
+            return None;
+        }
+        self.emitted = true;
+        Some(text)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::strip_output_tags;
+    use super::{strip_output_tags, SentenceBuffer, SseLines};
 
     #[test]
     fn strip_output_tags_extracts_inner_text() {
@@ -186,4 +412,26 @@ mod tests {
     fn strip_output_tags_falls_back_when_missing() {
         assert_eq!(strip_output_tags("Hello world."), "Hello world.");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn sentence_buffer_splits_on_sentence_ends_and_drops_tags() {
+        let mut buffer = SentenceBuffer::default();
+        let mut sentences = Vec::new();
+        for delta in ["<out", "put>Hello", " world. How", " are you?", " Fine</output>"] {
+            sentences.extend(buffer.push(delta));
+        }
+        sentences.push(buffer.finish());
+        assert_eq!(sentences, ["Hello world. ", "How are you? ", "Fine"]);
+    }
+
+    #[test]
+    fn sse_lines_keep_characters_split_across_chunks() {
+        let event = "data: {\"content\": \"Günaydın 👋\"}\n\n".as_bytes();
+        // Cut inside the two bytes of ü and the four of 👋
+        let mut lines = SseLines::default();
+        assert!(lines.push(&event[..21]).is_empty());
+        assert!(lines.push(&event[21..32]).is_empty());
+        let got = lines.push(&event[32..]);
+        assert_eq!(got, ["data: {\"content\": \"Günaydın 👋\"}", ""]);
+    }
+}
@@ -0,0 +1,160 @@
+// Spoken dictation commands ("new line", "comma", "all caps ...") turned into
+// the punctuation/formatting they stand for. Runs on the cleaned text, so it has
+// to cope with the commas and periods Whisper likes to put around them.
+
+enum Action {
+    /// Punctuation that attaches to the previous word
+    Punct(&'static str),
+    /// Punctuation that ends a sentence, the next word is capitalized
+    EndSentence(&'static str),
+    /// Line break(s), the next word is capitalized
+    Break(&'static str),
+    /// Opening bracket/quote, the next word attaches to it
+    Open(&'static str),
+    /// Uppercase the next word
+    AllCaps,
+}
+
+// Longest phrases first so "new paragraph" isn't read as "new" + "paragraph"
+const COMMANDS: &[(&[&str], Action)] = &[
+    (&["exclamation", "mark"], Action::EndSentence("!")),
+    (&["exclamation", "point"], Action::EndSentence("!")),
+    (&["question", "mark"], Action::EndSentence("?")),
+    (&["full", "stop"], Action::EndSentence(".")),
+    (&["new", "paragraph"], Action::Break("\n\n")),
+    (&["new", "line"], Action::Break("\n")),
+    (&["open", "quote"], Action::Open("\"")),
+    (&["close", "quote"], Action::Punct("\"")),
+    (&["open", "paren"], Action::Open("(")),
+    (&["close", "paren"], Action::Punct(")")),
+    (&["all", "caps"], Action::AllCaps),
+    (&["period"], Action::EndSentence(".")),
+    (&["comma"], Action::Punct(",")),
+    (&["colon"], Action::Punct(":")),
+    (&["semicolon"], Action::Punct(";")),
+];
+
+/// Characters Whisper/cleanup may have added around a spoken command
+fn is_stray_punct(c: char) -> bool {
+    matches!(c, ',' | '.' | '!' | '?' | ';' | ':')
+}
+
+fn normalize(token: &str) -> String {
+    token.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase()
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+pub fn apply_voice_commands(text: &str) -> String {
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    let normalized: Vec<String> = tokens.iter().map(|t| normalize(t)).collect();
+
+    let mut out = String::with_capacity(text.len());
+    let mut attach = false;
+    let mut capitalize_next = false;
+    let mut caps_next = false;
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let command = COMMANDS.iter().find(|(phrase, _)| {
+            normalized.get(i..i + phrase.len()).is_some_and(|words| words.iter().zip(phrase.iter()).all(|(a, b)| a == b))
+        });
+
+        let Some((phrase, action)) = command else {
+            let mut word = tokens[i].to_string();
+            if caps_next {
+                word = word.to_uppercase();
+                caps_next = false;
+            } else if capitalize_next {
+                word = capitalize(&word);
+            }
+            capitalize_next = false;
+            if !out.is_empty() && !attach && !out.ends_with('\n') {
+                out.push(' ');
+            }
+            out.push_str(&word);
+            attach = false;
+            i += 1;
+            continue;
+        };
+        i += phrase.len();
+
+        // "Hello, comma, world." -> drop what was added around the spoken command
+        let trim_previous = |out: &mut String| {
+            let trimmed = out.trim_end_matches(|c: char| c == ' ' || is_stray_punct(c)).len();
+            out.truncate(trimmed);
+        };
+
+        match action {
+            Action::Punct(symbol) => {
+                trim_previous(&mut out);
+                out.push_str(symbol);
+            }
+            Action::EndSentence(symbol) => {
+                trim_previous(&mut out);
+                out.push_str(symbol);
+                capitalize_next = true;
+            }
+            Action::Break(breaks) => {
+                let trimmed = out.trim_end_matches([' ', ',']).len();
+                out.truncate(trimmed);
+                out.push_str(breaks);
+                capitalize_next = true;
+            }
+            Action::Open(symbol) => {
+                if !out.is_empty() && !out.ends_with('\n') {
+                    out.push(' ');
+                }
+                out.push_str(symbol);
+                attach = true;
+                continue;
+            }
+            Action::AllCaps => {
+                caps_next = true;
+                continue;
+            }
+        }
+        attach = false;
+
+        // The stray punctuation after a command token belongs to the command
+        if let Some(next) = tokens.get(i) {
+            if next.chars().all(is_stray_punct) {
+                i += 1;
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::apply_voice_commands;
+
+    #[test]
+    fn converts_punctuation_and_breaks() {
+        assert_eq!(
+            apply_voice_commands("Hello, comma, world period. New line how are you question mark"),
+            "Hello, world.\nHow are you?"
+        );
+    }
+
+    #[test]
+    fn handles_quotes_and_caps() {
+        assert_eq!(
+            apply_voice_commands("she said open quote all caps stop close quote"),
+            "she said \"STOP\""
+        );
+    }
+
+    #[test]
+    fn leaves_plain_text_alone() {
+        assert_eq!(apply_voice_commands("Nothing to see here."), "Nothing to see here.");
+    }
+}
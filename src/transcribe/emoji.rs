@@ -0,0 +1,188 @@
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Names understood without an emoji file.
+const BUILTIN: &[(&str, &str)] = &[
+    ("thumbs up", "👍"),
+    ("thumbsup", "👍"),
+    ("plus one", "👍"),
+    ("thumbs down", "👎"),
+    ("smile", "🙂"),
+    ("smiley", "😊"),
+    ("grin", "😁"),
+    ("joy", "😂"),
+    ("laughing", "😂"),
+    ("sweat smile", "😅"),
+    ("wink", "😉"),
+    ("heart eyes", "😍"),
+    ("sunglasses", "😎"),
+    ("thinking", "🤔"),
+    ("upside down face", "🙃"),
+    ("sad", "🙁"),
+    ("cry", "😢"),
+    ("facepalm", "🤦"),
+    ("shrug", "🤷"),
+    ("skull", "💀"),
+    ("eyes", "👀"),
+    ("wave", "👋"),
+    ("ok hand", "👌"),
+    ("clap", "👏"),
+    ("raised hands", "🙌"),
+    ("pray", "🙏"),
+    ("muscle", "💪"),
+    ("heart", "❤️"),
+    ("red heart", "❤️"),
+    ("fire", "🔥"),
+    ("hundred", "💯"),
+    ("100", "💯"),
+    ("sparkles", "✨"),
+    ("star", "⭐"),
+    ("tada", "🎉"),
+    ("party", "🎉"),
+    ("rocket", "🚀"),
+    ("bug", "🐛"),
+    ("coffee", "☕"),
+    ("check mark", "✅"),
+    ("white check mark", "✅"),
+    ("cross mark", "❌"),
+    ("warning", "⚠️"),
+];
+
+/// Contents of the emoji file, extending and overriding the built-in names.
+///
+/// ```toml
+/// [emoji]
+/// "ship it" = "🚢"
+/// croak = "🐸"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+struct EmojiFile {
+    #[serde(default)]
+    emoji: BTreeMap<String, String>,
+}
+
+/// Turns spoken emoji into the characters: ":thumbs up:" (what Whisper writes for
+/// "colon thumbs up colon") and "thumbs up emoji" both become 👍.
+///
+/// Like replacements, the file is re-read when its modification time changes.
+pub struct Emoji {
+    path: PathBuf,
+    loaded: Mutex<Loaded>,
+}
+
+struct Loaded {
+    /// `None` until the first `apply`, so a missing file still loads the built-in names
+    modified: Option<Option<SystemTime>>,
+    names: HashMap<String, String>,
+    pattern: Option<Regex>,
+}
+
+impl Emoji {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            loaded: Mutex::new(Loaded { modified: None, names: HashMap::new(), pattern: None }),
+        }
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        let mut loaded = self.loaded.lock().unwrap_or_else(|e| e.into_inner());
+        self.reload_if_changed(&mut loaded);
+
+        let Some(ref pattern) = loaded.pattern else {
+            return text.to_string();
+        };
+        pattern
+            .replace_all(text, |caps: &Captures| {
+                let name = caps.name("colons").or_else(|| caps.name("suffix")).map_or("", |m| m.as_str());
+                loaded.names.get(&normalize(name)).cloned().unwrap_or_else(|| caps[0].to_string())
+            })
+            .into_owned()
+    }
+
+    fn reload_if_changed(&self, loaded: &mut Loaded) {
+        // A missing file just means the built-in names
+        let modified = std::fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+        if loaded.modified == Some(modified) {
+            return;
+        }
+        loaded.modified = Some(modified);
+
+        let mut names: HashMap<String, String> =
+            BUILTIN.iter().map(|(name, emoji)| (name.to_string(), emoji.to_string())).collect();
+        if modified.is_some() {
+            let parsed = std::fs::read_to_string(&self.path)
+                .map_err(|e| e.to_string())
+                .and_then(|contents| toml::from_str::<EmojiFile>(&contents).map_err(|e| e.to_string()));
+            match parsed {
+                Ok(file) => {
+                    tracing::info!("Loaded {} emoji from {:?}", file.emoji.len(), self.path);
+                    names.extend(file.emoji.into_iter().map(|(name, emoji)| (normalize(&name), emoji)));
+                }
+                // Keep the previous names so a half-saved file doesn't drop them all
+                Err(e) => {
+                    tracing::warn!("Failed to load emoji from {:?}: {}", self.path, e);
+                    return;
+                }
+            }
+        }
+        names.retain(|name, _| !name.is_empty());
+        loaded.pattern = compile(names.keys());
+        loaded.names = names;
+    }
+}
+
+/// Lowercase, with spaces, underscores and dashes between words collapsed to one space.
+fn normalize(name: &str) -> String {
+    name.split(|c: char| c.is_whitespace() || c == '_' || c == '-')
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn compile<'a>(names: impl Iterator<Item = &'a String>) -> Option<Regex> {
+    let mut names: Vec<&String> = names.collect();
+    if names.is_empty() {
+        return None;
+    }
+    // Longest first, so "red heart" wins over "heart"
+    names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+    let alternatives = names
+        .iter()
+        .map(|name| name.split(' ').map(regex::escape).collect::<Vec<_>>().join(r"[\s_-]+"))
+        .collect::<Vec<_>>()
+        .join("|");
+    let pattern = format!(
+        r"(?i):[ \t]*(?P<colons>{alternatives})[ \t]*:|\b(?P<suffix>{alternatives})[ \t]+emoji\b"
+    );
+    match Regex::new(&pattern) {
+        Ok(regex) => Some(regex),
+        Err(e) => {
+            tracing::warn!("Couldn't build the emoji pattern: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_shortcodes_and_spoken_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("emoji.toml");
+        let emoji = Emoji::new(&path);
+
+        assert_eq!(emoji.apply("Ship it :thumbs_up: and Fire emoji."), "Ship it 👍 and 🔥.");
+        assert_eq!(emoji.apply("a red heart emoji, :nope: warning"), "a ❤️, :nope: warning");
+
+        std::fs::write(&path, "[emoji]\n\"ship it\" = \"🚢\"\nfire = \"🧯\"\n").unwrap();
+        assert_eq!(emoji.apply(":Ship It: fire emoji"), "🚢 🧯");
+    }
+}
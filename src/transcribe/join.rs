@@ -0,0 +1,70 @@
+// Back-to-back dictations into the same window are transcribed independently, so
+// the second one starts without a space and, from Whisper, always capitalised.
+
+/// Adjust the start of `text` to follow `previous`, the text output just before it:
+/// add a separating space, and fix the case of the first letter depending on whether
+/// `previous` ended a sentence.
+pub fn join(previous: &str, text: &str) -> String {
+    let Some(first) = text.chars().next() else {
+        return String::new();
+    };
+    let Some(last) = previous.chars().last() else {
+        return text.to_string();
+    };
+
+    let mut joined = String::with_capacity(text.len() + 1);
+    if !last.is_whitespace() && !first.is_whitespace() && !matches!(first, ',' | '.' | ';' | ':' | '!' | '?' | ')') {
+        joined.push(' ');
+    }
+
+    // A line break ends a sentence as far as capitalisation goes
+    let sentence_ended = previous
+        .trim_end_matches([' ', '\t'])
+        .chars()
+        .last()
+        .is_none_or(|c| matches!(c, '.' | '!' | '?' | '\n'));
+    let body = text.trim_start();
+    let leading = &text[..text.len() - body.len()];
+    joined.push_str(leading);
+
+    let word = body.split_whitespace().next().unwrap_or("");
+    let mut chars = body.chars();
+    match chars.next() {
+        Some(c) if sentence_ended && c.is_lowercase() => {
+            joined.extend(c.to_uppercase());
+            joined.push_str(chars.as_str());
+        }
+        Some(c) if !sentence_ended && is_sentence_case(word) => {
+            joined.extend(c.to_lowercase());
+            joined.push_str(chars.as_str());
+        }
+        _ => joined.push_str(body),
+    }
+    joined
+}
+
+/// A capitalised ordinary word ("The"), not "I", an acronym or a name with inner capitals.
+fn is_sentence_case(word: &str) -> bool {
+    let letters: Vec<char> = word.chars().filter(|c| c.is_alphabetic()).collect();
+    let word_start = word.split(['\'', '’']).next().unwrap_or("");
+    letters.len() > 1
+        && letters[0].is_uppercase()
+        && letters[1..].iter().all(|c| c.is_lowercase())
+        && word_start != "I"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::join;
+
+    #[test]
+    fn joins_sentences_and_continuations() {
+        assert_eq!(join("It works.", "The tests pass."), " The tests pass.");
+        assert_eq!(join("It works.", "and fast"), " And fast");
+        assert_eq!(join("we should ship", "The release on Friday."), " the release on Friday.");
+        assert_eq!(join("so", "I'm sure. NASA said so."), " I'm sure. NASA said so.");
+        assert_eq!(join("Done\n", "Next item"), "Next item");
+        assert_eq!(join("hello", ", world"), ", world");
+        assert_eq!(join("", "Hello"), "Hello");
+    }
+}
@@ -0,0 +1,49 @@
+// Overlapping chunks are transcribed independently, so the words spoken in the
+// overlap show up at the end of one chunk and the start of the next.
+const MAX_OVERLAP_WORDS: usize = 12;
+
+fn normalize_word(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// Drop the leading words of `next` that repeat the end of `previous`.
+///
+/// Comparison ignores case and punctuation, since Whisper often capitalises or
+/// punctuates the same words differently at a chunk boundary.
+pub fn strip_overlap(previous: &str, next: &str) -> String {
+    let prev_words: Vec<&str> = previous.split_whitespace().collect();
+    let next_words: Vec<&str> = next.split_whitespace().collect();
+
+    // Find the longest suffix of `previous` that matches a prefix of `next`
+    let max = MAX_OVERLAP_WORDS.min(prev_words.len()).min(next_words.len());
+    let overlap = (1..=max)
+        .rev()
+        .find(|&n| {
+            prev_words[prev_words.len() - n..]
+                .iter()
+                .zip(&next_words[..n])
+                .all(|(a, b)| normalize_word(a) == normalize_word(b))
+        })
+        .unwrap_or(0);
+
+    next_words[overlap..].join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::strip_overlap;
+
+    #[test]
+    fn strip_overlap_drops_repeated_words() {
+        assert_eq!(strip_overlap("we should ship the release on", "The release on Friday."), "Friday.");
+    }
+
+    #[test]
+    fn strip_overlap_keeps_unrelated_text() {
+        assert_eq!(strip_overlap("Hello there.", "General Kenobi."), "General Kenobi.");
+        assert_eq!(strip_overlap("", "Hi  there"), "Hi there");
+    }
+}
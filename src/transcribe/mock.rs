@@ -0,0 +1,42 @@
+use super::backend::{TranscriptionBackend, TranscriptionError};
+use crate::audio::Recording;
+use crate::config::Config;
+use async_trait::async_trait;
+use std::time::Duration;
+
+/// Fake provider for trying out hotkeys, state changes, overlays and output without an API
+/// key or network (`croaker serve --mock`). Every recording transcribes to `mock.text`.
+pub struct MockBackend {
+    text: String,
+    delay: Duration,
+}
+
+impl MockBackend {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            text: config.mock.text.clone(),
+            delay: Duration::from_millis(config.mock.delay_ms),
+        }
+    }
+}
+
+#[async_trait]
+impl TranscriptionBackend for MockBackend {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn model(&self, _translate: bool) -> &str {
+        "mock"
+    }
+
+    async fn transcribe(&self, _recording: &Recording, language: &str) -> Result<String, TranscriptionError> {
+        tracing::info!("Mock transcription (language: {})", if language.is_empty() { "auto" } else { language });
+        tokio::time::sleep(self.delay).await;
+        Ok(self.text.clone())
+    }
+
+    async fn translate(&self, recording: &Recording) -> Result<String, TranscriptionError> {
+        self.transcribe(recording, "en").await
+    }
+}
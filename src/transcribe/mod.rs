@@ -1,6 +1,18 @@
+pub mod backend;
 pub mod whisper;
 pub mod cleanup;
+pub mod commands;
+pub mod emoji;
+pub mod join;
+pub mod merge;
+pub mod mock;
+pub mod nospeech;
+pub mod spool;
+pub mod redact;
+pub mod replace;
+#[cfg(feature = "vosk")]
+pub mod vosk;
 
-pub use whisper::WhisperClient;
+pub use backend::TranscriptionBackend;
 pub use cleanup::CleanupClient;
 
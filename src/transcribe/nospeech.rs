@@ -0,0 +1,41 @@
+// Detect transcripts of recordings where nobody actually said anything.
+// Whisper fills silence with stock phrases from its YouTube-heavy training data.
+
+const HALLUCINATIONS: &[&str] = &[
+    "you",
+    "bye",
+    "thank you for watching",
+    "thanks for watching",
+    "thank you very much for watching",
+    "please subscribe",
+    "like and subscribe",
+    "subtitles by the amaraorg community",
+    "transcribed by otterai",
+];
+
+/// Whether `text` is empty or nothing but a well-known silence hallucination.
+pub fn is_no_speech(text: &str) -> bool {
+    let normalized: String = text
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+
+    normalized.is_empty() || HALLUCINATIONS.contains(&normalized.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_no_speech;
+
+    #[test]
+    fn detects_blank_and_stock_phrases() {
+        assert!(is_no_speech("  ...  "));
+        assert!(is_no_speech("Thanks for watching!"));
+        assert!(is_no_speech("Subtitles by the Amara.org community"));
+        assert!(!is_no_speech("Thanks for watching the kids tonight."));
+    }
+}
@@ -0,0 +1,118 @@
+use crate::config::RedactionConfig;
+use regex::{Captures, Regex};
+
+const EMAIL: &str = r"(?i)\b[a-z0-9._%+-]+@[a-z0-9.-]+\.[a-z]{2,}\b";
+// 13-19 digits, optionally grouped with spaces or dashes; checked with Luhn before masking
+const CREDIT_CARD: &str = r"\b\d(?:[ -]?\d){12,18}\b";
+// At least 7 digits with the usual separators, optional leading +
+const PHONE: &str = r"(?:\+|\b)\d(?:[ ().-]{0,2}\d){6,14}\b";
+
+/// Masks personal data in transcripts before they are output or stored.
+pub struct Redactor {
+    rules: Vec<Rule>,
+}
+
+struct Rule {
+    regex: Regex,
+    replacement: &'static str,
+    /// Only mask matches that pass the Luhn checksum
+    luhn: bool,
+}
+
+impl Redactor {
+    /// Build the enabled rules. Invalid custom patterns are skipped with a warning.
+    pub fn new(config: &RedactionConfig) -> Self {
+        let mut rules = Vec::new();
+        let builtin = |pattern: &str, replacement, luhn| Rule {
+            regex: Regex::new(pattern).expect("built-in pattern is valid"),
+            replacement,
+            luhn,
+        };
+
+        if config.emails {
+            rules.push(builtin(EMAIL, "[email]", false));
+        }
+        // Cards before phone numbers, otherwise a card number reads as a long phone number
+        if config.credit_cards {
+            rules.push(builtin(CREDIT_CARD, "[card]", true));
+        }
+        if config.phone_numbers {
+            rules.push(builtin(PHONE, "[phone]", false));
+        }
+        for pattern in &config.patterns {
+            match Regex::new(pattern) {
+                Ok(regex) => rules.push(Rule {
+                    regex,
+                    replacement: "[redacted]",
+                    luhn: false,
+                }),
+                Err(e) => tracing::warn!("Ignoring invalid redaction pattern {:?}: {}", pattern, e),
+            }
+        }
+
+        Self { rules }
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for rule in &self.rules {
+            text = rule
+                .regex
+                .replace_all(&text, |caps: &Captures| {
+                    let matched = &caps[0];
+                    if rule.luhn && !luhn_valid(matched) {
+                        matched.to_string()
+                    } else {
+                        rule.replacement.to_string()
+                    }
+                })
+                .into_owned();
+        }
+        text
+    }
+}
+
+fn luhn_valid(number: &str) -> bool {
+    let digits: Vec<u32> = number.chars().filter_map(|c| c.to_digit(10)).collect();
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| if i % 2 == 1 { if d * 2 > 9 { d * 2 - 9 } else { d * 2 } } else { d })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redactor(patterns: &[&str]) -> Redactor {
+        Redactor::new(&RedactionConfig {
+            enabled: true,
+            emails: true,
+            phone_numbers: true,
+            credit_cards: true,
+            patterns: patterns.iter().map(|p| p.to_string()).collect(),
+        })
+    }
+
+    #[test]
+    fn masks_builtin_kinds() {
+        let text = "Mail jane.doe@example.com or call +1 (555) 123-4567, card 4111 1111 1111 1111.";
+        assert_eq!(
+            redactor(&[]).redact(text),
+            "Mail [email] or call [phone], card [card]."
+        );
+    }
+
+    #[test]
+    fn leaves_short_numbers_alone() {
+        assert_eq!(redactor(&[]).redact("We shipped 3 releases in 2024."), "We shipped 3 releases in 2024.");
+    }
+
+    #[test]
+    fn applies_custom_patterns() {
+        assert_eq!(redactor(&[r"ACME-\d+"]).redact("Ticket ACME-42 is done"), "Ticket [redacted] is done");
+    }
+}
@@ -0,0 +1,144 @@
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Contents of `replacements.toml`.
+///
+/// ```toml
+/// [words]
+/// crocker = "croaker"
+/// api = "API"
+///
+/// [regex]
+/// '(?i)\bk8s\b' = "Kubernetes"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+struct ReplacementsFile {
+    /// Whole words, matched case-insensitively
+    #[serde(default)]
+    words: BTreeMap<String, String>,
+    /// Regex → replacement (`$1` etc. refer to capture groups)
+    #[serde(default)]
+    regex: BTreeMap<String, String>,
+}
+
+/// User-defined replacements applied after cleanup.
+///
+/// The file is re-read whenever its modification time changes, so edits take
+/// effect on the next transcription without restarting the daemon.
+pub struct Replacements {
+    path: PathBuf,
+    loaded: Mutex<Loaded>,
+}
+
+#[derive(Default)]
+struct Loaded {
+    modified: Option<SystemTime>,
+    rules: Vec<(Regex, String)>,
+}
+
+impl Replacements {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            loaded: Mutex::new(Loaded::default()),
+        }
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        let mut loaded = self.loaded.lock().unwrap_or_else(|e| e.into_inner());
+        self.reload_if_changed(&mut loaded);
+
+        let mut text = text.to_string();
+        for (regex, replacement) in &loaded.rules {
+            text = regex.replace_all(&text, replacement.as_str()).into_owned();
+        }
+        text
+    }
+
+    fn reload_if_changed(&self, loaded: &mut Loaded) {
+        // A missing file just means no replacements
+        let modified = std::fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+        if modified == loaded.modified {
+            return;
+        }
+        loaded.modified = modified;
+
+        if modified.is_none() {
+            loaded.rules.clear();
+            return;
+        }
+
+        let parsed = std::fs::read_to_string(&self.path)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| toml::from_str::<ReplacementsFile>(&contents).map_err(|e| e.to_string()));
+        match parsed {
+            Ok(file) => {
+                loaded.rules = compile(&file);
+                tracing::info!("Loaded {} replacements from {:?}", loaded.rules.len(), self.path);
+            }
+            // Keep the previous rules so a half-saved file doesn't drop them all
+            Err(e) => tracing::warn!("Failed to load replacements from {:?}: {}", self.path, e),
+        }
+    }
+}
+
+fn compile(file: &ReplacementsFile) -> Vec<(Regex, String)> {
+    let words = file.words.iter().map(|(word, replacement)| {
+        let pattern = format!(r"(?i)\b{}\b", regex::escape(word));
+        // `$` would otherwise be read as a capture group reference
+        (pattern, replacement.replace('$', "$$"))
+    });
+    let regexes = file.regex.iter().map(|(pattern, replacement)| (pattern.clone(), replacement.clone()));
+
+    words
+        .chain(regexes)
+        .filter_map(|(pattern, replacement)| match Regex::new(&pattern) {
+            Ok(regex) => Some((regex, replacement)),
+            Err(e) => {
+                tracing::warn!("Ignoring invalid replacement pattern {:?}: {}", pattern, e);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn applies_words_and_regexes_and_reloads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("replacements.toml");
+        std::fs::write(
+            &path,
+            r#"
+            [words]
+            crocker = "croaker"
+            api = "API"
+
+            [regex]
+            '(\d+) percent' = "$1%"
+            "#,
+        )
+        .unwrap();
+        let replacements = Replacements::new(&path);
+
+        assert_eq!(
+            replacements.apply("Crocker calls the api, rapid 50 percent"),
+            "croaker calls the API, rapid 50%"
+        );
+
+        std::fs::write(&path, "[words]\ncrocker = \"Croaker\"\n").unwrap();
+        // Make sure the change is visible even on filesystems with coarse timestamps
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(5)).unwrap();
+
+        assert_eq!(replacements.apply("crocker api"), "Croaker api");
+    }
+}
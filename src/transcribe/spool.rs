@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Recordings whose transcription failed because the network was down, kept
+/// on disk until they can be retried.
+///
+/// Each entry is a `<timestamp>.wav` plus a `<timestamp>.json` with the
+/// metadata needed to transcribe it later. Entries that fail for other
+/// reasons are renamed to `.failed.wav` so they aren't retried forever.
+#[derive(Debug, Clone)]
+pub struct Spool {
+    dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpoolMeta {
+    pub language: String,
+    #[serde(default)]
+    pub translate: bool,
+    pub recorded_at: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct SpoolEntry {
+    pub wav: PathBuf,
+    pub meta: SpoolMeta,
+}
+
+impl Spool {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub async fn save(&self, wav: &[u8], language: &str, translate: bool) -> Result<PathBuf, std::io::Error> {
+        fs::create_dir_all(&self.dir).await?;
+        let now = chrono::Local::now();
+        let stem = now.format("%Y%m%d-%H%M%S%.3f").to_string();
+        let meta = SpoolMeta {
+            language: language.to_string(),
+            translate,
+            recorded_at: now.to_rfc3339(),
+        };
+
+        let wav_path = self.dir.join(format!("{}.wav", stem));
+        fs::write(&wav_path, wav).await?;
+        fs::write(self.dir.join(format!("{}.json", stem)), serde_json::to_vec_pretty(&meta)?).await?;
+        Ok(wav_path)
+    }
+
+    /// Entries waiting to be retried, oldest first.
+    pub async fn pending(&self) -> Vec<SpoolEntry> {
+        let Ok(mut dir) = fs::read_dir(&self.dir).await else {
+            return Vec::new();
+        };
+
+        let mut entries = Vec::new();
+        while let Ok(Some(item)) = dir.next_entry().await {
+            let wav = item.path();
+            let name = wav.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if !name.ends_with(".wav") || name.ends_with(".failed.wav") {
+                continue;
+            }
+            let meta = match fs::read(wav.with_extension("json")).await {
+                Ok(bytes) => serde_json::from_slice(&bytes).ok(),
+                Err(_) => None,
+            };
+            entries.push(SpoolEntry {
+                meta: meta.unwrap_or_else(|| SpoolMeta {
+                    language: String::new(),
+                    translate: false,
+                    recorded_at: String::new(),
+                }),
+                wav,
+            });
+        }
+
+        // Timestamped names sort chronologically
+        entries.sort_by(|a, b| a.wav.cmp(&b.wav));
+        entries
+    }
+
+    pub async fn remove(&self, entry: &SpoolEntry) {
+        let _ = fs::remove_file(&entry.wav).await;
+        let _ = fs::remove_file(entry.wav.with_extension("json")).await;
+    }
+
+    /// Keep the audio but stop retrying it.
+    pub async fn mark_failed(&self, entry: &SpoolEntry) {
+        let failed = failed_path(&entry.wav);
+        if let Err(e) = fs::rename(&entry.wav, &failed).await {
+            tracing::warn!("Failed to mark spooled recording {:?} as failed: {}", entry.wav, e);
+        }
+    }
+}
+
+fn failed_path(wav: &Path) -> PathBuf {
+    wav.with_extension("failed.wav")
+}
@@ -0,0 +1,77 @@
+use super::backend::{TranscriptionBackend, TranscriptionError};
+use crate::audio::{wav, Recording};
+use crate::config::Config;
+use async_trait::async_trait;
+use std::sync::Arc;
+use vosk::{Model, Recognizer};
+
+/// Fully offline transcription with a local Vosk model.
+///
+/// Much less accurate than Whisper, but runs in real time on low-end machines
+/// and needs no network.
+pub struct VoskBackend {
+    model: Arc<Model>,
+    model_name: String,
+}
+
+impl VoskBackend {
+    pub fn new(config: &Config) -> Result<Self, TranscriptionError> {
+        let model_dir = &config.vosk.model_dir;
+        tracing::info!("Loading Vosk model from {}", model_dir);
+        // Vosk prints a lot of decoder internals to stderr otherwise
+        vosk::set_log_level(vosk::LogLevel::Error);
+        let model = Model::new(model_dir.as_str())
+            .ok_or_else(|| TranscriptionError::VoskError(format!("Failed to load model from {}", model_dir)))?;
+
+        let model_name = std::path::Path::new(model_dir)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "vosk".to_string());
+
+        Ok(Self {
+            model: Arc::new(model),
+            model_name,
+        })
+    }
+}
+
+#[async_trait]
+impl TranscriptionBackend for VoskBackend {
+    fn name(&self) -> &'static str {
+        "vosk"
+    }
+
+    fn model(&self, _translate: bool) -> &str {
+        &self.model_name
+    }
+
+    // NOTE: Vosk models are single-language, so `language` is ignored
+    async fn transcribe(&self, recording: &Recording, _language: &str) -> Result<String, TranscriptionError> {
+        let bytes = recording.read().await
+            .map_err(|e| TranscriptionError::VoskError(format!("Failed to read recording: {}", e)))?;
+        let (format, samples) = wav::read_samples(&bytes)
+            .ok_or_else(|| TranscriptionError::VoskError("Recording is not a PCM WAV file".to_string()))?;
+        let samples: Vec<i16> = wav::downmix(&samples, format.channels)
+            .iter()
+            .map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+            .collect();
+
+        let model = self.model.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut recognizer = Recognizer::new(&model, format.sample_rate as f32)
+                .ok_or_else(|| TranscriptionError::VoskError("Failed to create recognizer".to_string()))?;
+            recognizer
+                .accept_waveform(&samples)
+                .map_err(|e| TranscriptionError::VoskError(e.to_string()))?;
+            let text = recognizer
+                .final_result()
+                .single()
+                .map(|result| result.text.to_string())
+                .unwrap_or_default();
+            tracing::info!("Vosk transcription completed: {} chars", text.len());
+            Ok(text)
+        })
+        .await
+        .map_err(|e| TranscriptionError::VoskError(e.to_string()))?
+    }
+}
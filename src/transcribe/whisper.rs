@@ -1,9 +1,11 @@
+use super::backend::{TranscriptionBackend, TranscriptionError};
+use crate::audio::encode::{encode_for_upload, EncodedAudio};
+use crate::audio::Recording;
 use crate::config::Config;
+use async_trait::async_trait;
 use reqwest::multipart;
 use reqwest::Client;
-use std::path::Path;
 use thiserror::Error;
-use tokio::fs;
 use tokio::time::{timeout, Duration};
 
 #[derive(Debug, Error)]
@@ -14,8 +16,8 @@ pub enum WhisperError {
     RequestError(#[from] reqwest::Error),
     #[error("API returned error: {0}")]
     ApiError(String),
-    #[error("Invalid response format")]
-    InvalidResponse,
+    #[error("Request timed out after {0} seconds")]
+    Timeout(u64),
 }
 
 #[derive(Clone)]
@@ -23,12 +25,32 @@ pub struct WhisperClient {
     client: Client,
     config: Config,
     api_key: String,
-    language: String,
+    prompt: String,
 }
 
 #[derive(Debug, serde::Deserialize)]
 struct WhisperResponse {
     text: String,
+    /// Only present with `response_format=verbose_json`
+    #[serde(default)]
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct Segment {
+    #[serde(default)]
+    no_speech_prob: f64,
+    #[serde(default)]
+    avg_logprob: f64,
+}
+
+/// Whisper's own silence rule: a segment is silent when the model thinks there's no
+/// speech and isn't confident about the text it produced anyway.
+fn all_silent(segments: &[Segment], threshold: f64) -> bool {
+    !segments.is_empty()
+        && segments
+            .iter()
+            .all(|s| s.no_speech_prob > threshold && s.avg_logprob < -1.0)
 }
 
 impl WhisperClient {
@@ -37,49 +59,89 @@ impl WhisperClient {
             .timeout(std::time::Duration::from_secs(120)) // Increased timeout, but wrapper timeout will catch it first
             .build()
             .expect("Failed to create HTTP client");
-        
-        let language = config.general.language.clone();
-        
+
+        // A missing glossary shouldn't stop transcription, it just loses the biasing
+        let prompt = config.load_whisper_prompt().unwrap_or_else(|e| {
+            tracing::warn!("Failed to load Whisper prompt: {}", e);
+            config.groq.whisper_prompt.trim().to_string()
+        });
+        if !prompt.is_empty() {
+            tracing::info!("Using Whisper prompt ({} chars)", prompt.len());
+        }
+
         Self {
             client,
             config,
             api_key,
-            language,
+            prompt,
         }
     }
 
-    pub async fn transcribe(&self, audio_path: &Path) -> Result<String, WhisperError> {
-        self.transcribe_with_language(audio_path, &self.language).await
+    pub async fn transcribe_with_language(&self, recording: &Recording, language: &str) -> Result<String, WhisperError> {
+        match recording {
+            Recording::File(path) => tracing::info!("Transcribing audio file: {:?} (language: {})", path, language),
+            Recording::Memory(bytes) => tracing::info!("Transcribing {} bytes of in-memory audio (language: {})", bytes.len(), language),
+        }
+
+        let groq = &self.config.groq;
+        self.send_audio(recording, groq.transcription_url(), &groq.whisper_model, language).await
     }
 
-    pub async fn transcribe_with_language(&self, audio_path: &Path, language: &str) -> Result<String, WhisperError> {
-        tracing::info!("Transcribing audio file: {:?} (language: {})", audio_path, language);
+    /// Transcribe and translate to English via the translations endpoint.
+    pub async fn translate_to_english(&self, recording: &Recording) -> Result<String, WhisperError> {
+        tracing::info!("Translating recording to English");
+
+        // The translations endpoint detects the source language itself
+        let groq = &self.config.groq;
+        self.send_audio(recording, groq.translation_url(), &groq.translation_model, "").await
+    }
+
+    async fn send_audio(&self, recording: &Recording, url: String, model: &str, language: &str) -> Result<String, WhisperError> {
 
         // Wrap the API call in a timeout to prevent hanging
         let transcription_timeout = Duration::from_secs(90); // 90 seconds total timeout
         
         let result = timeout(transcription_timeout, async {
-            // Read audio file
-            let audio_data = fs::read(audio_path).await?;
+            // Encode audio for upload, falling back to the raw WAV if encoding fails
+            let codec = self.config.audio.upload_codec;
+            let wav = recording.read().await?;
+            let audio = match encode_for_upload(wav.clone(), codec).await {
+                Ok(audio) => audio,
+                Err(e) => {
+                    tracing::warn!("Failed to encode audio as {:?}, uploading WAV instead: {}", codec, e);
+                    EncodedAudio::wav(wav)
+                }
+            };
+            tracing::debug!("Uploading {} bytes as {}", audio.data.len(), audio.file_name);
 
             // Create multipart form
-            let file_part = multipart::Part::bytes(audio_data)
-                .file_name("audio.wav")
-                .mime_str("audio/wav")?;
+            let file_part = multipart::Part::bytes(audio.data)
+                .file_name(audio.file_name)
+                .mime_str(audio.mime)?;
 
+            let threshold = self.config.groq.no_speech_threshold;
             let mut form = multipart::Form::new()
-                .text("model", self.config.groq.whisper_model.clone())
+                .text("model", model.to_string())
                 .part("file", file_part);
 
+            // verbose_json adds per-segment no-speech probabilities
+            if threshold > 0.0 {
+                form = form.text("response_format", "verbose_json");
+            }
+
             // Add language if specified
             if !language.is_empty() {
                 form = form.text("language", language.to_string());
             }
 
+            if !self.prompt.is_empty() {
+                form = form.text("prompt", self.prompt.clone());
+            }
+
             // Make request
             let response = self
                 .client
-                .post("https://api.groq.com/openai/v1/audio/transcriptions")
+                .post(url)
                 .header("Authorization", format!("Bearer {}", self.api_key))
                 .multipart(form)
                 .send()
@@ -98,7 +160,12 @@ impl WhisperClient {
 
             // Parse response
             let whisper_response: WhisperResponse = response.json().await?;
-            
+
+            if threshold > 0.0 && all_silent(&whisper_response.segments, threshold) {
+                tracing::info!("All segments look like silence, dropping {:?}", whisper_response.text);
+                return Ok(String::new());
+            }
+
             Ok(whisper_response.text)
         }).await;
 
@@ -113,12 +180,45 @@ impl WhisperClient {
             }
             Err(_) => {
                 tracing::error!("Transcription request timed out after {} seconds", transcription_timeout.as_secs());
-                Err(WhisperError::ApiError(format!(
-                    "Request timed out after {} seconds",
-                    transcription_timeout.as_secs()
-                )))
+                Err(WhisperError::Timeout(transcription_timeout.as_secs()))
             }
         }
     }
 }
 
+#[async_trait]
+impl TranscriptionBackend for WhisperClient {
+    fn name(&self) -> &'static str {
+        "groq"
+    }
+
+    fn model(&self, translate: bool) -> &str {
+        if translate {
+            &self.config.groq.translation_model
+        } else {
+            &self.config.groq.whisper_model
+        }
+    }
+
+    async fn transcribe(&self, recording: &Recording, language: &str) -> Result<String, TranscriptionError> {
+        Ok(self.transcribe_with_language(recording, language).await?)
+    }
+
+    async fn translate(&self, recording: &Recording) -> Result<String, TranscriptionError> {
+        Ok(self.translate_to_english(recording).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{all_silent, Segment};
+
+    #[test]
+    fn silence_needs_every_segment_to_be_silent() {
+        let silent = Segment { no_speech_prob: 0.9, avg_logprob: -1.5 };
+        let speech = Segment { no_speech_prob: 0.9, avg_logprob: -0.3 };
+        assert!(all_silent(&[silent], 0.6));
+        assert!(!all_silent(&[Segment { no_speech_prob: 0.9, avg_logprob: -1.5 }, speech], 0.6));
+        assert!(!all_silent(&[], 0.6));
+    }
+}
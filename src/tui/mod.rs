@@ -0,0 +1,288 @@
+// `croaker tui`: a terminal client for the daemon. It follows the socket's `subscribe` stream,
+// shows the input level while recording, lists the last transcriptions from the history database
+// and sends commands for keys, so the daemon can be driven over SSH or without a desktop.
+
+use crate::daemon::state::DaemonState;
+use crate::history::{History, HistoryEntry};
+use crate::input::socket::{Event, LevelEvent, Request, Status, LEVEL_FLOOR_DBFS};
+use ratatui::crossterm::event::{self, Event as TermEvent, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Gauge, List, ListItem, Paragraph};
+use ratatui::Frame;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// Transcriptions listed under the status
+const TRANSCRIPTS: usize = 8;
+/// Redraw this often for the time spent in the current state
+const TICK: Duration = Duration::from_secs(1);
+
+const HELP: &str = "space toggle · c cancel · l language · m output mode · x translate · p pause · q quit";
+
+/// What a key asks of the TUI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    /// Send this socket command
+    Command(&'static str),
+    /// `pause` or `resume`, whichever isn't the current one
+    PauseResume,
+    Quit,
+}
+
+fn action_for(code: KeyCode, modifiers: KeyModifiers) -> Option<Action> {
+    match code {
+        KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => Some(Action::Quit),
+        KeyCode::Char(' ') | KeyCode::Char('t') => Some(Action::Command("toggle")),
+        KeyCode::Char('c') | KeyCode::Esc => Some(Action::Command("cancel")),
+        KeyCode::Char('l') => Some(Action::Command("toggle-language")),
+        KeyCode::Char('m') => Some(Action::Command("toggle-output-mode")),
+        KeyCode::Char('x') => Some(Action::Command("toggle-translate")),
+        KeyCode::Char('p') => Some(Action::PauseResume),
+        KeyCode::Char('q') => Some(Action::Quit),
+        _ => None,
+    }
+}
+
+/// A line of the `subscribe` stream.
+enum Update {
+    Status(Status),
+    Level(f32),
+}
+
+fn parse_update(line: &str) -> serde_json::Result<Update> {
+    let value: serde_json::Value = serde_json::from_str(line)?;
+    if value["event"] == "level" {
+        return Ok(Update::Level(serde_json::from_value::<LevelEvent>(value)?.level));
+    }
+    Ok(Update::Status(serde_json::from_value::<Event>(value)?.status))
+}
+
+/// How full the level gauge is: empty at `LEVEL_FLOOR_DBFS`, full at 0 dBFS.
+fn level_ratio(level: f32) -> f64 {
+    ((level - LEVEL_FLOOR_DBFS) / -LEVEL_FLOOR_DBFS).clamp(0.0, 1.0) as f64
+}
+
+struct App {
+    status: Status,
+    /// When the daemon entered its current state
+    since: Instant,
+    /// When `status` arrived, to keep its uptime counting
+    received: Instant,
+    /// `None` when history is off
+    history: Option<History>,
+    transcripts: Vec<HistoryEntry>,
+    /// Result of the last command, or why it failed
+    message: String,
+}
+
+impl App {
+    fn update(&mut self, update: Update) {
+        let status = match update {
+            Update::Status(status) => status,
+            Update::Level(level) => {
+                self.status.audio_level = Some(level);
+                return;
+            }
+        };
+        if status.state != self.status.state {
+            self.since = Instant::now();
+            if status.state == DaemonState::Idle {
+                self.load_transcripts();
+            }
+        }
+        self.status = status;
+        self.received = Instant::now();
+    }
+
+    fn load_transcripts(&mut self) {
+        if let Some(ref history) = self.history {
+            match history.list(TRANSCRIPTS) {
+                Ok(entries) => self.transcripts = entries,
+                Err(e) => self.message = format!("Can't read history: {}", e),
+            }
+        }
+    }
+
+    async fn run(&mut self, action: Action) {
+        let command = match action {
+            Action::Command(command) => command,
+            Action::PauseResume if self.status.paused => "resume",
+            Action::PauseResume => "pause",
+            Action::Quit => return,
+        };
+        self.message = match crate::send_command(Request::new(command)).await {
+            Ok(result) if result.is_empty() => command.to_string(),
+            Ok(result) => format!("{}: {}", command, result),
+            Err(e) => format!("{} failed: {}", command, e),
+        };
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [status_area, level_area, transcripts_area, help_area] = Layout::vertical([
+            Constraint::Length(7),
+            Constraint::Length(3),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let status = &self.status;
+        let (state, color) = match status.state {
+            DaemonState::Idle if status.paused => ("Paused (hotkeys off)".to_string(), Color::DarkGray),
+            DaemonState::Idle => ("Ready".to_string(), Color::Gray),
+            DaemonState::Recording => (format!("● Recording {}", elapsed(self.since)), Color::Red),
+            DaemonState::Processing => (format!("Processing {}", elapsed(self.since)), Color::Yellow),
+            DaemonState::Outputting => ("Outputting".to_string(), Color::Green),
+            DaemonState::Error(ref reason) => (format!("Error: {}", reason), Color::Magenta),
+        };
+        let mut lines = vec![
+            Line::from(Span::styled(state, Style::new().fg(color).bold())),
+            Line::from(format!(
+                "Language: {}{}   Output: {}   Profile: {}",
+                status.language.to_uppercase(),
+                if status.translate { " → EN" } else { "" },
+                status.output_mode.as_str(),
+                status.profile.as_deref().unwrap_or("none"),
+            )),
+            Line::from(format!("Recorder: {}   Up: {}", status.recorder, duration(status.uptime_secs + self.received.elapsed().as_secs()))),
+        ];
+        if let Some(ref error) = status.last_error {
+            lines.push(Line::from(Span::styled(format!("Last error: {}", error), Style::new().fg(Color::Magenta))));
+        }
+        if !self.message.is_empty() {
+            lines.push(Line::from(self.message.as_str().italic()));
+        }
+        let title = match crate::daemon::instance::name() {
+            Some(name) => format!(" croaker ({}) ", name),
+            None => " croaker ".to_string(),
+        };
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(title)), status_area);
+
+        let level = match status.audio_level {
+            Some(level) if status.state == DaemonState::Recording => {
+                Gauge::default().ratio(level_ratio(level)).label(format!("{:.0} dBFS", level)).gauge_style(Color::Red)
+            }
+            _ => Gauge::default().ratio(0.0).label("not recording".dark_gray()),
+        };
+        frame.render_widget(level.block(Block::bordered().title(" Input level ")), level_area);
+
+        let items: Vec<ListItem> = if self.history.is_none() {
+            vec![ListItem::new("History is off (history.enabled = false)".dark_gray())]
+        } else {
+            self.transcripts
+                .iter()
+                .map(|entry| {
+                    // `created_at` is RFC 3339; show the time of day
+                    let time = entry.created_at.get(11..16).unwrap_or_default();
+                    let text = entry.cleaned_text.lines().next().unwrap_or_default();
+                    ListItem::new(Line::from(vec![
+                        Span::styled(format!("{} {} ", time, entry.language), Style::new().fg(Color::DarkGray)),
+                        Span::raw(text.to_string()),
+                    ]))
+                })
+                .collect()
+        };
+        frame.render_widget(List::new(items).block(Block::bordered().title(" Last transcriptions ")), transcripts_area);
+
+        frame.render_widget(Paragraph::new(HELP.dark_gray()), help_area);
+    }
+}
+
+/// `0:07` for seven seconds
+fn elapsed(since: Instant) -> String {
+    let secs = since.elapsed().as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+fn duration(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// Run the TUI until `q`, Ctrl+C or the daemon going away.
+pub async fn run(history: Option<History>) -> anyhow::Result<()> {
+    let mut updates = crate::subscribe().await?;
+    // The current status comes first
+    let first = updates
+        .next_line()
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("The daemon closed the connection"))?;
+    let status = serde_json::from_str::<Event>(&first)?.status;
+    let mut app = App {
+        status,
+        since: Instant::now(),
+        received: Instant::now(),
+        history,
+        transcripts: Vec::new(),
+        message: String::new(),
+    };
+    app.load_transcripts();
+
+    // crossterm's reads block, so keys come from a thread; it ends with the process
+    let (key_tx, mut keys) = mpsc::channel(16);
+    std::thread::spawn(move || loop {
+        match event::read() {
+            Ok(TermEvent::Key(key)) if key.kind == KeyEventKind::Press => {
+                if key_tx.blocking_send(key).is_err() {
+                    break;
+                }
+            }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    });
+
+    let mut terminal = ratatui::init();
+    let mut tick = tokio::time::interval(TICK);
+    let result = loop {
+        if let Err(e) = terminal.draw(|frame| app.draw(frame)) {
+            break Err(e.into());
+        }
+        tokio::select! {
+            line = updates.next_line() => match line {
+                Ok(Some(line)) => match parse_update(&line) {
+                    Ok(update) => app.update(update),
+                    Err(e) => break Err(e.into()),
+                },
+                Ok(None) => break Err(anyhow::anyhow!("The daemon closed the connection")),
+                Err(e) => break Err(e.into()),
+            },
+            Some(key) = keys.recv() => match action_for(key.code, key.modifiers) {
+                Some(Action::Quit) => break Ok(()),
+                Some(action) => app.run(action).await,
+                None => {}
+            },
+            _ = tick.tick() => {}
+        }
+    };
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_map_to_socket_commands() {
+        assert_eq!(action_for(KeyCode::Char(' '), KeyModifiers::NONE), Some(Action::Command("toggle")));
+        assert_eq!(action_for(KeyCode::Char('c'), KeyModifiers::NONE), Some(Action::Command("cancel")));
+        assert_eq!(action_for(KeyCode::Char('c'), KeyModifiers::CONTROL), Some(Action::Quit));
+        assert_eq!(action_for(KeyCode::Char('z'), KeyModifiers::NONE), None);
+    }
+
+    #[test]
+    fn level_events_fill_the_gauge() {
+        let Ok(Update::Level(level)) = parse_update(r#"{"v": 1, "event": "level", "level": -30.0}"#) else {
+            panic!("not a level event");
+        };
+        assert_eq!(level_ratio(level), 0.5);
+        assert_eq!(level_ratio(LEVEL_FLOOR_DBFS - 10.0), 0.0);
+        assert_eq!(level_ratio(3.0), 1.0);
+    }
+}